@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use crate::{play::types::Color, search::time::TimeControl};
+
+/// One side's time control: what it starts with, and what it's given back
+/// after each move it completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSettings {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl ClockSettings {
+    pub fn new(base: Duration, increment: Duration) -> Self {
+        ClockSettings { base, increment }
+    }
+}
+
+/// The chess clock of a game between two engines, kept the way cutechess-cli
+/// keeps it: a move is charged the whole time it took, a side whose move
+/// took longer than it had left (plus the margin) loses on time even though
+/// the move was made, and the increment is only added after a move in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameClock {
+    white: ClockSettings,
+    black: ClockSettings,
+    white_remaining: Duration,
+    black_remaining: Duration,
+    /// overrun tolerated before a side is flagged, for process latency
+    margin: Duration,
+    flagged: Option<Color>,
+}
+
+impl GameClock {
+    /// Both sides on the same time control.
+    pub fn new(settings: ClockSettings) -> Self {
+        GameClock::with_odds(settings, settings)
+    }
+
+    /// Each side on its own time control, for time odds matches.
+    pub fn with_odds(white: ClockSettings, black: ClockSettings) -> Self {
+        GameClock {
+            white,
+            black,
+            white_remaining: white.base,
+            black_remaining: black.base,
+            margin: Duration::ZERO,
+            flagged: None,
+        }
+    }
+
+    pub fn with_margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn remaining(&self, color: Color) -> Duration {
+        match color {
+            Color::White => self.white_remaining,
+            Color::Black => self.black_remaining,
+        }
+    }
+
+    /// The side that ran out of time, if either has.
+    pub fn flagged(&self) -> Option<Color> {
+        self.flagged
+    }
+
+    /// Charges `elapsed` to a move by `color` and returns whether it was made
+    /// in time. Once a side has flagged the clock stops.
+    pub fn punch(&mut self, color: Color, elapsed: Duration) -> bool {
+        if self.flagged.is_some() {
+            return false;
+        }
+        let (remaining, settings) = match color {
+            Color::White => (&mut self.white_remaining, self.white),
+            Color::Black => (&mut self.black_remaining, self.black),
+        };
+        if elapsed > *remaining + self.margin {
+            *remaining = Duration::ZERO;
+            self.flagged = Some(color);
+            return false;
+        }
+        *remaining = remaining.saturating_sub(elapsed) + settings.increment;
+        true
+    }
+
+    /// The clock as a UCI `go` command would send it.
+    pub fn time_control(&self) -> TimeControl {
+        TimeControl {
+            wtime: Some(self.white_remaining.as_millis() as u64),
+            btime: Some(self.black_remaining.as_millis() as u64),
+            winc: Some(self.white.increment.as_millis() as u64),
+            binc: Some(self.black.increment.as_millis() as u64),
+            ..TimeControl::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_punch_adds_increment() {
+        let mut clock = GameClock::new(ClockSettings::new(ms(1_000), ms(100)));
+        assert!(clock.punch(Color::White, ms(300)));
+        assert_eq!(clock.remaining(Color::White), ms(800));
+        assert_eq!(clock.remaining(Color::Black), ms(1_000));
+        // using exactly what's left is still in time
+        assert!(clock.punch(Color::Black, ms(1_000)));
+        assert_eq!(clock.remaining(Color::Black), ms(100));
+        assert_eq!(clock.flagged(), None);
+
+        let tc = clock.time_control();
+        assert_eq!((tc.wtime, tc.btime), (Some(800), Some(100)));
+        assert_eq!((tc.winc, tc.binc), (Some(100), Some(100)));
+    }
+
+    #[test]
+    fn test_flag() {
+        let mut clock = GameClock::new(ClockSettings::new(ms(500), ms(100)));
+        // the increment doesn't save a move that overran
+        assert!(!clock.punch(Color::White, ms(501)));
+        assert_eq!(clock.flagged(), Some(Color::White));
+        assert_eq!(clock.remaining(Color::White), Duration::ZERO);
+        // and the clock stops once one side has flagged
+        assert!(!clock.punch(Color::Black, ms(1)));
+        assert_eq!(clock.remaining(Color::Black), ms(500));
+
+        let mut clock = GameClock::new(ClockSettings::new(ms(500), ms(0))).with_margin(ms(50));
+        assert!(clock.punch(Color::White, ms(550)));
+        assert_eq!(clock.remaining(Color::White), Duration::ZERO);
+        assert!(!clock.punch(Color::White, ms(51)));
+    }
+
+    #[test]
+    fn test_time_odds() {
+        let mut clock = GameClock::with_odds(
+            ClockSettings::new(ms(2_000), ms(0)),
+            ClockSettings::new(ms(1_000), ms(50)),
+        );
+        assert!(clock.punch(Color::White, ms(1_500)));
+        assert!(!clock.punch(Color::Black, ms(1_500)));
+        assert_eq!(clock.flagged(), Some(Color::Black));
+        let tc = clock.time_control();
+        assert_eq!((tc.wtime, tc.winc, tc.binc), (Some(500), Some(0), Some(50)));
+    }
+}
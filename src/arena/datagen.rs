@@ -0,0 +1,326 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    sync::Arc,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    play::{r#move::make_move, types::Color, GameState},
+    search::{
+        search, time::SearchControl, tt::TranspositionTable, RootRandomization, SearchConfig, MATE,
+        MAX_PLY,
+    },
+};
+
+use super::{adjudicate, GameResult, Termination};
+
+const DEFAULT_GAMES: u32 = 100;
+const DEFAULT_DEPTH: u8 = 6;
+const DEFAULT_SEED: u64 = 1;
+const DATAGEN_TT_MB: usize = 16;
+
+/// Plies from the start position where the search picks at random among
+/// the moves within `RANDOM_WINDOW` of its best, so no two games follow the
+/// same line. Their positions aren't kept, being off the best line.
+const RANDOM_PLIES: u16 = 8;
+const RANDOM_WINDOW: i32 = 50;
+/// A game still going this many plies in is called a draw.
+const MAX_PLIES: u16 = 400;
+
+/// A score at least this big for this many plies in a row, as searched by
+/// both sides, is called a win for the side it favours.
+const WIN_SCORE: i32 = 1000;
+const WIN_PLIES: u32 = 4;
+/// From this ply on, a score this close to level for this many plies in a
+/// row is called a draw.
+const DRAW_FROM_PLY: u16 = 80;
+const DRAW_SCORE: i32 = 10;
+const DRAW_PLIES: u32 = 8;
+
+/// How a run of self-play games is played and which of their positions are
+/// kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatagenConfig {
+    pub games: u32,
+    /// what each move is searched to
+    pub depth: u8,
+    /// the chance each quiet position is kept
+    pub sample_rate: f64,
+    pub seed: u64,
+}
+
+impl Default for DatagenConfig {
+    fn default() -> Self {
+        DatagenConfig {
+            games: DEFAULT_GAMES,
+            depth: DEFAULT_DEPTH,
+            sample_rate: 0.5,
+            seed: DEFAULT_SEED,
+        }
+    }
+}
+
+/// A kept position and its search score, White's point of view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub fen: String,
+    pub score: i32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DatagenSummary {
+    pub games: u32,
+    pub positions: usize,
+}
+
+/// Calls a game once the searches have agreed on the score for long enough.
+#[derive(Debug, Default)]
+struct ScoreAdjudicator {
+    winning: u32,
+    losing: u32,
+    level: u32,
+}
+
+impl ScoreAdjudicator {
+    fn update(&mut self, white_score: i32, plies: u16) -> Option<GameResult> {
+        let run = |count: &mut u32, holds: bool| {
+            *count = if holds { *count + 1 } else { 0 };
+            *count
+        };
+        if run(&mut self.winning, white_score >= WIN_SCORE) >= WIN_PLIES {
+            return Some(GameResult::Win(Color::White, Termination::Adjudication));
+        }
+        if run(&mut self.losing, white_score <= -WIN_SCORE) >= WIN_PLIES {
+            return Some(GameResult::Win(Color::Black, Termination::Adjudication));
+        }
+        let level = plies >= DRAW_FROM_PLY && white_score.abs() <= DRAW_SCORE;
+        if run(&mut self.level, level) >= DRAW_PLIES {
+            return Some(GameResult::Draw(Termination::Adjudication));
+        }
+        None
+    }
+}
+
+/// Plays one game against itself, opening with randomized moves, and keeps
+/// some of the quiet positions along the way: those out of check, where the
+/// move found is no capture or promotion and the score is no mate.
+pub fn play_datagen_game(
+    config: &DatagenConfig,
+    search_config: &SearchConfig,
+    rng: &mut StdRng,
+    tt: &mut TranspositionTable,
+) -> (Vec<Sample>, GameResult) {
+    let mut state = GameState::default();
+    let mut plies = 0;
+    let mut samples = vec![];
+    let mut adjudicator = ScoreAdjudicator::default();
+    tt.clear();
+
+    let result = loop {
+        if let Some(result) = adjudicate(&state, plies, MAX_PLIES) {
+            break result;
+        }
+        let side = state.position.side_to_move;
+        let control = Arc::new(SearchControl::new(None, false));
+        let result = search(&state, config.depth, search_config, control, tt, &mut ());
+        let mv = match result.best_move {
+            Some(mv) => mv,
+            None => break GameResult::Win(side.opposing(), Termination::IllegalMove),
+        };
+        let white_score = match side {
+            Color::White => result.score,
+            Color::Black => -result.score,
+        };
+        let quiet = plies >= RANDOM_PLIES
+            && !state.position.in_check()
+            && mv.captured().is_none()
+            && mv.promoted().is_none()
+            && result.score.abs() < MATE - MAX_PLY as i32;
+        if quiet && rng.gen_bool(config.sample_rate) {
+            samples.push(Sample {
+                fen: state.to_fen(),
+                score: white_score,
+            });
+        }
+        if let Some(result) = adjudicator.update(white_score, plies) {
+            break result;
+        }
+        if make_move(mv, &mut state).is_err() {
+            break GameResult::Win(side.opposing(), Termination::IllegalMove);
+        }
+        plies += 1;
+    };
+    (samples, result)
+}
+
+/// The game's result as training data has it, White's point of view.
+fn result_text(result: GameResult) -> &'static str {
+    match result {
+        GameResult::Win(Color::White, _) => "1.0",
+        GameResult::Win(Color::Black, _) => "0.0",
+        GameResult::Draw(_) => "0.5",
+    }
+}
+
+/// Plays `config.games` games, writing each kept position to `out` as
+/// `<fen> | <score> | <result>`, the text format Texel tuners and most NNUE
+/// trainers read, with the score in centipawns and the result 1.0, 0.5 or
+/// 0.0, both White's point of view.
+pub fn run_datagen(config: &DatagenConfig, out: &mut dyn Write) -> io::Result<DatagenSummary> {
+    let mut search_config = SearchConfig {
+        root_randomization: Some(RootRandomization {
+            plies: RANDOM_PLIES,
+            window: RANDOM_WINDOW,
+        }),
+        ..SearchConfig::default()
+    };
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut tt = TranspositionTable::new(DATAGEN_TT_MB);
+    let mut summary = DatagenSummary::default();
+    for _ in 0..config.games {
+        // each game's randomized plies pick differently
+        search_config.seed = rng.gen();
+        let (samples, result) = play_datagen_game(config, &search_config, &mut rng, &mut tt);
+        for sample in &samples {
+            writeln!(
+                out,
+                "{} | {} | {}",
+                sample.fen,
+                sample.score,
+                result_text(result)
+            )?;
+        }
+        summary.games += 1;
+        summary.positions += samples.len();
+    }
+    out.flush()?;
+    Ok(summary)
+}
+
+/// What `datagen` was asked to do.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatagenArgs {
+    pub out: String,
+    pub config: DatagenConfig,
+}
+
+impl DatagenArgs {
+    /// Reads `<out> [--games N] [--depth N] [--seed N]`.
+    pub fn parse(args: &[String]) -> Result<DatagenArgs, String> {
+        let (out, mut rest) = match args {
+            [out, rest @ ..] => (out.clone(), rest),
+            _ => return Err("datagen needs a file to write".to_string()),
+        };
+        let mut config = DatagenConfig::default();
+        while let [flag, value, tail @ ..] = rest {
+            let bad = || format!("bad value {} for {}", value, flag);
+            match flag.as_str() {
+                "--games" => config.games = value.parse().map_err(|_| bad())?,
+                "--depth" => config.depth = value.parse().map_err(|_| bad())?,
+                "--seed" => config.seed = value.parse().map_err(|_| bad())?,
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(format!("unknown options {}", rest.join(" ")));
+        }
+        Ok(DatagenArgs { out, config })
+    }
+}
+
+/// Generates the data `args` ask for and says how much went in.
+pub fn run_datagen_file(args: &DatagenArgs) -> io::Result<DatagenSummary> {
+    let mut out = BufWriter::new(File::create(&args.out)?);
+    let summary = run_datagen(&args.config, &mut out)?;
+    println!(
+        "{} games played, {} positions written to {}",
+        summary.games, summary.positions, args.out
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_score_adjudication() {
+        let mut adjudicator = ScoreAdjudicator::default();
+        for _ in 1..WIN_PLIES {
+            assert_eq!(adjudicator.update(WIN_SCORE, 20), None);
+        }
+        assert_eq!(
+            adjudicator.update(WIN_SCORE + 50, 20),
+            Some(GameResult::Win(Color::White, Termination::Adjudication))
+        );
+
+        // a level score only counts once the game is long enough, and the
+        // run starts over when it's broken
+        let mut adjudicator = ScoreAdjudicator::default();
+        for _ in 0..DRAW_PLIES {
+            assert_eq!(adjudicator.update(0, DRAW_FROM_PLY - 1), None);
+        }
+        for _ in 1..DRAW_PLIES {
+            assert_eq!(adjudicator.update(0, DRAW_FROM_PLY), None);
+        }
+        assert_eq!(adjudicator.update(-300, DRAW_FROM_PLY), None);
+        assert_eq!(adjudicator.update(0, DRAW_FROM_PLY), None);
+    }
+
+    #[test]
+    fn test_run_datagen() {
+        let config = DatagenConfig {
+            games: 1,
+            depth: 1,
+            sample_rate: 1.0,
+            seed: 7,
+        };
+        let mut out = vec![];
+        let summary = run_datagen(&config, &mut out).unwrap();
+        assert_eq!(summary.games, 1);
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), summary.positions);
+        assert!(summary.positions > 0);
+        let results: Vec<&str> = out
+            .lines()
+            .map(|l| l.rsplit(" | ").next().unwrap())
+            .collect();
+        assert!(results.iter().all(|result| *result == results[0]));
+        for line in out.lines() {
+            let fields: Vec<&str> = line.split(" | ").collect();
+            assert_eq!(fields.len(), 3);
+            let state = GameState::from_fen(fields[0]).unwrap();
+            assert!(!state.position.in_check());
+            assert!(fields[1].parse::<i32>().unwrap().abs() < MATE - MAX_PLY as i32);
+        }
+
+        // the same seed plays the same games
+        let mut again = vec![];
+        run_datagen(&config, &mut again).unwrap();
+        assert_eq!(String::from_utf8(again).unwrap(), out);
+        // and another opens differently
+        let mut other = vec![];
+        run_datagen(&DatagenConfig { seed: 8, ..config }, &mut other).unwrap();
+        assert_ne!(String::from_utf8(other).unwrap(), out);
+    }
+
+    #[test]
+    fn test_parse_datagen_args() {
+        let args: Vec<String> = ["data.txt", "--games", "10", "--depth", "4"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let parsed = DatagenArgs::parse(&args).unwrap();
+        assert_eq!(parsed.out, "data.txt");
+        assert_eq!(parsed.config.games, 10);
+        assert_eq!(parsed.config.depth, 4);
+        assert_eq!(parsed.config.seed, DEFAULT_SEED);
+        assert!(DatagenArgs::parse(&[]).is_err());
+        assert!(DatagenArgs::parse(&args[..2]).is_err());
+        assert!(DatagenArgs::parse(&args[..3]).is_ok());
+    }
+}
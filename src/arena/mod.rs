@@ -0,0 +1,303 @@
+pub mod clock;
+pub mod datagen;
+pub mod tournament;
+
+use std::{sync::Arc, time::Instant};
+
+use crate::{
+    play::{error::FENParsingError, types::Color, GameState},
+    record::{GameRecorder, PgnResult},
+    search::{
+        search,
+        time::{SearchControl, TimeManager},
+        tt::TranspositionTable,
+        SearchConfig, MAX_PLY,
+    },
+};
+
+use self::clock::{ClockSettings, GameClock};
+
+/// Size of each player's transposition table.
+const PLAYER_TT_MB: usize = 16;
+
+/// An engine in an in-process match: the search settings it plays with.
+#[derive(Debug, Clone)]
+pub struct Player {
+    pub name: String,
+    pub config: SearchConfig,
+    pub time_manager: TimeManager,
+}
+
+impl Player {
+    pub fn new(name: impl ToString, config: SearchConfig) -> Self {
+        Player {
+            name: name.to_string(),
+            config,
+            time_manager: TimeManager::default(),
+        }
+    }
+}
+
+/// Why a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    /// won by the variant's own rules, such as a king reaching the hill
+    VariantEnd,
+    Repetition,
+    FiftyMoves,
+    TimeForfeit,
+    /// the engine came up with no move, or one that couldn't be played
+    IllegalMove,
+    /// adjudicated a draw for running past the match's ply limit
+    MaxPlies,
+    /// adjudicated on the searches' scores, one side's lasting advantage or
+    /// a long level stretch
+    Adjudication,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Win(Color, Termination),
+    Draw(Termination),
+}
+
+impl GameResult {
+    fn pgn_result(&self) -> PgnResult {
+        match self {
+            GameResult::Win(color, _) => PgnResult::win_for(*color),
+            GameResult::Draw(_) => PgnResult::Draw,
+        }
+    }
+
+    /// The PGN `Termination` tag for the result.
+    fn pgn_termination(&self) -> &'static str {
+        let termination = match self {
+            GameResult::Win(_, termination) | GameResult::Draw(termination) => termination,
+        };
+        match termination {
+            Termination::TimeForfeit => "time forfeit",
+            Termination::IllegalMove => "rules infraction",
+            Termination::MaxPlies | Termination::Adjudication => "adjudication",
+            _ => "normal",
+        }
+    }
+}
+
+/// A finished game: its moves with each player's clock and score, how it
+/// ended, and the clock as it stood at the end.
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub game: GameRecorder,
+    pub result: GameResult,
+    pub clock: GameClock,
+}
+
+/// Plays one game from `start_fen` on `clock`, each player searching until
+/// its time manager's budget for the move runs out.
+pub fn play_game(
+    start_fen: &str,
+    white: &Player,
+    black: &Player,
+    mut clock: GameClock,
+    max_plies: u16,
+) -> Result<GameRecord, FENParsingError> {
+    let mut game = GameRecorder::new(start_fen)?;
+    game.set_header("White", &white.name);
+    game.set_header("Black", &black.name);
+    let white_tt = TranspositionTable::new(PLAYER_TT_MB);
+    let black_tt = TranspositionTable::new(PLAYER_TT_MB);
+
+    let result = loop {
+        let state = game.state();
+        if let Some(result) = adjudicate(state, game.moves().len() as u16, max_plies) {
+            break result;
+        }
+        let side = state.position.side_to_move;
+        let (player, tt) = match side {
+            Color::White => (white, &white_tt),
+            Color::Black => (black, &black_tt),
+        };
+        let budget = player.time_manager.allocate(&clock.time_control(), side);
+        let control = Arc::new(SearchControl::new(budget, false));
+
+        let started = Instant::now();
+        let result = search(state, MAX_PLY, &player.config, control, tt, &mut ());
+        if !clock.punch(side, started.elapsed()) {
+            break GameResult::Win(side.opposing(), Termination::TimeForfeit);
+        }
+        let mv = match result.best_move {
+            Some(mv) => mv,
+            None => break GameResult::Win(side.opposing(), Termination::IllegalMove),
+        };
+        let remaining = clock.remaining(side);
+        if let Err(e) = game.record(mv, Some(remaining), Some(result.score)) {
+            eprintln!("{}", e);
+            break GameResult::Win(side.opposing(), Termination::IllegalMove);
+        }
+    };
+    game.set_result(result.pgn_result());
+    game.set_header("Termination", result.pgn_termination());
+    Ok(GameRecord {
+        game,
+        result,
+        clock,
+    })
+}
+
+/// The result of the game at `state` if it's over before a move is searched.
+fn adjudicate(state: &GameState, plies: u16, max_plies: u16) -> Option<GameResult> {
+    let position = &state.position;
+    if let Some(winner) = position.winner() {
+        return Some(GameResult::Win(winner, Termination::VariantEnd));
+    }
+    if !position.has_legal_move() {
+        let stalemate_winner = position.variant.stalemate_winner(position.side_to_move);
+        return Some(if position.in_check() {
+            GameResult::Win(position.side_to_move.opposing(), Termination::Checkmate)
+        } else if let Some(winner) = stalemate_winner {
+            GameResult::Win(winner, Termination::VariantEnd)
+        } else {
+            GameResult::Draw(Termination::Stalemate)
+        });
+    }
+    if state.is_threefold_repetition() {
+        return Some(GameResult::Draw(Termination::Repetition));
+    }
+    if state.is_fifty_move_draw() {
+        return Some(GameResult::Draw(Termination::FiftyMoves));
+    }
+    if plies >= max_plies {
+        return Some(GameResult::Draw(Termination::MaxPlies));
+    }
+    None
+}
+
+/// Wins, draws and losses from the first player's point of view.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// Plays `games` games between `first` and `second` from `start_fen`,
+/// swapping colors each game, with `first`'s and `second`'s own time controls.
+pub fn play_match(
+    start_fen: &str,
+    first: &Player,
+    second: &Player,
+    games: u32,
+    first_clock: ClockSettings,
+    second_clock: ClockSettings,
+    max_plies: u16,
+) -> Result<(MatchScore, Vec<GameRecord>), FENParsingError> {
+    let mut score = MatchScore::default();
+    let mut records = vec![];
+    for game in 0..games {
+        let first_color = if game.is_multiple_of(2) {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let record = match first_color {
+            Color::White => play_game(
+                start_fen,
+                first,
+                second,
+                GameClock::with_odds(first_clock, second_clock),
+                max_plies,
+            )?,
+            Color::Black => play_game(
+                start_fen,
+                second,
+                first,
+                GameClock::with_odds(second_clock, first_clock),
+                max_plies,
+            )?,
+        };
+        match record.result {
+            GameResult::Win(color, _) if color == first_color => score.wins += 1,
+            GameResult::Win(_, _) => score.losses += 1,
+            GameResult::Draw(_) => score.draws += 1,
+        }
+        records.push(record);
+    }
+    Ok((score, records))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+
+    use super::*;
+    use crate::play::START_FEN;
+
+    fn player(name: &str) -> Player {
+        Player::new(name, SearchConfig::default())
+    }
+
+    #[test]
+    fn test_play_game_to_mate() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let clock = GameClock::new(ClockSettings::new(Duration::from_secs(10), Duration::ZERO));
+        let record = play_game(fen, &player("a"), &player("b"), clock, 100).unwrap();
+        assert_eq!(
+            record.game.to_uci(),
+            format!("position fen {} moves a1a8", fen)
+        );
+        assert_eq!(
+            record.result,
+            GameResult::Win(Color::White, Termination::Checkmate)
+        );
+        let pgn = record.game.to_pgn();
+        assert!(pgn.contains("[White \"a\"]\n[Black \"b\"]\n[Result \"1-0\"]"));
+        assert!(pgn.contains("[Termination \"normal\"]"));
+        assert!(pgn.contains("1. Ra8# {[%eval #1] [%clk 0:00:"));
+        assert!(record.clock.remaining(Color::White) < Duration::from_secs(10));
+        assert_eq!(
+            record.clock.remaining(Color::Black),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn test_time_forfeit() {
+        // with no time at all the first move is already too late
+        let clock = GameClock::with_odds(
+            ClockSettings::new(Duration::ZERO, Duration::ZERO),
+            ClockSettings::new(Duration::from_secs(10), Duration::ZERO),
+        );
+        let record = play_game(START_FEN, &player("a"), &player("b"), clock, 100).unwrap();
+        assert!(record.game.moves().is_empty());
+        assert!(record
+            .game
+            .to_pgn()
+            .contains("[Termination \"time forfeit\"]"));
+        assert_eq!(
+            record.result,
+            GameResult::Win(Color::Black, Termination::TimeForfeit)
+        );
+        assert_eq!(record.clock.flagged(), Some(Color::White));
+    }
+
+    #[test]
+    fn test_play_match() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let settings = ClockSettings::new(Duration::from_secs(10), Duration::ZERO);
+        let (score, records) =
+            play_match(fen, &player("a"), &player("b"), 2, settings, settings, 100).unwrap();
+        // whoever has white mates straight away
+        assert_eq!(
+            score,
+            MatchScore {
+                wins: 1,
+                draws: 0,
+                losses: 1
+            }
+        );
+        assert_eq!(records.len(), 2);
+    }
+}
@@ -0,0 +1,682 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    time::Duration,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    book::{book_moves, read_book, BookEntry},
+    epd::{EpdError, EpdRecord},
+    play::{error::FENParsingError, r#move::make_move, GameState, START_FEN},
+    record::PgnResult,
+    search::SearchConfig,
+    uci::set_option,
+    Error,
+};
+
+use super::{
+    clock::{ClockSettings, GameClock},
+    play_game, GameRecord, Player,
+};
+
+const DEFAULT_MAX_PLIES: u16 = 400;
+const DEFAULT_BOOK_PLIES: u16 = 8;
+/// fixed so a resumed tournament draws the same lines from its book
+const BOOK_SEED: u64 = 1;
+
+/// Who plays whom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// every engine plays every other
+    RoundRobin,
+    /// the first engine plays each of the others, and they don't play
+    /// one another
+    Gauntlet,
+}
+
+/// A time control in the config file, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct TimeSettings {
+    pub base_ms: u64,
+    #[serde(default)]
+    pub increment_ms: u64,
+}
+
+impl From<TimeSettings> for ClockSettings {
+    fn from(time: TimeSettings) -> Self {
+        ClockSettings::new(
+            Duration::from_millis(time.base_ms),
+            Duration::from_millis(time.increment_ms),
+        )
+    }
+}
+
+/// An engine in the tournament: the search with its UCI options set, e.g.
+/// `{"name": "aggressive", "options": {"Personality": "aggressive"}}`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EngineConfig {
+    pub name: String,
+    /// option values, as strings or as JSON numbers and booleans
+    #[serde(default)]
+    pub options: BTreeMap<String, serde_json::Value>,
+    /// the engine's own time control, for time odds, instead of the
+    /// tournament's
+    #[serde(default)]
+    pub time: Option<TimeSettings>,
+}
+
+/// A tournament as read from its JSON config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TournamentConfig {
+    pub format: Format,
+    pub engines: Vec<EngineConfig>,
+    /// how many times each pairing plays its pair of games
+    #[serde(default = "one_round")]
+    pub rounds: u32,
+    /// an EPD file of opening positions, each pair of games starting from
+    /// the next, or the start position without one
+    #[serde(default)]
+    pub openings: Option<String>,
+    /// a Polyglot book to draw one opening line for each pair of games
+    /// from, up to `book_plies` deep, taken in turn after the EPD's
+    #[serde(default)]
+    pub book: Option<String>,
+    #[serde(default = "default_book_plies")]
+    pub book_plies: u16,
+    pub time: TimeSettings,
+    /// plies after which a game is adjudicated a draw
+    #[serde(default = "default_max_plies")]
+    pub max_plies: u16,
+}
+
+fn one_round() -> u32 {
+    1
+}
+
+fn default_max_plies() -> u16 {
+    DEFAULT_MAX_PLIES
+}
+
+fn default_book_plies() -> u16 {
+    DEFAULT_BOOK_PLIES
+}
+
+/// One game of the schedule, the engines by their place in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pairing {
+    pub round: u32,
+    pub white: usize,
+    pub black: usize,
+    /// which of the openings it starts from
+    pub opening: usize,
+}
+
+/// Every game of a tournament in the order they're played. Each pairing
+/// plays a pair of games a round, one with each color, from the same
+/// opening, and the openings are taken in turn one pair after another.
+pub fn schedule(format: Format, engines: usize, rounds: u32, openings: usize) -> Vec<Pairing> {
+    let pairs: Vec<(usize, usize)> = match format {
+        Format::RoundRobin => (0..engines)
+            .flat_map(|first| (first + 1..engines).map(move |second| (first, second)))
+            .collect(),
+        Format::Gauntlet => (1..engines).map(|second| (0, second)).collect(),
+    };
+    let mut games = vec![];
+    for round in 1..=rounds {
+        for &(first, second) in &pairs {
+            let opening = games.len() / 2 % openings.max(1);
+            for (white, black) in [(first, second), (second, first)] {
+                games.push(Pairing {
+                    round,
+                    white,
+                    black,
+                    opening,
+                });
+            }
+        }
+    }
+    games
+}
+
+/// The FENs of the positions in an EPD file, skipping blank and `#` lines.
+pub fn read_openings(epd: &str) -> Result<Vec<String>, EpdError> {
+    epd.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| EpdRecord::parse(line).map(|record| record.to_fen()))
+        .collect()
+}
+
+/// The FENs `count` lines through the book `entries` lead to from the start
+/// position, each played until `plies` deep or out of book, picking each
+/// move at random by its weight.
+pub fn book_openings(entries: &[BookEntry], count: usize, plies: u16) -> Vec<String> {
+    let mut rng = StdRng::seed_from_u64(BOOK_SEED);
+    (0..count)
+        .map(|_| {
+            let mut state = GameState::default();
+            for _ in 0..plies {
+                let moves = book_moves(entries, &state);
+                let total: u32 = moves.iter().map(|(_, weight)| *weight as u32).sum();
+                if total == 0 {
+                    break;
+                }
+                let mut roll = rng.gen_range(0..total);
+                let (mv, _) = moves
+                    .iter()
+                    .find(|(_, weight)| match roll.checked_sub(*weight as u32) {
+                        Some(rest) => {
+                            roll = rest;
+                            false
+                        }
+                        None => true,
+                    })
+                    .expect("the roll is within the total weight");
+                if make_move(*mv, &mut state).is_err() {
+                    break;
+                }
+            }
+            state.to_fen()
+        })
+        .collect()
+}
+
+/// The results so far, kept in a JSON file so a tournament that's stopped
+/// can be picked up where it left off.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TournamentState {
+    /// the engines' names, to tell it's the same tournament
+    pub engines: Vec<String>,
+    /// the result of each game played, in the schedule's order
+    pub results: Vec<String>,
+}
+
+impl TournamentState {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// A tournament ready to play: its engines set up and its games scheduled.
+#[derive(Debug, Clone)]
+pub struct Tournament {
+    players: Vec<Player>,
+    clocks: Vec<ClockSettings>,
+    openings: Vec<String>,
+    schedule: Vec<Pairing>,
+    max_plies: u16,
+}
+
+impl Tournament {
+    /// Sets up the engines of `config` to play from `openings`, or from the
+    /// start position if there are none.
+    pub fn new(config: &TournamentConfig, openings: Vec<String>) -> Result<Self, Error> {
+        let invalid = |msg: String| Error::Io(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        if config.engines.len() < 2 {
+            return Err(invalid(
+                "a tournament needs at least two engines".to_string(),
+            ));
+        }
+        let mut players = vec![];
+        for engine in &config.engines {
+            let mut player = Player::new(&engine.name, SearchConfig::default());
+            for (name, value) in &engine.options {
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                if !set_option(name, &value, &mut player.time_manager, &mut player.config) {
+                    return Err(invalid(format!("{} has no option {}", engine.name, name)));
+                }
+            }
+            players.push(player);
+        }
+        let clocks = config
+            .engines
+            .iter()
+            .map(|engine| engine.time.unwrap_or(config.time).into())
+            .collect();
+        let openings = if openings.is_empty() {
+            vec![START_FEN.to_string()]
+        } else {
+            openings
+        };
+        let schedule = schedule(config.format, players.len(), config.rounds, openings.len());
+        Ok(Tournament {
+            players,
+            clocks,
+            openings,
+            schedule,
+            max_plies: config.max_plies,
+        })
+    }
+
+    pub fn schedule(&self) -> &[Pairing] {
+        &self.schedule
+    }
+
+    /// A state with no games played yet.
+    pub fn start(&self) -> TournamentState {
+        TournamentState {
+            engines: self.players.iter().map(|p| p.name.clone()).collect(),
+            results: vec![],
+        }
+    }
+
+    /// Whether `state` was saved by this tournament, so it can carry on
+    /// from it.
+    pub fn can_resume(&self, state: &TournamentState) -> bool {
+        state.engines == self.start().engines
+            && state.results.len() <= self.schedule.len()
+            && state.results.iter().all(|r| PgnResult::parse(r).is_some())
+    }
+
+    /// Plays the first game of the schedule `state` has no result for, and
+    /// records its result; `None` once every game's been played.
+    pub fn play_next(
+        &self,
+        state: &mut TournamentState,
+    ) -> Result<Option<(Pairing, GameRecord)>, FENParsingError> {
+        let pairing = match self.schedule.get(state.results.len()) {
+            Some(pairing) => *pairing,
+            None => return Ok(None),
+        };
+        let clock = GameClock::with_odds(self.clocks[pairing.white], self.clocks[pairing.black]);
+        let mut record = play_game(
+            &self.openings[pairing.opening],
+            &self.players[pairing.white],
+            &self.players[pairing.black],
+            clock,
+            self.max_plies,
+        )?;
+        record.game.set_header("Event", "lasker tournament");
+        record.game.set_header(
+            "Round",
+            format!("{}.{}", pairing.round, state.results.len() + 1),
+        );
+        state
+            .results
+            .push(record.result.pgn_result().as_str().to_string());
+        Ok(Some((pairing, record)))
+    }
+
+    /// The crosstable of the games `state` has results for.
+    pub fn crosstable(&self, state: &TournamentState) -> Crosstable {
+        let mut table = Crosstable::new(self.players.iter().map(|p| p.name.clone()).collect());
+        for (pairing, result) in self.schedule.iter().zip(&state.results) {
+            if let Some(result) = PgnResult::parse(result) {
+                table.add(pairing.white, pairing.black, result);
+            }
+        }
+        table
+    }
+}
+
+/// What each engine scored against each other, counted in half points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crosstable {
+    names: Vec<String>,
+    half_points: Vec<Vec<u32>>,
+    games: Vec<Vec<u32>>,
+}
+
+impl Crosstable {
+    pub fn new(names: Vec<String>) -> Self {
+        let n = names.len();
+        Crosstable {
+            names,
+            half_points: vec![vec![0; n]; n],
+            games: vec![vec![0; n]; n],
+        }
+    }
+
+    /// Adds a game between `white` and `black`; an unfinished one scores
+    /// nothing and isn't counted.
+    pub fn add(&mut self, white: usize, black: usize, result: PgnResult) {
+        let (white_half_points, black_half_points) = match result {
+            PgnResult::WhiteWins => (2, 0),
+            PgnResult::BlackWins => (0, 2),
+            PgnResult::Draw => (1, 1),
+            PgnResult::Unfinished => return,
+        };
+        self.half_points[white][black] += white_half_points;
+        self.half_points[black][white] += black_half_points;
+        self.games[white][black] += 1;
+        self.games[black][white] += 1;
+    }
+
+    pub fn points(&self, engine: usize) -> f64 {
+        self.half_points[engine].iter().sum::<u32>() as f64 / 2.0
+    }
+
+    pub fn games(&self, engine: usize) -> u32 {
+        self.games[engine].iter().sum()
+    }
+
+    /// The engines from most points to fewest, ties in the config's order.
+    pub fn standings(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order
+            .sort_by_key(|&engine| std::cmp::Reverse(self.half_points[engine].iter().sum::<u32>()));
+        order
+    }
+}
+
+/// The standings, each engine's row giving its score against the engine
+/// in each column, the columns numbered as the rows are.
+impl fmt::Display for Crosstable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let order = self.standings();
+        let width = self.names.iter().map(String::len).max().unwrap_or(0).max(6);
+        write!(
+            f,
+            "{:>2}  {:<width$}  {:>6}  {:>5}",
+            "#", "Engine", "Points", "Games"
+        )?;
+        for column in 1..=order.len() {
+            write!(f, "  {:>5}", column)?;
+        }
+        writeln!(f)?;
+        for (rank, &engine) in order.iter().enumerate() {
+            write!(
+                f,
+                "{:>2}  {:<width$}  {:>6.1}  {:>5}",
+                rank + 1,
+                self.names[engine],
+                self.points(engine),
+                self.games(engine)
+            )?;
+            for &opponent in &order {
+                let cell = if opponent == engine || self.games[engine][opponent] == 0 {
+                    "-".to_string()
+                } else {
+                    format!("{:.1}", self.half_points[engine][opponent] as f64 / 2.0)
+                };
+                write!(f, "  {:>5}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The command line of `lasker tournament`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TournamentArgs {
+    pub config: String,
+    /// where the results are kept between games, to resume from
+    pub state: Option<String>,
+    /// where each game is appended as it finishes
+    pub pgn: Option<String>,
+}
+
+impl TournamentArgs {
+    /// Reads `<config.json> [--state <file>] [--pgn <file>]`.
+    pub fn parse(args: &[String]) -> Result<TournamentArgs, String> {
+        let (config, mut rest) = match args {
+            [config, rest @ ..] => (config.clone(), rest),
+            _ => return Err("tournament needs a config file".to_string()),
+        };
+        let (mut state, mut pgn) = (None, None);
+        while let [flag, value, tail @ ..] = rest {
+            match flag.as_str() {
+                "--state" => state = Some(value.clone()),
+                "--pgn" => pgn = Some(value.clone()),
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(format!("unknown options {}", rest.join(" ")));
+        }
+        Ok(TournamentArgs { config, state, pgn })
+    }
+}
+
+/// Plays the tournament `args` configure, from where its state file left
+/// off if there's one, reporting each game as it finishes and the
+/// crosstable at the end.
+pub fn run_tournament_file(args: &TournamentArgs) -> Result<Crosstable, Error> {
+    let config: TournamentConfig =
+        serde_json::from_str(&fs::read_to_string(&args.config)?).map_err(io::Error::from)?;
+    let mut openings = match &config.openings {
+        Some(path) => read_openings(&fs::read_to_string(path)?)?,
+        None => vec![],
+    };
+    if let Some(path) = &config.book {
+        let entries = read_book(&mut fs::File::open(path)?)?;
+        let pairs = schedule(config.format, config.engines.len(), config.rounds, 1).len() / 2;
+        openings.extend(book_openings(&entries, pairs, config.book_plies));
+    }
+    let tournament = Tournament::new(&config, openings)?;
+    let mut state = match &args.state {
+        Some(path) if Path::new(path).exists() => TournamentState::load(path)?,
+        _ => tournament.start(),
+    };
+    if !tournament.can_resume(&state) {
+        let msg = "the state file is from another tournament";
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into());
+    }
+    let mut pgn = match &args.pgn {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+        None => None,
+    };
+
+    let total = tournament.schedule().len();
+    while let Some((pairing, record)) = tournament.play_next(&mut state)? {
+        println!(
+            "game {}/{}: {} - {} {}",
+            state.results.len(),
+            total,
+            config.engines[pairing.white].name,
+            config.engines[pairing.black].name,
+            record.result.pgn_result().as_str()
+        );
+        if let Some(pgn) = &mut pgn {
+            writeln!(pgn, "{}", record.game.to_pgn())?;
+        }
+        if let Some(path) = &args.state {
+            state.save(path)?;
+        }
+    }
+    let crosstable = tournament.crosstable(&state);
+    print!("{}", crosstable);
+    Ok(crosstable)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::book::{encode_move, polyglot_key};
+
+    /// Whoever has white mates at once.
+    const MATE_IN_ONE: &str = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+
+    fn tournament_config(format: Format, names: &[&str]) -> TournamentConfig {
+        TournamentConfig {
+            format,
+            engines: names
+                .iter()
+                .map(|name| EngineConfig {
+                    name: name.to_string(),
+                    options: BTreeMap::new(),
+                    time: None,
+                })
+                .collect(),
+            rounds: 1,
+            openings: None,
+            book: None,
+            book_plies: DEFAULT_BOOK_PLIES,
+            time: TimeSettings {
+                base_ms: 10_000,
+                increment_ms: 0,
+            },
+            max_plies: DEFAULT_MAX_PLIES,
+        }
+    }
+
+    #[test]
+    fn test_schedule() {
+        let games = schedule(Format::RoundRobin, 3, 1, 2);
+        let pairs: Vec<(usize, usize, usize)> = games
+            .iter()
+            .map(|game| (game.white, game.black, game.opening))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (0, 1, 0),
+                (1, 0, 0),
+                (0, 2, 1),
+                (2, 0, 1),
+                (1, 2, 0),
+                (2, 1, 0)
+            ]
+        );
+
+        let games = schedule(Format::Gauntlet, 4, 2, 1);
+        assert_eq!(games.len(), 12);
+        assert!(games.iter().all(|game| game.white == 0 || game.black == 0));
+        assert_eq!(games[6].round, 2);
+    }
+
+    #[test]
+    fn test_config() {
+        let json = r#"{
+            "format": "gauntlet",
+            "engines": [
+                {"name": "base"},
+                {"name": "weak", "options": {"Skill Level": 3, "Ponder": false},
+                 "time": {"base_ms": 5000}}
+            ],
+            "time": {"base_ms": 10000, "increment_ms": 100}
+        }"#;
+        let config: TournamentConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.format, Format::Gauntlet);
+        assert_eq!((config.rounds, config.max_plies), (1, DEFAULT_MAX_PLIES));
+        let tournament = Tournament::new(&config, vec![]).unwrap();
+        assert_eq!(tournament.players[1].config.strength.skill_level, 3);
+        assert_eq!(tournament.clocks[1].base, Duration::from_secs(5));
+        assert_eq!(tournament.clocks[0].increment, Duration::from_millis(100));
+        assert_eq!(tournament.openings, vec![START_FEN.to_string()]);
+
+        let mut config = config;
+        config.engines[1]
+            .options
+            .insert("Hashes".to_string(), "64".into());
+        assert!(Tournament::new(&config, vec![]).is_err());
+        config.engines.pop();
+        assert!(Tournament::new(&config, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_read_openings() {
+        let epd = "# two openings\n\n6k1/5ppp/8/8/8/8/8/R5K1 w - - id \"mate\";\n";
+        assert_eq!(read_openings(epd).unwrap(), vec![MATE_IN_ONE.to_string()]);
+        assert!(read_openings("not a position").is_err());
+    }
+
+    #[test]
+    fn test_book_openings() {
+        let find_move = |state: &GameState, uci: &str| state.position.parse_move(uci).unwrap();
+        let entry = |state: &GameState, uci: &str, weight| BookEntry {
+            key: polyglot_key(state),
+            mv: encode_move(find_move(state, uci)),
+            weight,
+            learn: 0,
+        };
+        let start = GameState::default();
+        let mut e4 = start.clone();
+        make_move(find_move(&e4, "e2e4"), &mut e4).unwrap();
+        let mut entries = vec![
+            entry(&start, "e2e4", 1),
+            entry(&start, "d2d4", 1),
+            entry(&start, "a2a3", 0),
+            entry(&e4, "c7c5", 1),
+        ];
+        entries.sort_by_key(|entry| entry.key);
+
+        let openings = book_openings(&entries, 16, DEFAULT_BOOK_PLIES);
+        let e4_c5 = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2";
+        let d4 = "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1";
+        assert!(openings.iter().any(|fen| fen == e4_c5));
+        assert!(openings.iter().any(|fen| fen == d4));
+        assert!(openings.iter().all(|fen| fen == e4_c5 || fen == d4));
+        // the same lines every time, so a tournament can be resumed
+        assert_eq!(book_openings(&entries, 16, DEFAULT_BOOK_PLIES), openings);
+        assert_eq!(book_openings(&entries, 2, 0), vec![START_FEN; 2]);
+    }
+
+    #[test]
+    fn test_resume_and_crosstable() {
+        let config = tournament_config(Format::RoundRobin, &["first", "second"]);
+        let tournament = Tournament::new(&config, vec![MATE_IN_ONE.to_string()]).unwrap();
+        let mut state = tournament.start();
+        let (pairing, record) = tournament.play_next(&mut state).unwrap().unwrap();
+        assert_eq!((pairing.white, pairing.black), (0, 1));
+        assert!(record.game.to_pgn().contains("[Round \"1.1\"]"));
+
+        let path =
+            std::env::temp_dir().join(format!("lasker-tournament-{}.json", std::process::id()));
+        state.save(&path).unwrap();
+        let mut state = TournamentState::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(tournament.can_resume(&state));
+        let other = tournament_config(Format::RoundRobin, &["first", "third"]);
+        let other = Tournament::new(&other, vec![]).unwrap();
+        assert!(!other.can_resume(&state));
+
+        // the second game is the one left, and then there are none
+        let (pairing, _) = tournament.play_next(&mut state).unwrap().unwrap();
+        assert_eq!((pairing.white, pairing.black), (1, 0));
+        assert!(tournament.play_next(&mut state).unwrap().is_none());
+        assert_eq!(state.results, vec!["1-0", "1-0"]);
+
+        let crosstable = tournament.crosstable(&state);
+        assert_eq!((crosstable.points(0), crosstable.games(0)), (1.0, 2));
+    }
+
+    #[test]
+    fn test_crosstable_display() {
+        let names = ["a", "b", "c"].iter().map(|n| n.to_string()).collect();
+        let mut table = Crosstable::new(names);
+        table.add(0, 1, PgnResult::BlackWins);
+        table.add(1, 2, PgnResult::Draw);
+        table.add(2, 0, PgnResult::Unfinished);
+        assert_eq!(table.standings(), vec![1, 2, 0]);
+        assert_eq!(
+            table.to_string(),
+            " #  Engine  Points  Games      1      2      3
+ 1  b          1.5      2      -    0.5    1.0
+ 2  c          0.5      1    0.5      -      -
+ 3  a          0.0      1    0.0      -      -
+"
+        );
+    }
+
+    #[test]
+    fn test_parse_tournament_args() {
+        let args: Vec<String> = ["t.json", "--pgn", "games.pgn", "--state", "t.state"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let parsed = TournamentArgs::parse(&args).unwrap();
+        assert_eq!(parsed.config, "t.json");
+        assert_eq!(parsed.pgn.as_deref(), Some("games.pgn"));
+        assert_eq!(parsed.state.as_deref(), Some("t.state"));
+        assert!(TournamentArgs::parse(&[]).is_err());
+        assert!(TournamentArgs::parse(&args[..2]).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use std::{cmp::Reverse, collections::BTreeMap, fs::File, io::BufWriter};
+
+use crate::{
+    play::{
+        r#move::{make_move, Move},
+        types::Color,
+        GameState,
+    },
+    record::{
+        pgn::{parse_pgn_file, PgnError, PgnGame},
+        PgnResult,
+    },
+};
+
+use super::{encode_move, polyglot_key, write_book, BookEntry};
+
+/// How far into a game, in plies, moves are taken into the book.
+const DEFAULT_BOOK_DEPTH: u16 = 20;
+
+/// How often a move was played from a position and how it went for the
+/// side that played it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct MoveStats {
+    games: u32,
+    wins: u32,
+    draws: u32,
+}
+
+impl MoveStats {
+    /// Polyglot's own weighting, two for a win and one for a draw.
+    fn score(&self) -> u32 {
+        2 * self.wins + self.draws
+    }
+}
+
+/// Gathers the moves played in games, position by position, into a book.
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    depth: u16,
+    stats: BTreeMap<(u64, u16), MoveStats>,
+}
+
+impl BookBuilder {
+    pub fn new(depth: u16) -> Self {
+        BookBuilder {
+            depth,
+            stats: BTreeMap::new(),
+        }
+    }
+
+    /// Replays `game`, counting each of its first `depth` moves. A move
+    /// that can't be played ends the game there, with the moves before it
+    /// already counted, and is returned as the error.
+    pub fn add_game(&mut self, game: &PgnGame) -> Result<(), PgnError> {
+        let mut state = GameState::from_fen(game.start_fen())?;
+        for (index, pgn_move) in game.moves.iter().take(self.depth as usize).enumerate() {
+            let mv = Move::from_san(&state.position, &pgn_move.san)
+                .map_err(|err| PgnError::Move { index, err })?;
+            let stats = self
+                .stats
+                .entry((polyglot_key(&state), encode_move(mv)))
+                .or_default();
+            stats.games += 1;
+            match (game.result, state.position.side_to_move) {
+                (PgnResult::WhiteWins, Color::White) | (PgnResult::BlackWins, Color::Black) => {
+                    stats.wins += 1
+                }
+                (PgnResult::Draw, _) => stats.draws += 1,
+                _ => {}
+            }
+            make_move(mv, &mut state)?;
+        }
+        Ok(())
+    }
+
+    /// The book, sorted by key and within a position by weight, heaviest
+    /// first. Moves that never scored are left out, and a position's
+    /// weights are scaled down together where they'd overflow.
+    pub fn entries(&self) -> Vec<BookEntry> {
+        let mut entries = vec![];
+        let mut position: Vec<(u16, u32)> = vec![];
+        let mut keys = self.stats.iter().peekable();
+        while let Some(((key, mv), stats)) = keys.next() {
+            if stats.score() > 0 {
+                position.push((*mv, stats.score()));
+            }
+            if keys.peek().map(|((next, _), _)| next) == Some(key) {
+                continue;
+            }
+            let max = position.iter().map(|(_, score)| *score).max().unwrap_or(0);
+            let scale = (max as f64 / u16::MAX as f64).max(1.0);
+            position.sort_by_key(|(_, score)| Reverse(*score));
+            for (mv, score) in position.drain(..) {
+                entries.push(BookEntry {
+                    key: *key,
+                    mv,
+                    weight: ((score as f64 / scale) as u16).max(1),
+                    learn: 0,
+                });
+            }
+        }
+        entries
+    }
+}
+
+/// What `book build` was asked to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildArgs {
+    pub pgn: String,
+    pub out: String,
+    pub depth: u16,
+}
+
+impl BuildArgs {
+    /// Reads `<games.pgn> <out.bin> [--depth N]`, the depth in plies.
+    pub fn parse(args: &[String]) -> Result<BuildArgs, String> {
+        let (pgn, out, rest) = match args {
+            [pgn, out, rest @ ..] => (pgn.clone(), out.clone(), rest),
+            _ => return Err("book build needs a PGN file and a book to write".to_string()),
+        };
+        let depth = match rest {
+            [] => DEFAULT_BOOK_DEPTH,
+            [flag, depth] if flag == "--depth" => {
+                depth.parse().map_err(|_| format!("bad depth {}", depth))?
+            }
+            _ => return Err(format!("unknown options {}", rest.join(" "))),
+        };
+        Ok(BuildArgs { pgn, out, depth })
+    }
+}
+
+/// Builds the book `args` ask for and says how many games and entries went
+/// in. A game with a move that can't be played is reported and counts only
+/// up to that move.
+pub fn run_build(args: &BuildArgs) -> Result<(), PgnError> {
+    let mut builder = BookBuilder::new(args.depth);
+    let games = parse_pgn_file(&args.pgn)?;
+    let mut whole = 0;
+    for (i, game) in games.iter().enumerate() {
+        match builder.add_game(game) {
+            Ok(()) => whole += 1,
+            Err(e) => eprintln!("game {}: {}", i + 1, e),
+        }
+    }
+    let entries = builder.entries();
+    write_book(&entries, &mut BufWriter::new(File::create(&args.out)?))?;
+    println!(
+        "{} of {} games read whole, {} entries written to {}",
+        whole,
+        games.len(),
+        entries.len(),
+        args.out
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::record::pgn::parse_pgn;
+
+    const GAMES: &str = "\
+[Result \"1-0\"]
+
+1. e4 e5 2. Nf3 1-0
+
+[Result \"1/2-1/2\"]
+
+1. e4 c5 1/2-1/2
+
+[Result \"0-1\"]
+
+1. d4 d5 0-1
+";
+
+    #[test]
+    fn test_build_book() {
+        let mut builder = BookBuilder::new(2);
+        for game in parse_pgn(GAMES).unwrap() {
+            builder.add_game(&game).unwrap();
+        }
+        let entries = builder.entries();
+        assert!(entries.windows(2).all(|pair| pair[0].key <= pair[1].key));
+
+        let start = GameState::default();
+        let from_start: Vec<(u16, u16)> = entries
+            .iter()
+            .filter(|entry| entry.key == polyglot_key(&start))
+            .map(|entry| (entry.mv, entry.weight))
+            .collect();
+        // e4 won once and drew once, d4 only lost
+        assert_eq!(from_start, vec![(0x031c, 3)]);
+        // c5 and d5 besides, with e5 and d4 lost and Nf3 past the depth
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[test]
+    fn test_bad_game_keeps_its_moves() {
+        let game = &parse_pgn("1. e4 e5 2. Ke3 1-0").unwrap()[0];
+        let mut builder = BookBuilder::new(10);
+        assert!(matches!(
+            builder.add_game(game),
+            Err(PgnError::Move { index: 2, .. })
+        ));
+        assert_eq!(builder.entries().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_build_args() {
+        let args: Vec<String> = ["games.pgn", "book.bin", "--depth", "12"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let parsed = BuildArgs::parse(&args).unwrap();
+        assert_eq!(parsed.pgn, "games.pgn");
+        assert_eq!(parsed.out, "book.bin");
+        assert_eq!(parsed.depth, 12);
+        assert_eq!(
+            BuildArgs::parse(&args[..2]).unwrap().depth,
+            DEFAULT_BOOK_DEPTH
+        );
+        assert!(BuildArgs::parse(&args[..1]).is_err());
+        assert!(BuildArgs::parse(&args[..3]).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+pub mod build;
+mod random;
+
+use std::io::{self, Read, Write};
+
+use self::random::RANDOM64;
+use crate::play::{
+    r#move::Move,
+    types::{Color, Piece, PieceType, Square},
+    GameState,
+};
+
+/// The bytes one book entry takes on disk: key, move, weight and learn.
+pub const ENTRY_SIZE: usize = 16;
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+/// Polyglot orders pieces black before white within each type.
+fn piece_kind(piece: Piece) -> usize {
+    let color = match piece.color() {
+        Color::Black => 0,
+        Color::White => 1,
+    };
+    2 * piece.piece_type() as usize + color
+}
+
+/// The book key of the state's position, computed as Polyglot does from its
+/// Random64 table, so it matches the keys of books other engines write. As
+/// with our own position keys an en passant square only counts when a pawn
+/// stands ready to take on it.
+pub fn polyglot_key(state: &GameState) -> u64 {
+    let random = &RANDOM64;
+    let position = &state.position;
+    let mut key = 0;
+    for color in [Color::White, Color::Black] {
+        for piece_type in [
+            PieceType::Pawn,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+            PieceType::King,
+        ] {
+            let piece = Piece::of(piece_type, color);
+            for sq in position.board.bitboard(piece) {
+                key ^= random[64 * piece_kind(piece) + sq as usize];
+            }
+        }
+    }
+
+    let rights = position.castling_permissions;
+    for (i, has_right) in [
+        rights.white_kingside(),
+        rights.white_queenside(),
+        rights.black_kingside(),
+        rights.black_queenside(),
+    ]
+    .iter()
+    .enumerate()
+    {
+        if *has_right {
+            key ^= random[CASTLING_OFFSET + i];
+        }
+    }
+
+    if let Some(sq) = position.en_passant {
+        if position.ep_capture_possible() {
+            key ^= random[EN_PASSANT_OFFSET + sq.file() as usize];
+        }
+    }
+
+    if position.side_to_move == Color::White {
+        key ^= random[TURN_OFFSET];
+    }
+    key
+}
+
+/// A move as a book stores it: to file and rank, from file and rank, then
+/// the promotion piece, three bits each. Castling is written as the king
+/// taking its own rook, e.g. `e1h1`.
+pub fn encode_move(mv: Move) -> u16 {
+    let from = mv.from_sq();
+    let mut to = mv.to_sq();
+    if mv.castle() {
+        to = match to {
+            Square::G1 => Square::H1,
+            Square::C1 => Square::A1,
+            Square::G8 => Square::H8,
+            Square::C8 => Square::A8,
+            sq => sq,
+        };
+    }
+    let promotion = match mv.promoted().map(|piece| piece.piece_type()) {
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(PieceType::Queen) => 4,
+        _ => 0,
+    };
+    (to as u16) | (from as u16) << 6 | promotion << 12
+}
+
+/// One move from one position, the weight it's played with relative to the
+/// position's other moves and a learn field we leave at zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    pub fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> Self {
+        let mut key = [0; 8];
+        key.copy_from_slice(&bytes[..8]);
+        BookEntry {
+            key: u64::from_be_bytes(key),
+            mv: u16::from_be_bytes([bytes[8], bytes[9]]),
+            weight: u16::from_be_bytes([bytes[10], bytes[11]]),
+            learn: u32::from_be_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+        }
+    }
+}
+
+/// Writes `entries` big-endian, as they're given; a book must be sorted by
+/// key for a prober's binary search to find them.
+pub fn write_book(entries: &[BookEntry], out: &mut dyn Write) -> io::Result<()> {
+    for entry in entries {
+        out.write_all(&entry.to_bytes())?;
+    }
+    out.flush()
+}
+
+pub fn read_book(input: &mut dyn Read) -> io::Result<Vec<BookEntry>> {
+    let mut bytes = vec![];
+    input.read_to_end(&mut bytes)?;
+    if bytes.len() % ENTRY_SIZE != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("book of {} bytes isn't whole entries", bytes.len()),
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(ENTRY_SIZE)
+        .map(|chunk| {
+            let mut entry = [0; ENTRY_SIZE];
+            entry.copy_from_slice(chunk);
+            BookEntry::from_bytes(&entry)
+        })
+        .collect())
+}
+
+/// The moves `entries` give for the state's position and their weights,
+/// in the book's order. The entries must be sorted by key, as a book's are.
+pub fn book_moves(entries: &[BookEntry], state: &GameState) -> Vec<(Move, u16)> {
+    let key = polyglot_key(state);
+    let legal: Vec<Move> = state.position.legal_moves().into_iter().collect();
+    let first = entries.partition_point(|entry| entry.key < key);
+    entries[first..]
+        .iter()
+        .take_while(|entry| entry.key == key)
+        .filter_map(|entry| {
+            legal
+                .iter()
+                .find(|mv| encode_move(**mv) == entry.mv)
+                .map(|mv| (*mv, entry.weight))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::r#move::make_move;
+
+    fn find_move(state: &GameState, uci: &str) -> Move {
+        state.position.parse_move(uci).unwrap()
+    }
+
+    #[test]
+    fn test_encode_move() {
+        let state = GameState::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let start = GameState::default();
+        assert_eq!(encode_move(find_move(&start, "e2e4")), 0x031c);
+        assert_eq!(
+            encode_move(find_move(&state, "e1g1")),
+            Square::H1 as u16 | (Square::E1 as u16) << 6
+        );
+        assert_eq!(
+            encode_move(find_move(&state, "e1c1")),
+            Square::A1 as u16 | (Square::E1 as u16) << 6
+        );
+        assert_eq!(
+            encode_move(find_move(&state, "b7a8q")),
+            Square::A8 as u16 | (Square::B7 as u16) << 6 | 4 << 12
+        );
+    }
+
+    #[test]
+    fn test_polyglot_key() {
+        // the keys Polyglot's own documentation lists
+        for (fen, key) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                0x463b96181691fc9c,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823c9b50fd114196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756b94461c50fb0,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+                0x662fafb965db29d4,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a48b5a8e47ff78,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+                0x652a607ca3f242c1,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+                0x00fdd303c946bdd9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3c8123ea7b067637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f9b829b279560,
+            ),
+        ] {
+            assert_eq!(
+                polyglot_key(&GameState::from_fen(fen).unwrap()),
+                key,
+                "{}",
+                fen
+            );
+        }
+
+        // knights out and back leave the start position's key as it was
+        let mut state = GameState::default();
+        let start = polyglot_key(&state);
+        assert_eq!(start, 0x463b96181691fc9c);
+        let mut keys = vec![];
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            make_move(find_move(&state, uci), &mut state).unwrap();
+            keys.push(polyglot_key(&state));
+        }
+        assert!(keys[..3].iter().all(|key| *key != start));
+        assert_eq!(keys[3], start);
+
+        // an en passant square no pawn can take on leaves the key alone
+        let no_taker =
+            GameState::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+                .unwrap();
+        let without =
+            GameState::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+                .unwrap();
+        assert_eq!(polyglot_key(&no_taker), polyglot_key(&without));
+        let taker =
+            GameState::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+                .unwrap();
+        let no_square =
+            GameState::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+                .unwrap();
+        assert_ne!(polyglot_key(&taker), polyglot_key(&no_square));
+    }
+
+    #[test]
+    fn test_book_moves() {
+        let start = GameState::default();
+        let e4 = find_move(&start, "e2e4");
+        let d4 = find_move(&start, "d2d4");
+        let mut entries = vec![
+            BookEntry {
+                key: polyglot_key(&start),
+                mv: encode_move(e4),
+                weight: 3,
+                learn: 0,
+            },
+            BookEntry {
+                key: polyglot_key(&start),
+                mv: encode_move(d4),
+                weight: 1,
+                learn: 0,
+            },
+            BookEntry {
+                key: 1,
+                mv: encode_move(e4),
+                weight: 1,
+                learn: 0,
+            },
+        ];
+        entries.sort_by_key(|entry| entry.key);
+        assert_eq!(book_moves(&entries, &start), vec![(e4, 3), (d4, 1)]);
+
+        let mut state = start;
+        make_move(e4, &mut state).unwrap();
+        assert!(book_moves(&entries, &state).is_empty());
+    }
+
+    #[test]
+    fn test_entries_round_trip() {
+        let entries = vec![
+            BookEntry {
+                key: 0x0123_4567_89ab_cdef,
+                mv: 0x031c,
+                weight: 3,
+                learn: 0,
+            },
+            BookEntry {
+                key: u64::MAX,
+                mv: 1,
+                weight: u16::MAX,
+                learn: 7,
+            },
+        ];
+        let mut bytes = vec![];
+        write_book(&entries, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 2 * ENTRY_SIZE);
+        assert_eq!(
+            &bytes[..8],
+            &[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]
+        );
+        assert_eq!(read_book(&mut bytes.as_slice()).unwrap(), entries);
+        assert!(read_book(&mut &bytes[1..]).is_err());
+    }
+}
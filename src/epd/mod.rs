@@ -0,0 +1,328 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::play::{error::FENParsingError, GameState};
+
+/// Why an EPD record couldn't be read.
+#[derive(Debug)]
+pub enum EpdError {
+    /// a line that isn't EPD at all
+    Syntax(String),
+    Fen(FENParsingError),
+    /// an operand that doesn't fit its opcode, e.g. a `ce` that isn't a number
+    Operand {
+        opcode: String,
+        operand: String,
+    },
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::Syntax(msg) => f.write_str(msg),
+            EpdError::Fen(err) => err.fmt(f),
+            EpdError::Operand { opcode, operand } => {
+                write!(f, "bad operand `{}` for {}", operand, opcode)
+            }
+        }
+    }
+}
+
+impl Error for EpdError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EpdError::Fen(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<FENParsingError> for EpdError {
+    fn from(err: FENParsingError) -> Self {
+        EpdError::Fen(err)
+    }
+}
+
+/// One line of EPD: a position, as the first four fields of a FEN, and the
+/// operations on it. The opcodes test suites and tuning data lean on are
+/// read into fields of their own; every other operation is kept as written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpdRecord {
+    pub position: String,
+    /// from `hmvc`, or a FEN's own move clocks after the position
+    pub halfmove_clock: Option<u16>,
+    pub fullmove_number: Option<u16>,
+    /// `bm`, the moves to find, in SAN as written
+    pub best_moves: Vec<String>,
+    /// `am`, the moves to steer clear of
+    pub avoid_moves: Vec<String>,
+    pub id: Option<String>,
+    /// `ce`, the evaluation in centipawns from the side to move's point of view
+    pub centipawns: Option<i32>,
+    /// the other operations, each opcode with its operands, in their order
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl EpdRecord {
+    /// Reads an EPD line, `<position> <opcode> <operands>; ...`. Operations
+    /// may also start with a `;`, as in perft suites' `;D1 20 ;D2 400`, and
+    /// the position may carry a FEN's move clocks.
+    pub fn parse(line: &str) -> Result<EpdRecord, EpdError> {
+        let mut rest = line.trim();
+        let mut fields = vec![];
+        for _ in 0..4 {
+            let (field, tail) = next_word(rest)
+                .ok_or_else(|| EpdError::Syntax(format!("no position in `{}`", line.trim())))?;
+            fields.push(field);
+            rest = tail;
+        }
+        let mut record = EpdRecord {
+            position: fields.join(" "),
+            ..EpdRecord::default()
+        };
+        if let Some((halfmove, fullmove, tail)) = move_clocks(rest) {
+            record.halfmove_clock = Some(halfmove);
+            record.fullmove_number = Some(fullmove);
+            rest = tail;
+        }
+
+        for operation in split_operations(rest)? {
+            let mut words = operands(&operation)?.into_iter();
+            let opcode = match words.next() {
+                Some(opcode) => opcode,
+                None => continue,
+            };
+            if !opcode.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                return Err(EpdError::Syntax(format!("bad opcode `{}`", opcode)));
+            }
+            let operands: Vec<String> = words.collect();
+            match opcode.as_str() {
+                "bm" => record.best_moves.extend(operands),
+                "am" => record.avoid_moves.extend(operands),
+                "id" => record.id = Some(operands.join(" ")),
+                "ce" => record.centipawns = Some(number(&opcode, &operands)?),
+                "hmvc" => record.halfmove_clock = Some(number(&opcode, &operands)?),
+                "fmvn" => record.fullmove_number = Some(number(&opcode, &operands)?),
+                _ => record.operations.push((opcode, operands)),
+            }
+        }
+        // what it takes to be played from, not only parsed
+        GameState::from_fen(record.to_fen())?;
+        Ok(record)
+    }
+
+    /// The operands of the first operation with `opcode`, among those
+    /// without a field of their own.
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// The position as a full FEN, its clocks 0 and 1 where none were given.
+    pub fn to_fen(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.position,
+            self.halfmove_clock.unwrap_or(0),
+            self.fullmove_number.unwrap_or(1)
+        )
+    }
+}
+
+impl fmt::Display for EpdRecord {
+    /// Writes the record back as EPD, the opcodes with fields of their own
+    /// first.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.position)?;
+        if !self.best_moves.is_empty() {
+            write!(f, " bm {};", self.best_moves.join(" "))?;
+        }
+        if !self.avoid_moves.is_empty() {
+            write!(f, " am {};", self.avoid_moves.join(" "))?;
+        }
+        if let Some(ce) = self.centipawns {
+            write!(f, " ce {};", ce)?;
+        }
+        if let Some(hmvc) = self.halfmove_clock {
+            write!(f, " hmvc {};", hmvc)?;
+        }
+        if let Some(fmvn) = self.fullmove_number {
+            write!(f, " fmvn {};", fmvn)?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, " id \"{}\";", id)?;
+        }
+        for (opcode, operands) in &self.operations {
+            f.write_str(" ")?;
+            f.write_str(opcode)?;
+            for operand in operands {
+                if operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';') {
+                    write!(f, " \"{}\"", operand)?;
+                } else {
+                    write!(f, " {}", operand)?;
+                }
+            }
+            f.write_str(";")?;
+        }
+        Ok(())
+    }
+}
+
+/// The first whitespace separated word of `s` and what follows it.
+fn next_word(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    if s.is_empty() {
+        return None;
+    }
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    Some(s.split_at(end))
+}
+
+/// A FEN's halfmove clock and fullmove number, where they follow the
+/// position in place of operations.
+fn move_clocks(s: &str) -> Option<(u16, u16, &str)> {
+    let (halfmove, tail) = next_word(s)?;
+    let (fullmove, tail) = next_word(tail)?;
+    Some((halfmove.parse().ok()?, fullmove.parse().ok()?, tail))
+}
+
+/// The one operand of a numeric operation such as `ce`.
+fn number<T: FromStr>(opcode: &str, operands: &[String]) -> Result<T, EpdError> {
+    match operands {
+        [operand] => operand.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| EpdError::Operand {
+        opcode: opcode.to_string(),
+        operand: operands.join(" "),
+    })
+}
+
+/// Splits operations at the semicolons between them, leaving those inside
+/// quoted operands be.
+fn split_operations(s: &str) -> Result<Vec<String>, EpdError> {
+    let mut operations = vec![];
+    let mut operation = String::new();
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            ';' if !quoted => operations.push(std::mem::take(&mut operation)),
+            '"' => {
+                quoted = !quoted;
+                operation.push(c);
+            }
+            c => operation.push(c),
+        }
+    }
+    if quoted {
+        return Err(EpdError::Syntax(format!(
+            "unclosed quote in `{}`",
+            s.trim()
+        )));
+    }
+    operations.push(operation);
+    Ok(operations)
+}
+
+/// The opcode and operands of one operation, quotes taken off.
+fn operands(operation: &str) -> Result<Vec<String>, EpdError> {
+    let mut words = vec![];
+    let mut rest = operation.trim_start();
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').ok_or_else(|| {
+                EpdError::Syntax(format!("unclosed quote in `{}`", operation.trim()))
+            })?;
+            words.push(quoted[..end].to_string());
+            rest = quoted[end + 1..].trim_start();
+        } else if let Some((word, tail)) = next_word(rest) {
+            words.push(word.to_string());
+            rest = tail.trim_start();
+        }
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_test_suite_record() {
+        let line = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; \
+                    id \"WAC.001\"; c0 \"mate in 3; the queen goes\";";
+        let record = EpdRecord::parse(line).unwrap();
+        assert_eq!(
+            record.position,
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - -"
+        );
+        assert_eq!(record.best_moves, ["Qg6"]);
+        assert_eq!(record.id.as_deref(), Some("WAC.001"));
+        assert_eq!(
+            record.operation("c0"),
+            Some(&["mate in 3; the queen goes".to_string()][..])
+        );
+        assert_eq!(record.to_fen(), format!("{} 0 1", record.position));
+    }
+
+    #[test]
+    fn test_parse_typed_opcodes() {
+        let record = EpdRecord::parse(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - am Kd1 Kf1; ce -35; hmvc 12; fmvn 40; acd 10;",
+        )
+        .unwrap();
+        assert_eq!(record.avoid_moves, ["Kd1", "Kf1"]);
+        assert_eq!(record.centipawns, Some(-35));
+        assert_eq!(record.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 w - - 12 40");
+        assert_eq!(record.operation("acd"), Some(&["10".to_string()][..]));
+        assert_eq!(record.operation("bm"), None);
+
+        // perft suite style, a full FEN then operations each led by `;`
+        let record = EpdRecord::parse(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400",
+        )
+        .unwrap();
+        assert_eq!(record.fullmove_number, Some(1));
+        assert_eq!(
+            record.operations,
+            vec![
+                ("D1".to_string(), vec!["20".to_string()]),
+                ("D2".to_string(), vec!["400".to_string()])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_reads_back() {
+        let line = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6 Qh5; \
+                    ce 900; id \"WAC 001\"; c0 \"two words\"; D1 44;";
+        let record = EpdRecord::parse(line).unwrap();
+        let written = record.to_string();
+        assert_eq!(
+            written,
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6 Qh5; ce 900; \
+             id \"WAC 001\"; c0 \"two words\"; D1 44;"
+        );
+        assert_eq!(EpdRecord::parse(&written).unwrap(), record);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for line in [
+            "",
+            "8/8/8 w",
+            "4k3/8/8/8/8/8/8/8 w - - bm Ke2;",
+            "4k3/8/8/8/8/8/8/4K3 w - - id \"open;",
+            "4k3/8/8/8/8/8/8/4K3 w - - 3x 1;",
+            "4k3/8/8/8/8/8/8/4K3 w - - hmvc -1;",
+        ] {
+            assert!(EpdRecord::parse(line).is_err(), "{}", line);
+        }
+        assert!(matches!(
+            EpdRecord::parse("4k3/8/8/8/8/8/8/4K3 w - - ce lots;"),
+            Err(EpdError::Operand { .. })
+        ));
+    }
+}
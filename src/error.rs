@@ -0,0 +1,119 @@
+use std::{error, fmt, io};
+
+use crate::{
+    epd::EpdError,
+    play::error::{DrawClaimError, FENParsingError, MoveError, ParseTypeError, SanError},
+    record::pgn::PgnError,
+    uci::error::UciError,
+};
+
+/// Any of the errors the crate's modules return, for callers that would
+/// rather `?` them all into one type than match on each.
+#[derive(Debug)]
+pub enum Error {
+    Fen(FENParsingError),
+    Parse(ParseTypeError),
+    Move(MoveError),
+    San(SanError),
+    DrawClaim(DrawClaimError),
+    Epd(EpdError),
+    Pgn(PgnError),
+    Uci(UciError),
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    fn inner(&self) -> &(dyn error::Error + 'static) {
+        match self {
+            Error::Fen(err) => err,
+            Error::Parse(err) => err,
+            Error::Move(err) => err,
+            Error::San(err) => err,
+            Error::DrawClaim(err) => err,
+            Error::Epd(err) => err,
+            Error::Pgn(err) => err,
+            Error::Uci(err) => err,
+            Error::Io(err) => err,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.inner(), f)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(self.inner())
+    }
+}
+
+macro_rules! impl_from {
+    ($($variant:ident($err:ty)),* $(,)?) => {
+        $(
+            impl From<$err> for Error {
+                fn from(err: $err) -> Self {
+                    Error::$variant(err)
+                }
+            }
+        )*
+    };
+}
+
+impl_from!(
+    Fen(FENParsingError),
+    Parse(ParseTypeError),
+    Move(MoveError),
+    San(SanError),
+    DrawClaim(DrawClaimError),
+    Epd(EpdError),
+    Pgn(PgnError),
+    Uci(UciError),
+    Io(io::Error),
+);
+
+#[cfg(test)]
+mod tests {
+
+    use std::error::Error as _;
+
+    use super::*;
+    use crate::play::{
+        error::{FENErrorKind, InvalidPosition},
+        types::Square,
+        GameState,
+    };
+
+    fn parse(fen: &str) -> Result<GameState> {
+        Ok(GameState::from_fen(fen)?)
+    }
+
+    #[test]
+    fn test_conversions() {
+        let err = parse("8/8/8/8/8/8/8/8 w - - 0 1").unwrap_err();
+        assert!(err.to_string().contains("White has 0 kings"));
+        let fen = match &err {
+            Error::Fen(fen) => fen,
+            err => panic!("expected a FEN error, got {:?}", err),
+        };
+        assert!(matches!(
+            fen.kind(),
+            FENErrorKind::InvalidPosition(InvalidPosition::KingCount(..))
+        ));
+        // the position's fault is the root cause
+        let root = err.source().and_then(|fen| fen.source()).unwrap();
+        assert_eq!(root.to_string(), "White has 0 kings, expected one");
+
+        let err = Error::from(Square::from_fen("e9").unwrap_err());
+        assert_eq!(err.to_string(), "Error parsing fen: invalid char '9'");
+        let err = Error::from(Square::from_fen("e").unwrap_err());
+        assert_eq!(err.to_string(), "Error parsing fen: invalid square e");
+
+        let err = Error::from("e9".parse::<Square>().unwrap_err());
+        assert_eq!(err.to_string(), "invalid square \"e9\"");
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+
+use crate::play::{
+    constants::{FILES, RANKS, SQUARES},
+    types::{Color, Square},
+    GameState,
+};
+
+use super::{pst::piece_square, Evaluator};
+
+/// How hot a square is, from cold to hottest, by its value's size against
+/// the biggest on the map.
+const SHADES: &[u8] = b" .:-=+*#%@";
+/// What each attacker on a king's zone counts for, pawns to kings, the
+/// weights a king safety term would go by.
+const KING_ATTACK_WEIGHTS: [i32; 6] = [1, 2, 2, 3, 5, 0];
+
+/// A value for each square, drawn as a board of numbers beside one shaded
+/// by their size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    pub title: &'static str,
+    /// indexed by square, a1 first
+    pub values: [i32; 64],
+}
+
+impl Heatmap {
+    fn new(title: &'static str, value: impl Fn(Square) -> i32) -> Self {
+        let mut values = [0; 64];
+        for sq in SQUARES {
+            values[sq as usize] = value(sq);
+        }
+        Heatmap { title, values }
+    }
+
+    pub fn value(&self, sq: Square) -> i32 {
+        self.values[sq as usize]
+    }
+
+    fn shade(&self, value: i32) -> char {
+        let max = self.values.iter().map(|v| v.abs()).max().unwrap_or(0);
+        if max == 0 || value == 0 {
+            return ' ';
+        }
+        // anything but 0 gets at least the faintest shade
+        let steps = SHADES.len() as i32 - 1;
+        let idx = (value.abs() * steps + max - 1) / max;
+        SHADES[idx as usize] as char
+    }
+}
+
+/// The board from White's side, with a `.` and a blank shade standing
+/// for 0.
+impl fmt::Display for Heatmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.title)?;
+        for &rank in RANKS.iter().rev() {
+            let mut numbers = String::new();
+            let mut shades = String::new();
+            for &file in FILES.iter() {
+                let value = self.value(Square::new(file, rank));
+                if value == 0 {
+                    numbers.push_str("    .");
+                } else {
+                    numbers.push_str(&format!("{:>5}", value));
+                }
+                shades.push(self.shade(value));
+            }
+            writeln!(f, "{} {}   |{}|", rank as u8, numbers, shades)?;
+        }
+        let files: String = FILES
+            .iter()
+            .map(|file| format!("{:>5}", char::from(file)))
+            .collect();
+        let letters: String = FILES.iter().map(char::from).collect();
+        write!(f, "  {}    {}", files, letters)
+    }
+}
+
+/// The enemy pieces attacking a square, each weighted by its type.
+fn weighted_attackers(state: &GameState, sq: Square, color: Color) -> i32 {
+    let board = &state.position.board;
+    board
+        .attackers_bitboard(sq, color)
+        .iter()
+        .filter_map(|from| board.piece(&from))
+        .map(|piece| KING_ATTACK_WEIGHTS[piece.piece_type() as usize])
+        .sum()
+}
+
+impl Evaluator {
+    /// How many of `color`'s pieces attack each square.
+    pub fn attack_heatmap(&self, state: &GameState, color: Color) -> Heatmap {
+        let title = match color {
+            Color::White => "Attacks by White",
+            Color::Black => "Attacks by Black",
+        };
+        let board = &state.position.board;
+        Heatmap::new(title, |sq| {
+            board.attackers_bitboard(sq, color).pop_count() as i32
+        })
+    }
+
+    /// The piece-square bonus of the piece on each square, tapered by the
+    /// game phase, White's point of view.
+    pub fn pst_heatmap(&self, state: &GameState) -> Heatmap {
+        let board = &state.position.board;
+        Heatmap::new("Piece-square bonuses, White's point of view", |sq| {
+            let piece = match board.piece(&sq) {
+                Some(piece) => piece,
+                None => return 0,
+            };
+            let bonus = piece_square(piece, sq).taper(state.phase);
+            match piece.color() {
+                Color::White => bonus,
+                Color::Black => -bonus,
+            }
+        })
+    }
+
+    /// The squares around each king, weighted by the enemy pieces attacking
+    /// them and scaled by the king safety weight, White's point of view: a
+    /// threatened white king's zone shows negative.
+    pub fn king_danger_heatmap(&self, state: &GameState) -> Heatmap {
+        let board = &state.position.board;
+        let kings: Vec<(Color, Square)> = [Color::White, Color::Black]
+            .iter()
+            .filter_map(|&color| board.king_square(color).map(|king| (color, king)))
+            .collect();
+        let percent = self.weights.king_safety as i32;
+        Heatmap::new("King danger, White's point of view", |sq| {
+            kings
+                .iter()
+                .filter(|(_, king)| king.distance(sq) <= 1)
+                .map(|&(color, _)| {
+                    let danger = weighted_attackers(state, sq, color.opposing()) * percent / 100;
+                    match color {
+                        Color::White => -danger,
+                        Color::Black => danger,
+                    }
+                })
+                .sum()
+        })
+    }
+
+    /// Every heatmap of the position.
+    pub fn heatmaps(&self, state: &GameState) -> Vec<Heatmap> {
+        vec![
+            self.attack_heatmap(state, Color::White),
+            self.attack_heatmap(state, Color::Black),
+            self.pst_heatmap(state),
+            self.king_danger_heatmap(state),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        eval::EvalWeights,
+        play::{
+            r#move::{make_move, Move},
+            variant::Variant,
+        },
+    };
+
+    #[test]
+    fn test_attack_heatmap() {
+        let state = GameState::default();
+        let eval = Evaluator::default();
+        let white = eval.attack_heatmap(&state, Color::White);
+        // f3 is covered by the e2 and g2 pawns and the g1 knight
+        assert_eq!(white.value(Square::F3), 3);
+        assert_eq!(white.value(Square::E4), 0);
+        let black = eval.attack_heatmap(&state, Color::Black);
+        assert_eq!(black.value(Square::C6), 3);
+        assert_eq!(
+            white.values.iter().sum::<i32>(),
+            black.values.iter().sum::<i32>()
+        );
+
+        let map = white.to_string();
+        let lines: Vec<&str> = map.lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0], "Attacks by White");
+        assert_eq!(
+            lines[6],
+            "3     2    2    3    2    2    3    2    2   |++#++#++|"
+        );
+        assert!(lines[4].ends_with("|        |"));
+        assert!(lines[9].ends_with("h    abcdefgh"));
+    }
+
+    #[test]
+    fn test_pst_heatmap() {
+        let state = GameState::default();
+        let map = Evaluator::default().pst_heatmap(&state);
+        // the start position is symmetrical
+        assert_eq!(map.values.iter().sum::<i32>(), 0);
+        assert_eq!(map.value(Square::E1), -map.value(Square::E8));
+        assert_eq!(map.value(Square::E4), 0);
+    }
+
+    #[test]
+    fn test_king_danger_heatmap() {
+        let state = GameState::from_fen("6k1/5ppp/8/8/2B5/8/1Q6/6K1 w - - 0 1").unwrap();
+        let eval = Evaluator::default();
+        let map = eval.king_danger_heatmap(&state);
+        // the bishop hits f7 and the queen g7, while nothing nears White's
+        assert_eq!(map.value(Square::F7), 2);
+        assert_eq!(map.value(Square::G7), 5);
+        assert_eq!(map.value(Square::A1), 0);
+        assert!(map.values.iter().all(|&v| v >= 0));
+
+        let doubled = Evaluator::new(EvalWeights {
+            king_safety: 200,
+            ..EvalWeights::default()
+        });
+        assert_eq!(doubled.king_danger_heatmap(&state).value(Square::G7), 10);
+        assert_eq!(eval.heatmaps(&state).len(), 4);
+
+        // only the black king's zone is left to map once an antichess king
+        // is taken
+        let mut state = GameState::from_fen("k7/8/8/8/8/8/2q4P/K7 w - - 0 1")
+            .unwrap()
+            .with_variant(Variant::Antichess);
+        for san in ["Ka2", "Qxa2"] {
+            let mv = Move::from_san(&state.position, san).unwrap();
+            make_move(mv, &mut state).unwrap();
+        }
+        let map = eval.king_danger_heatmap(&state);
+        assert!(map.values.iter().all(|&v| v >= 0));
+        assert_eq!(eval.heatmaps(&state).len(), 4);
+    }
+}
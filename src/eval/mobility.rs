@@ -0,0 +1,105 @@
+use crate::play::{
+    types::{Color, Piece, PieceType},
+    GameState, FILE_MASKS,
+};
+
+use super::Score;
+
+/// Per safe square, by piece type, less the square count a piece of that
+/// type typically has, so an ordinary piece scores about nothing.
+const MOBILITY: [(PieceType, Score, i32); 4] = [
+    (PieceType::Knight, Score::new(4, 4), 4),
+    (PieceType::Bishop, Score::new(5, 5), 6),
+    (PieceType::Rook, Score::new(2, 4), 7),
+    (PieceType::Queen, Score::new(1, 2), 13),
+];
+const ROOK_OPEN_FILE: Score = Score::new(25, 10);
+const ROOK_SEMI_OPEN_FILE: Score = Score::new(10, 5);
+const BISHOP_PAIR: Score = Score::new(30, 50);
+
+/// Safe squares the minor and major pieces reach, for one side from its own
+/// point of view. A square is safe if it's empty or an enemy's and no enemy
+/// pawn covers it.
+pub(super) fn side_mobility(state: &GameState, color: Color) -> Score {
+    let board = &state.position.board;
+    let safe = !board.occupied_by(color).0 & !board.pawn_attacks(color.opposing()).0;
+    let mut score = Score::default();
+    for (piece_type, per_square, typical) in MOBILITY {
+        for sq in board.bitboard(Piece::of(piece_type, color)) {
+            let squares = (board.piece_attacks(piece_type, sq).0 & safe).count_ones() as i32;
+            let extra = squares - typical;
+            score += Score::new(per_square.mg * extra, per_square.eg * extra);
+        }
+    }
+    score
+}
+
+/// Rooks on files free of pawns, or of their own, and the bishop pair, for
+/// one side from its own point of view.
+pub(super) fn side_activity(state: &GameState, color: Color) -> Score {
+    let board = &state.position.board;
+    let own_pawns = board.bitboard(Piece::of(PieceType::Pawn, color)).0;
+    let enemy_pawns = board
+        .bitboard(Piece::of(PieceType::Pawn, color.opposing()))
+        .0;
+    let mut score = Score::default();
+    for sq in board.bitboard(Piece::of(PieceType::Rook, color)) {
+        let file = FILE_MASKS[sq as usize % 8].0;
+        if own_pawns & file == 0 {
+            score += if enemy_pawns & file == 0 {
+                ROOK_OPEN_FILE
+            } else {
+                ROOK_SEMI_OPEN_FILE
+            };
+        }
+    }
+    if board
+        .bitboard(Piece::of(PieceType::Bishop, color))
+        .pop_count()
+        >= 2
+    {
+        score += BISHOP_PAIR;
+    }
+    score
+}
+
+/// Mobility, White's less Black's.
+pub fn mobility(state: &GameState) -> Score {
+    side_mobility(state, Color::White) - side_mobility(state, Color::Black)
+}
+
+/// Rook file and bishop pair bonuses, White's less Black's.
+pub fn piece_activity(state: &GameState) -> Score {
+    side_activity(state, Color::White) - side_activity(state, Color::Black)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn state(fen: &str) -> GameState {
+        GameState::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn test_mobility() {
+        // a centralised knight against one in the corner
+        let score = mobility(&state("n3k3/8/8/8/4N3/8/8/4K3 w - - 0 1"));
+        assert_eq!(score, Score::new(4 * (8 - 2), 4 * (8 - 2)));
+        // squares a pawn covers don't count
+        let covered = mobility(&state("4k3/8/3p1p2/8/4N3/8/8/4K3 w - - 0 1"));
+        let free = mobility(&state("4k3/8/8/8/4N3/8/8/4K3 w - - 0 1"));
+        assert!(covered.mg < free.mg);
+        assert_eq!(mobility(&GameState::default()), Score::default());
+    }
+
+    #[test]
+    fn test_piece_activity() {
+        // an open file for white's rook, a half-open one for black's
+        let score = piece_activity(&state("3rk3/8/8/3P4/8/8/8/R3K3 w - - 0 1"));
+        assert_eq!(score, ROOK_OPEN_FILE - ROOK_SEMI_OPEN_FILE);
+        let pair = piece_activity(&state("2b1k3/8/8/8/8/8/8/2B1KB2 w - - 0 1"));
+        assert_eq!(pair, BISHOP_PAIR);
+    }
+}
@@ -0,0 +1,274 @@
+pub mod heatmap;
+mod mobility;
+#[cfg(feature = "nnue")]
+pub mod nnue;
+mod passed;
+pub mod pawns;
+mod pst;
+mod scaling;
+mod threats;
+mod trace;
+
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+use crate::play::{
+    types::{Color, PieceType},
+    GameState, MAX_PHASE, PIECES, PIECE_VALUES,
+};
+
+use self::{
+    mobility::{mobility, piece_activity},
+    passed::passed_pawns,
+    pawns::PawnTable,
+    pst::piece_square,
+    scaling::{scale_factor, SCALE_NORMAL},
+    threats::threats,
+};
+
+/// Percentages the positional evaluation terms are scaled by, 100 leaving
+/// them as tuned. King safety isn't evaluated yet, so its weight is carried
+/// for the term to come.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalWeights {
+    pub king_safety: u32,
+    pub mobility: u32,
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        EvalWeights {
+            king_safety: 100,
+            mobility: 100,
+        }
+    }
+}
+
+/// A score in centipawns as it stands in the midgame and in the endgame,
+/// blended by how far the game has gone between the two.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Score { mg, eg }
+    }
+
+    /// The score scaled by `percent`, as an [`EvalWeights`] weight.
+    pub fn scaled(self, percent: u32) -> Score {
+        let percent = percent as i32;
+        Score::new(self.mg * percent / 100, self.eg * percent / 100)
+    }
+
+    /// The blend of the two at `phase`, all midgame at [`MAX_PHASE`] and all
+    /// endgame at 0. Phases past the maximum, e.g. after promotions, count
+    /// as the maximum.
+    pub fn taper(self, phase: u8) -> i32 {
+        let phase = phase.min(MAX_PHASE) as i32;
+        let max = MAX_PHASE as i32;
+        (self.mg * phase + self.eg * (max - phase)) / max
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, other: Score) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Score {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+/// The endgame scale factor for the side `score` has ahead, and the score,
+/// White's less Black's, with its endgame part so scaled and tapered by the
+/// state's game phase.
+fn scale_and_taper(state: &GameState, score: Score) -> (i32, i32) {
+    let strong = if score.eg >= 0 {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let scale = scale_factor(state, strong);
+    let eg = score.eg * scale / SCALE_NORMAL;
+    (scale, Score::new(score.mg, eg).taper(state.phase))
+}
+
+/// Material and piece-square bonuses, White's less Black's. Kings carry no
+/// material, there being one a side.
+pub fn material_and_placement(state: &GameState) -> Score {
+    let board = &state.position.board;
+    let mut score = Score::default();
+    for piece in PIECES {
+        let value = match piece.piece_type() {
+            PieceType::King => 0,
+            _ => PIECE_VALUES[piece as usize] as i32,
+        };
+        for sq in board.bitboard(piece) {
+            let term = Score::new(value, value) + piece_square(piece, sq);
+            match piece.color() {
+                Color::White => score += term,
+                Color::Black => score -= term,
+            }
+        }
+    }
+    score
+}
+
+/// What each piece left on the board costs its side in antichess.
+pub(super) const GIVEAWAY_PIECE: i32 = 100;
+
+/// The antichess evaluation, from the side to move's perspective. Every
+/// piece is one more to give away, so the side with fewer left is ahead;
+/// the usual terms, built around material to keep and a king to shelter,
+/// don't apply.
+fn giveaway(state: &GameState) -> i32 {
+    let board = &state.position.board;
+    let color = state.position.side_to_move;
+    let count = |color| board.occupied_by(color).pop_count() as i32;
+    (count(color.opposing()) - count(color)) * GIVEAWAY_PIECE
+}
+
+/// Evaluates positions with a set of weights, keeping the pawn structures
+/// it has scored. The default evaluator has an empty pawn table, which
+/// scores every structure afresh. Built with the `nnue` feature, an
+/// evaluator made while a network is loaded evaluates with that instead.
+#[derive(Debug, Default)]
+pub struct Evaluator {
+    pub weights: EvalWeights,
+    pawns: PawnTable,
+    #[cfg(feature = "nnue")]
+    nnue: Option<nnue::NnueEvaluator>,
+}
+
+impl Evaluator {
+    pub fn new(weights: EvalWeights) -> Self {
+        Evaluator {
+            weights,
+            pawns: PawnTable::new(),
+            #[cfg(feature = "nnue")]
+            nnue: nnue::network().map(nnue::NnueEvaluator::new),
+        }
+    }
+
+    /// Every term, White's less Black's, before tapering.
+    pub fn score(&mut self, state: &GameState) -> Score {
+        let pawns = self.pawns.probe(state);
+        material_and_placement(state)
+            + pawns.score
+            + passed_pawns(state, pawns.passed)
+            + mobility(state).scaled(self.weights.mobility)
+            + piece_activity(state)
+            + threats(state)
+    }
+
+    /// Static evaluation from the side to move's perspective, tapered by
+    /// the state's game phase. The endgame part is first scaled down where
+    /// the material is drawish for the side ahead. Antichess positions are
+    /// counted piece by piece instead.
+    pub fn evaluate(&mut self, state: &GameState) -> i32 {
+        if !state.position.variant.royal_king() {
+            return giveaway(state);
+        }
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &mut self.nnue {
+            return nnue.evaluate(state);
+        }
+        let (_, score) = scale_and_taper(state, self.score(state));
+        match state.position.side_to_move {
+            Color::White => score,
+            Color::Black => -score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::START_FEN;
+
+    #[test]
+    fn test_start_position_is_level() {
+        let state = GameState::from_fen(START_FEN).unwrap();
+        assert_eq!(state.phase, MAX_PHASE);
+        assert_eq!(Evaluator::default().evaluate(&state), 0);
+    }
+
+    #[test]
+    fn test_evaluate_is_symmetric() {
+        // the same position with colors swapped and the board mirrored
+        let white = GameState::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        )
+        .unwrap();
+        let black = GameState::from_fen(
+            "rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4",
+        )
+        .unwrap();
+        let mut eval = Evaluator::default();
+        assert_eq!(eval.evaluate(&white), eval.evaluate(&black));
+        assert_ne!(eval.evaluate(&white), 0);
+    }
+
+    #[test]
+    fn test_mobility_weight() {
+        let state = GameState::from_fen("n3k3/8/8/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+        let without = Evaluator::new(EvalWeights {
+            mobility: 0,
+            ..EvalWeights::default()
+        })
+        .evaluate(&state);
+        let doubled = Evaluator::new(EvalWeights {
+            mobility: 200,
+            ..EvalWeights::default()
+        })
+        .evaluate(&state);
+        let tuned = Evaluator::default().evaluate(&state);
+        assert!(without < tuned && tuned < doubled);
+        assert_eq!(doubled - tuned, tuned - without);
+    }
+
+    #[test]
+    fn test_taper() {
+        let score = Score::new(100, -20);
+        assert_eq!(score.taper(MAX_PHASE), 100);
+        assert_eq!(score.taper(0), -20);
+        assert_eq!(score.taper(MAX_PHASE / 2), 40);
+        assert_eq!(score.taper(MAX_PHASE + 4), 100);
+
+        // a king and pawn ending is scored by its endgame terms alone
+        let state = GameState::from_fen("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1").unwrap();
+        assert_eq!(state.phase, 0);
+        let mut eval = Evaluator::default();
+        assert_eq!(eval.evaluate(&state), eval.score(&state).eg);
+    }
+}
@@ -0,0 +1,233 @@
+mod network;
+
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::play::{
+    dirty::DirtyPieces,
+    types::{Color, Piece, PieceType, Square},
+    GameState, PIECES,
+};
+
+pub use self::network::Network;
+use self::network::{FEATURES, HALF_DIMENSIONS};
+
+/// The network loaded with `setoption name EvalFile`, which evaluators made
+/// from then on use.
+static NETWORK: RwLock<Option<Arc<Network>>> = RwLock::new(None);
+
+/// How many moves back an accumulator is looked for to update from, before
+/// working it out afresh is cheaper.
+const MAX_UPDATE_PLIES: usize = 8;
+
+pub fn network() -> Option<Arc<Network>> {
+    NETWORK
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Loads the network at `path` for evaluators to come, or with an empty
+/// path goes back to the classical evaluation.
+pub fn load_network(path: &str) -> std::io::Result<()> {
+    let network = match path {
+        "" => None,
+        path => Some(Arc::new(Network::load(path)?)),
+    };
+    *NETWORK.write().unwrap_or_else(PoisonError::into_inner) = network;
+    Ok(())
+}
+
+/// The first layer's output for both sides' perspectives, White's first.
+type Accumulator = [[i16; HALF_DIMENSIONS]; 2];
+
+/// The input a piece on a square is for one side's perspective. Black sees
+/// the board turned around, and each side sees its own pieces as White's.
+fn feature(perspective: Color, king: Square, piece: Piece, sq: Square) -> usize {
+    let orient = |sq: Square| match perspective {
+        Color::White => sq as usize,
+        Color::Black => sq as usize ^ 63,
+    };
+    let theirs = (piece.color() != perspective) as usize;
+    let piece_index = 1 + 128 * piece.piece_type() as usize + 64 * theirs;
+    orient(king) * (FEATURES / 64) + piece_index + orient(sq)
+}
+
+/// A [`Network`] evaluating positions with accumulators kept by search
+/// ply, each updated from the one before it by the pieces the moves in
+/// between changed.
+#[derive(Debug)]
+pub struct NnueEvaluator {
+    network: Arc<Network>,
+    /// the position key each accumulator is for
+    accumulators: Vec<Option<(u64, Accumulator)>>,
+}
+
+impl NnueEvaluator {
+    pub fn new(network: Arc<Network>) -> Self {
+        NnueEvaluator {
+            network,
+            accumulators: vec![],
+        }
+    }
+
+    fn add(&self, half: &mut [i16; HALF_DIMENSIONS], feature: usize, sign: i16) {
+        let weights = &self.network.feature_weights[feature * HALF_DIMENSIONS..];
+        for (v, w) in half.iter_mut().zip(weights) {
+            *v = v.wrapping_add(sign.wrapping_mul(*w));
+        }
+    }
+
+    /// One perspective's half worked out from every piece on the board.
+    fn refresh(&self, state: &GameState, perspective: Color) -> [i16; HALF_DIMENSIONS] {
+        let board = &state.position.board;
+        let mut half = [0; HALF_DIMENSIONS];
+        half.copy_from_slice(&self.network.feature_biases);
+        // every input is relative to the king, so a side without one has
+        // nothing but its biases
+        let king = match board.king_square(perspective) {
+            Some(king) => king,
+            None => return half,
+        };
+        for &piece in PIECES.iter() {
+            if piece.piece_type() == PieceType::King {
+                continue;
+            }
+            for sq in board.bitboard(piece) {
+                self.add(&mut half, feature(perspective, king, piece, sq), 1);
+            }
+        }
+        half
+    }
+
+    /// `previous` brought up to date with the pieces `moves` changed. A side
+    /// whose king moved has its half worked out afresh instead, every one
+    /// of its inputs having changed, as do both after a move too big to
+    /// list.
+    fn update(
+        &self,
+        state: &GameState,
+        previous: &Accumulator,
+        moves: &[DirtyPieces],
+    ) -> Accumulator {
+        let board = &state.position.board;
+        let mut accumulator = *previous;
+        for (i, perspective) in [Color::White, Color::Black].iter().copied().enumerate() {
+            let own_king = Piece::of(PieceType::King, perspective);
+            if moves.iter().any(|m| m.is_everything())
+                || moves
+                    .iter()
+                    .flat_map(|m| m.iter())
+                    .any(|d| d.piece == own_king)
+            {
+                accumulator[i] = self.refresh(state, perspective);
+                continue;
+            }
+            let king = match board.king_square(perspective) {
+                Some(king) => king,
+                None => continue,
+            };
+            for dirty in moves.iter().flat_map(|m| m.iter()) {
+                if dirty.piece.piece_type() == PieceType::King {
+                    continue;
+                }
+                if let Some(from) = dirty.from {
+                    let f = feature(perspective, king, dirty.piece, from);
+                    self.add(&mut accumulator[i], f, -1);
+                }
+                if let Some(to) = dirty.to {
+                    let f = feature(perspective, king, dirty.piece, to);
+                    self.add(&mut accumulator[i], f, 1);
+                }
+            }
+        }
+        accumulator
+    }
+
+    /// The accumulator for `state`, from an earlier ply's where one of the
+    /// last few positions has it, else afresh.
+    fn accumulator(&mut self, state: &GameState) -> Accumulator {
+        let ply = state.search_ply as usize;
+        if self.accumulators.len() <= ply {
+            self.accumulators.resize(ply + 1, None);
+        }
+        if let Some((key, accumulator)) = self.accumulators[ply] {
+            if key == state.position_key {
+                return accumulator;
+            }
+        }
+
+        let keys = &state.key_history;
+        let moves = &state.dirty_pieces;
+        let back = ply.min(keys.len()).min(moves.len()).min(MAX_UPDATE_PLIES);
+        let earlier = (1..=back).find_map(|n| match self.accumulators[ply - n] {
+            Some((key, accumulator)) if key == keys[keys.len() - n] => Some((n, accumulator)),
+            _ => None,
+        });
+        let accumulator = match earlier {
+            Some((n, previous)) => self.update(state, &previous, &moves[moves.len() - n..]),
+            None => [
+                self.refresh(state, Color::White),
+                self.refresh(state, Color::Black),
+            ],
+        };
+        self.accumulators[ply] = Some((state.position_key, accumulator));
+        accumulator
+    }
+
+    /// Static evaluation from the side to move's perspective.
+    pub fn evaluate(&mut self, state: &GameState) -> i32 {
+        let [white, black] = self.accumulator(state);
+        match state.position.side_to_move {
+            Color::White => self.network.propagate(&white, &black),
+            Color::Black => self.network.propagate(&black, &white),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::r#move::make_move;
+
+    #[test]
+    fn test_feature_index() {
+        // a pawn in front of its king, seen by each side with the board
+        // turned around, is the same input
+        let white = feature(Color::White, Square::E1, Piece::WhitePawn, Square::E2);
+        let black = feature(Color::Black, Square::D8, Piece::BlackPawn, Square::D7);
+        assert_eq!(white, black);
+        assert_eq!(white, 4 * 641 + 1 + 12);
+        let theirs = feature(Color::White, Square::E1, Piece::BlackQueen, Square::D8);
+        assert_eq!(theirs, 4 * 641 + 1 + 4 * 128 + 64 + 59);
+        assert!(feature(Color::Black, Square::A1, Piece::WhiteQueen, Square::A1) < FEATURES);
+    }
+
+    #[test]
+    fn test_updates_match_refresh() {
+        let network = Arc::new(Network::random(3));
+        let mut incremental = NnueEvaluator::new(network.clone());
+        // castling both ways, en passant and a capturing promotion
+        let mut state = GameState::from_fen("n3k2r/1P4p1/8/5P2/8/8/8/R3K2R b KQk - 0 1").unwrap();
+        incremental.evaluate(&state);
+        for uci in ["g7g5", "f5g6", "e8g8", "b7a8q", "f8a8", "e1c1", "g8g7"] {
+            let mv = state.position.parse_move(uci).unwrap();
+            make_move(mv, &mut state).unwrap();
+            let fresh = NnueEvaluator::new(network.clone()).evaluate(&state);
+            assert_eq!(incremental.evaluate(&state), fresh, "after {}", uci);
+        }
+    }
+
+    #[test]
+    fn test_side_to_move() {
+        let network = Arc::new(Network::random(5));
+        let white = GameState::from_fen("4k3/8/8/3q4/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = GameState::from_fen("3k4/8/8/8/4Q3/8/8/3K4 b - - 0 1").unwrap();
+        // the same position turned around with the colours swapped scores
+        // the same
+        assert_eq!(
+            NnueEvaluator::new(network.clone()).evaluate(&white),
+            NnueEvaluator::new(network).evaluate(&black)
+        );
+    }
+}
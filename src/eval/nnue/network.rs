@@ -0,0 +1,253 @@
+use std::{
+    fmt, fs,
+    io::{self, Write},
+};
+
+/// The version word every network file of this architecture starts with.
+const VERSION: u32 = 0x7AF3_2F16;
+
+/// One feature per king square, for each of the ten non-king pieces on
+/// each square, plus one unused feature per king square, as Stockfish
+/// numbers them.
+pub const FEATURES: usize = 64 * (10 * 64 + 1);
+/// Neurons in each side's half of the first layer, the accumulator.
+pub const HALF_DIMENSIONS: usize = 256;
+const HIDDEN: usize = 32;
+
+/// The accumulator is clipped to `0..=127` on the way into the next layer,
+/// and each layer's output is shifted down by this much before it is.
+const WEIGHT_SCALE_BITS: u32 = 6;
+/// The output divided by this is in centipawns.
+const OUTPUT_SCALE: i32 = 16;
+
+/// A HalfKP network, 41024 inputs a side into 256, then two hidden layers
+/// of 32 and one output, read from the files Stockfish 12 and engines
+/// following it use.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Network {
+    pub description: String,
+    pub(super) feature_biases: Vec<i16>,
+    /// `HALF_DIMENSIONS` weights per feature, feature by feature
+    pub(super) feature_weights: Vec<i16>,
+    hidden1_biases: Vec<i32>,
+    hidden1_weights: Vec<i8>,
+    hidden2_biases: Vec<i32>,
+    hidden2_weights: Vec<i8>,
+    output_bias: i32,
+    output_weights: Vec<i8>,
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Network")
+            .field("description", &self.description)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads the little-endian values a network file is made of.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < n {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "network file ends early",
+            ));
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i16s(&mut self, n: usize) -> io::Result<Vec<i16>> {
+        Ok(self
+            .take(2 * n)?
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect())
+    }
+
+    fn i32s(&mut self, n: usize) -> io::Result<Vec<i32>> {
+        Ok(self
+            .take(4 * n)?
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+
+    fn i8s(&mut self, n: usize) -> io::Result<Vec<i8>> {
+        Ok(self.take(n)?.iter().map(|b| *b as i8).collect())
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Network {
+    /// Reads a network from a file's bytes. The hashes before each part
+    /// are skipped rather than checked, the sizes being checked instead.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Network> {
+        let mut reader = Reader { bytes };
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(invalid(format!("unknown network version {:#x}", version)));
+        }
+        reader.u32()?;
+        let description_len = reader.u32()? as usize;
+        let description = String::from_utf8_lossy(reader.take(description_len)?).into_owned();
+
+        reader.u32()?;
+        let feature_biases = reader.i16s(HALF_DIMENSIONS)?;
+        let feature_weights = reader.i16s(HALF_DIMENSIONS * FEATURES)?;
+        reader.u32()?;
+        let hidden1_biases = reader.i32s(HIDDEN)?;
+        let hidden1_weights = reader.i8s(HIDDEN * 2 * HALF_DIMENSIONS)?;
+        let hidden2_biases = reader.i32s(HIDDEN)?;
+        let hidden2_weights = reader.i8s(HIDDEN * HIDDEN)?;
+        let output_bias = reader.i32s(1)?[0];
+        let output_weights = reader.i8s(HIDDEN)?;
+        if !reader.bytes.is_empty() {
+            return Err(invalid(format!(
+                "{} bytes left over after the network",
+                reader.bytes.len()
+            )));
+        }
+        Ok(Network {
+            description,
+            feature_biases,
+            feature_weights,
+            hidden1_biases,
+            hidden1_weights,
+            hidden2_biases,
+            hidden2_weights,
+            output_bias,
+            output_weights,
+        })
+    }
+
+    pub fn load(path: &str) -> io::Result<Network> {
+        Network::from_bytes(&fs::read(path)?)
+    }
+
+    /// Writes the network as it's read, with zero for each hash.
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(&(self.description.len() as u32).to_le_bytes())?;
+        out.write_all(self.description.as_bytes())?;
+        out.write_all(&0u32.to_le_bytes())?;
+        for v in self.feature_biases.iter().chain(&self.feature_weights) {
+            out.write_all(&v.to_le_bytes())?;
+        }
+        out.write_all(&0u32.to_le_bytes())?;
+        for (biases, weights) in [
+            (&self.hidden1_biases, &self.hidden1_weights),
+            (&self.hidden2_biases, &self.hidden2_weights),
+            (&vec![self.output_bias], &self.output_weights),
+        ] {
+            for v in biases {
+                out.write_all(&v.to_le_bytes())?;
+            }
+            let bytes: Vec<u8> = weights.iter().map(|w| *w as u8).collect();
+            out.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+
+    /// The evaluation in centipawns from the accumulator halves of the side
+    /// to move and of its opponent.
+    pub fn propagate(&self, own: &[i16; HALF_DIMENSIONS], other: &[i16; HALF_DIMENSIONS]) -> i32 {
+        let input: Vec<i32> = own
+            .iter()
+            .chain(other.iter())
+            .map(|v| (*v as i32).clamp(0, 127))
+            .collect();
+        let hidden1 = affine(&self.hidden1_biases, &self.hidden1_weights, &input);
+        let hidden1: Vec<i32> = hidden1.into_iter().map(clipped_relu).collect();
+        let hidden2 = affine(&self.hidden2_biases, &self.hidden2_weights, &hidden1);
+        let hidden2: Vec<i32> = hidden2.into_iter().map(clipped_relu).collect();
+        let output = affine(&[self.output_bias], &self.output_weights, &hidden2)[0];
+        output / OUTPUT_SCALE
+    }
+
+    /// A network with weights drawn from `seed`, small enough that no
+    /// accumulator overflows, for checking the evaluation's plumbing.
+    #[cfg(test)]
+    pub fn random(seed: u64) -> Network {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut i16s = |n, range: i16| (0..n).map(|_| rng.gen_range(-range..=range)).collect();
+        let feature_biases = i16s(HALF_DIMENSIONS, 64);
+        let feature_weights = i16s(HALF_DIMENSIONS * FEATURES, 16);
+        let mut i8s = |n| (0..n).map(|_| rng.gen_range(-64..=64)).collect();
+        Network {
+            description: format!("random {}", seed),
+            feature_biases,
+            feature_weights,
+            hidden1_biases: vec![100; HIDDEN],
+            hidden1_weights: i8s(HIDDEN * 2 * HALF_DIMENSIONS),
+            hidden2_biases: vec![100; HIDDEN],
+            hidden2_weights: i8s(HIDDEN * HIDDEN),
+            output_bias: 0,
+            output_weights: i8s(HIDDEN),
+        }
+    }
+}
+
+/// `weights` holds a row of `input.len()` per output.
+fn affine(biases: &[i32], weights: &[i8], input: &[i32]) -> Vec<i32> {
+    biases
+        .iter()
+        .zip(weights.chunks_exact(input.len()))
+        .map(|(bias, row)| {
+            bias + row
+                .iter()
+                .zip(input)
+                .map(|(w, x)| *w as i32 * x)
+                .sum::<i32>()
+        })
+        .collect()
+}
+
+fn clipped_relu(v: i32) -> i32 {
+    (v >> WEIGHT_SCALE_BITS).clamp(0, 127)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_network_round_trip() {
+        let network = Network::random(1);
+        let mut bytes = vec![];
+        network.write(&mut bytes).unwrap();
+        assert_eq!(Network::from_bytes(&bytes).unwrap(), network);
+
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        bytes.push(0);
+        assert!(Network::from_bytes(&bytes).is_err());
+        bytes[0] ^= 1;
+        assert!(Network::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_affine() {
+        assert_eq!(affine(&[1, -1], &[1, 2, 3, 4], &[10, 100]), vec![211, 429]);
+        assert_eq!(clipped_relu(64 * 200), 127);
+        assert_eq!(clipped_relu(-5), 0);
+        assert_eq!(clipped_relu(64 * 3 + 10), 3);
+    }
+}
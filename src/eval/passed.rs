@@ -0,0 +1,105 @@
+use crate::play::{
+    types::{Color, Piece, PieceType, Square},
+    Bitboard, GameState,
+};
+
+use super::Score;
+
+/// By rank counted from the pawn's own side, the first and last unused.
+const PASSED: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(5, 10),
+    Score::new(5, 15),
+    Score::new(10, 25),
+    Score::new(20, 40),
+    Score::new(35, 65),
+    Score::new(60, 100),
+    Score::new(0, 0),
+];
+/// Endgame centipawns per square the enemy king is from the pawn's stop
+/// square, and taken off per square its own king is, on each rank from the
+/// fourth; a pawn further back is too far from queening for it to matter.
+const ENEMY_KING_DISTANCE: i32 = 5;
+const OWN_KING_DISTANCE: i32 = 2;
+const KING_DISTANCE_FROM_RANK: usize = 3;
+
+/// Passed pawns for one side from its own point of view, each worth more
+/// the further on it is, less when something stands in its way, and in the
+/// endgame more the further the kings leave it to run.
+pub(super) fn side_passed(state: &GameState, passed: Bitboard, color: Color) -> Score {
+    let board = &state.position.board;
+    let own_king = board.king_square(color);
+    let enemy_king = board.king_square(color.opposing());
+    let mut score = Score::default();
+    for sq in passed & board.bitboard(Piece::of(PieceType::Pawn, color)) {
+        let (relative_rank, stop) = match color {
+            Color::White => (sq as usize / 8, sq as usize + 8),
+            Color::Black => (7 - sq as usize / 8, sq as usize - 8),
+        };
+        let stop = Square::from_mailbox_no(stop as i8);
+        let mut bonus = PASSED[relative_rank];
+        match board.piece(&stop) {
+            // blockaded, and only a capture can shift the blocker
+            Some(piece) if piece.color() != color => {
+                bonus = Score::new(bonus.mg / 2, bonus.eg / 2);
+            }
+            // held up by its own side, which can at least move out the way
+            Some(_) => bonus = Score::new(bonus.mg * 3 / 4, bonus.eg * 3 / 4),
+            None => {}
+        }
+        score += bonus;
+
+        // with both kings on the board, which they needn't be in every variant
+        if let (Some(own_king), Some(enemy_king)) = (own_king, enemy_king) {
+            let ranks_on = relative_rank.saturating_sub(KING_DISTANCE_FROM_RANK) as i32;
+            let distance = ENEMY_KING_DISTANCE * enemy_king.distance(stop) as i32
+                - OWN_KING_DISTANCE * own_king.distance(stop) as i32;
+            score += Score::new(0, ranks_on * distance);
+        }
+    }
+    score
+}
+
+/// Passed pawns, White's less Black's, out of those in `passed`.
+pub fn passed_pawns(state: &GameState, passed: Bitboard) -> Score {
+    side_passed(state, passed, Color::White) - side_passed(state, passed, Color::Black)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::eval::pawns::pawn_structure;
+
+    fn passed_score(fen: &str) -> Score {
+        let state = GameState::from_fen(fen).unwrap();
+        passed_pawns(&state, pawn_structure(&state).passed)
+    }
+
+    #[test]
+    fn test_passed_pawns() {
+        // kings too far back for their distance to count
+        let free = passed_score("8/8/8/k7/8/4P3/8/K7 w - - 0 1");
+        assert_eq!(free, PASSED[2]);
+        let blockaded = passed_score("8/8/8/k7/4n3/4P3/8/K7 w - - 0 1");
+        assert_eq!(blockaded, Score::new(PASSED[2].mg / 2, PASSED[2].eg / 2));
+        let held_up = passed_score("8/8/8/k7/4N3/4P3/8/K7 w - - 0 1");
+        assert_eq!(
+            held_up,
+            Score::new(PASSED[2].mg * 3 / 4, PASSED[2].eg * 3 / 4)
+        );
+    }
+
+    #[test]
+    fn test_king_distance() {
+        // the same pawn on the sixth, its stop square far from black's king
+        // and then next to it
+        let running = passed_score("k7/8/4P3/8/8/8/8/K7 w - - 0 1");
+        let caught = passed_score("5k2/8/4P3/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(running.mg, caught.mg);
+        assert!(running.eg > caught.eg);
+        // and a black pawn scores the same from its own side
+        let black = passed_score("k7/8/8/8/8/4p3/8/K7 b - - 0 1");
+        assert_eq!(running, -black);
+    }
+}
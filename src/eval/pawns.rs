@@ -0,0 +1,168 @@
+use crate::play::{
+    types::{Color, Piece},
+    Bitboard, GameState, ADJACENT_FILE_MASKS,
+};
+
+use super::Score;
+
+/// Slots in a pawn table. Pawn structures change far less often than
+/// positions do, so a small table already catches most of them.
+const PAWN_TABLE_ENTRIES: usize = 1 << 14;
+
+const DOUBLED: Score = Score::new(-10, -20);
+const ISOLATED: Score = Score::new(-10, -15);
+const BACKWARD: Score = Score::new(-8, -10);
+
+/// What the pawns alone say about a position: the structure's score,
+/// White's less Black's, and the passed pawns of both sides, which are
+/// scored afresh each time against the pieces around them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PawnEntry {
+    pub score: Score,
+    pub passed: Bitboard,
+}
+
+/// Doubled, isolated and backward pawns for one side, from that side's
+/// point of view, and which of its pawns are passed.
+pub(super) fn pawn_terms(own: Bitboard, enemy: Bitboard, color: Color) -> (Score, Bitboard) {
+    let enemy_attacks = enemy.pawn_attacks(color.opposing());
+    let mut score = Score::default();
+    let mut passed = Bitboard::empty();
+    for sq in own {
+        let file = sq as usize % 8;
+        let front = Bitboard::front_span(color, sq);
+        let passed_mask = Bitboard::passed_pawn_mask(color, sq);
+        // the rear pawn of a doubled pair is held up by its own
+        let doubled = (own & front).0 != 0;
+        if doubled {
+            score += DOUBLED;
+        }
+        let is_passed = (enemy & passed_mask).0 == 0;
+        if is_passed && !doubled {
+            passed |= sq.into();
+        }
+
+        let neighbours = own & ADJACENT_FILE_MASKS[file];
+        if neighbours.0 == 0 {
+            score += ISOLATED;
+        } else if !is_passed && neighbours.0 & !(passed_mask.0 & !front.0) == 0 {
+            // every neighbour has gone on ahead, leaving this one unable to be
+            // defended by a pawn and its way forward covered
+            let stop = match color {
+                Color::White => sq as u32 + 8,
+                Color::Black => sq as u32 - 8,
+            };
+            if enemy_attacks.0 & (1 << stop) != 0 {
+                score += BACKWARD;
+            }
+        }
+    }
+    (score, passed)
+}
+
+/// The pawn structure terms, White's less Black's, with the passed pawns.
+pub fn pawn_structure(state: &GameState) -> PawnEntry {
+    let board = &state.position.board;
+    let white = board.bitboard(Piece::WhitePawn);
+    let black = board.bitboard(Piece::BlackPawn);
+    let (white_score, white_passed) = pawn_terms(white, black, Color::White);
+    let (black_score, black_passed) = pawn_terms(black, white, Color::Black);
+    PawnEntry {
+        score: white_score - black_score,
+        passed: white_passed | black_passed,
+    }
+}
+
+/// Pawn structures by [`GameState::pawn_key`], always replacing. The
+/// default table has no slots and scores every structure afresh.
+#[derive(Debug, Default)]
+pub struct PawnTable {
+    entries: Vec<Option<(u64, PawnEntry)>>,
+}
+
+impl PawnTable {
+    pub fn new() -> Self {
+        PawnTable {
+            entries: vec![None; PAWN_TABLE_ENTRIES],
+        }
+    }
+
+    /// The state's pawn structure, from the table if it's been seen.
+    pub fn probe(&mut self, state: &GameState) -> PawnEntry {
+        if self.entries.is_empty() {
+            return pawn_structure(state);
+        }
+        let idx = (state.pawn_key % self.entries.len() as u64) as usize;
+        match self.entries[idx] {
+            Some((key, entry)) if key == state.pawn_key => entry,
+            _ => {
+                let entry = pawn_structure(state);
+                self.entries[idx] = Some((state.pawn_key, entry));
+                entry
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::types::Square;
+
+    fn white_terms(fen: &str) -> (Score, Bitboard) {
+        let state = GameState::from_fen(fen).unwrap();
+        let board = &state.position.board;
+        pawn_terms(
+            board.bitboard(Piece::WhitePawn),
+            board.bitboard(Piece::BlackPawn),
+            Color::White,
+        )
+    }
+
+    #[test]
+    fn test_pawn_terms() {
+        // a lone pawn on the fifth is both isolated and passed
+        assert_eq!(
+            white_terms("4k3/8/8/4P3/8/8/8/4K3 w - - 0 1"),
+            (ISOLATED, Square::E5.into())
+        );
+        // doubled and isolated on the e-file, only the front one passed
+        assert_eq!(
+            white_terms("4k3/8/8/4P3/4P3/8/8/4K3 w - - 0 1"),
+            (DOUBLED + ISOLATED + ISOLATED, Square::E5.into())
+        );
+        // d3 is left behind by c4 and e4, with d4 covered by black's c5
+        assert_eq!(
+            white_terms("4k3/8/8/2p5/2P1P3/3P4/8/4K3 w - - 0 1"),
+            (BACKWARD, Square::E4.into())
+        );
+        // a structure with nothing wrong and nothing passed scores nothing
+        assert_eq!(
+            white_terms("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1"),
+            (Score::default(), Bitboard::empty())
+        );
+    }
+
+    #[test]
+    fn test_pawn_structure_is_symmetric() {
+        let white = GameState::from_fen("4k3/p7/8/3P4/8/8/PP6/4K3 w - - 0 1").unwrap();
+        let black = GameState::from_fen("4k3/pp6/8/8/3p4/8/P7/4K3 b - - 0 1").unwrap();
+        assert_eq!(pawn_structure(&white).score, -pawn_structure(&black).score);
+        assert_eq!(pawn_structure(&white).passed, Square::D5.into());
+        assert_eq!(pawn_structure(&black).passed, Square::D4.into());
+    }
+
+    #[test]
+    fn test_pawn_table() {
+        let state = GameState::from_fen("4k3/p7/8/3P4/8/8/PP6/4K3 w - - 0 1").unwrap();
+        let mut table = PawnTable::new();
+        assert_eq!(table.probe(&state), pawn_structure(&state));
+        assert_eq!(table.entries.iter().flatten().count(), 1);
+        // a king move leaves the pawns, and so the entry, as they were
+        let moved = GameState::from_fen("3k4/p7/8/3P4/8/8/PP6/4K3 w - - 0 1").unwrap();
+        assert_eq!(table.probe(&moved), pawn_structure(&state));
+        assert_eq!(table.entries.iter().flatten().count(), 1);
+        assert_eq!(PawnTable::default().probe(&state), pawn_structure(&state));
+    }
+}
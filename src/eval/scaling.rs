@@ -0,0 +1,139 @@
+use crate::play::{
+    types::{Color, Piece, PieceType, Square},
+    GameState, FILE_MASKS,
+};
+
+/// The endgame score is scaled by a factor out of this much.
+pub const SCALE_NORMAL: i32 = 64;
+/// Bishops of opposite colours with only pawns beside them.
+const SCALE_OPPOSITE_BISHOPS: i32 = 16;
+/// A rook and bishop against a rook, with no pawns.
+const SCALE_ROOK_BISHOP_VS_ROOK: i32 = 8;
+
+/// How many of each piece type a side has, pawn to queen.
+fn counts(state: &GameState, color: Color) -> [u32; 5] {
+    let board = &state.position.board;
+    let mut counts = [0; 5];
+    for (count, piece_type) in counts.iter_mut().zip([
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+    ]) {
+        *count = board.bitboard(Piece::of(piece_type, color)).pop_count();
+    }
+    counts
+}
+
+/// A bishop and pawns all on one rook file, where the bishop doesn't cover
+/// the queening square and the defending king stands by it.
+fn wrong_bishop(state: &GameState, strong: Color) -> bool {
+    let board = &state.position.board;
+    let pawns = board.bitboard(Piece::of(PieceType::Pawn, strong)).0;
+    let queening = if pawns & !FILE_MASKS[0].0 == 0 {
+        [Square::A8, Square::A1]
+    } else if pawns & !FILE_MASKS[7].0 == 0 {
+        [Square::H8, Square::H1]
+    } else {
+        return false;
+    }[strong as usize];
+    let bishop = match board
+        .bitboard(Piece::of(PieceType::Bishop, strong))
+        .into_iter()
+        .next()
+    {
+        Some(sq) => sq,
+        None => return false,
+    };
+    bishop.color() != queening.color()
+        && board
+            .king_square(strong.opposing())
+            .is_some_and(|king| king.distance(queening) <= 1)
+}
+
+/// The factor out of [`SCALE_NORMAL`] the endgame score is scaled by, for
+/// material that's known to be drawish however far `strong` is ahead.
+pub fn scale_factor(state: &GameState, strong: Color) -> i32 {
+    let board = &state.position.board;
+    let own = counts(state, strong);
+    let other = counts(state, strong.opposing());
+    let pieces = |counts: [u32; 5]| counts[1..].iter().sum::<u32>();
+
+    // [pawns, knights, bishops, rooks, queens]
+    if own[0] > 0 && own[1..] == [0, 1, 0, 0] && pieces(other) == 0 && wrong_bishop(state, strong) {
+        return 0;
+    }
+    if own[0] == 0 && other[0] == 0 && own[1..] == [0, 1, 1, 0] && other[1..] == [0, 0, 1, 0] {
+        return SCALE_ROOK_BISHOP_VS_ROOK;
+    }
+    if own[1..] == [0, 1, 0, 0] && other[1..] == [0, 1, 0, 0] {
+        let white = board.bitboard(Piece::of(PieceType::Bishop, Color::White));
+        let black = board.bitboard(Piece::of(PieceType::Bishop, Color::Black));
+        if let (Some(white), Some(black)) = (white.into_iter().next(), black.into_iter().next()) {
+            if white.color() != black.color() {
+                return SCALE_OPPOSITE_BISHOPS;
+            }
+        }
+    }
+    SCALE_NORMAL
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn factor(fen: &str, strong: Color) -> i32 {
+        scale_factor(&GameState::from_fen(fen).unwrap(), strong)
+    }
+
+    #[test]
+    fn test_opposite_bishops() {
+        assert_eq!(
+            factor("4k3/4b3/8/3PP3/8/8/4B3/4K3 w - - 0 1", Color::White),
+            SCALE_OPPOSITE_BISHOPS
+        );
+        // bishops of the same colour are no draw
+        assert_eq!(
+            factor("4k3/5b2/8/3PP3/8/8/4B3/4K3 w - - 0 1", Color::White),
+            SCALE_NORMAL
+        );
+        assert_eq!(factor(crate::play::START_FEN, Color::White), SCALE_NORMAL);
+    }
+
+    #[test]
+    fn test_rook_bishop_vs_rook() {
+        assert_eq!(
+            factor("4k3/8/8/3r4/8/8/4B3/R3K3 w - - 0 1", Color::White),
+            SCALE_ROOK_BISHOP_VS_ROOK
+        );
+        // a pawn changes things
+        assert_eq!(
+            factor("4k3/8/8/3r4/8/8/4BP2/R3K3 w - - 0 1", Color::White),
+            SCALE_NORMAL
+        );
+    }
+
+    #[test]
+    fn test_wrong_bishop() {
+        // a light bishop can't drive the king from the dark h8
+        assert_eq!(factor("7k/8/8/7P/8/8/8/3BK3 w - - 0 1", Color::White), 0);
+        // the right bishop wins
+        assert_eq!(
+            factor("7k/8/8/7P/8/8/8/2B1K3 w - - 0 1", Color::White),
+            SCALE_NORMAL
+        );
+        // and so does the wrong one with the king too far away
+        assert_eq!(
+            factor("8/8/8/k6P/8/8/8/3BK3 w - - 0 1", Color::White),
+            SCALE_NORMAL
+        );
+        // for black, the queening square is a1
+        assert_eq!(
+            factor("4k3/8/8/8/p7/8/8/3b2K1 b - - 0 1", Color::Black),
+            SCALE_NORMAL
+        );
+        assert_eq!(factor("4k3/8/8/8/p7/8/1K6/3b4 b - - 0 1", Color::Black), 0);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::play::{
+    types::{Color, Piece, PieceType},
+    Bitboard, GameState,
+};
+
+use super::Score;
+
+/// Per piece attacked by an enemy pawn, knights up to queens.
+const THREAT_BY_PAWN: Score = Score::new(45, 35);
+/// Per rook or queen attacked by an enemy knight or bishop.
+const THREAT_BY_MINOR: Score = Score::new(35, 25);
+/// Per queen attacked by an enemy rook.
+const THREAT_BY_ROOK: Score = Score::new(30, 20);
+/// Per piece, pawns and the king aside, attacked and not defended.
+const HANGING: Score = Score::new(25, 30);
+
+/// The squares the pieces of `color` and of the given types attack.
+fn attacks_by(state: &GameState, color: Color, piece_types: &[PieceType]) -> u64 {
+    let board = &state.position.board;
+    let mut attacks = 0;
+    for &piece_type in piece_types {
+        for sq in board.bitboard(Piece::of(piece_type, color)) {
+            attacks |= board.piece_attacks(piece_type, sq).0;
+        }
+    }
+    attacks
+}
+
+/// The threats against one side's pieces, as the penalty it takes.
+pub(super) fn side_threats(state: &GameState, color: Color) -> Score {
+    let board = &state.position.board;
+    let enemy = color.opposing();
+    let own = |piece_type| board.bitboard(Piece::of(piece_type, color)).0;
+    let minors = own(PieceType::Knight) | own(PieceType::Bishop);
+    let majors = own(PieceType::Rook) | own(PieceType::Queen);
+
+    let count = |bits: u64| Bitboard(bits).pop_count() as i32;
+    let by_pawn = count((minors | majors) & board.pawn_attacks(enemy).0);
+    let by_minor =
+        count(majors & attacks_by(state, enemy, &[PieceType::Knight, PieceType::Bishop]));
+    let by_rook = count(own(PieceType::Queen) & attacks_by(state, enemy, &[PieceType::Rook]));
+    let hanging = count(board.hanging_pieces(color).0 & !own(PieceType::Pawn));
+
+    let mut score = Score::default();
+    for (term, n) in [
+        (THREAT_BY_PAWN, by_pawn),
+        (THREAT_BY_MINOR, by_minor),
+        (THREAT_BY_ROOK, by_rook),
+        (HANGING, hanging),
+    ] {
+        score += Score::new(term.mg * n, term.eg * n);
+    }
+    score
+}
+
+/// Pieces attacked by lesser ones and pieces left hanging, Black's threats
+/// against White taken from White's against Black.
+pub fn threats(state: &GameState) -> Score {
+    side_threats(state, Color::Black) - side_threats(state, Color::White)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn state(fen: &str) -> GameState {
+        GameState::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn test_threats() {
+        assert_eq!(threats(&GameState::default()), Score::default());
+        // a pawn forks a defended knight and an undefended rook
+        let fork = threats(&state("4k3/8/8/1n1r4/2P5/8/8/4K3 b - - 0 1"));
+        assert_eq!(fork, THREAT_BY_PAWN + THREAT_BY_PAWN + HANGING);
+        // a knight on the queen, which the g-pawn guards
+        let on_queen = threats(&state("4k3/6p1/5q2/3N4/8/8/8/4K3 w - - 0 1"));
+        assert_eq!(on_queen, THREAT_BY_MINOR);
+        // a rook on the queen, mirrored for the other side
+        let on_queen = threats(&state("5rk1/8/8/8/8/5Q2/6P1/4K3 w - - 0 1"));
+        assert_eq!(on_queen, -THREAT_BY_ROOK);
+    }
+}
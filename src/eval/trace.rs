@@ -0,0 +1,251 @@
+use std::fmt;
+
+use crate::play::{
+    types::{Color, Piece, PieceType},
+    GameState, MAX_PHASE, PIECES, PIECE_VALUES,
+};
+
+use super::{
+    mobility::{side_activity, side_mobility},
+    passed::side_passed,
+    pawns::pawn_terms,
+    pst::piece_square,
+    scale_and_taper,
+    scaling::SCALE_NORMAL,
+    threats::side_threats,
+    Evaluator, Score, GIVEAWAY_PIECE,
+};
+
+/// One evaluation term as each side scores it, from its own point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceTerm {
+    pub name: &'static str,
+    pub white: Score,
+    pub black: Score,
+}
+
+impl TraceTerm {
+    pub fn total(&self) -> Score {
+        self.white - self.black
+    }
+}
+
+/// The evaluation of a position taken apart term by term, for checking
+/// what a change to one of them does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub terms: Vec<TraceTerm>,
+    pub phase: u8,
+    /// out of [`SCALE_NORMAL`], what the endgame part was scaled by
+    pub scale: i32,
+    /// the evaluation, White's point of view
+    pub eval: i32,
+}
+
+impl Trace {
+    pub fn total(&self) -> Score {
+        self.terms
+            .iter()
+            .fold(Score::default(), |total, term| total + term.total())
+    }
+}
+
+/// Material and piece-square bonuses for one side, apart.
+fn material_and_placement(state: &GameState, color: Color) -> (Score, Score) {
+    let board = &state.position.board;
+    let mut material = Score::default();
+    let mut placement = Score::default();
+    for &piece in PIECES.iter().filter(|piece| piece.color() == color) {
+        let value = match piece.piece_type() {
+            PieceType::King => 0,
+            _ => PIECE_VALUES[piece as usize] as i32,
+        };
+        for sq in board.bitboard(piece) {
+            material += Score::new(value, value);
+            placement += piece_square(piece, sq);
+        }
+    }
+    (material, placement)
+}
+
+/// The antichess evaluation as a trace, its one term what each side has
+/// left to give away.
+fn giveaway_trace(state: &GameState) -> Trace {
+    let board = &state.position.board;
+    let left = |color| {
+        let cost = -(board.occupied_by(color).pop_count() as i32) * GIVEAWAY_PIECE;
+        Score::new(cost, cost)
+    };
+    let term = TraceTerm {
+        name: "Giveaway",
+        white: left(Color::White),
+        black: left(Color::Black),
+    };
+    Trace {
+        eval: term.total().mg,
+        terms: vec![term],
+        phase: state.phase,
+        scale: SCALE_NORMAL,
+    }
+}
+
+impl Evaluator {
+    /// Every term of [`Evaluator::score`] for each side, worked out afresh
+    /// rather than from the pawn table. Where the king isn't royal, the
+    /// one term of the antichess evaluation instead.
+    pub fn trace(&self, state: &GameState) -> Trace {
+        if !state.position.variant.royal_king() {
+            return giveaway_trace(state);
+        }
+        let board = &state.position.board;
+        let per_side = |term: &dyn Fn(Color) -> Score| [term(Color::White), term(Color::Black)];
+
+        let material = per_side(&|color| material_and_placement(state, color).0);
+        let placement = per_side(&|color| material_and_placement(state, color).1);
+        let pawns = |color: Color| {
+            pawn_terms(
+                board.bitboard(Piece::of(PieceType::Pawn, color)),
+                board.bitboard(Piece::of(PieceType::Pawn, color.opposing())),
+                color,
+            )
+        };
+        let structure = per_side(&|color| pawns(color).0);
+        let passed = per_side(&|color| side_passed(state, pawns(color).1, color));
+        // not evaluated yet, though it has a weight waiting for it
+        let king_safety = [Score::default(); 2];
+        let mobility = per_side(&|color| side_mobility(state, color).scaled(self.weights.mobility));
+        let activity = per_side(&|color| side_activity(state, color));
+        // what a side threatens is what its opponent is penalised for
+        let threats = per_side(&|color| side_threats(state, color.opposing()));
+
+        let terms = [
+            ("Material", material),
+            ("PST", placement),
+            ("Pawns", structure),
+            ("Passed", passed),
+            ("King safety", king_safety),
+            ("Mobility", mobility),
+            ("Pieces", activity),
+            ("Threats", threats),
+        ]
+        .iter()
+        .map(|&(name, [white, black])| TraceTerm { name, white, black })
+        .collect::<Vec<_>>();
+        let total = terms
+            .iter()
+            .fold(Score::default(), |total, term| total + term.total());
+        let (scale, eval) = scale_and_taper(state, total);
+        Trace {
+            terms,
+            phase: state.phase,
+            scale,
+            eval,
+        }
+    }
+}
+
+impl fmt::Display for Trace {
+    /// A table of the terms in centipawns, midgame and endgame, for each
+    /// side and the two together.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = "------------+-------------+-------------+-------------";
+        let scores = |score: Score| format!("{:>5} {:>5}", score.mg, score.eg);
+        writeln!(f, "{:>11} |    White    |    Black    |    Total", "Term")?;
+        writeln!(f, "{:>11} |   MG    EG  |   MG    EG  |   MG    EG", "")?;
+        writeln!(f, "{}", rule)?;
+        for term in &self.terms {
+            writeln!(
+                f,
+                "{:>11} | {} | {} | {}",
+                term.name,
+                scores(term.white),
+                scores(term.black),
+                scores(term.total())
+            )?;
+        }
+        writeln!(f, "{}", rule)?;
+        writeln!(
+            f,
+            "{:>11} | {:>11} | {:>11} | {}",
+            "Total",
+            "",
+            "",
+            scores(self.total())
+        )?;
+        writeln!(
+            f,
+            "Phase {}/{}, endgame scaled {}/{}",
+            self.phase, MAX_PHASE, self.scale, SCALE_NORMAL
+        )?;
+        write!(
+            f,
+            "Final evaluation: {:+} (White's point of view)",
+            self.eval
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::{r#move::make_move, variant::Variant};
+
+    /// An antichess game whose white king has just been taken.
+    fn kingless() -> GameState {
+        let mut state = GameState::from_fen("k7/8/8/8/8/8/2q4P/K7 w - - 0 1")
+            .unwrap()
+            .with_variant(Variant::Antichess);
+        for uci in ["a1a2", "c2a2"] {
+            let mv = state.position.parse_move(uci).unwrap();
+            make_move(mv, &mut state).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn test_giveaway_trace() {
+        let state = kingless();
+        assert_eq!(state.position.board.king_square(Color::White), None);
+        let mut eval = Evaluator::default();
+        let trace = eval.trace(&state);
+        assert_eq!(trace.terms.len(), 1);
+        // White has a pawn left to give away, Black a king and queen
+        assert_eq!(trace.eval, GIVEAWAY_PIECE);
+        assert_eq!(trace.eval, eval.evaluate(&state));
+        assert!(trace.to_string().contains("Giveaway"));
+    }
+
+    #[test]
+    fn test_trace_adds_up() {
+        let state = GameState::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        )
+        .unwrap();
+        let mut eval = Evaluator::default();
+        let trace = eval.trace(&state);
+        assert_eq!(trace.total(), eval.score(&state));
+        assert_eq!(trace.eval, eval.evaluate(&state));
+        assert_eq!(trace.terms.len(), 8);
+
+        let black = GameState::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 4 4",
+        )
+        .unwrap();
+        assert_eq!(eval.trace(&black).eval, -eval.evaluate(&black));
+    }
+
+    #[test]
+    fn test_trace_display() {
+        let trace = Evaluator::default().trace(&GameState::default());
+        let table = trace.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 15);
+        assert_eq!(
+            lines[3],
+            "   Material |  4200  4200 |  4200  4200 |     0     0"
+        );
+        assert!(lines[7].starts_with("King safety |"));
+        assert_eq!(lines[14], "Final evaluation: +0 (White's point of view)");
+    }
+}
@@ -0,0 +1,59 @@
+//! A UCI chess engine, and the pieces it's built from: a bitboard board and
+//! move generator, an alpha-beta search and a classical evaluation.
+//!
+//! The engine itself is the `lasker` binary, a thin layer over this crate.
+//! The commonly used types are re-exported at the crate root, so that
+//! counting the legal moves of a position is just
+//!
+//! ```
+//! use lasker::GameState;
+//!
+//! let state = GameState::from_fen(lasker::START_FEN).unwrap();
+//! assert_eq!(state.position.legal_moves().len(), 20);
+//! ```
+//!
+//! Without the default `std` feature only [`play`], the board and move
+//! generation, is built, on `core` and `alloc` alone.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod arena;
+#[cfg(feature = "std")]
+pub mod book;
+#[cfg(feature = "std")]
+pub mod epd;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod eval;
+#[cfg(feature = "std")]
+pub mod perft;
+pub mod play;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "std")]
+pub mod record;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "std")]
+pub mod uci;
+
+#[cfg(feature = "std")]
+pub use error::{Error, Result};
+#[cfg(feature = "std")]
+pub use eval::Evaluator;
+pub use play::{
+    board::{bitboard::Bitboard, Board},
+    error::{FENParsingError, MoveError},
+    position::Position,
+    r#move::{make_move, unmake_move, Move, MoveList},
+    types::{CastlingRights, Color, File, Piece, PieceType, Rank, Square},
+    GameState, START_FEN,
+};
+#[cfg(feature = "std")]
+pub use search::{search, SearchConfig, SearchResult};
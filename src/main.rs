@@ -1,7 +1,164 @@
-mod perft;
-mod play;
-mod uci;
+use lasker::{arena, book, perft, record, search, uci};
 
-fn main() {
-    uci::uci_loop();
+fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        // `lasker perft suite <file>` checks movegen against known counts
+        [perft, suite, path] if perft == "perft" && suite == "suite" => {
+            if !perft::suite::run_suite_file(path)?.all_passed() {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        // `lasker perft compare --engine <path>` finds where movegen and a
+        // reference engine's part ways
+        [perft, compare, rest @ ..] if perft == "perft" && compare == "compare" => {
+            let args = match perft::compare::CompareArgs::parse(rest) {
+                Ok(args) => args,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            };
+            if !perft::compare::run_compare(&args)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        // `lasker book build <games.pgn> <out.bin>` writes an opening book of
+        // the moves played in a collection of games
+        [book, build, rest @ ..] if book == "book" && build == "build" => {
+            let args = match book::build::BuildArgs::parse(rest) {
+                Ok(args) => args,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            };
+            if let Err(e) = book::build::run_build(&args) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        // `lasker annotate <in.pgn> <out.pgn>` marks each game's moves with
+        // the engine's evaluation and its mistakes and blunders
+        [annotate, rest @ ..] if annotate == "annotate" => {
+            let args = match record::annotate::AnnotateArgs::parse(rest) {
+                Ok(args) => args,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            };
+            if let Err(e) = record::annotate::run_annotate(&args) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        // `lasker report <game.pgn | "position ...">` lists each move's loss
+        // against the engine's choice and sums up each side's mistakes
+        [report, rest @ ..] if report == "report" => {
+            let args = match record::report::ReportArgs::parse(rest) {
+                Ok(args) => args,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            };
+            if let Err(e) = record::report::run_report(&args, &mut std::io::stdout()) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        // `lasker datagen <out> [--games N]` plays the engine against itself
+        // for positions to tune the evaluation on
+        [datagen, rest @ ..] if datagen == "datagen" => {
+            let args = match arena::datagen::DatagenArgs::parse(rest) {
+                Ok(args) => args,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            };
+            arena::datagen::run_datagen_file(&args)?;
+            Ok(())
+        }
+        // `lasker tournament <config.json> [--state <file>] [--pgn <file>]`
+        // plays a round-robin or gauntlet between differently set up engines
+        [tournament, rest @ ..] if tournament == "tournament" => {
+            let args = match arena::tournament::TournamentArgs::parse(rest) {
+                Ok(args) => args,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            };
+            if let Err(e) = arena::tournament::run_tournament_file(&args) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        // `lasker bench [depth]` searches a fixed set of positions for a
+        // node count signature
+        [bench, rest @ ..] if bench == "bench" && rest.len() <= 1 => {
+            let depth = match rest.first().map(|d| d.parse()) {
+                Some(Ok(depth)) => depth,
+                Some(Err(_)) => {
+                    eprintln!("bad depth {}", rest[0]);
+                    std::process::exit(2);
+                }
+                None => search::bench::DEFAULT_BENCH_DEPTH,
+            };
+            search::bench::bench(
+                &search::bench::BENCH_POSITIONS,
+                depth,
+                &mut std::io::stdout(),
+            )?;
+            Ok(())
+        }
+        // `lasker tree <depth> [--fen <fen>] [--format json|dot]` dumps the
+        // nodes of a search for looking into its pruning
+        [tree, rest @ ..] if tree == "tree" => {
+            let args = match search::tree::TreeArgs::parse(rest) {
+                Ok(args) => args,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            };
+            search::tree::run_tree(&args, &mut std::io::stdout())?;
+            Ok(())
+        }
+        // `lasker testsuite <file> [movetime <ms>]` scores the search on an
+        // EPD test suite such as WAC or STS
+        [testsuite, path, rest @ ..] if testsuite == "testsuite" => {
+            let movetime = match rest {
+                [] => search::testsuite::DEFAULT_MOVETIME_MS,
+                [movetime, ms] if movetime == "movetime" => match ms.parse() {
+                    Ok(ms) => ms,
+                    Err(_) => {
+                        eprintln!("bad movetime {}", ms);
+                        std::process::exit(2);
+                    }
+                },
+                _ => {
+                    eprintln!("usage: lasker testsuite <file> [movetime <ms>]");
+                    std::process::exit(2);
+                }
+            };
+            let movetime = std::time::Duration::from_millis(movetime);
+            search::testsuite::run_testsuite_file(path, movetime)?;
+            Ok(())
+        }
+        // `lasker tui [fen]` analyses a position on a full-screen board
+        #[cfg(feature = "tui")]
+        [tui, rest @ ..] if tui == "tui" && rest.len() <= 1 => {
+            lasker::tui::run(rest.first().map_or(lasker::START_FEN, String::as_str))
+        }
+        _ => uci::uci_loop(),
+    }
 }
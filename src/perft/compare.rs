@@ -0,0 +1,314 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use crate::play::{r#move::make_move, GameState, START_FEN};
+
+use super::perft_divide;
+
+const DEFAULT_DEPTH: u64 = 4;
+
+/// Where the node counts to check ours against come from: divide counts,
+/// by root move in coordinate notation, of the position `moves` from `fen`.
+pub trait DivideSource {
+    fn divide(
+        &mut self,
+        fen: &str,
+        moves: &[String],
+        depth: u64,
+    ) -> io::Result<BTreeMap<String, u64>>;
+}
+
+/// A UCI engine that understands `go perft`, such as Stockfish, run as a
+/// child process.
+pub struct ReferenceEngine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl ReferenceEngine {
+    pub fn spawn(path: &str) -> io::Result<Self> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        let stdout = child.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        Ok(ReferenceEngine {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+}
+
+impl DivideSource for ReferenceEngine {
+    fn divide(
+        &mut self,
+        fen: &str,
+        moves: &[String],
+        depth: u64,
+    ) -> io::Result<BTreeMap<String, u64>> {
+        let mut position = format!("position fen {}", fen);
+        if !moves.is_empty() {
+            position.push_str(" moves ");
+            position.push_str(&moves.join(" "));
+        }
+        writeln!(self.stdin, "{}", position)?;
+        writeln!(self.stdin, "go perft {}", depth)?;
+        self.stdin.flush()?;
+        read_divide(&mut self.stdout)
+    }
+}
+
+impl Drop for ReferenceEngine {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit");
+        let _ = self.stdin.flush();
+        let _ = self.child.wait();
+    }
+}
+
+/// Reads Stockfish-style divide output, `move: nodes` lines up to the
+/// `Nodes searched` total, skipping anything else the engine says.
+fn read_divide(reader: &mut impl BufRead) -> io::Result<BTreeMap<String, u64>> {
+    let mut divide = BTreeMap::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "reference engine stopped before its perft total",
+            ));
+        }
+        if line.starts_with("Nodes searched") {
+            return Ok(divide);
+        }
+        if let Some((mv, nodes)) = line.trim().split_once(": ") {
+            if let Ok(nodes) = nodes.parse() {
+                divide.insert(mv.to_string(), nodes);
+            }
+        }
+    }
+}
+
+/// Our own divide counts, in the same form as a [`DivideSource`]'s.
+fn own_divide(fen: &str, moves: &[String], depth: u64) -> io::Result<BTreeMap<String, u64>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let mut state = GameState::from_fen(fen).map_err(|e| invalid(e.to_string()))?;
+    for uci in moves {
+        let mv = state
+            .position
+            .parse_move(uci)
+            .ok_or_else(|| invalid(format!("no move {} to follow", uci)))?;
+        make_move(mv, &mut state).map_err(|_| invalid(format!("couldn't make {}", uci)))?;
+    }
+    Ok(perft_divide(&mut state, depth)
+        .into_iter()
+        .map(|(mv, nodes)| (mv.to_uci(), nodes))
+        .collect())
+}
+
+/// The position where our move generation and the reference engine's part
+/// ways, and the moves only one of them has there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub fen: String,
+    /// the moves leading from `fen` to the faulty position
+    pub moves: Vec<String>,
+    /// moves the reference engine has that we don't
+    pub missing: Vec<String>,
+    /// moves we have that the reference engine doesn't
+    pub extra: Vec<String>,
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "position fen {}", self.fen)?;
+        if !self.moves.is_empty() {
+            write!(f, " moves {}", self.moves.join(" "))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "missing: {}", self.missing.join(" "))?;
+        write!(f, "extra: {}", self.extra.join(" "))
+    }
+}
+
+/// Compares divide counts at `depth` below `fen` with `reference`, and
+/// while the totals differ follows a root move whose count is off a ply
+/// deeper, until it reaches a position where the two disagree on the moves
+/// themselves. Returns `None` if the counts agree.
+pub fn bisect(
+    fen: &str,
+    depth: u64,
+    reference: &mut dyn DivideSource,
+) -> io::Result<Option<Discrepancy>> {
+    let mut moves = vec![];
+    for depth in (1..=depth).rev() {
+        let ours = own_divide(fen, &moves, depth)?;
+        let theirs = reference.divide(fen, &moves, depth)?;
+        let missing: Vec<String> = theirs
+            .keys()
+            .filter(|mv| !ours.contains_key(*mv))
+            .cloned()
+            .collect();
+        let extra: Vec<String> = ours
+            .keys()
+            .filter(|mv| !theirs.contains_key(*mv))
+            .cloned()
+            .collect();
+        if !missing.is_empty() || !extra.is_empty() {
+            return Ok(Some(Discrepancy {
+                fen: fen.to_string(),
+                moves,
+                missing,
+                extra,
+            }));
+        }
+        match ours
+            .iter()
+            .find(|(mv, nodes)| theirs.get(*mv) != Some(nodes))
+        {
+            Some((mv, _)) => moves.push(mv.clone()),
+            None => return Ok(None),
+        }
+    }
+    Ok(None)
+}
+
+/// What `perft compare` was asked to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompareArgs {
+    pub engine: String,
+    pub depth: u64,
+    pub fen: String,
+}
+
+impl CompareArgs {
+    /// Reads `--engine <path> [--depth N] [--fen <fen>]`, the FEN as one
+    /// argument, defaulting to the start position.
+    pub fn parse(args: &[String]) -> Result<CompareArgs, String> {
+        let mut engine = None;
+        let mut depth = DEFAULT_DEPTH;
+        let mut fen = START_FEN.to_string();
+        let mut it = args.iter();
+        while let Some(arg) = it.next() {
+            let value = it.next().ok_or_else(|| format!("{} needs a value", arg))?;
+            match arg.as_str() {
+                "--engine" => engine = Some(value.clone()),
+                "--depth" => depth = value.parse().map_err(|_| format!("bad depth {}", value))?,
+                "--fen" => fen = value.clone(),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+        let engine = engine.ok_or("--engine is required")?;
+        Ok(CompareArgs { engine, depth, fen })
+    }
+}
+
+/// Runs `perft compare`, printing where the counts part ways, if they do.
+/// Returns whether they agreed.
+pub fn run_compare(args: &CompareArgs) -> io::Result<bool> {
+    let mut engine = ReferenceEngine::spawn(&args.engine)?;
+    match bisect(&args.fen, args.depth, &mut engine)? {
+        Some(discrepancy) => {
+            println!("{}", discrepancy);
+            Ok(false)
+        }
+        None => {
+            println!("perft {} agrees with {}", args.depth, args.engine);
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Our own counts, plus one move we don't have in the position `after`
+    /// reaches, to stand in for a reference engine that knows better. The
+    /// move is a leaf, so it adds a node to each count on the way to it.
+    struct Doctored {
+        after: Vec<String>,
+        extra_move: String,
+    }
+
+    impl DivideSource for Doctored {
+        fn divide(
+            &mut self,
+            fen: &str,
+            moves: &[String],
+            depth: u64,
+        ) -> io::Result<BTreeMap<String, u64>> {
+            let mut divide = own_divide(fen, moves, depth)?;
+            if moves == self.after.as_slice() {
+                divide.insert(self.extra_move.clone(), 1);
+            } else if self.after.starts_with(moves) {
+                *divide.get_mut(&self.after[moves.len()]).unwrap() += 1;
+            }
+            Ok(divide)
+        }
+    }
+
+    #[test]
+    fn test_bisect_finds_missing_move() {
+        let mut reference = Doctored {
+            after: vec!["e2e4".to_string(), "e7e5".to_string()],
+            extra_move: "e1e3".to_string(),
+        };
+        let discrepancy = bisect(START_FEN, 3, &mut reference).unwrap().unwrap();
+        assert_eq!(discrepancy.moves, vec!["e2e4", "e7e5"]);
+        assert_eq!(discrepancy.missing, vec!["e1e3"]);
+        assert!(discrepancy.extra.is_empty());
+        assert!(discrepancy
+            .to_string()
+            .starts_with(&format!("position fen {} moves e2e4 e7e5\n", START_FEN)));
+    }
+
+    #[test]
+    fn test_bisect_agrees_with_itself() {
+        struct Own;
+        impl DivideSource for Own {
+            fn divide(
+                &mut self,
+                fen: &str,
+                moves: &[String],
+                depth: u64,
+            ) -> io::Result<BTreeMap<String, u64>> {
+                own_divide(fen, moves, depth)
+            }
+        }
+        assert_eq!(bisect(START_FEN, 3, &mut Own).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_divide() {
+        let out =
+            "Stockfish 16 by the Stockfish developers\na2a3: 20\nb2b3: 20\n\nNodes searched: 40\n";
+        let divide = read_divide(&mut out.as_bytes()).unwrap();
+        assert_eq!(divide.len(), 2);
+        assert_eq!(divide["b2b3"], 20);
+        assert!(read_divide(&mut "a2a3: 20\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_parse_compare_args() {
+        let args: Vec<String> = ["--engine", "stockfish", "--depth", "5"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let parsed = CompareArgs::parse(&args).unwrap();
+        assert_eq!(parsed.engine, "stockfish");
+        assert_eq!(parsed.depth, 5);
+        assert_eq!(parsed.fen, START_FEN);
+        assert!(CompareArgs::parse(&args[2..]).is_err());
+        assert!(CompareArgs::parse(&args[..1]).is_err());
+    }
+}
@@ -1,51 +1,472 @@
+pub mod compare;
+pub mod suite;
+
+use std::{
+    fmt,
+    ops::AddAssign,
+    time::{Duration, Instant},
+};
+
 use crate::play::{
-    r#move::{make_move, unmake_move},
+    r#move::{make_move, unmake_move, Move},
+    types::Square,
     GameState,
 };
 
-pub fn run_perft(state: &mut GameState, depth: u64) -> u64 {
+/// Receives a perft run's results as they're counted, so embedders can
+/// consume them without parsing printed output.
+pub trait PerftObserver {
+    /// The nodes below one root move.
+    fn on_root_move(&mut self, _mv: Move, _nodes: u64) {}
+    /// The total at `depth`, once every root move is counted.
+    fn on_depth(&mut self, _depth: u64, _nodes: u64) {}
+    /// How long the whole run took, once it's done.
+    fn on_finish(&mut self, _timing: &PerftTiming) {}
+}
+
+/// The nodes a perft run counted and the wall time it took, to keep an eye on
+/// movegen speed from one release to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerftTiming {
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// false when the run was stopped, leaving `nodes` a partial count
+    pub complete: bool,
+}
+
+impl PerftTiming {
+    pub fn nps(&self) -> u64 {
+        // a run can finish quicker than the clock can tell
+        let micros = self.elapsed.as_micros().max(1);
+        (self.nodes as u128 * 1_000_000 / micros) as u64
+    }
+}
+
+/// Ignores everything.
+impl PerftObserver for () {}
+
+/// Passes the node count below each root move to `observer`, then the
+/// total, which is also returned. Root moves go in order of their UCI
+/// strings, as Stockfish lists them, so the output can be diffed with its.
+/// Once `stopped` says so the run winds up early with what it has counted.
+pub fn run_perft(
+    state: &mut GameState,
+    depth: u64,
+    observer: &mut dyn PerftObserver,
+    stopped: &dyn Fn() -> bool,
+) -> u64 {
+    let start = Instant::now();
+    let mut divide = perft_divide_until(state, depth, stopped);
+    divide.sort_by_cached_key(|(mv, _)| mv.to_uci());
+    let nodes = divide
+        .into_iter()
+        .map(|(mv, nodes)| {
+            observer.on_root_move(mv, nodes);
+            nodes
+        })
+        .sum();
+    observer.on_depth(depth, nodes);
+    observer.on_finish(&PerftTiming {
+        nodes,
+        elapsed: start.elapsed(),
+        complete: !stopped(),
+    });
+    nodes
+}
+
+/// Runs [`run_perft`] at each depth from 1 up to `depth`, for the totals
+/// at every depth; divide counts are only passed on for the last.
+pub fn run_perft_to_depth(
+    state: &mut GameState,
+    depth: u64,
+    observer: &mut dyn PerftObserver,
+) -> Vec<u64> {
+    (1..=depth)
+        .map(|d| {
+            if d == depth {
+                run_perft(state, d, observer, &|| false)
+            } else {
+                let nodes = perft(state, d);
+                observer.on_depth(d, nodes);
+                nodes
+            }
+        })
+        .collect()
+}
+
+/// Counts the leaf nodes of the move tree `depth` plies below `state`, which
+/// is left as it was found. A move that can't be made isn't counted.
+pub fn perft(state: &mut GameState, depth: u64) -> u64 {
+    perft_until(state, depth, &|| false)
+}
+
+/// [`perft`], giving up once `stopped` says so with the nodes counted so far.
+pub fn perft_until(state: &mut GameState, depth: u64, stopped: &dyn Fn() -> bool) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    // the generator is legal, so the last ply's moves need only be counted
+    if depth == 1 {
+        return state.position.count_legal_moves() as u64;
+    }
+    let mut nodes: u64 = 0;
+    for mv in state.position.legal_moves() {
+        if stopped() {
+            break;
+        }
+        if make_move(mv, state).is_err() {
+            continue;
+        }
+        nodes += perft_until(state, depth - 1, stopped);
+        // a failed unmake leaves the state unusable for the remaining moves
+        if unmake_move(mv, state).is_err() {
+            break;
+        }
+    }
+    nodes
+}
+
+/// [`perft`] split by root move, in generation order.
+pub fn perft_divide(state: &mut GameState, depth: u64) -> Vec<(Move, u64)> {
+    perft_divide_until(state, depth, &|| false)
+}
+
+/// [`perft_divide`], leaving out the root moves not reached once `stopped`
+/// says so.
+pub fn perft_divide_until(
+    state: &mut GameState,
+    depth: u64,
+    stopped: &dyn Fn() -> bool,
+) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return vec![];
+    }
     state
         .position
         .legal_moves()
-        .map(|mv| {
+        .into_iter()
+        .take_while(|_| !stopped())
+        .filter_map(|mv| {
             let mut s = state.clone();
-            match make_move(mv, &mut s) {
-                Ok(()) => {}
-                Err(e) => {
-                    println!("error making move: {:?}", mv.to_string());
-                    e.print_msg()
-                }
-            };
-            let nodes: u64 = perft(&mut s, depth - 1);
-            println!("{mv}: {nodes}");
-            nodes
+            make_move(mv, &mut s).ok()?;
+            Some((mv, perft_until(&mut s, depth - 1, stopped)))
         })
-        .fold(0, |acc, x| acc + x)
+        .collect()
+}
+
+/// What the moves at the last ply of a perft were, in the categories of the
+/// chessprogramming wiki's Perft Results tables, so a wrong node count can be
+/// narrowed down to the kind of move being generated wrongly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passants: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    /// checks given by a piece other than the one that moved
+    pub discovered_checks: u64,
+    pub double_checks: u64,
+    pub checkmates: u64,
 }
 
-fn perft(state: &mut GameState, depth: u64) -> u64 {
+impl AddAssign for PerftStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passants += other.en_passants;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.discovered_checks += other.discovered_checks;
+        self.double_checks += other.double_checks;
+        self.checkmates += other.checkmates;
+    }
+}
+
+impl fmt::Display for PerftStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "nodes: {}", self.nodes)?;
+        writeln!(f, "captures: {}", self.captures)?;
+        writeln!(f, "en passant: {}", self.en_passants)?;
+        writeln!(f, "castles: {}", self.castles)?;
+        writeln!(f, "promotions: {}", self.promotions)?;
+        writeln!(f, "checks: {}", self.checks)?;
+        writeln!(f, "discovered checks: {}", self.discovered_checks)?;
+        writeln!(f, "double checks: {}", self.double_checks)?;
+        write!(f, "checkmates: {}", self.checkmates)
+    }
+}
+
+/// [`perft`], tallying the kinds of move made at the last ply. There's no
+/// bulk counting, as every last move has to be made to see what it does.
+pub fn perft_detail(state: &mut GameState, depth: u64) -> PerftStats {
+    perft_detail_until(state, depth, &|| false)
+}
+
+/// [`perft_detail`], giving up once `stopped` says so with what it has.
+pub fn perft_detail_until(
+    state: &mut GameState,
+    depth: u64,
+    stopped: &dyn Fn() -> bool,
+) -> PerftStats {
+    let mut stats = PerftStats::default();
     if depth == 0 {
-        return 1;
+        stats.nodes = 1;
+        return stats;
     }
-    let mut nodes: u64 = 0;
     for mv in state.position.legal_moves() {
-        match make_move(mv, state) {
-            Ok(()) => {}
-            Err(e) => {
-                println!("\n\rerror making move: {:?}", mv.to_string());
-                println!("board: {:?}", state.position.board);
-                e.print_msg()
+        if stopped() {
+            break;
+        }
+        if make_move(mv, state).is_err() {
+            continue;
+        }
+        if depth == 1 {
+            stats += leaf_stats(mv, state);
+        } else {
+            stats += perft_detail_until(state, depth - 1, stopped);
+        }
+        if unmake_move(mv, state).is_err() {
+            break;
+        }
+    }
+    stats
+}
+
+/// The categories `mv` falls into, `state` being the position it led to.
+fn leaf_stats(mv: Move, state: &GameState) -> PerftStats {
+    let position = &state.position;
+    let mut stats = PerftStats {
+        nodes: 1,
+        captures: mv.captured().is_some() as u64,
+        en_passants: mv.en_passant() as u64,
+        castles: mv.castle() as u64,
+        promotions: mv.promoted().is_some() as u64,
+        ..PerftStats::default()
+    };
+    if position.in_check() {
+        // castling checks with the rook, which is the piece that moved to check
+        let moved = if mv.castle() {
+            match mv.to_sq() {
+                Square::G1 => Square::F1,
+                Square::C1 => Square::D1,
+                Square::G8 => Square::F8,
+                _ => Square::D8,
             }
+        } else {
+            mv.to_sq()
         };
-        nodes += perft(state, depth - 1);
-        match unmake_move(mv, state) {
-            Ok(()) => {}
-            Err(e) => {
-                println!("\n\rerror unmaking move: {:?}", mv.to_string());
-                println!("board: {:?}", state.position.board);
-                e.print_msg()
-            }
+        stats.checks = 1;
+        stats.discovered_checks = position.checkers.iter().any(|sq| sq != moved) as u64;
+        stats.double_checks = (position.checkers.pop_count() > 1) as u64;
+        stats.checkmates = !position.has_legal_move() as u64;
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::types::Square;
+
+    #[test]
+    fn test_perft_start_position() {
+        let mut state = GameState::default();
+        assert_eq!(perft(&mut state, 0), 1);
+        assert_eq!(perft(&mut state, 1), 20);
+        assert_eq!(perft(&mut state, 2), 400);
+        assert_eq!(perft(&mut state, 3), 8_902);
+        assert_eq!(state, GameState::default());
+    }
+
+    #[test]
+    fn test_perft_variants() {
+        use crate::play::variant::Variant;
+
+        // by depth 4 an atomic capture can blow up a king, and antichess
+        // has had forced captures from the second move
+        let mut atomic = GameState::default().with_variant(Variant::Atomic);
+        assert_eq!(perft(&mut atomic, 3), 8_902);
+        assert_eq!(perft(&mut atomic, 4), 197_326);
+        let mut antichess = GameState::default().with_variant(Variant::Antichess);
+        assert_eq!(perft(&mut antichess, 3), 8_067);
+        assert_eq!(perft(&mut antichess, 4), 153_299);
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        let mut state = GameState::default();
+        let divide = perft_divide(&mut state, 3);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8_902);
+
+        let (_, e2e4) = divide
+            .iter()
+            .find(|(mv, _)| mv.from_sq() == Square::E2 && mv.to_sq() == Square::E4)
+            .unwrap();
+        assert_eq!(*e2e4, 600);
+        assert!(perft_divide(&mut state, 0).is_empty());
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        root_moves: Vec<(Move, u64)>,
+        depths: Vec<(u64, u64)>,
+        timing: Option<PerftTiming>,
+    }
+
+    impl PerftObserver for Recorder {
+        fn on_root_move(&mut self, mv: Move, nodes: u64) {
+            self.root_moves.push((mv, nodes));
+        }
+
+        fn on_depth(&mut self, depth: u64, nodes: u64) {
+            self.depths.push((depth, nodes));
+        }
+
+        fn on_finish(&mut self, timing: &PerftTiming) {
+            self.timing = Some(*timing);
+        }
+    }
+
+    #[test]
+    fn test_perft_stops_early() {
+        let mut state = GameState::default();
+        assert_eq!(perft_until(&mut state, 5, &|| true), 0);
+        assert!(perft_divide_until(&mut state, 3, &|| true).is_empty());
+
+        // stopped after a couple of root moves are counted
+        let checks = std::cell::Cell::new(0);
+        let stopped = || {
+            checks.set(checks.get() + 1);
+            checks.get() > 50
         };
+        let mut recorder = Recorder::default();
+        let nodes = run_perft(&mut state, 3, &mut recorder, &stopped);
+        assert!(nodes < 8_902);
+        assert!(!recorder.timing.unwrap().complete);
+        assert_eq!(state, GameState::default());
+    }
+
+    #[test]
+    fn test_perft_timing_nps() {
+        let timing = PerftTiming {
+            nodes: 5_000,
+            elapsed: Duration::from_millis(250),
+            complete: true,
+        };
+        assert_eq!(timing.nps(), 20_000);
+        let instant = PerftTiming {
+            nodes: 20,
+            elapsed: Duration::ZERO,
+            complete: true,
+        };
+        assert_eq!(instant.nps(), 20_000_000);
+    }
+
+    #[test]
+    fn test_perft_observer() {
+        let mut state = GameState::default();
+        let mut recorder = Recorder::default();
+        assert_eq!(
+            run_perft_to_depth(&mut state, 3, &mut recorder),
+            vec![20, 400, 8_902]
+        );
+        assert_eq!(recorder.depths, vec![(1, 20), (2, 400), (3, 8_902)]);
+        assert_eq!(recorder.timing.unwrap().nodes, 8_902);
+        assert_eq!(recorder.root_moves.len(), 20);
+        let ucis: Vec<String> = recorder
+            .root_moves
+            .iter()
+            .map(|(mv, _)| mv.to_uci())
+            .collect();
+        assert!(ucis.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(ucis[0], "a2a3");
+        assert_eq!(
+            recorder.root_moves.iter().map(|(_, n)| n).sum::<u64>(),
+            8_902
+        );
+        assert_eq!(state, GameState::default());
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut state = GameState::from_fen(fen).unwrap();
+        assert_eq!(perft(&mut state, 1), 48);
+        assert_eq!(perft(&mut state, 2), 2_039);
+        assert_eq!(perft(&mut state, 3), 97_862);
+    }
+
+    #[test]
+    fn test_perft_en_passant_discovered_check() {
+        // after e2e4 or g2g4 the f4 pawn can't take en passant, as that would
+        // leave its king on the same rank as the rook on b4
+        let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        let mut state = GameState::from_fen(fen).unwrap();
+        assert_eq!(perft(&mut state, 1), 14);
+        assert_eq!(perft(&mut state, 2), 191);
+        assert_eq!(perft(&mut state, 3), 2_812);
+        assert_eq!(perft(&mut state, 4), 43_238);
+    }
+
+    #[test]
+    fn test_perft_detail() {
+        let mut state = GameState::default();
+        let stats = perft_detail(&mut state, 4);
+        assert_eq!(
+            stats,
+            PerftStats {
+                nodes: 197_281,
+                captures: 1_576,
+                en_passants: 0,
+                castles: 0,
+                promotions: 0,
+                checks: 469,
+                discovered_checks: 0,
+                double_checks: 0,
+                checkmates: 8,
+            }
+        );
+        assert_eq!(state, GameState::default());
+
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut state = GameState::from_fen(fen).unwrap();
+        let stats = perft_detail(&mut state, 2);
+        assert_eq!(
+            stats,
+            PerftStats {
+                nodes: 2_039,
+                captures: 351,
+                en_passants: 1,
+                castles: 91,
+                promotions: 0,
+                checks: 3,
+                discovered_checks: 0,
+                double_checks: 0,
+                checkmates: 0,
+            }
+        );
+
+        let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        let mut state = GameState::from_fen(fen).unwrap();
+        let stats = perft_detail(&mut state, 4);
+        assert_eq!(
+            stats,
+            PerftStats {
+                nodes: 43_238,
+                captures: 3_348,
+                en_passants: 123,
+                castles: 0,
+                promotions: 0,
+                checks: 1_680,
+                discovered_checks: 106,
+                double_checks: 0,
+                checkmates: 17,
+            }
+        );
     }
-    nodes
 }
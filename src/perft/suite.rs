@@ -0,0 +1,165 @@
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use crate::{epd::EpdRecord, play::GameState};
+
+use super::perft;
+
+/// One position of a perft suite and the node counts expected below it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteEntry {
+    pub fen: String,
+    /// (depth, nodes), in the order listed
+    pub expected: Vec<(u64, u64)>,
+}
+
+impl SuiteEntry {
+    /// Reads an EPD line whose operations give node counts by depth,
+    /// `<fen> ;D1 20 ;D2 400 ...`. The FEN may leave out its move clocks.
+    pub fn parse(line: &str) -> Result<SuiteEntry, String> {
+        let record = EpdRecord::parse(line).map_err(|e| e.to_string())?;
+        let expected = record
+            .operations
+            .iter()
+            .map(|(opcode, operands)| {
+                let depth = opcode.strip_prefix('D').and_then(|d| d.parse().ok());
+                let nodes = match operands.as_slice() {
+                    [nodes] => nodes.parse().ok(),
+                    _ => None,
+                };
+                match (depth, nodes) {
+                    (Some(depth), Some(nodes)) => Ok((depth, nodes)),
+                    _ => Err(format!(
+                        "bad depth field `{} {}`",
+                        opcode,
+                        operands.join(" ")
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if expected.is_empty() {
+            return Err("no depths listed".to_string());
+        }
+        Ok(SuiteEntry {
+            fen: record.to_fen(),
+            expected,
+        })
+    }
+
+    /// Counts each listed depth in turn, stopping at the first that's off,
+    /// which is returned with the count found.
+    pub fn run(&self) -> Result<Option<(u64, u64, u64)>, String> {
+        let mut state = GameState::from_fen(&self.fen).map_err(|e| e.to_string())?;
+        for &(depth, expected) in &self.expected {
+            let nodes = perft(&mut state, depth);
+            if nodes != expected {
+                return Ok(Some((depth, expected, nodes)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SuiteSummary {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+impl SuiteSummary {
+    pub fn all_passed(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+/// Runs every position in `epd`, one per line, writing whether each passed
+/// and a summary to `out`. Blank lines and those starting `#` are skipped;
+/// a line that can't be read counts as a failure.
+pub fn run_suite(epd: &str, out: &mut dyn Write) -> io::Result<SuiteSummary> {
+    let mut summary = SuiteSummary::default();
+    for (i, line) in epd.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let outcome =
+            SuiteEntry::parse(line).and_then(|entry| entry.run().map(|mismatch| (entry, mismatch)));
+        match outcome {
+            Ok((entry, None)) => {
+                summary.passed += 1;
+                let (depth, nodes) = entry.expected[entry.expected.len() - 1];
+                writeln!(out, "pass {} (D{} {})", entry.fen, depth, nodes)?;
+            }
+            Ok((entry, Some((depth, expected, nodes)))) => {
+                summary.failed += 1;
+                writeln!(
+                    out,
+                    "FAIL {} at D{}: expected {}, got {}",
+                    entry.fen, depth, expected, nodes
+                )?;
+            }
+            Err(e) => {
+                summary.failed += 1;
+                writeln!(out, "FAIL line {}: {}", i + 1, e)?;
+            }
+        }
+    }
+    writeln!(out, "{} passed, {} failed", summary.passed, summary.failed)?;
+    Ok(summary)
+}
+
+/// [`run_suite`] on the file at `path`, reporting to stdout.
+pub fn run_suite_file(path: &str) -> io::Result<SuiteSummary> {
+    let epd = fs::read_to_string(path)?;
+    run_suite(&epd, &mut io::stdout())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_entry() {
+        let entry = SuiteEntry::parse(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ;D1 20 ;D2 400",
+        )
+        .unwrap();
+        assert_eq!(
+            entry.fen,
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(entry.expected, vec![(1, 20), (2, 400)]);
+        assert!(SuiteEntry::parse("8/8/8/8/8/8/8/8 w - - ;D1").is_err());
+        assert!(SuiteEntry::parse("8/8/8/8/8/8/8/8 w - -").is_err());
+    }
+
+    #[test]
+    fn test_run_suite() {
+        let epd = "\
+# the start position, then kiwipete with a wrong count
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400
+r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ;D1 48 ;D2 2040
+
+not a position ;D1 1
+";
+        let mut out = vec![];
+        let summary = run_suite(epd, &mut out).unwrap();
+        assert_eq!(
+            summary,
+            SuiteSummary {
+                passed: 1,
+                failed: 2
+            }
+        );
+        assert!(!summary.all_passed());
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].starts_with("pass rnbqkbnr/"));
+        assert!(lines[1].ends_with("at D2: expected 2040, got 2039"));
+        assert!(lines[2].starts_with("FAIL line 5: "));
+        assert_eq!(lines[3], "1 passed, 2 failed");
+    }
+}
@@ -1,14 +1,180 @@
-use std::{
+use alloc::{format, vec::Vec};
+use core::{
     fmt::Debug,
     ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign},
 };
 
 use crate::play::{
     constants::{FILES, RANKS, SQUARES},
-    types::Square,
-    utils::set_bits,
+    types::{Color, File, Rank, Square},
 };
 
+/// The squares of each file, A first.
+pub const FILE_MASKS: [Bitboard; 8] = file_masks();
+/// The squares of each rank, the first rank first.
+pub const RANK_MASKS: [Bitboard; 8] = rank_masks();
+/// The squares of each diagonal running up to the right, by file less rank
+/// plus 7, so the long a1-h8 diagonal is 7.
+pub const DIAGONAL_MASKS: [Bitboard; 15] = diagonal_masks(false);
+/// The squares of each diagonal running up to the left, by file plus rank,
+/// so the long h1-a8 diagonal is 7.
+pub const ANTI_DIAGONAL_MASKS: [Bitboard; 15] = diagonal_masks(true);
+/// By pair of squares, those strictly between the two when they share a
+/// file, rank or diagonal, and none otherwise. Static rather than const, so
+/// the 32K table isn't copied into every use.
+pub static BETWEEN: [[Bitboard; 64]; 64] = between_table();
+/// By pair of squares, the whole file, rank or diagonal they share, edge
+/// to edge and the two included, or none when they share none.
+pub static LINE: [[Bitboard; 64]; 64] = line_table();
+/// The squares of the files either side of each file.
+pub const ADJACENT_FILE_MASKS: [Bitboard; 8] = adjacent_file_masks();
+/// By color and square, the squares in front on the same file, as that
+/// color's pawns push.
+pub const FRONT_SPANS: [[Bitboard; 64]; 2] = [front_spans(Color::White), front_spans(Color::Black)];
+/// By color and square, the front span and the squares in front on the
+/// adjacent files, which a pawn is passed once no enemy pawn stands on.
+pub const PASSED_PAWN_MASKS: [[Bitboard; 64]; 2] = [
+    passed_pawn_masks(Color::White),
+    passed_pawn_masks(Color::Black),
+];
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const FILE_H: u64 = FILE_A << 7;
+
+const fn file_masks() -> [Bitboard; 8] {
+    let mut masks = [Bitboard(0); 8];
+    let mut file = 0;
+    while file < 8 {
+        masks[file] = Bitboard(FILE_A << file);
+        file += 1;
+    }
+    masks
+}
+
+const fn rank_masks() -> [Bitboard; 8] {
+    let mut masks = [Bitboard(0); 8];
+    let mut rank = 0;
+    while rank < 8 {
+        masks[rank] = Bitboard(0xFF << (8 * rank));
+        rank += 1;
+    }
+    masks
+}
+
+const fn diagonal_masks(anti: bool) -> [Bitboard; 15] {
+    let mut masks = [Bitboard(0); 15];
+    let mut sq = 0;
+    while sq < 64 {
+        let (file, rank) = (sq % 8, sq / 8);
+        let idx = if anti { file + rank } else { file + 7 - rank };
+        masks[idx].0 |= 1 << sq;
+        sq += 1;
+    }
+    masks
+}
+
+/// The (file, rank) step from `a` toward `b`, if they share a line.
+const fn line_step(a: usize, b: usize) -> Option<(i32, i32)> {
+    let df = (b % 8) as i32 - (a % 8) as i32;
+    let dr = (b / 8) as i32 - (a / 8) as i32;
+    if a == b || !(df == 0 || dr == 0 || df == dr || df == -dr) {
+        return None;
+    }
+    Some((df.signum(), dr.signum()))
+}
+
+const fn between_table() -> [[Bitboard; 64]; 64] {
+    let mut table = [[Bitboard(0); 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut b = 0;
+        while b < 64 {
+            if let Some((df, dr)) = line_step(a, b) {
+                let mut sq = a as i32 + df + 8 * dr;
+                while sq != b as i32 {
+                    table[a][b].0 |= 1 << sq;
+                    sq += df + 8 * dr;
+                }
+            }
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+}
+
+const fn line_table() -> [[Bitboard; 64]; 64] {
+    let ranks = rank_masks();
+    let diagonals = diagonal_masks(false);
+    let anti_diagonals = diagonal_masks(true);
+    let mut table = [[Bitboard(0); 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let (file, rank) = (a % 8, a / 8);
+        let mut b = 0;
+        while b < 64 {
+            table[a][b] = match line_step(a, b) {
+                Some((0, _)) => Bitboard(FILE_A << file),
+                Some((_, 0)) => ranks[rank],
+                Some((df, dr)) if df == dr => diagonals[file + 7 - rank],
+                Some(_) => anti_diagonals[file + rank],
+                None => Bitboard(0),
+            };
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+}
+
+const fn adjacent_file_masks() -> [Bitboard; 8] {
+    let mut masks = [Bitboard(0); 8];
+    let mut file = 0;
+    while file < 8 {
+        let mut mask = 0;
+        if file > 0 {
+            mask |= FILE_A << (file - 1);
+        }
+        if file < 7 {
+            mask |= FILE_A << (file + 1);
+        }
+        masks[file] = Bitboard(mask);
+        file += 1;
+    }
+    masks
+}
+
+/// The ranks strictly in front of `rank` for `color`.
+const fn ranks_ahead(rank: usize, color: Color) -> u64 {
+    match color {
+        Color::White if rank < 7 => u64::MAX << (8 * (rank + 1)),
+        Color::White => 0,
+        Color::Black => (1 << (8 * rank)) - 1,
+    }
+}
+
+const fn front_spans(color: Color) -> [Bitboard; 64] {
+    let mut spans = [Bitboard(0); 64];
+    let mut sq = 0;
+    while sq < 64 {
+        spans[sq] = Bitboard(ranks_ahead(sq / 8, color) & (FILE_A << (sq % 8)));
+        sq += 1;
+    }
+    spans
+}
+
+const fn passed_pawn_masks(color: Color) -> [Bitboard; 64] {
+    let adjacent = adjacent_file_masks();
+    let mut masks = [Bitboard(0); 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let files = (FILE_A << (sq % 8)) | adjacent[sq % 8].0;
+        masks[sq] = Bitboard(ranks_ahead(sq / 8, color) & files);
+        sq += 1;
+    }
+    masks
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Bitboard(pub u64);
 
@@ -18,9 +184,9 @@ impl From<u64> for Bitboard {
     }
 }
 
-impl Into<u64> for Bitboard {
-    fn into(self) -> u64 {
-        self.0
+impl From<Bitboard> for u64 {
+    fn from(val: Bitboard) -> Self {
+        val.0
     }
 }
 
@@ -34,30 +200,44 @@ impl From<Vec<Square>> for Bitboard {
     }
 }
 
-impl Into<Vec<Square>> for Bitboard {
-    fn into(self) -> Vec<Square> {
-        let set = set_bits(self.into());
-        let mut squares = Vec::new();
-        for s in set {
-            if let Some(sq) = SQUARES.get(s) {
-                squares.push(*sq)
-            }
-        }
-        squares
+impl From<Bitboard> for Vec<Square> {
+    fn from(val: Bitboard) -> Self {
+        val.iter().collect()
     }
 }
 
-impl Iterator for Bitboard {
+/// The squares set in a bitboard, A1 first, popped off its lowest bit one at
+/// a time so iterating allocates nothing.
+#[derive(Debug, Clone, Copy)]
+pub struct BitboardIter(u64);
+
+impl Iterator for BitboardIter {
     type Item = Square;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let lsb = self.0.trailing_zeros();
-        if lsb == 64 {
-            None
-        } else {
-            self.0 -= 0b1 << lsb;
-            Some(SQUARES[lsb as usize])
+        if self.0 == 0x0 {
+            return None;
         }
+        let lsb = self.0.trailing_zeros();
+        // clears the lowest set bit
+        self.0 &= self.0 - 1;
+        Some(SQUARES[lsb as usize])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for BitboardIter {}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        BitboardIter(self.0)
     }
 }
 
@@ -74,9 +254,108 @@ impl Bitboard {
         self.0.count_ones()
     }
 
+    pub fn iter(&self) -> BitboardIter {
+        BitboardIter(self.0)
+    }
+
+    /// Takes the lowest set square off the board, if any is set.
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let mut iter = self.iter();
+        let sq = iter.next();
+        self.0 = iter.0;
+        sq
+    }
+
     pub fn flip(&self) -> Bitboard {
         Bitboard(!self.0)
     }
+
+    pub fn file_mask(file: File) -> Bitboard {
+        FILE_MASKS[file as usize]
+    }
+
+    pub fn rank_mask(rank: Rank) -> Bitboard {
+        RANK_MASKS[rank as usize - 1]
+    }
+
+    /// The diagonal through `sq` running up to the right.
+    pub fn diagonal_mask(sq: Square) -> Bitboard {
+        DIAGONAL_MASKS[sq as usize % 8 + 7 - sq as usize / 8]
+    }
+
+    /// The diagonal through `sq` running up to the left.
+    pub fn anti_diagonal_mask(sq: Square) -> Bitboard {
+        ANTI_DIAGONAL_MASKS[sq as usize % 8 + sq as usize / 8]
+    }
+
+    /// See [`BETWEEN`].
+    pub fn between(a: Square, b: Square) -> Bitboard {
+        BETWEEN[a as usize][b as usize]
+    }
+
+    /// See [`LINE`].
+    pub fn line(a: Square, b: Square) -> Bitboard {
+        LINE[a as usize][b as usize]
+    }
+
+    /// Every square moved one rank up, those on the eighth falling off.
+    pub fn north_one(self) -> Bitboard {
+        Bitboard(self.0 << 8)
+    }
+
+    /// Every square moved one rank down, those on the first falling off.
+    pub fn south_one(self) -> Bitboard {
+        Bitboard(self.0 >> 8)
+    }
+
+    /// Every square moved one file right, those on the H-file falling off.
+    pub fn east_one(self) -> Bitboard {
+        Bitboard((self.0 << 1) & !FILE_A)
+    }
+
+    /// Every square moved one file left, those on the A-file falling off.
+    pub fn west_one(self) -> Bitboard {
+        Bitboard((self.0 >> 1) & !FILE_H)
+    }
+
+    /// Every square and all those above it on its file.
+    pub fn north_fill(self) -> Bitboard {
+        let mut b = self.0;
+        b |= b << 8;
+        b |= b << 16;
+        b |= b << 32;
+        Bitboard(b)
+    }
+
+    /// Every square and all those below it on its file.
+    pub fn south_fill(self) -> Bitboard {
+        let mut b = self.0;
+        b |= b >> 8;
+        b |= b >> 16;
+        b |= b >> 32;
+        Bitboard(b)
+    }
+
+    /// The whole file of every square set.
+    pub fn file_fill(self) -> Bitboard {
+        self.north_fill() | self.south_fill()
+    }
+
+    pub fn front_span(color: Color, sq: Square) -> Bitboard {
+        FRONT_SPANS[color as usize][sq as usize]
+    }
+
+    pub fn passed_pawn_mask(color: Color, sq: Square) -> Bitboard {
+        PASSED_PAWN_MASKS[color as usize][sq as usize]
+    }
+
+    /// The squares pawns of `color` on this board's squares attack.
+    pub fn pawn_attacks(self, color: Color) -> Bitboard {
+        Bitboard(match color {
+            Color::White => ((self.0 << 7) & !FILE_H) | ((self.0 << 9) & !FILE_A),
+            Color::Black => ((self.0 >> 7) & !FILE_A) | ((self.0 >> 9) & !FILE_H),
+        })
+    }
 }
 
 impl BitAnd for Bitboard {
@@ -122,7 +401,7 @@ impl BitXorAssign for Bitboard {
 }
 
 impl Debug for Bitboard {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let line_br = "+---+---+---+---+---+---+---+---+\n";
         f.write_str(line_br)?;
         for rank in RANKS.iter().rev() {
@@ -149,6 +428,129 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_pawn_masks() {
+        let e2_front: Bitboard = vec![
+            Square::E3,
+            Square::E4,
+            Square::E5,
+            Square::E6,
+            Square::E7,
+            Square::E8,
+        ]
+        .into();
+        assert_eq!(Bitboard::front_span(Color::White, Square::E2), e2_front);
+        assert_eq!(
+            Bitboard::front_span(Color::Black, Square::A2),
+            Square::A1.into()
+        );
+        assert_eq!(
+            Bitboard::front_span(Color::White, Square::H8),
+            Bitboard::empty()
+        );
+        let h6_passed: Bitboard = vec![Square::G7, Square::H7, Square::G8, Square::H8].into();
+        assert_eq!(
+            Bitboard::passed_pawn_mask(Color::White, Square::H6),
+            h6_passed
+        );
+        assert_eq!(ADJACENT_FILE_MASKS[0], FILE_MASKS[1]);
+        assert_eq!(
+            Bitboard::from(vec![Square::A2, Square::H7]).pawn_attacks(Color::White),
+            vec![Square::B3, Square::G8].into()
+        );
+    }
+
+    #[test]
+    fn test_masks() {
+        assert_eq!(Bitboard::file_mask(File::C), FILE_MASKS[2]);
+        assert_eq!(Bitboard::rank_mask(Rank::Rank1), Bitboard(0xFF));
+        assert_eq!(
+            Bitboard::diagonal_mask(Square::C3),
+            DIAGONAL_MASKS[7],
+            "c3 is on the long diagonal"
+        );
+        assert_eq!(DIAGONAL_MASKS[7].pop_count(), 8);
+        assert_eq!(
+            Bitboard::diagonal_mask(Square::G1),
+            vec![Square::G1, Square::H2].into()
+        );
+        assert_eq!(
+            Bitboard::anti_diagonal_mask(Square::B2),
+            vec![Square::C1, Square::B2, Square::A3].into()
+        );
+        assert_eq!(ANTI_DIAGONAL_MASKS[0], Square::A1.into());
+        assert_eq!(ANTI_DIAGONAL_MASKS[14], Square::H8.into());
+        for sq in SQUARES {
+            for mask in [
+                Bitboard::file_mask(sq.file()),
+                Bitboard::rank_mask(sq.rank()),
+                Bitboard::diagonal_mask(sq),
+                Bitboard::anti_diagonal_mask(sq),
+            ] {
+                assert_ne!(mask & sq.into(), Bitboard::empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_between_and_line() {
+        assert_eq!(
+            Bitboard::between(Square::A1, Square::D4),
+            vec![Square::B2, Square::C3].into()
+        );
+        assert_eq!(
+            Bitboard::between(Square::H4, Square::E4),
+            vec![Square::G4, Square::F4].into()
+        );
+        assert_eq!(
+            Bitboard::between(Square::E8, Square::E5),
+            vec![Square::E7, Square::E6].into()
+        );
+        assert_eq!(Bitboard::between(Square::E4, Square::E5), Bitboard::empty());
+        // not on a line, or the same square
+        assert_eq!(Bitboard::between(Square::A1, Square::B3), Bitboard::empty());
+        assert_eq!(Bitboard::line(Square::A1, Square::B3), Bitboard::empty());
+        assert_eq!(Bitboard::line(Square::E4, Square::E4), Bitboard::empty());
+
+        assert_eq!(Bitboard::line(Square::B2, Square::C3), DIAGONAL_MASKS[7]);
+        assert_eq!(Bitboard::line(Square::E2, Square::E7), FILE_MASKS[4]);
+        assert_eq!(Bitboard::line(Square::H3, Square::A3), RANK_MASKS[2]);
+        assert_eq!(
+            Bitboard::line(Square::D1, Square::A4),
+            Bitboard::anti_diagonal_mask(Square::C2)
+        );
+        for a in SQUARES {
+            for b in SQUARES {
+                assert_eq!(BETWEEN[a as usize][b as usize], Bitboard::between(b, a));
+                let between = Bitboard::between(a, b);
+                assert_eq!(between & Bitboard::line(a, b), between);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shifts_and_fills() {
+        let bb: Bitboard = vec![Square::A1, Square::H4, Square::D8].into();
+        assert_eq!(bb.north_one(), vec![Square::A2, Square::H5].into());
+        assert_eq!(bb.south_one(), vec![Square::H3, Square::D7].into());
+        assert_eq!(bb.east_one(), vec![Square::B1, Square::E8].into());
+        assert_eq!(bb.west_one(), vec![Square::G4, Square::C8].into());
+
+        let e4 = Bitboard::from(Square::E4);
+        assert_eq!(
+            e4.north_fill(),
+            Bitboard::front_span(Color::White, Square::E4) | e4
+        );
+        assert_eq!(
+            e4.south_fill(),
+            Bitboard::front_span(Color::Black, Square::E4) | e4
+        );
+        assert_eq!(
+            bb.file_fill(),
+            FILE_MASKS[0] | FILE_MASKS[3] | FILE_MASKS[7]
+        );
+    }
+
     #[test]
     fn test_from_u64() {
         let b = 0x15;
@@ -212,6 +614,25 @@ mod tests {
         assert_eq!(no_sq, no_sq_from_bb);
     }
 
+    #[test]
+    fn test_iter_and_pop_lsb() {
+        let mut bb = Bitboard::from(vec![Square::H8, Square::C3, Square::A1]);
+        let iter = bb.iter();
+        assert_eq!(iter.len(), 3);
+        // iterating leaves the bitboard itself alone
+        assert_eq!(
+            iter.collect::<Vec<Square>>(),
+            vec![Square::A1, Square::C3, Square::H8]
+        );
+        assert_eq!(bb.pop_count(), 3);
+
+        assert_eq!(bb.pop_lsb(), Some(Square::A1));
+        assert_eq!(bb.pop_lsb(), Some(Square::C3));
+        assert_eq!(bb, Square::H8.into());
+        assert_eq!(bb.pop_lsb(), Some(Square::H8));
+        assert_eq!(bb.pop_lsb(), None);
+    }
+
     #[test]
     fn test_bit_and() {
         let bb = Bitboard(0x15);
@@ -0,0 +1,370 @@
+use alloc::vec::Vec;
+
+use crate::play::{
+    board::bitboard::Bitboard,
+    constants::SQUARES,
+    once::Once,
+    types::{Direction, Square},
+};
+
+static ATTACKS: Once<SliderAttacks> = Once::new();
+
+/// the rays along files and ranks, and along diagonals
+const ROOK_DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+const BISHOP_DIRECTIONS: [Direction; 4] = [
+    Direction::NorthEast,
+    Direction::SouthEast,
+    Direction::SouthWest,
+    Direction::NorthWest,
+];
+
+// found once by trying sparse random numbers until each square's blocker
+// occupancies all landed on slots they could share, which is too slow to
+// redo on every start
+/// indexed by square, A1 first
+const ROOK_MAGICS: [u64; 64] = [
+    0x0080001020804000,
+    0x0040400020001000,
+    0xc100090040102000,
+    0x2080041000800800,
+    0x5d00030004100800,
+    0x2e00820024003008,
+    0x210001001a0000c4,
+    0x4200008844020025,
+    0x0414800080264014,
+    0x8005400040201000,
+    0xa000802000100080,
+    0x0000800800801000,
+    0x81c0808008000400,
+    0x4002000200051008,
+    0x0031000200840100,
+    0x00420018944a0504,
+    0x10c2708001400a80,
+    0x4090004040002002,
+    0x0000420022008014,
+    0x0422020010200840,
+    0x5400050008010010,
+    0x4012008004008002,
+    0x5280440008b00112,
+    0x0402020005027484,
+    0x9020882480004000,
+    0x0000400100210084,
+    0x0200200080801000,
+    0x0126000a00204011,
+    0x0208000880800400,
+    0x5212000200080410,
+    0x0204010400081002,
+    0x0460240200004081,
+    0x8580084005402000,
+    0x0110400090802000,
+    0x0048441101002000,
+    0x0130400a02001020,
+    0x0118010045000810,
+    0x5004004100400200,
+    0xc001424804001001,
+    0x0000010092000054,
+    0x3800208040008004,
+    0x2450002000404001,
+    0x0090008020008010,
+    0x0000081001010020,
+    0x0c00040008008080,
+    0x0402000804010100,
+    0x8900484210040081,
+    0x8041008120420004,
+    0x10c081003a004200,
+    0x0000802000400480,
+    0x0210040800200020,
+    0x0021102108420200,
+    0x0000800400080080,
+    0x2200040080020080,
+    0x0a00280192100400,
+    0x08047c088b264200,
+    0x441a0141008a5022,
+    0x8108400104821021,
+    0x20051048c2200101,
+    0x0500289001002025,
+    0x0182000810042002,
+    0x0006008410280142,
+    0x0000081012014084,
+    0x0003000022008041,
+];
+/// indexed by square, A1 first
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0004041002060115,
+    0x0a08420084110002,
+    0x00090a0a0200101a,
+    0x4004043380108c84,
+    0x20140d200008a008,
+    0x0002301008000300,
+    0x0401009024e10100,
+    0x0030202410080800,
+    0x200848280108460c,
+    0x0200a22202460200,
+    0x0000300109530280,
+    0x1001040400900000,
+    0x04040c5040000e10,
+    0x0080110108c00000,
+    0x0000004410055000,
+    0xa002020049480810,
+    0x0004500820086202,
+    0x0008026088010041,
+    0x0092013004004008,
+    0x0824040844000840,
+    0x0008222402080048,
+    0x0341000810480420,
+    0x4001008058080420,
+    0x0000440024020818,
+    0x0008040008901002,
+    0x0010024825080200,
+    0x084c044002080100,
+    0x0401040008040890,
+    0x0000820004010408,
+    0x00a10600040a0104,
+    0x448085143c040200,
+    0x4004022089108a00,
+    0x1050021000228448,
+    0x0000900808040830,
+    0x4408492600500402,
+    0xb101010800610040,
+    0x0a120084008a0020,
+    0x0810044200054500,
+    0x440808004ca12100,
+    0x0184045082004400,
+    0x0048221005421003,
+    0x0084a28c50092000,
+    0x1001001802100400,
+    0x1001804010400204,
+    0x2504211124000201,
+    0x4021010501040200,
+    0x2002240842000084,
+    0x0088281050840c40,
+    0x0000411010120008,
+    0x0000242a08140000,
+    0x0000821084040800,
+    0x8800004042088081,
+    0x0121034010410804,
+    0x22144010c2808200,
+    0x0484049004210310,
+    0x200410022a022000,
+    0x090102880c882c00,
+    0x7011c08400880448,
+    0x0102000429180801,
+    0x0050000800840400,
+    0x0000022108102420,
+    0x800c000902188202,
+    0x0000220242480100,
+    0x0002200204105080,
+];
+
+/// Where a square's attack sets live in the shared table: the occupancy of
+/// the squares that can block it, multiplied by the magic and shifted down,
+/// indexes them without any collision that would give a wrong set.
+#[derive(Debug, Clone, Copy)]
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: u64) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+/// Lookup tables giving the squares a rook or bishop attacks from any square
+/// with any occupancy, in place of walking its rays square by square.
+pub struct SliderAttacks {
+    rook_magics: Vec<Magic>,
+    bishop_magics: Vec<Magic>,
+    table: Vec<u64>,
+}
+
+impl SliderAttacks {
+    pub fn new() -> Self {
+        let mut table = Vec::new();
+        let rook_magics = SQUARES
+            .iter()
+            .map(|sq| fill_slots(*sq, &ROOK_DIRECTIONS, ROOK_MAGICS[*sq as usize], &mut table))
+            .collect();
+        let bishop_magics = SQUARES
+            .iter()
+            .map(|sq| {
+                fill_slots(
+                    *sq,
+                    &BISHOP_DIRECTIONS,
+                    BISHOP_MAGICS[*sq as usize],
+                    &mut table,
+                )
+            })
+            .collect();
+        SliderAttacks {
+            rook_magics,
+            bishop_magics,
+            table,
+        }
+    }
+
+    /// The tables every attack lookup goes through, built on first use.
+    pub fn global() -> &'static SliderAttacks {
+        ATTACKS.get_or_init(SliderAttacks::new)
+    }
+
+    pub fn rook(&self, sq: Square, occupied: Bitboard) -> Bitboard {
+        Bitboard(self.table[self.rook_magics[sq as usize].index(occupied.0)])
+    }
+
+    pub fn bishop(&self, sq: Square, occupied: Bitboard) -> Bitboard {
+        Bitboard(self.table[self.bishop_magics[sq as usize].index(occupied.0)])
+    }
+
+    pub fn queen(&self, sq: Square, occupied: Bitboard) -> Bitboard {
+        self.rook(sq, occupied) | self.bishop(sq, occupied)
+    }
+}
+
+impl Default for SliderAttacks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Attacks found the slow way, walking each ray up to the first piece.
+fn ray_attacks(sq: Square, occupied: u64, directions: &[Direction]) -> u64 {
+    let mut attacks = 0;
+    for direction in directions {
+        for to in sq.ray_iter(*direction) {
+            attacks |= 0x1 << to as u64;
+            if occupied & (0x1 << to as u64) != 0x0 {
+                break;
+            }
+        }
+    }
+    attacks
+}
+
+/// The squares whose occupancy matters to a slider on `sq`: its rays, less
+/// the last square of each, which is attacked whether it's occupied or not.
+fn blocker_mask(sq: Square, directions: &[Direction]) -> u64 {
+    let mut mask = 0;
+    for direction in directions {
+        let mut ray = sq.ray_iter(*direction).peekable();
+        while let Some(to) = ray.next() {
+            if ray.peek().is_some() {
+                mask |= 0x1 << to as u64;
+            }
+        }
+    }
+    mask
+}
+
+/// Appends the attack sets of a slider on `sq` to `table`, in the slots
+/// `magic` maps each occupancy of its blockers to.
+fn fill_slots(sq: Square, directions: &[Direction], magic: u64, table: &mut Vec<u64>) -> Magic {
+    let mask = blocker_mask(sq, directions);
+    let bits = mask.count_ones();
+    let magic = Magic {
+        mask,
+        magic,
+        shift: 64 - bits,
+        offset: table.len(),
+    };
+    table.resize(table.len() + (1 << bits), 0);
+    // every subset of the mask, by the carry-rippler trick
+    let mut occupied = 0u64;
+    loop {
+        let attacks = ray_attacks(sq, occupied, directions);
+        let slot = &mut table[magic.index(occupied)];
+        debug_assert!(
+            *slot == 0 || *slot == attacks,
+            "bad magic for square {}",
+            sq
+        );
+        *slot = attacks;
+        occupied = occupied.wrapping_sub(mask) & mask;
+        if occupied == 0 {
+            break;
+        }
+    }
+    magic
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_blocker_mask() {
+        // a rook in the corner sees six squares up and six along that matter
+        assert_eq!(blocker_mask(Square::A1, &ROOK_DIRECTIONS).count_ones(), 12);
+        assert_eq!(blocker_mask(Square::D4, &ROOK_DIRECTIONS).count_ones(), 10);
+        assert_eq!(blocker_mask(Square::D4, &BISHOP_DIRECTIONS).count_ones(), 9);
+        assert_eq!(blocker_mask(Square::A1, &BISHOP_DIRECTIONS).count_ones(), 6);
+    }
+
+    #[test]
+    fn test_lookups_match_ray_walks() {
+        let attacks = SliderAttacks::global();
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let occupied = rng.gen::<u64>() & rng.gen::<u64>();
+            for sq in SQUARES {
+                assert_eq!(
+                    attacks.rook(sq, Bitboard(occupied)).0,
+                    ray_attacks(sq, occupied, &ROOK_DIRECTIONS)
+                );
+                assert_eq!(
+                    attacks.bishop(sq, Bitboard(occupied)).0,
+                    ray_attacks(sq, occupied, &BISHOP_DIRECTIONS)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rook_and_bishop_attacks() {
+        let attacks = SliderAttacks::global();
+        let occupied = Bitboard::from(vec![Square::D6, Square::F4, Square::B2]);
+        let expected = Bitboard::from(vec![
+            Square::D5,
+            Square::D6,
+            Square::D3,
+            Square::D2,
+            Square::D1,
+            Square::E4,
+            Square::F4,
+            Square::C4,
+            Square::B4,
+            Square::A4,
+        ]);
+        assert_eq!(attacks.rook(Square::D4, occupied), expected);
+        let expected = Bitboard::from(vec![
+            Square::E5,
+            Square::F6,
+            Square::G7,
+            Square::H8,
+            Square::E3,
+            Square::F2,
+            Square::G1,
+            Square::C3,
+            Square::B2,
+            Square::C5,
+            Square::B6,
+            Square::A7,
+        ]);
+        assert_eq!(attacks.bishop(Square::D4, occupied), expected);
+        assert_eq!(
+            attacks.queen(Square::D4, occupied),
+            attacks.rook(Square::D4, occupied) | attacks.bishop(Square::D4, occupied)
+        );
+    }
+}
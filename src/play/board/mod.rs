@@ -1,15 +1,24 @@
-pub(in crate::play) mod bitboard;
-
-use std::{convert::TryFrom, fmt};
+pub mod bitboard;
+pub(in crate::play) mod magic;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{convert::TryFrom, fmt};
 
 use crate::play::{
-    board::bitboard::Bitboard,
-    constants::{BLACK_PIECES, DIRECTIONS, FILES, RANKS, SQUARES, WHITE_PIECES},
+    board::{bitboard::Bitboard, magic::SliderAttacks},
+    constants::{BLACK_PIECES, DIRECTIONS, FILES, PIECE_VALUES, RANKS, SQUARES, WHITE_PIECES},
     error::{FENParsingError, MoveError, MoveErrorType},
-    types::{Color, Piece, Rank, Square},
-    utils::{self, set_bits},
+    once::Once,
+    types::{Color, Direction, Piece, PieceType, Rank, Square},
 };
 
+static KNIGHT_ATTACKS: Once<[Bitboard; 64]> = Once::new();
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Board {
     white_pawns: Bitboard,
@@ -44,6 +53,77 @@ impl Board {
         }
     }
 
+    /// Every occupied square.
+    pub fn occupied(&self) -> Bitboard {
+        self.bitboard_union()
+    }
+
+    /// The squares occupied by `color`.
+    pub fn occupied_by(&self, color: Color) -> Bitboard {
+        match color {
+            Color::White => {
+                self.white_pawns
+                    | self.white_knights
+                    | self.white_bishops
+                    | self.white_rooks
+                    | self.white_queens
+                    | self.white_king
+            }
+            Color::Black => {
+                self.black_pawns
+                    | self.black_bishops
+                    | self.black_knights
+                    | self.black_rooks
+                    | self.black_queens
+                    | self.black_king
+            }
+        }
+    }
+
+    /// The squares a rook, bishop or queen on `sq` attacks, up to and
+    /// including the first piece in each direction.
+    pub fn slider_attacks(&self, piece_type: PieceType, sq: Square) -> Bitboard {
+        let attacks = SliderAttacks::global();
+        let occupied = self.bitboard_union();
+        match piece_type {
+            PieceType::Rook => attacks.rook(sq, occupied),
+            PieceType::Bishop => attacks.bishop(sq, occupied),
+            PieceType::Queen => attacks.queen(sq, occupied),
+            _ => Bitboard::empty(),
+        }
+    }
+
+    /// The squares a knight on `sq` attacks, looked up rather than stepped
+    /// out so evaluation can afford it at every node.
+    pub fn knight_attacks(sq: Square) -> Bitboard {
+        KNIGHT_ATTACKS.get_or_init(|| {
+            let mut attacks = [Bitboard::empty(); 64];
+            for from in SQUARES {
+                for dir in &DIRECTIONS[Piece::WhiteKnight.attack_direction_idx()] {
+                    if let Some(to) = from.offset(*dir) {
+                        attacks[from as usize] |= to.into();
+                    }
+                }
+            }
+            attacks
+        })[sq as usize]
+    }
+
+    /// The squares a knight, bishop, rook or queen on `sq` attacks, as
+    /// [`Board::slider_attacks`] counts them for sliders.
+    pub fn piece_attacks(&self, piece_type: PieceType, sq: Square) -> Bitboard {
+        match piece_type {
+            PieceType::Knight => Board::knight_attacks(sq),
+            _ => self.slider_attacks(piece_type, sq),
+        }
+    }
+
+    /// Every square `color`'s pawns attack.
+    pub fn pawn_attacks(&self, color: Color) -> Bitboard {
+        self.bitboard(Piece::of(PieceType::Pawn, color))
+            .pawn_attacks(color)
+    }
+
     fn bitboard_union(&self) -> Bitboard {
         self.white_pawns
             | self.white_knights
@@ -81,31 +161,13 @@ impl Board {
     }
 
     pub fn sq_taken_by_color(&self, sq: Square, color: Color) -> bool {
-        let bb = match color {
-            Color::White => {
-                self.white_pawns
-                    | self.white_knights
-                    | self.white_bishops
-                    | self.white_rooks
-                    | self.white_queens
-                    | self.white_king
-            }
-            Color::Black => {
-                self.black_pawns
-                    | self.black_bishops
-                    | self.black_knights
-                    | self.black_rooks
-                    | self.black_queens
-                    | self.black_king
-            }
-        };
-        (bb & sq.into()).0 != 0x0
+        (self.occupied_by(color) & sq.into()).0 != 0x0
     }
 
     pub fn add_piece(&mut self, piece: Piece, sq: Square) -> Result<(), MoveError> {
         let sq_bb: Bitboard = sq.into();
         if self.sq_taken(sq) {
-            Err(MoveError::new(MoveErrorType::SquareTakenError(sq)))
+            Err(MoveError::new(MoveErrorType::SquareTaken(sq)))
         } else {
             match piece {
                 Piece::WhitePawn => self.white_pawns |= sq_bb,
@@ -145,13 +207,13 @@ impl Board {
                 }
                 Ok(piece)
             }
-            None => Err(MoveError::new(MoveErrorType::NoPieceOnSquareError(sq))),
+            None => Err(MoveError::new(MoveErrorType::NoPieceOnSquare(sq))),
         }
     }
 
     pub fn move_piece(&mut self, origin: Square, dest: Square) -> Result<(), MoveError> {
         if self.sq_taken(dest) {
-            Err(MoveError::new(MoveErrorType::SquareTakenError(dest)))
+            Err(MoveError::new(MoveErrorType::SquareTaken(dest)))
         } else {
             let piece = self.piece(&origin).unwrap();
             self.remove_piece(origin)?;
@@ -175,6 +237,17 @@ impl Board {
         v
     }
 
+    pub fn material(&self, color: Color) -> u32 {
+        let piece_arr = match color {
+            Color::White => WHITE_PIECES,
+            Color::Black => BLACK_PIECES,
+        };
+        piece_arr
+            .iter()
+            .map(|piece| self.bitboard(*piece).pop_count() * PIECE_VALUES[*piece as usize])
+            .sum()
+    }
+
     pub fn piece(&self, sq: &Square) -> Option<Piece> {
         let sq_bb: Bitboard = (*sq).into();
         if (sq_bb & self.white_pawns).0 != 0x0 {
@@ -206,121 +279,213 @@ impl Board {
         }
     }
 
+    /// The board of a FEN's piece placement field, which must list exactly
+    /// eight ranks of exactly eight squares each, rank 8 first.
     pub fn from_fen(fen: impl ToString) -> Result<Board, FENParsingError> {
         let mut board = Board::empty();
-        let line_break = '/';
-        let mut sq_counter: usize = 56; // start with A8 == 56
-
-        for ch in fen.to_string().chars() {
-            if ch == line_break {
-                sq_counter -= 8 * 2;
-                continue;
+        let fen = fen.to_string();
+        let ranks: Vec<&str> = fen.split('/').collect();
+        if ranks.len() != RANKS.len() {
+            return Err(FENParsingError::new(format!(
+                "Error parsing fen: {} has {} ranks, expected {}",
+                fen,
+                ranks.len(),
+                RANKS.len()
+            )));
+        }
+        for (&rank, squares) in RANKS.iter().rev().zip(ranks) {
+            let mut file_idx: usize = 0;
+            for ch in squares.chars() {
+                let next = match ch.to_digit(10) {
+                    Some(d @ 1..=8) => file_idx.checked_add(d as usize),
+                    Some(_) => None,
+                    None => {
+                        let piece = Piece::try_from(ch)?;
+                        let file = FILES.get(file_idx).ok_or_else(|| rank_error(&fen, rank))?;
+                        let _ = board.add_piece(piece, Square::new(*file, rank));
+                        file_idx.checked_add(1)
+                    }
+                };
+                file_idx = next
+                    .filter(|&idx| idx <= FILES.len())
+                    .ok_or_else(|| rank_error(&fen, rank))?;
             }
-            if let Some(d) = ch.to_digit(10) {
-                sq_counter += d as usize;
-            } else {
-                let piece = Piece::try_from(ch)?;
-                let sq = Square::try_from(sq_counter)?;
-                let _ = board.add_piece(piece, sq);
-                sq_counter += 1;
+            if file_idx != FILES.len() {
+                return Err(rank_error(&fen, rank));
             }
         }
         Ok(board)
     }
 
-    fn king_sq(&self, color: Color) -> Square {
+    /// The piece placement field of a FEN, rank 8 first.
+    pub fn to_fen(self) -> String {
+        let mut fen = String::new();
+        for rank in RANKS.iter().rev() {
+            let mut empty = 0;
+            for &file in FILES.iter() {
+                match self.piece(&Square::new(file, *rank)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.into());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if *rank != Rank::Rank1 {
+                fen.push('/');
+            }
+        }
+        fen
+    }
+
+    /// The square of `color`'s king, read off its bitboard's lowest set bit,
+    /// or `None` when it has none: an antichess king can be taken, and an
+    /// atomic one blown up.
+    pub fn king_square(&self, color: Color) -> Option<Square> {
         let king_bb = match color {
             Color::White => self.white_king,
             Color::Black => self.black_king,
         };
-        let set = set_bits(king_bb.0);
-        SQUARES[set[0]]
+        king_bb.iter().next()
     }
 
+    /// Whether `color`'s king is attacked, never so for a side without one.
     pub fn is_king_in_check(&self, color: Color) -> bool {
-        let sq = self.king_sq(color);
-        return self.is_square_attacked(sq, color.opposing());
+        self.king_square(color)
+            .is_some_and(|sq| self.is_square_attacked(sq, color.opposing()))
     }
 
-    // TODO (tcd 9/2/24): this also evaluates to true if the king was in check
-    // when the caller called this method, which means it indicates that every
-    // piece is "pinned" when you call it like that
     pub fn is_square_pinned(&self, sq: &Square) -> bool {
-        if let Some(piece) = self.piece(sq) {
-            let square: Square = *sq;
-            let sq_bb: Bitboard = square.into();
-            let mut future_board: Board = self.clone();
+        self.pin_ray(sq).is_some()
+    }
 
-            match piece {
-                Piece::WhitePawn => future_board.white_pawns &= sq_bb.flip(),
-                Piece::WhiteKnight => future_board.white_knights &= sq_bb.flip(),
-                Piece::WhiteBishop => future_board.white_bishops &= sq_bb.flip(),
-                Piece::WhiteRook => future_board.white_rooks &= sq_bb.flip(),
-                Piece::WhiteQueen => future_board.white_queens &= sq_bb.flip(),
-                Piece::WhiteKing => return false,
-                Piece::BlackPawn => future_board.black_pawns &= sq_bb.flip(),
-                Piece::BlackKnight => future_board.black_knights &= sq_bb.flip(),
-                Piece::BlackBishop => future_board.black_bishops &= sq_bb.flip(),
-                Piece::BlackRook => future_board.black_rooks &= sq_bb.flip(),
-                Piece::BlackQueen => future_board.black_queens &= sq_bb.flip(),
-                Piece::BlackKing => return false,
-            }
+    /// When the piece on `sq` is pinned to its own king, the squares it can
+    /// still move to without exposing the king: those between the king and
+    /// the pinning slider, and the pinner's own.
+    pub fn pin_ray(&self, sq: &Square) -> Option<Bitboard> {
+        let piece = self.piece(sq)?;
+        self.pins(piece.color())
+            .into_iter()
+            .find(|(pinned, _)| pinned == sq)
+            .map(|(_, ray)| ray)
+    }
 
-            let king_sq = self.king_sq(piece.color());
-            future_board.is_square_attacked(king_sq, piece.color().opposing())
-        } else {
-            false
+    /// Every piece of `color` pinned to its king, with its pin ray as
+    /// [`Board::pin_ray`] gives it.
+    pub fn pins(&self, color: Color) -> Vec<(Square, Bitboard)> {
+        let king_sq = match self.king_square(color) {
+            Some(sq) => sq,
+            None => return vec![],
+        };
+        let enemy = color.opposing();
+        let attacks = SliderAttacks::global();
+        let queens = self.bitboard(Piece::of(PieceType::Queen, enemy));
+        let rooks = self.bitboard(Piece::of(PieceType::Rook, enemy)) | queens;
+        let bishops = self.bitboard(Piece::of(PieceType::Bishop, enemy)) | queens;
+        // sliders that would attack the king were none of its own pieces in the way
+        let enemies = self.occupied_by(enemy);
+        let on_file_or_rank = attacks.rook(king_sq, enemies) & rooks;
+        let on_diagonal = attacks.bishop(king_sq, enemies) & bishops;
+
+        let occupied = self.occupied();
+        let mut pins = vec![];
+        for sniper in on_file_or_rank | on_diagonal {
+            let between = Bitboard::between(king_sq, sniper);
+            let blockers = between & occupied;
+            if blockers.pop_count() == 1 {
+                pins.push((Square::from_bitboard(blockers), between | sniper.into()));
+            }
         }
+        pins
     }
 
     pub fn is_square_attacked(&self, sq: Square, color: Color) -> bool {
+        self.attackers_bitboard(sq, color).0 != 0x0
+    }
+
+    /// The pieces of `color` attacking `sq`. Sliders only see through empty
+    /// squares, so a piece lined up behind another isn't included.
+    pub fn attackers_bitboard(&self, sq: Square, color: Color) -> Bitboard {
         let piece_array: &[Piece; 6] = match color {
             Color::White => &WHITE_PIECES,
             Color::Black => &BLACK_PIECES,
         };
+        let mut attackers = Bitboard::empty();
         for piece in piece_array {
-            let attack_dirs = DIRECTIONS[piece.attack_direction_idx()].to_vec();
             let attack_bb = self.bitboard(*piece);
-
             if attack_bb.0 == 0x0 {
                 continue;
             }
+            // a slider attacks sq from wherever it would attack from sq
+            if piece.can_slide() {
+                attackers |= self.slider_attacks(piece.piece_type(), sq) & attack_bb;
+                continue;
+            }
+            for dir in &DIRECTIONS[piece.attack_direction_idx()] {
+                if let Some(from) = sq.offset(*dir) {
+                    attackers |= Bitboard::from(from) & attack_bb;
+                }
+            }
+        }
+        attackers
+    }
 
-            if !piece.can_slide() {
-                for dir in attack_dirs {
-                    let mailbox_no = sq + dir as i8;
-                    if mailbox_no >= 0 {
-                        let other_sq_bb: Bitboard = Square::from_mailbox_no(mailbox_no).into();
-                        if (other_sq_bb & attack_bb).0 != 0x0 {
-                            return true;
-                        }
-                    }
+    /// Every square attacked by `color`, including those holding its own
+    /// pieces, i.e. the squares it defends.
+    pub fn attack_map(&self, color: Color) -> Bitboard {
+        let piece_array: &[Piece; 6] = match color {
+            Color::White => &WHITE_PIECES,
+            Color::Black => &BLACK_PIECES,
+        };
+        let mut attacked = Bitboard::empty();
+        for piece in piece_array {
+            if piece.can_slide() {
+                for sq in self.bitboard(*piece) {
+                    attacked |= self.slider_attacks(piece.piece_type(), sq);
                 }
+                continue;
+            }
+            // pawns attack forwards, unlike the direction they're found in
+            let dirs: &[Direction] = if piece.piece_type() == PieceType::Pawn {
+                color.pawn_diagonals()
             } else {
-                if utils::recur_attack_sq_search(&self, attack_dirs, sq, 1, attack_bb) {
-                    return true;
+                &DIRECTIONS[piece.attack_direction_idx()]
+            };
+            for sq in self.bitboard(*piece) {
+                for dir in dirs {
+                    if let Some(to) = sq.offset(*dir) {
+                        attacked |= to.into();
+                    }
                 }
             }
         }
-        false
+        attacked
     }
 
-    pub fn attackers_bitboard(&self, sq: Square, color: Color) -> Bitboard {
-        let mut bb = Bitboard::empty();
-
-        bb
+    /// The pieces of `color`, its king aside, that the opponent attacks and
+    /// none of its own defend.
+    pub fn hanging_pieces(&self, color: Color) -> Bitboard {
+        let king = self.bitboard(Piece::of(PieceType::King, color));
+        Bitboard(
+            self.occupied_by(color).0
+                & !king.0
+                & self.attack_map(color.opposing()).0
+                & !self.attack_map(color).0,
+        )
     }
 
-    pub fn pinners_bitboard(&self, sq: Square, color: Color) -> Bitboard {
-        let mut bb = Bitboard::empty();
-
-        bb
+    pub fn pinners_bitboard(&self, _sq: Square, _color: Color) -> Bitboard {
+        Bitboard::empty()
     }
 
-    pub fn blockers_bitboard(&self, sq: Square, color: Color) -> Bitboard {
-        let mut bb = Bitboard::empty();
-
-        bb
+    pub fn blockers_bitboard(&self, _sq: Square, _color: Color) -> Bitboard {
+        Bitboard::empty()
     }
 }
 
@@ -344,7 +509,7 @@ impl Default for Board {
 }
 
 impl fmt::Debug for Board {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let line_br = "\n  +---+---+---+---+---+---+---+---+\n";
         f.write_str(line_br)?;
         for rank in RANKS.iter().rev() {
@@ -388,10 +553,21 @@ impl fmt::Debug for Board {
     }
 }
 
+/// The attacks of a rook or bishop on `sq` as though only `occupied` were
+/// taken.
+/// The error for a rank of a FEN's piece placement that isn't eight
+/// squares long.
+fn rank_error(fen: &str, rank: Rank) -> FENParsingError {
+    FENParsingError::new(format!(
+        "Error parsing fen: {} doesn't have 8 squares on rank {}",
+        fen, rank
+    ))
+}
+
 #[cfg(test)]
 mod tests {
 
-    use crate::play::board::bitboard::Bitboard;
+    use crate::play::{board::bitboard::Bitboard, constants::SQUARES};
 
     use super::*;
 
@@ -469,6 +645,19 @@ mod tests {
         assert!(board.remove_piece(Square::B1).is_err());
     }
 
+    #[test]
+    fn test_material() {
+        let board = Board::default();
+        assert_eq!(board.material(Color::White), board.material(Color::Black));
+
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR";
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(
+            board.material(Color::Black) - board.material(Color::White),
+            PIECE_VALUES[Piece::WhiteQueen as usize]
+        );
+    }
+
     #[test]
     fn test_board_from_fen() {
         let start_pos = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
@@ -477,20 +666,113 @@ mod tests {
     }
 
     #[test]
-    fn test_king_sq() {
+    fn test_board_from_fen_shape() {
+        for fen in [
+            "k/k/k/k/k/8/8/K",
+            "4k3/9/8/8/8/8/8/4K3",
+            "4k3/7/8/8/8/8/8/4K3",
+            "4k3/8/8/8/8/8/8/4K3/8",
+            "4k3/8/8/8/8/8/4K3",
+            "4k3/8/8/8/8/8/8/4K4",
+            "4k3/8/8/8/8/8/8/4K2p1",
+            "4k3/8/8/8/8/8/8/0K7",
+        ] {
+            assert!(Board::from_fen(fen).is_err(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_king_square() {
         let fen = "rnbqk1nr/ppp2ppp/4p3/b2NP2Q/3P4/2P2P2/PP4PP/R1B1KBNR";
         let board = Board::from_fen(fen).unwrap();
-        assert_eq!(board.king_sq(Color::White), Square::E1);
-        assert_eq!(board.king_sq(Color::Black), Square::E8);
+        assert_eq!(board.king_square(Color::White), Some(Square::E1));
+        assert_eq!(board.king_square(Color::Black), Some(Square::E8));
+        let kingless = Board::from_fen("8/8/8/8/8/8/8/K7").unwrap();
+        assert_eq!(kingless.king_square(Color::Black), None);
+        assert!(!kingless.is_king_in_check(Color::Black));
     }
 
     #[test]
     fn test_is_square_pinned() {
         let fen = "rnb1k1nr/pp3ppp/4p3/3NP2Q/1bPP3q/8/PP1B1PPP/R3KBNR";
         let board = Board::from_fen(fen).unwrap();
-        let pinned = vec![Square::D2, Square::F2, Square::F7];
+        let pinned = [Square::D2, Square::F2, Square::F7];
         for sq in SQUARES {
             assert_eq!(pinned.contains(&sq), board.is_square_pinned(&sq));
         }
     }
+
+    #[test]
+    fn test_attackers_bitboard() {
+        let board = Board::from_fen("4k3/8/8/3p4/4P3/2N5/8/B2QK3").unwrap();
+        // the bishop is behind the knight, so doesn't count
+        let expected: Bitboard = vec![Square::E4, Square::C3, Square::D1].into();
+        assert_eq!(board.attackers_bitboard(Square::D5, Color::White), expected);
+        let expected: Bitboard = vec![Square::D5].into();
+        assert_eq!(board.attackers_bitboard(Square::E4, Color::Black), expected);
+        assert_eq!(
+            board.attackers_bitboard(Square::A8, Color::Black),
+            Bitboard::empty()
+        );
+    }
+
+    #[test]
+    fn test_attack_map() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/3P4/R3K3").unwrap();
+        let expected: Bitboard = vec![
+            // the pawn
+            Square::C3,
+            Square::E3,
+            // the rook, up to and including the king
+            Square::A2,
+            Square::A3,
+            Square::A4,
+            Square::A5,
+            Square::A6,
+            Square::A7,
+            Square::A8,
+            Square::B1,
+            Square::C1,
+            Square::D1,
+            Square::E1,
+            // the king
+            Square::D1,
+            Square::D2,
+            Square::E2,
+            Square::F2,
+            Square::F1,
+        ]
+        .into();
+        assert_eq!(board.attack_map(Color::White), expected);
+        for sq in SQUARES {
+            let sq_bb: Bitboard = sq.into();
+            assert_eq!(
+                (board.attack_map(Color::Black) & sq_bb).0 != 0x0,
+                board.is_square_attacked(sq, Color::Black)
+            );
+        }
+    }
+
+    #[test]
+    fn test_knight_and_pawn_attacks() {
+        let corner: Bitboard = vec![Square::B3, Square::C2].into();
+        assert_eq!(Board::knight_attacks(Square::A1), corner);
+        assert_eq!(Board::knight_attacks(Square::E4).pop_count(), 8);
+
+        let board = Board::from_fen("4k3/7p/8/8/8/8/P3P3/4K3").unwrap();
+        let white: Bitboard = vec![Square::B3, Square::D3, Square::F3].into();
+        assert_eq!(board.pawn_attacks(Color::White), white);
+        let black: Bitboard = vec![Square::G6].into();
+        assert_eq!(board.pawn_attacks(Color::Black), black);
+    }
+
+    #[test]
+    fn test_hanging_pieces() {
+        // the knight on c6 is attacked and undefended, the one on f6 defended
+        // by the g-pawn, and the king doesn't count
+        let board = Board::from_fen("4k3/6p1/2n2n2/8/8/2R2Q2/8/4K3").unwrap();
+        assert_eq!(board.hanging_pieces(Color::Black), Square::C6.into());
+        // the rook is attacked by nothing and the queen defended by nothing
+        assert_eq!(board.hanging_pieces(Color::White), Bitboard::empty());
+    }
 }
@@ -1,4 +1,6 @@
-use crate::play::types::{Color, Direction, File, Piece, Rank, Square};
+use crate::play::types::{Color, Direction, File, Piece, PieceType, Rank, Square};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
 // using Little-Endian Rank File Mapping
 // @see https://www.chessprogramming.org/Square_Mapping_Considerations
@@ -6,8 +8,15 @@ pub const FILE_A: u64 = 0x0101010101010101;
 pub const RANK_1: u64 = 0xFF;
 pub const A1_H8_DIAGONAL: u64 = 0x8040201008040201;
 pub const H1_A1_DIAGONAL: u64 = 0x0102040810204080;
-const WHITE_SQUARES: u64 = 0x55AA55AA55AA55AA;
-const BLACK_SQUARES: u64 = 0xAA55AA55AA55AA55;
+pub const WHITE_SQUARES: u64 = 0x55AA55AA55AA55AA;
+pub const BLACK_SQUARES: u64 = 0xAA55AA55AA55AA55;
+
+// a game long enough to hit this is already well past any draw claim; the
+// history is reserved for typical games up front and grows up to the cap
+pub const HISTORY_RESERVE: usize = 256;
+pub const MAX_GAME_PLY: usize = 2048;
+// fifty moves by each side without a capture or pawn move
+pub const FIFTY_MOVE_PLIES: u16 = 100;
 
 pub const COLORS: [Color; 2] = [Color::White, Color::Black];
 
@@ -30,6 +39,14 @@ pub const DIRECTIONS: [Direction; 16] = [
     Direction::WestNorthL,
 ];
 
+/// What a pawn can promote to, the queen first.
+pub const PROMOTION_TYPES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
 pub const WHITE_PAWN_DIAGONALS: [Direction; 2] = [Direction::NorthWest, Direction::NorthEast];
 
 pub const BLACK_PAWN_DIAGONALS: [Direction; 2] = [Direction::SouthWest, Direction::SouthEast];
@@ -71,6 +88,11 @@ pub const PIECE_VALUES: [u32; 12] = [
     100, 325, 325, 550, 1000, 50000, 100, 325, 325, 550, 1000, 50000,
 ];
 
+/// What each piece adds to the game phase, which falls from [`MAX_PHASE`]
+/// with every piece on as they come off. Pawns and kings don't count.
+pub const PHASE_WEIGHTS: [u8; 12] = [0, 1, 1, 2, 4, 0, 0, 1, 1, 2, 4, 0];
+pub const MAX_PHASE: u8 = 24;
+
 pub const IS_MINOR_PIECE: [bool; 12] = [
     false, true, true, false, false, false, false, true, true, false, false, false,
 ];
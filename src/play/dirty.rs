@@ -0,0 +1,139 @@
+use super::{
+    position::Position,
+    r#move::{castling_rook, Move},
+    types::{Color, Direction, Piece, Square},
+};
+
+/// A piece a move took off a square, put on one, or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirtyPiece {
+    pub piece: Piece,
+    pub from: Option<Square>,
+    pub to: Option<Square>,
+}
+
+/// The pieces a move changed, for evaluations kept up to date move by move
+/// rather than worked out afresh. A capturing promotion changes the most,
+/// three: the captured piece, the pawn and the piece it becomes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirtyPieces {
+    pieces: [Option<DirtyPiece>; 3],
+    /// the move changed more than fits, so nothing can be kept
+    everything: bool,
+}
+
+impl DirtyPieces {
+    /// What `mv` is about to change on `position`'s board.
+    pub fn of(mv: Move, position: &Position) -> Self {
+        let mut dirty = DirtyPieces::default();
+        if let Some(captured) = mv.captured() {
+            let sq = if mv.en_passant() {
+                let dir = match position.side_to_move {
+                    Color::White => Direction::South,
+                    Color::Black => Direction::North,
+                };
+                mv.to_sq().offset(dir)
+            } else {
+                Some(mv.to_sq())
+            };
+            dirty.push(captured, sq, None);
+        }
+        if let Some(piece) = mv.dropped() {
+            dirty.push(piece, None, Some(mv.to_sq()));
+        } else if let Some(piece) = position.board.piece(&mv.from_sq()) {
+            match mv.promoted() {
+                Some(promoted) => {
+                    dirty.push(piece, Some(mv.from_sq()), None);
+                    dirty.push(promoted, None, Some(mv.to_sq()));
+                }
+                None => dirty.push(piece, Some(mv.from_sq()), Some(mv.to_sq())),
+            }
+        }
+        if mv.castle() {
+            if let Some((rook, from, to)) = castling_rook(mv.to_sq()) {
+                dirty.push(rook, Some(from), Some(to));
+            }
+        }
+        dirty
+    }
+
+    /// For a move that changed too many pieces to list, an atomic capture
+    /// blowing up a crowd of them.
+    pub fn everything() -> Self {
+        DirtyPieces {
+            everything: true,
+            ..DirtyPieces::default()
+        }
+    }
+
+    pub fn is_everything(&self) -> bool {
+        self.everything
+    }
+
+    fn push(&mut self, piece: Piece, from: Option<Square>, to: Option<Square>) {
+        if let Some(slot) = self.pieces.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(DirtyPiece { piece, from, to });
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DirtyPiece> {
+        self.pieces.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::GameState;
+
+    fn dirty(fen: &str, uci: &str) -> Vec<DirtyPiece> {
+        let state = GameState::from_fen(fen).unwrap();
+        let mv = state.position.parse_move(uci).unwrap();
+        DirtyPieces::of(mv, &state.position)
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_dirty_pieces() {
+        let castle = dirty("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1");
+        assert_eq!(
+            castle,
+            vec![
+                DirtyPiece {
+                    piece: Piece::WhiteKing,
+                    from: Some(Square::E1),
+                    to: Some(Square::G1)
+                },
+                DirtyPiece {
+                    piece: Piece::WhiteRook,
+                    from: Some(Square::H1),
+                    to: Some(Square::F1)
+                },
+            ]
+        );
+
+        let en_passant = dirty("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6");
+        assert_eq!(
+            en_passant[0],
+            DirtyPiece {
+                piece: Piece::BlackPawn,
+                from: Some(Square::D5),
+                to: None
+            }
+        );
+
+        let promotion = dirty("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q");
+        assert_eq!(promotion.len(), 3);
+        assert_eq!(
+            promotion[2],
+            DirtyPiece {
+                piece: Piece::WhiteQueen,
+                from: None,
+                to: Some(Square::B8)
+            }
+        );
+    }
+}
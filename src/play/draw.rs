@@ -0,0 +1,128 @@
+use super::{
+    error::DrawClaimError,
+    r#move::{make_move, Move},
+    GameState,
+};
+
+/// The draws a player has to claim, rather than the game ending by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawClaim {
+    ThreefoldRepetition,
+    FiftyMoves,
+}
+
+impl DrawClaim {
+    fn holds(&self, state: &GameState) -> bool {
+        match self {
+            DrawClaim::ThreefoldRepetition => state.is_threefold_repetition(),
+            DrawClaim::FiftyMoves => state.is_fifty_move_draw(),
+        }
+    }
+}
+
+impl GameState {
+    /// Checks a draw claim against the game's history the way an arbiter
+    /// would over the board: it's correct if the current position already
+    /// qualifies, or if it will once the claimant's `intended` move is made,
+    /// which is then played. Nothing changes when the claim is wrong.
+    pub fn claim_draw(
+        &mut self,
+        claim: DrawClaim,
+        intended: Option<Move>,
+    ) -> Result<(), DrawClaimError> {
+        let mv = match intended {
+            None if claim.holds(self) => return Ok(()),
+            None => return Err(DrawClaimError::NotDrawn(claim)),
+            Some(mv) => mv,
+        };
+        if !self.position.legal_moves().contains(&mv) || !self.position.is_legal(mv) {
+            return Err(DrawClaimError::IllegalMove(mv.to_uci()));
+        }
+        let mut after = self.clone();
+        make_move(mv, &mut after).map_err(|_| DrawClaimError::IllegalMove(mv.to_uci()))?;
+        if !claim.holds(&after) {
+            return Err(DrawClaimError::NotDrawn(claim));
+        }
+        *self = after;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::{play, types::Square};
+
+    fn find(state: &GameState, uci: &str) -> Move {
+        state.position.parse_move(uci).unwrap()
+    }
+
+    #[test]
+    fn test_claim_threefold_repetition() {
+        let mut state = GameState::default();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        play(&mut state, &shuffle);
+        play(&mut state, &shuffle[..3]);
+        let claim = DrawClaim::ThreefoldRepetition;
+        assert_eq!(
+            state.claim_draw(claim, None),
+            Err(DrawClaimError::NotDrawn(claim))
+        );
+        let before = state.clone();
+        let mv = find(&state, "f6e4");
+        assert_eq!(
+            state.claim_draw(claim, Some(mv)),
+            Err(DrawClaimError::NotDrawn(claim))
+        );
+        assert_eq!(state, before);
+
+        // Ng8 brings the starting position round a third time
+        let mv = find(&state, "f6g8");
+        assert_eq!(state.claim_draw(claim, Some(mv)), Ok(()));
+        assert_eq!(state.search_ply, before.search_ply + 1);
+        assert_eq!(state.claim_draw(claim, None), Ok(()));
+    }
+
+    #[test]
+    fn test_claim_fifty_moves() {
+        let mut state = GameState::default();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        for _ in 0..24 {
+            play(&mut state, &shuffle);
+        }
+        play(&mut state, &shuffle[..3]);
+        let claim = DrawClaim::FiftyMoves;
+        assert_eq!(
+            state.claim_draw(claim, None),
+            Err(DrawClaimError::NotDrawn(claim))
+        );
+        // a pawn move resets the count instead
+        let mv = find(&state, "e7e5");
+        assert_eq!(
+            state.claim_draw(claim, Some(mv)),
+            Err(DrawClaimError::NotDrawn(claim))
+        );
+        let mv = find(&state, "f6g8");
+        assert_eq!(state.claim_draw(claim, Some(mv)), Ok(()));
+    }
+
+    #[test]
+    fn test_claim_with_illegal_move() {
+        let mut state = GameState::from_fen("4k3/8/8/8/7b/8/5P2/1N2K3 b - - 0 1").unwrap();
+        let shuffle = ["e8d8", "b1c3", "d8e8", "c3b1"];
+        for _ in 0..24 {
+            play(&mut state, &shuffle);
+        }
+        play(&mut state, &shuffle[..3]);
+
+        // the f-pawn is pinned, so isn't even generated
+        let mv = Move::quiet(Square::F2, Square::F3);
+        assert_eq!(
+            state.claim_draw(DrawClaim::FiftyMoves, Some(mv)),
+            Err(DrawClaimError::IllegalMove("f2f3".to_string()))
+        );
+        let mv = find(&state, "c3b1");
+        assert_eq!(state.claim_draw(DrawClaim::FiftyMoves, Some(mv)), Ok(()));
+    }
+}
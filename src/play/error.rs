@@ -1,16 +1,26 @@
-use crate::play::types::Square;
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::{error::Error, fmt};
+
+use crate::play::{
+    draw::DrawClaim,
+    types::{Color, Square},
+};
 
 #[derive(Debug)]
 pub struct MoveError {
     err: MoveErrorType,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum MoveErrorType {
-    SquareTakenError(Square),
-    NoPieceOnSquareError(Square),
+    SquareTaken(Square),
+    NoPieceOnSquare(Square),
     InsufficientHistory(String),
     StateMismatch(String),
+    HistoryFull(usize),
 }
 
 impl MoveError {
@@ -18,17 +28,27 @@ impl MoveError {
         MoveError { err }
     }
 
-    pub fn print_msg(&self) {
-        let msg = match &self.err {
-            MoveErrorType::SquareTakenError(sq) => format!("Square: {} is already taken", sq),
-            MoveErrorType::NoPieceOnSquareError(sq) => format!("No piece on square: {}", sq),
-            MoveErrorType::InsufficientHistory(s) => format!("Tried popping from empty: {}", s),
-            MoveErrorType::StateMismatch(s) => s.clone(),
-        };
-        eprintln!("{}", msg)
+    pub fn kind(&self) -> &MoveErrorType {
+        &self.err
+    }
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.err {
+            MoveErrorType::SquareTaken(sq) => write!(f, "square {} is already taken", sq),
+            MoveErrorType::NoPieceOnSquare(sq) => write!(f, "no piece on square {}", sq),
+            MoveErrorType::InsufficientHistory(s) => {
+                write!(f, "no {} history left to unmake the move with", s)
+            }
+            MoveErrorType::StateMismatch(s) => f.write_str(s),
+            MoveErrorType::HistoryFull(n) => write!(f, "game history is full at {} plies", n),
+        }
     }
 }
 
+impl Error for MoveError {}
+
 #[derive(Debug)]
 pub struct SquareIndexError {
     idx: usize,
@@ -40,6 +60,14 @@ impl SquareIndexError {
     }
 }
 
+impl fmt::Display for SquareIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "square index {} is off the board", self.idx)
+    }
+}
+
+impl Error for SquareIndexError {}
+
 #[derive(Debug)]
 pub struct InvalidCharError {
     ch: char,
@@ -51,30 +79,188 @@ impl InvalidCharError {
     }
 }
 
+impl fmt::Display for InvalidCharError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid char {:?}", self.ch)
+    }
+}
+
+impl Error for InvalidCharError {}
+
+/// A square, piece, file, rank, color or set of castling rights that
+/// couldn't be read from a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTypeError {
+    what: &'static str,
+    input: String,
+}
+
+impl ParseTypeError {
+    pub fn new(what: &'static str, input: impl ToString) -> Self {
+        ParseTypeError {
+            what,
+            input: input.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} {:?}", self.what, self.input)
+    }
+}
+
+impl Error for ParseTypeError {}
+
 #[derive(Debug)]
 pub struct FENParsingError {
     msg: String,
+    kind: FENErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FENErrorKind {
+    /// the FEN isn't well formed
+    Syntax,
+    /// the FEN is well formed, but the position it describes can't be
+    /// played from
+    InvalidPosition(InvalidPosition),
+}
+
+/// Why a position can't be played from, as [`Position::validate`] finds.
+///
+/// [`Position::validate`]: crate::play::position::Position::validate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidPosition {
+    /// a side with no king or more than one, with how many it has
+    KingCount(Color, u32),
+    PawnOnBackRank(Square),
+    /// the side that just moved left its king in check
+    OpponentInCheck,
+    /// an en passant square no double pawn push could have left
+    EnPassant(Square),
+    /// a castling right, as its FEN char, whose king or rook has moved
+    CastlingRight(char),
+}
+
+impl fmt::Display for InvalidPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidPosition::KingCount(color, n) => {
+                write!(f, "{:?} has {} kings, expected one", color, n)
+            }
+            InvalidPosition::PawnOnBackRank(sq) => write!(f, "pawn on back rank square {}", sq),
+            InvalidPosition::OpponentInCheck => f.write_str("side not to move is in check"),
+            InvalidPosition::EnPassant(sq) => {
+                write!(f, "no pawn could have just passed en passant square {}", sq)
+            }
+            InvalidPosition::CastlingRight(ch) => {
+                write!(
+                    f,
+                    "castling right {} without its king and rook in place",
+                    ch
+                )
+            }
+        }
+    }
 }
 
+impl Error for InvalidPosition {}
+
 impl FENParsingError {
     pub fn new(msg: impl ToString) -> Self {
         FENParsingError {
             msg: msg.to_string(),
+            kind: FENErrorKind::Syntax,
+        }
+    }
+
+    pub fn kind(&self) -> &FENErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for FENParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl Error for FENParsingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            FENErrorKind::Syntax => None,
+            FENErrorKind::InvalidPosition(err) => Some(err),
         }
     }
 }
 
 impl From<InvalidCharError> for FENParsingError {
     fn from(err: InvalidCharError) -> Self {
-        FENParsingError::new(format!("Error parsing fen: invalid char {}", err.ch))
+        FENParsingError::new(format!("Error parsing fen: {}", err))
+    }
+}
+
+impl From<InvalidPosition> for FENParsingError {
+    fn from(err: InvalidPosition) -> Self {
+        FENParsingError {
+            msg: format!("Error parsing fen: invalid position: {}", err),
+            kind: FENErrorKind::InvalidPosition(err),
+        }
     }
 }
 
 impl From<SquareIndexError> for FENParsingError {
     fn from(err: SquareIndexError) -> Self {
-        FENParsingError::new(format!(
-            "Error parsing fen: invalid square index: {}",
-            err.idx
-        ))
+        FENParsingError::new(format!("Error parsing fen: {}", err))
+    }
+}
+
+/// Why a move in standard algebraic notation couldn't be read, each with
+/// the SAN as given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    /// not a move in SAN at all
+    Malformed(String),
+    /// no legal move fits it
+    Illegal(String),
+    /// more than one legal move fits it, for want of a file or rank to tell
+    /// them apart
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Malformed(san) => write!(f, "not a move in SAN: {}", san),
+            SanError::Illegal(san) => write!(f, "illegal move {}", san),
+            SanError::Ambiguous(san) => write!(f, "ambiguous move {}", san),
+        }
     }
 }
+
+impl Error for SanError {}
+
+/// Why a draw claim was turned down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrawClaimError {
+    /// the move the claim was made with, in coordinate notation
+    IllegalMove(String),
+    NotDrawn(DrawClaim),
+}
+
+impl fmt::Display for DrawClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawClaimError::IllegalMove(mv) => write!(f, "illegal move {}", mv),
+            DrawClaimError::NotDrawn(DrawClaim::ThreefoldRepetition) => {
+                f.write_str("the position hasn't occurred three times")
+            }
+            DrawClaimError::NotDrawn(DrawClaim::FiftyMoves) => {
+                f.write_str("fifty moves haven't passed without a capture or pawn move")
+            }
+        }
+    }
+}
+
+impl Error for DrawClaimError {}
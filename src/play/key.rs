@@ -1,6 +1,19 @@
-use rand::random;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use crate::play::{constants::SQUARES, types::Color, GameState};
+use crate::play::{
+    constants::{BLACK_PIECES, WHITE_PIECES},
+    once::Once,
+    position::Position,
+    types::{Color, Piece, PieceType, Square},
+    GameState,
+};
+
+// fixed so keys, and with them anything keyed on them, are the same run to run
+const KEY_SEED: u64 = 0x6c61_736b_6572;
+/// The most pieces of one type a hand is keyed apart by: sixteen pawns.
+const MAX_HAND: usize = 16;
+
+static KEYS: Once<PositionKeyGenerator> = Once::new();
 
 pub struct PositionKeyGenerator {
     key: u64,
@@ -8,37 +21,64 @@ pub struct PositionKeyGenerator {
     en_passant_hashes: [u64; 64],
     side_to_move_hash: u64,
     castling_permission_hashes: [u64; 16], // 4!
+    /// by color, piece type and how many are held, for variants with drops
+    hand_hashes: [[[u64; MAX_HAND + 1]; 5]; 2],
+}
+
+impl Default for PositionKeyGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PositionKeyGenerator {
     pub fn new() -> Self {
+        let mut rng = StdRng::seed_from_u64(KEY_SEED);
         // pieces
         let mut p = [[0; 64]; 12];
-        for i in 0..p.len() {
-            for j in 0..p[i].len() {
-                p[i][j] = random::<u64>();
+        for piece_hashes in p.iter_mut() {
+            for h in piece_hashes.iter_mut() {
+                *h = rng.gen::<u64>();
             }
         }
         // castling
         let mut c = [0; 16];
-        for i in 0..c.len() {
-            c[i] = random::<u64>();
+        for h in c.iter_mut() {
+            *h = rng.gen::<u64>();
         }
         // en passant
         let mut e = [0; 64];
-        for i in 0..e.len() {
-            e[i] = random::<u64>();
+        for h in e.iter_mut() {
+            *h = rng.gen::<u64>();
+        }
+
+        let side_to_move_hash = rng.gen::<u64>();
+        // hands, drawn last so the keys of the other variants stay as they
+        // were before there were any
+        let mut h = [[[0; MAX_HAND + 1]; 5]; 2];
+        for h in h.iter_mut().flatten().flatten() {
+            *h = rng.gen::<u64>();
         }
 
         PositionKeyGenerator {
             key: 0,
             piece_hashes: p,
             en_passant_hashes: e,
-            side_to_move_hash: random::<u64>(),
+            side_to_move_hash,
             castling_permission_hashes: c,
+            hand_hashes: h,
         }
     }
 
+    /// The generator every [`GameState::position_key`] is computed with.
+    pub fn global() -> &'static PositionKeyGenerator {
+        KEYS.get_or_init(PositionKeyGenerator::new)
+    }
+
+    pub fn piece_hash(&self, piece: Piece, sq: Square) -> u64 {
+        self.piece_hashes[piece as usize][sq as usize]
+    }
+
     pub fn key(&self) -> u64 {
         self.key
     }
@@ -49,26 +89,63 @@ impl PositionKeyGenerator {
         let mut key = 0;
 
         // pieces
-        for sq in SQUARES.iter() {
-            if let Some(piece) = state.position.board.piece(sq) {
-                let piece_idx = piece as usize;
-                key ^= self.piece_hashes[piece_idx][*sq as usize];
+        for piece in WHITE_PIECES.iter().chain(BLACK_PIECES.iter()) {
+            for sq in state.position.board.bitboard(*piece) {
+                key ^= self.piece_hash(*piece, sq);
+            }
+        }
+
+        key ^ self.hash_state(&state.position)
+    }
+
+    /// The keys of the pawns alone and of every other piece, kings included,
+    /// which between them make up the piece placement part of the position
+    /// key. Pawn structure and the pieces around it can then be hashed apart.
+    pub fn hash_sub_keys(&self, position: &Position) -> (u64, u64) {
+        let (mut pawn_key, mut non_pawn_key) = (0, 0);
+        for piece in WHITE_PIECES.iter().chain(BLACK_PIECES.iter()) {
+            let sub_key = if piece.piece_type() == PieceType::Pawn {
+                &mut pawn_key
+            } else {
+                &mut non_pawn_key
+            };
+            for sq in position.board.bitboard(*piece) {
+                *sub_key ^= self.piece_hash(*piece, sq);
             }
         }
+        (pawn_key, non_pawn_key)
+    }
 
+    /// The part of the key that isn't piece placement, which make_move
+    /// swaps out wholesale rather than tracking piece by piece.
+    pub fn hash_state(&self, position: &Position) -> u64 {
         // castling
-        key ^= self.castling_permission_hashes[state.position.castling_permissions.0 as usize];
+        let mut key = self.castling_permission_hashes[position.castling_permissions.0 as usize];
 
-        // en passant
-        if let Some(sq) = state.position.en_passant {
-            key ^= self.en_passant_hashes[sq as usize]
+        // en passant, only when it can actually be taken so that otherwise
+        // identical positions hash the same
+        if let Some(sq) = position.en_passant {
+            if position.ep_capture_possible() {
+                key ^= self.en_passant_hashes[sq as usize]
+            }
         }
 
         // to move
-        if state.position.side_to_move == Color::White {
+        if position.side_to_move == Color::White {
             key ^= self.side_to_move_hash
         }
 
+        // pieces in hand
+        if position.variant.has_hands() {
+            for color in [Color::White, Color::Black] {
+                let hand = &position.hands[color as usize];
+                for (piece_type, &count) in hand.0.iter().enumerate() {
+                    let count = (count as usize).min(MAX_HAND);
+                    key ^= self.hand_hashes[color as usize][piece_type][count];
+                }
+            }
+        }
+
         key
     }
 }
@@ -94,8 +171,13 @@ mod tests {
 
         // switch en passant
         state.position.en_passant = Some(Square::C3);
-        assert_ne!(base_key, key_gen.hash_board(&state));
+        state.position.side_to_move = Color::Black;
+        let _ = state.position.board.add_piece(Piece::BlackPawn, Square::D4);
+        let ep_key = key_gen.hash_board(&state);
         state.position.en_passant = None;
+        assert_ne!(ep_key, key_gen.hash_board(&state));
+        let _ = state.position.board.remove_piece(Square::D4);
+        state.position.side_to_move = Color::White;
         assert_eq!(base_key, key_gen.hash_board(&state));
 
         // switch castling rights (default == 0b1111)
@@ -113,4 +195,37 @@ mod tests {
         let _ = state.position.board.remove_piece(Square::A4);
         assert_eq!(base_key, key_gen.hash_board(&state));
     }
+
+    #[test]
+    fn test_keys_are_fixed() {
+        let state = GameState::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let key = PositionKeyGenerator::new().hash_board(&state);
+        assert_eq!(PositionKeyGenerator::new().hash_board(&state), key);
+        assert_eq!(state.position_key, key);
+        assert_eq!(
+            GameState::default().position_key,
+            PositionKeyGenerator::global().hash_board(&GameState::default())
+        );
+    }
+
+    #[test]
+    fn test_hash_board_ignores_uncapturable_en_passant() {
+        let key_gen = PositionKeyGenerator::new();
+
+        // 1. e4 with no black pawn able to take on e3
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        let no_ep_fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        assert_eq!(
+            key_gen.hash_board(&GameState::from_fen(fen).unwrap()),
+            key_gen.hash_board(&GameState::from_fen(no_ep_fen).unwrap())
+        );
+
+        // black pawn on d4 makes the e3 square relevant
+        let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        let no_ep_fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        assert_ne!(
+            key_gen.hash_board(&GameState::from_fen(fen).unwrap()),
+            key_gen.hash_board(&GameState::from_fen(no_ep_fen).unwrap())
+        );
+    }
 }
@@ -1,48 +1,165 @@
-use std::fmt::Debug;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Debug;
 
-pub(in crate::play) mod board;
-pub(in crate::play) mod constants;
-mod error;
+pub mod board;
+pub mod constants;
+#[cfg(feature = "nnue")]
+pub mod dirty;
+pub mod draw;
+pub mod error;
 pub mod key;
 pub mod r#move;
 pub mod move_gen;
-pub(in crate::play) mod position;
-pub(in crate::play) mod types;
-mod utils;
+mod once;
+pub mod position;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod types;
+pub mod variant;
+
+pub use board::bitboard::{
+    Bitboard, ADJACENT_FILE_MASKS, ANTI_DIAGONAL_MASKS, BETWEEN, DIAGONAL_MASKS, FILE_MASKS, LINE,
+    RANK_MASKS,
+};
+pub use constants::{MAX_GAME_PLY, MAX_PHASE, PIECES, PIECE_VALUES, START_FEN};
 
 use error::FENParsingError;
 
-use self::position::Position;
+use self::{
+    constants::{FIFTY_MOVE_PLIES, HISTORY_RESERVE},
+    key::PositionKeyGenerator,
+    position::Position,
+    types::{CastlingRights, Piece, Square},
+    variant::Variant,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct GameState {
     pub position: Position,
-    pub fifty_move_counter: u8,
-    pub fifty_move_country_hist: Vec<u8>,
-    pub ply: u8,
-    pub history_ply: u8,
+    /// plies since the last capture or pawn move, for the fifty-move rule
+    pub halfmove_clock: u16,
+    pub halfmove_clock_history: Vec<u16>,
+    /// plies since the start of the game, counting those before the FEN
+    pub game_ply: u16,
+    /// plies made since the position the current search started from
+    pub search_ply: u16,
     pub position_key: u64,
+    pub key_history: Vec<u64>,
+    /// hashes pawn placement alone, for pawn structure lookups
+    pub pawn_key: u64,
+    /// hashes the placement of everything but pawns
+    pub non_pawn_key: u64,
+    /// (pawn_key, non_pawn_key) before each move
+    pub sub_key_history: Vec<(u64, u64)>,
+    /// the game phase, kept up to date as pieces are captured and promoted
+    /// to, see [`Position::game_phase`]
+    pub phase: u8,
+    /// the pieces each atomic capture blew up, and where from
+    pub explosion_history: Vec<Vec<(Piece, Square)>>,
+    /// what each move changed on the board, for the NNUE accumulators
+    #[cfg(feature = "nnue")]
+    pub dirty_pieces: Vec<dirty::DirtyPieces>,
 }
 
 impl Default for GameState {
     fn default() -> Self {
-        Self {
+        let mut state = Self {
             position: Position::default(),
-            fifty_move_counter: 0,
-            fifty_move_country_hist: vec![],
-            ply: 0,
-            history_ply: 0,
-            position_key: Default::default(),
-        }
+            halfmove_clock: 0,
+            halfmove_clock_history: Vec::with_capacity(HISTORY_RESERVE),
+            game_ply: 0,
+            search_ply: 0,
+            position_key: 0,
+            key_history: Vec::with_capacity(HISTORY_RESERVE),
+            pawn_key: 0,
+            non_pawn_key: 0,
+            sub_key_history: Vec::with_capacity(HISTORY_RESERVE),
+            phase: 0,
+            explosion_history: Vec::new(),
+            #[cfg(feature = "nnue")]
+            dirty_pieces: Vec::with_capacity(HISTORY_RESERVE),
+        };
+        state.position_key = PositionKeyGenerator::global().hash_board(&state);
+        (state.pawn_key, state.non_pawn_key) =
+            PositionKeyGenerator::global().hash_sub_keys(&state.position);
+        state.phase = state.position.game_phase();
+        state
     }
 }
 
 impl GameState {
+    #[cfg(feature = "std")]
     pub fn print_board(&self) {
         print!("{:?}", self.position.board)
     }
 
+    /// How many times the current position occurred earlier in the game.
+    /// Only positions since the last capture or pawn move, with the same side
+    /// to move, can match.
+    pub fn repetitions(&self) -> usize {
+        let reversible = (self.halfmove_clock as usize).min(self.key_history.len());
+        self.key_history
+            .iter()
+            .rev()
+            .take(reversible)
+            .skip(1)
+            .step_by(2)
+            .filter(|key| **key == self.position_key)
+            .count()
+    }
+
+    /// Drops the history of moves no repetition can reach back to, those
+    /// before the last capture or pawn move or more than fifty moves ago, so
+    /// a long game leaves room for a search's moves. Moves made from here on
+    /// can still be unmade, those before can't.
+    pub fn trim_history(&mut self) {
+        let keep = (self.halfmove_clock as usize).min(FIFTY_MOVE_PLIES as usize);
+        fn keep_last<T>(history: &mut Vec<T>, keep: usize) {
+            history.drain(..history.len().saturating_sub(keep));
+        }
+        keep_last(&mut self.halfmove_clock_history, keep);
+        keep_last(&mut self.position.castling_perms_history, keep);
+        keep_last(&mut self.position.en_passant_history, keep);
+        keep_last(&mut self.key_history, keep);
+        keep_last(&mut self.sub_key_history, keep);
+        // every explosion came with a capture, so none is that recent
+        self.explosion_history.clear();
+        #[cfg(feature = "nnue")]
+        keep_last(&mut self.dirty_pieces, keep);
+    }
+
+    /// Whether the current position occurred before. The search scores this
+    /// as a draw already, since whatever was best the first time round can
+    /// be repeated again.
+    pub fn is_repetition(&self) -> bool {
+        self.repetitions() > 0
+    }
+
+    /// Whether the current position has now occurred three times, so either
+    /// side can claim a draw.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetitions() >= 2
+    }
+
+    /// Whether fifty moves have passed without a capture or pawn move.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= FIFTY_MOVE_PLIES
+    }
+
     pub fn from_fen(fen: impl ToString) -> Result<GameState, FENParsingError> {
+        GameState::from_variant_fen(fen, Variant::Standard)
+    }
+
+    /// The game from `fen` played under `variant`'s rules, the position
+    /// validated under them too: an antichess position needn't have kings.
+    pub fn from_variant_fen(
+        fen: impl ToString,
+        variant: Variant,
+    ) -> Result<GameState, FENParsingError> {
         let mut state = GameState::default();
 
         let n_fields = 6;
@@ -57,12 +174,74 @@ impl GameState {
         let fields: Vec<String> = fen_str.split(' ').map(|s| s.to_string()).collect();
 
         // board
-        state.position = Position::from_fields(fields[..4].to_vec())?;
+        state.position = Position::from_variant_fields(fields[..4].to_vec(), variant)?;
+
+        // move clocks
+        state.halfmove_clock = fields[4].parse().map_err(|_| {
+            FENParsingError::new(format!("Error parsing fen: halfmove clock {}", fields[4]))
+        })?;
+        let fullmove: u16 = fields[5].trim().parse().map_err(|_| {
+            FENParsingError::new(format!("Error parsing fen: fullmove number {}", fields[5]))
+        })?;
+        state.game_ply = fullmove
+            .saturating_sub(1)
+            .checked_mul(2)
+            .and_then(|ply| {
+                ply.checked_add((state.position.side_to_move == types::Color::Black) as u16)
+            })
+            .ok_or_else(|| {
+                FENParsingError::new(format!(
+                    "Error parsing fen: fullmove number {} is too large",
+                    fullmove
+                ))
+            })?;
+
+        let keys = PositionKeyGenerator::global();
+        state.position_key = keys.hash_board(&state);
+        (state.pawn_key, state.non_pawn_key) = keys.hash_sub_keys(&state.position);
+        state.phase = state.position.game_phase();
+        Ok(state.with_variant(variant))
+    }
 
-        // TODO: parse plys + move clocks
+    /// The game played under `variant`'s rules instead.
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.position.variant = variant;
+        if !variant.royal_king() {
+            self.position.castling_permissions = CastlingRights::empty();
+        }
+        self.position.update_checks_and_pins();
+        // the pieces in hand are keyed only where they count
+        self.position_key = PositionKeyGenerator::global().hash_board(&self);
+        self
+    }
+
+    /// The fullmove number as a FEN gives it: 1 at the start, going up
+    /// after each of Black's moves.
+    pub fn fullmove_number(&self) -> u16 {
+        self.game_ply / 2 + 1
+    }
+
+    /// The full six-field FEN, move clocks included.
+    pub fn to_fen(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.position.to_fen(),
+            self.halfmove_clock,
+            self.fullmove_number()
+        )
+    }
+}
 
-        Ok(state)
+/// Plays `moves`, in coordinate notation, returning the last of them, for
+/// tests to set up the games they need.
+#[cfg(test)]
+fn play(state: &mut GameState, moves: &[&str]) -> r#move::Move {
+    let mut played = r#move::Move::empty();
+    for uci in moves {
+        played = state.position.parse_move(uci).unwrap();
+        r#move::make_move(played, state).unwrap();
     }
+    played
 }
 
 #[cfg(test)]
@@ -75,5 +254,98 @@ mod tests {
         let start_state = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let parsed_state = GameState::from_fen(start_state).unwrap();
         assert_eq!(parsed_state, GameState::default());
+
+        let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 7 23";
+        let state = GameState::from_fen(fen).unwrap();
+        assert_eq!(state.halfmove_clock, 7);
+        assert_eq!(state.game_ply, 45);
+        assert_eq!(state.search_ply, 0);
+        assert!(GameState::from_fen(fen.replace(" 7 ", " x ")).is_err());
+        assert_eq!(state.fullmove_number(), 23);
+        assert!(GameState::from_fen(fen.replace(" 23", " 40000")).is_err());
+        assert!(GameState::from_fen(fen.replace(" 23", " 70000")).is_err());
+
+        let long_game = GameState::from_fen(fen.replace(" 23", " 5000")).unwrap();
+        assert_eq!(long_game.game_ply, 9999);
+        assert_eq!(long_game.fullmove_number(), 5000);
+    }
+
+    #[test]
+    fn test_game_state_to_fen() {
+        for fen in [
+            START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b Kq e3 7 23",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+        ] {
+            assert_eq!(GameState::from_fen(fen).unwrap().to_fen(), fen);
+        }
+        assert_eq!(
+            GameState::default().position.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"
+        );
+
+        let mut state = GameState::default();
+        play(&mut state, &["e2e4", "c7c5", "g1f3"]);
+        assert_eq!(
+            state.to_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut state = GameState::default();
+        assert!(!state.is_repetition());
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        play(&mut state, &shuffle[..3]);
+        assert!(!state.is_repetition());
+        play(&mut state, &shuffle[3..]);
+        assert_eq!(state.repetitions(), 1);
+        assert!(state.is_repetition());
+        assert!(!state.is_threefold_repetition());
+        play(&mut state, &shuffle);
+        assert!(state.is_threefold_repetition());
+
+        // nothing from before a pawn move can come round again
+        play(&mut state, &["e2e4"]);
+        assert!(!state.is_repetition());
+        play(&mut state, &["g8f6", "g1f3", "f6g8", "f3g1"]);
+        assert_eq!(state.repetitions(), 1);
+    }
+
+    #[test]
+    fn test_trim_history() {
+        let mut state = GameState::default();
+        play(
+            &mut state,
+            &["e2e4", "e7e5", "g1f3", "g8f6", "f3g1", "f6g8"],
+        );
+        let repetitions = state.repetitions();
+        state.trim_history();
+        // only the shuffle since the last pawn move is left, and all it takes
+        // to find the repetition
+        assert_eq!(state.key_history.len(), 4);
+        assert_eq!(state.halfmove_clock_history.len(), 4);
+        assert_eq!(state.repetitions(), repetitions);
+        // and moves made after can still be unmade
+        let before = state.clone();
+        let mv = state.position.legal_moves().into_iter().next().unwrap();
+        r#move::make_move(mv, &mut state).unwrap();
+        r#move::unmake_move(mv, &mut state).unwrap();
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn test_fifty_move_draw() {
+        let mut state = GameState::default();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        for _ in 0..25 {
+            assert!(!state.is_fifty_move_draw());
+            play(&mut state, &shuffle);
+        }
+        assert!(state.is_fifty_move_draw());
+        play(&mut state, &["e2e4"]);
+        assert!(!state.is_fifty_move_draw());
     }
 }
@@ -0,0 +1,188 @@
+use crate::play::{
+    constants::SQUARES,
+    position::Position,
+    types::{Piece, PieceType, Rank, Square},
+    variant::HAND_PIECE_TYPES,
+};
+
+use super::Move;
+
+const KIND_PROMOTION: u16 = 1;
+const KIND_DROP: u16 = 2;
+
+/// A move in 16 bits, for tables that keep a great many of them: the from
+/// and to squares, six bits each, the promotion piece in two and two more
+/// saying whether it's a promotion or a drop, which keeps its piece where
+/// the from square would be. Captures, en passant, pawn starts and castling
+/// are all there to be read off the position the move is played from, so
+/// [`Move16::to_move`] fills them back in. All zeros, a1 to a1, is no move
+/// at all, which lets an empty table slot stand for none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Move16(u16);
+
+impl From<Move> for Move16 {
+    fn from(mv: Move) -> Self {
+        let to = (mv.to_sq() as u16) << 6;
+        if let Some(piece) = mv.dropped() {
+            return Move16(piece.piece_type() as u16 | to | KIND_DROP << 14);
+        }
+        let from = mv.from_sq() as u16;
+        match mv.promoted() {
+            // knight to queen as 0 to 3
+            Some(piece) => {
+                Move16(from | to | (piece.piece_type() as u16 - 1) << 12 | KIND_PROMOTION << 14)
+            }
+            None => Move16(from | to),
+        }
+    }
+}
+
+impl Move16 {
+    pub fn from_bits(bits: u16) -> Self {
+        Move16(bits)
+    }
+
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn is_none(self) -> bool {
+        self.0 == 0
+    }
+
+    fn kind(self) -> u16 {
+        self.0 >> 14
+    }
+
+    /// The square moved from, or for a drop the one dropped on.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_sq(self) -> Square {
+        match self.kind() {
+            KIND_DROP => self.to_sq(),
+            _ => SQUARES[(self.0 & 0x3F) as usize],
+        }
+    }
+
+    pub fn to_sq(self) -> Square {
+        SQUARES[((self.0 >> 6) & 0x3F) as usize]
+    }
+
+    /// The type a pawn is promoted to.
+    pub fn promoted(self) -> Option<PieceType> {
+        match self.kind() {
+            KIND_PROMOTION => Some(HAND_PIECE_TYPES[((self.0 >> 12) & 0b11) as usize + 1]),
+            _ => None,
+        }
+    }
+
+    /// The type of piece dropped.
+    pub fn dropped(self) -> Option<PieceType> {
+        match self.kind() {
+            KIND_DROP => HAND_PIECE_TYPES.get((self.0 & 0x3F) as usize).copied(),
+            _ => None,
+        }
+    }
+
+    /// The full move from `position`, with what it captures and its flags
+    /// read off the board, or `None` if the side to move has no piece to
+    /// make it with or it lands on one of its own. That's all that's
+    /// checked: a move out of a table may have been stored for another
+    /// position under the same key, so it still has to be found among the
+    /// legal moves before it's played.
+    pub fn to_move(self, position: &Position) -> Option<Move> {
+        if self.is_none() {
+            return None;
+        }
+        let color = position.side_to_move;
+        let board = &position.board;
+        let to = self.to_sq();
+        if let Some(piece_type) = self.dropped() {
+            return (!board.sq_taken(to)).then(|| Move::drop(Piece::of(piece_type, color), to));
+        }
+        let from = self.from_sq();
+        let piece = board.piece(&from).filter(|piece| piece.color() == color)?;
+        let captured = board.piece(&to);
+        if captured.is_some_and(|captured| captured.color() == color) {
+            return None;
+        }
+        let mut builder = Move::builder(from, to);
+        if let Some(captured) = captured {
+            builder = builder.captured(captured);
+        }
+        match piece.piece_type() {
+            PieceType::Pawn => {
+                let rank_diff = (to.rank() as i8 - from.rank() as i8).abs();
+                if rank_diff == 2 {
+                    builder = builder.pawn_start();
+                } else if from.file() != to.file() && Some(to) == position.en_passant {
+                    return Some(Move::en_passant_capture(from, to));
+                }
+                if let Some(promoted) = self.promoted() {
+                    builder = builder.promoted(Piece::of(promoted, color));
+                } else if matches!(to.rank(), Rank::Rank1 | Rank::Rank8) {
+                    return None;
+                }
+            }
+            PieceType::King if (to.file() as i8 - from.file() as i8).abs() == 2 => {
+                builder = builder.castle();
+            }
+            _ => (),
+        }
+        Some(builder.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::GameState;
+
+    #[test]
+    fn test_round_trip() {
+        // every legal move of a position with castling, en passant, a
+        // capture and a capturing promotion to be had
+        let fen = "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+        let moves = state.position.legal_moves();
+        assert!(moves.iter().any(|mv| mv.castle()));
+        assert!(moves.iter().any(|mv| mv.en_passant()));
+        assert!(moves
+            .iter()
+            .any(|mv| mv.promoted().is_some() && mv.captured().is_some()));
+        for mv in moves {
+            let compact = Move16::from(mv);
+            assert!(!compact.is_none());
+            assert_eq!(Move16::from_bits(compact.bits()), compact);
+            assert_eq!(compact.to_move(&state.position), Some(mv));
+        }
+
+        let drop = Move::drop(Piece::BlackKnight, Square::F6);
+        let compact = Move16::from(drop);
+        assert_eq!(compact.dropped(), Some(PieceType::Knight));
+        assert_eq!(compact.from_sq(), Square::F6);
+        let black = GameState::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(compact.to_move(&black.position), Some(drop));
+    }
+
+    #[test]
+    fn test_to_move_rejects() {
+        let state = GameState::default();
+        // nothing on e4 to move, and a black pawn with White to move
+        assert_eq!(
+            Move16::from(Move::quiet(Square::E4, Square::E5)).to_move(&state.position),
+            None
+        );
+        assert_eq!(
+            Move16::from(Move::quiet(Square::E7, Square::E6)).to_move(&state.position),
+            None
+        );
+        // a rook taking its own knight
+        assert_eq!(
+            Move16::from(Move::quiet(Square::A1, Square::B1)).to_move(&state.position),
+            None
+        );
+        assert_eq!(Move16::from_bits(0).to_move(&state.position), None);
+        assert!(Move16::from_bits(0).is_none());
+    }
+}
@@ -0,0 +1,72 @@
+use alloc::string::String;
+
+use crate::play::position::Position;
+
+use super::Move;
+
+/// A legal move along with what a GUI needs to list it or hint at it,
+/// so it doesn't have to work any of it out itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveInfo {
+    pub mv: Move,
+    pub uci: String,
+    pub san: String,
+    pub is_capture: bool,
+    pub is_check: bool,
+    pub gives_mate: bool,
+    /// material won (or lost) by the move once the exchange on its target
+    /// square plays out, see [`Position::see`]
+    pub see: i32,
+}
+
+impl MoveInfo {
+    /// `mv` must be a legal move from `position`.
+    pub fn new(mv: Move, position: &Position) -> Self {
+        MoveInfo {
+            mv,
+            uci: mv.to_uci(),
+            san: mv.to_san(position),
+            is_capture: mv.captured().is_some(),
+            is_check: position.gives_check(mv),
+            gives_mate: position.gives_mate(mv),
+            see: position.see(mv),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_legal_move_infos() {
+        let position = Position::default();
+        let infos = position.legal_move_infos();
+        assert_eq!(infos.len(), 20);
+        let e4 = infos.iter().find(|info| info.uci == "e2e4").unwrap();
+        assert_eq!(e4.san, "e4");
+        assert!(!e4.is_capture && !e4.is_check && !e4.gives_mate);
+        assert_eq!(e4.see, 0);
+
+        // fool's mate
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq -";
+        let position = Position::from_fen(fen).unwrap();
+        let infos = position.legal_move_infos();
+        let mate = infos.iter().find(|info| info.uci == "d8h4").unwrap();
+        assert_eq!(mate.san, "Qh4#");
+        assert!(mate.is_check && mate.gives_mate && !mate.is_capture);
+        assert_eq!(infos.iter().filter(|info| info.gives_mate).count(), 1);
+    }
+
+    #[test]
+    fn test_move_info_capture() {
+        let position = Position::from_fen("4k3/8/4p3/3p4/4P3/8/8/3QK3 w - -").unwrap();
+        let infos = position.legal_move_infos();
+        let exd5 = infos.iter().find(|info| info.uci == "e4d5").unwrap();
+        assert_eq!(exd5.san, "exd5");
+        assert!(exd5.is_capture && !exd5.is_check);
+        // exd5 exd5 Qxd5 nets a pawn
+        assert_eq!(exd5.see, 100);
+    }
+}
@@ -1,171 +1,369 @@
-use std::fmt::{self, Write};
+pub mod compact;
+pub mod info;
+mod san;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{convert::TryFrom, fmt};
 
 use crate::play::{
-    constants::{PIECES, SQUARES},
+    constants::{MAX_GAME_PLY, PIECES, SQUARES},
     types::{Piece, Square},
 };
 
 use super::{
     error::{MoveError, MoveErrorType},
-    types::{CastlingRight, CastlingRights, Color, Direction, File},
-    GameState,
+    key::PositionKeyGenerator,
+    position::Position,
+    types::{CastlingRight, CastlingRights, Color, Direction, File, PieceType, Rank},
+    variant, GameState,
 };
 
-///
-///
+/// Applies `mv` to `state`, pushing the history needed to reverse it with
+/// [`unmake_move`]. Fails without touching `state` once the game has
+/// [`MAX_GAME_PLY`] plies of history.
 pub fn make_move(mv: Move, state: &mut GameState) -> Result<(), MoveError> {
-    // update 50-move counter before the pieces are moved on the board
+    let history_len = state.halfmove_clock_history.len();
+    if history_len >= MAX_GAME_PLY {
+        return Err(MoveError::new(MoveErrorType::HistoryFull(history_len)));
+    }
+    // the history holds what the move is about to overwrite
+    state.halfmove_clock_history.push(state.halfmove_clock);
+    state
+        .position
+        .castling_perms_history
+        .push(state.position.castling_permissions);
+    state
+        .position
+        .en_passant_history
+        .push(state.position.en_passant);
+    state.key_history.push(state.position_key);
+    state
+        .sub_key_history
+        .push((state.pawn_key, state.non_pawn_key));
+    #[cfg(feature = "nnue")]
+    state
+        .dirty_pieces
+        .push(super::dirty::DirtyPieces::of(mv, &state.position));
+
+    // the key is updated piece by piece, with castling, en passant and side
+    // to move swapped out as a whole once the move is done
+    let keys = PositionKeyGenerator::global();
+    let mut key = state.position_key ^ keys.hash_state(&state.position);
+
+    // update 50-move counter before the pieces are moved on the board; a
+    // drop changes the hands for good, like a capture
     if (mv.captured().is_some())
+        | mv.is_drop()
         | (state.position.board.piece(&mv.from_sq()) == Some(Piece::WhitePawn))
         | (state.position.board.piece(&mv.from_sq()) == Some(Piece::BlackPawn))
     {
-        state.fifty_move_counter = 0;
+        state.halfmove_clock = 0;
     } else {
-        state.fifty_move_counter += 1;
+        state.halfmove_clock += 1;
     }
 
     if mv.captured().is_some() & !mv.en_passant() {
-        state.position.board.remove_piece(mv.to_sq())?;
+        let captured = state.position.board.remove_piece(mv.to_sq())?;
+        key ^= keys.piece_hash(captured, mv.to_sq());
+        toggle_sub_key(state, keys, captured, mv.to_sq());
+        state.phase -= captured.phase_weight();
+        take_in_hand(state, captured);
     } else if mv.captured().is_some() & mv.en_passant() {
         let dir = match state.position.side_to_move {
             Color::White => Direction::South, // white moving, capture black pawn on sq south of en passant sq
             Color::Black => Direction::North, // black moving, capture white pawn on sq north of en passant sq
         };
-        let capture_sq = match state.position.en_passant {
-            Some(sq) => Square::from_mailbox_no(sq + dir as i8),
+        let capture_sq = match state.position.en_passant.and_then(|sq| sq.offset(dir)) {
+            Some(sq) => sq,
             None => {
                 let msg = "Expected en_passant".to_string();
                 return Err(MoveError::new(MoveErrorType::StateMismatch(msg)));
             }
         };
-        let _ = state.position.board.remove_piece(capture_sq)?;
+        let captured = state.position.board.remove_piece(capture_sq)?;
+        key ^= keys.piece_hash(captured, capture_sq);
+        toggle_sub_key(state, keys, captured, capture_sq);
+        take_in_hand(state, captured);
     }
 
-    state.position.board.move_piece(mv.from_sq(), mv.to_sq())?;
+    if let Some(piece) = mv.dropped() {
+        let color = state.position.side_to_move as usize;
+        if !state.position.hands[color].remove(piece.piece_type()) {
+            let msg = format!("No {:?} in hand to drop", piece);
+            return Err(MoveError::new(MoveErrorType::StateMismatch(msg)));
+        }
+        state.position.board.add_piece(piece, mv.to_sq())?;
+        key ^= keys.piece_hash(piece, mv.to_sq());
+        toggle_sub_key(state, keys, piece, mv.to_sq());
+        state.phase += piece.phase_weight();
+    } else {
+        if let Some(piece) = state.position.board.piece(&mv.from_sq()) {
+            key ^= keys.piece_hash(piece, mv.from_sq()) ^ keys.piece_hash(piece, mv.to_sq());
+            toggle_sub_key(state, keys, piece, mv.from_sq());
+            toggle_sub_key(state, keys, piece, mv.to_sq());
+        }
+        state.position.board.move_piece(mv.from_sq(), mv.to_sq())?;
+    }
     if mv.castle() {
-        if mv.from_sq() == Square::E1 {
-            if mv.to_sq() == Square::G1 {
-                state.position.board.move_piece(Square::H1, Square::F1)?
-            } else if mv.to_sq() == Square::C1 {
-                state.position.board.move_piece(Square::A1, Square::D1)?
-            }
-            state.position.castling_permissions.unset_white_bits();
-        } else if mv.from_sq() == Square::E8 {
-            if mv.to_sq() == Square::G8 {
-                state.position.board.move_piece(Square::H8, Square::F8)?
-            } else if mv.to_sq() == Square::C8 {
-                state.position.board.move_piece(Square::A8, Square::D8)?
+        let (rook, rook_from, rook_to) = match castling_rook(mv.to_sq()) {
+            Some(rook_move) => rook_move,
+            None => {
+                let msg = format!("Unexpected castle to {}", mv.to_sq());
+                return Err(MoveError::new(MoveErrorType::StateMismatch(msg)));
             }
-
-            state.position.castling_permissions.unset_black_bits();
-        }
+        };
+        state.position.board.move_piece(rook_from, rook_to)?;
+        key ^= keys.piece_hash(rook, rook_from) ^ keys.piece_hash(rook, rook_to);
+        toggle_sub_key(state, keys, rook, rook_from);
+        toggle_sub_key(state, keys, rook, rook_to);
+    }
+    // moving the king or a rook off its starting square, or having the rook
+    // captured there, gives up the matching rights for good
+    for sq in [mv.from_sq(), mv.to_sq()] {
+        give_up_castling(&mut state.position.castling_permissions, sq);
     }
-    if (mv.from_sq() == Square::A1)
-        & (state.position.board.piece(&Square::A1) == Some(Piece::WhiteRook))
-    {
-        state.position.castling_permissions.0 &=
-            CastlingRights::all().0 - CastlingRight::WhiteQueen as u8;
-    } else if (mv.from_sq() == Square::H1)
-        & (state.position.board.piece(&Square::H1) == Some(Piece::WhiteRook))
-    {
-        state.position.castling_permissions.0 &=
-            CastlingRights::all().0 - CastlingRight::WhiteKing as u8;
-    } else if (mv.from_sq() == Square::A8)
-        & (state.position.board.piece(&Square::A8) == Some(Piece::BlackRook))
-    {
-        state.position.castling_permissions.0 &=
-            CastlingRights::all().0 - CastlingRight::BlackQueen as u8;
-    } else if (mv.from_sq() == Square::H8)
-        & (state.position.board.piece(&Square::H8) == Some(Piece::BlackRook))
-    {
-        state.position.castling_permissions.0 &=
-            CastlingRights::all().0 - CastlingRight::BlackKing as u8;
-    };
 
     if mv.pawn_start() {
         let dir = match state.position.side_to_move {
             Color::White => Direction::South,
             Color::Black => Direction::North,
         };
-        let sq = Square::from_mailbox_no(mv.to_sq() + dir as i8);
-        state.position.en_passant = Some(sq);
+        state.position.en_passant = mv.to_sq().offset(dir);
     } else {
         state.position.en_passant = None;
     }
 
-    // TODO: pos key
-
     if let Some(piece) = mv.promoted() {
-        state.position.board.remove_piece(mv.to_sq())?;
+        let pawn = state.position.board.remove_piece(mv.to_sq())?;
         state.position.board.add_piece(piece, mv.to_sq())?;
+        key ^= keys.piece_hash(pawn, mv.to_sq()) ^ keys.piece_hash(piece, mv.to_sq());
+        toggle_sub_key(state, keys, pawn, mv.to_sq());
+        toggle_sub_key(state, keys, piece, mv.to_sq());
+        state.phase += piece.phase_weight();
     }
 
-    state.ply += 1;
+    if state.position.variant.explodes() && mv.captured().is_some() {
+        let mut blown_up = vec![];
+        for sq in variant::exploded(&state.position.board, mv.to_sq()) {
+            let piece = state.position.board.remove_piece(sq)?;
+            key ^= keys.piece_hash(piece, sq);
+            toggle_sub_key(state, keys, piece, sq);
+            state.phase -= piece.phase_weight();
+            give_up_castling(&mut state.position.castling_permissions, sq);
+            blown_up.push((piece, sq));
+        }
+        state.explosion_history.push(blown_up);
+        // too many pieces may have gone to list, so the accumulators start
+        // again
+        #[cfg(feature = "nnue")]
+        if let Some(dirty) = state.dirty_pieces.last_mut() {
+            *dirty = super::dirty::DirtyPieces::everything();
+        }
+    }
+
+    state.game_ply += 1;
+    state.search_ply += 1;
     state.position.flip_side();
-    state.fifty_move_country_hist.push(state.fifty_move_counter);
+    state.position_key = key ^ keys.hash_state(&state.position);
+    state.position.update_checks_and_pins();
+
+    Ok(())
+}
+
+/// The rook that castling with the king to `king_to` moves, and from where
+/// to where.
+pub(in crate::play) fn castling_rook(king_to: Square) -> Option<(Piece, Square, Square)> {
+    match king_to {
+        Square::G1 => Some((Piece::WhiteRook, Square::H1, Square::F1)),
+        Square::C1 => Some((Piece::WhiteRook, Square::A1, Square::D1)),
+        Square::G8 => Some((Piece::BlackRook, Square::H8, Square::F8)),
+        Square::C8 => Some((Piece::BlackRook, Square::A8, Square::D8)),
+        _ => None,
+    }
+}
+
+/// Gives up the castling rights that need a king or rook on `sq`, once
+/// that piece has moved or been taken off.
+fn give_up_castling(perms: &mut CastlingRights, sq: Square) {
+    match sq {
+        Square::E1 => perms.unset_white_bits(),
+        Square::E8 => perms.unset_black_bits(),
+        Square::A1 => perms.0 &= !(CastlingRight::WhiteQueen as u8),
+        Square::H1 => perms.0 &= !(CastlingRight::WhiteKing as u8),
+        Square::A8 => perms.0 &= !(CastlingRight::BlackQueen as u8),
+        Square::H8 => perms.0 &= !(CastlingRight::BlackKing as u8),
+        _ => {}
+    }
+}
+
+/// Puts a piece the side to move just captured into its hand, in variants
+/// that keep one.
+fn take_in_hand(state: &mut GameState, captured: Piece) {
+    if state.position.variant.has_hands() {
+        let color = state.position.side_to_move as usize;
+        state.position.hands[color].add(captured.piece_type());
+    }
+}
+
+/// Toggles `piece` on `sq` in whichever of the pawn and non-pawn keys it
+/// belongs to.
+fn toggle_sub_key(state: &mut GameState, keys: &PositionKeyGenerator, piece: Piece, sq: Square) {
+    let hash = keys.piece_hash(piece, sq);
+    if piece.piece_type() == PieceType::Pawn {
+        state.pawn_key ^= hash;
+    } else {
+        state.non_pawn_key ^= hash;
+    }
+}
+
+/// Passes the turn without moving, for the search's null move pruning. The
+/// history is pushed as for [`make_move`], but the fifty-move counter is
+/// reset so no repetition is ever found across the pass.
+pub fn make_null_move(state: &mut GameState) -> Result<(), MoveError> {
+    let history_len = state.halfmove_clock_history.len();
+    if history_len >= MAX_GAME_PLY {
+        return Err(MoveError::new(MoveErrorType::HistoryFull(history_len)));
+    }
+    state.halfmove_clock_history.push(state.halfmove_clock);
     state
         .position
         .castling_perms_history
         .push(state.position.castling_permissions);
-
+    state
+        .position
+        .en_passant_history
+        .push(state.position.en_passant);
+    state.key_history.push(state.position_key);
+    state
+        .sub_key_history
+        .push((state.pawn_key, state.non_pawn_key));
+    #[cfg(feature = "nnue")]
+    state.dirty_pieces.push(Default::default());
+
+    let keys = PositionKeyGenerator::global();
+    let key = state.position_key ^ keys.hash_state(&state.position);
+    state.halfmove_clock = 0;
+    state.position.en_passant = None;
+    state.game_ply += 1;
+    state.search_ply += 1;
+    state.position.flip_side();
+    state.position_key = key ^ keys.hash_state(&state.position);
+    state.position.update_checks_and_pins();
     Ok(())
 }
 
-///
-///
+/// Reverses a move previously applied with [`make_move`].
 pub fn unmake_move(mv: Move, state: &mut GameState) -> Result<(), MoveError> {
-    if let Some(piece) = mv.promoted() {
+    state.position_key =
+        state
+            .key_history
+            .pop()
+            .ok_or(MoveError::new(MoveErrorType::InsufficientHistory(
+                "position_key".to_string(),
+            )))?;
+
+    (state.pawn_key, state.non_pawn_key) =
+        state
+            .sub_key_history
+            .pop()
+            .ok_or(MoveError::new(MoveErrorType::InsufficientHistory(
+                "sub_keys".to_string(),
+            )))?;
+
+    state.position.en_passant = state
+        .position
+        .en_passant_history
+        .pop()
+        .ok_or(MoveError::new(MoveErrorType::InsufficientHistory(
+            "en_passant".to_string(),
+        )))?;
+
+    state.halfmove_clock = state.halfmove_clock_history.pop().ok_or(MoveError::new(
+        MoveErrorType::InsufficientHistory("halfmove_clock".to_string()),
+    ))?;
+
+    #[cfg(feature = "nnue")]
+    state.dirty_pieces.pop();
+
+    state.position.castling_permissions =
+        state
+            .position
+            .castling_perms_history
+            .pop()
+            .ok_or(MoveError::new(MoveErrorType::InsufficientHistory(
+                "castling_permissions".to_string(),
+            )))?;
+
+    // the side that made the move, the turn not having passed back yet
+    let mover = state.position.side_to_move.opposing() as usize;
+    // put back what the capture blew up, the capturer back on the square
+    // it took on to be moved home below
+    if state.position.variant.explodes() && mv.captured().is_some() {
+        let blown_up = state.explosion_history.pop().ok_or(MoveError::new(
+            MoveErrorType::InsufficientHistory("explosions".to_string()),
+        ))?;
+        for (piece, sq) in blown_up {
+            state.position.board.add_piece(piece, sq)?;
+            state.phase += piece.phase_weight();
+        }
+    }
+    if let Some(piece) = mv.dropped() {
+        state.position.board.remove_piece(mv.to_sq())?;
+        state.position.hands[mover].add(piece.piece_type());
+        state.phase -= piece.phase_weight();
+    } else if let Some(piece) = mv.promoted() {
         state.position.board.remove_piece(mv.to_sq())?;
         let pawn = match piece.color() {
             Color::White => Piece::WhitePawn,
             Color::Black => Piece::BlackPawn,
         };
         state.position.board.add_piece(pawn, mv.from_sq())?;
+        state.phase -= piece.phase_weight();
     } else {
         state.position.board.move_piece(mv.to_sq(), mv.from_sq())?;
     }
 
+    if mv.castle() {
+        match mv.to_sq() {
+            Square::G1 => state.position.board.move_piece(Square::F1, Square::H1)?,
+            Square::C1 => state.position.board.move_piece(Square::D1, Square::A1)?,
+            Square::G8 => state.position.board.move_piece(Square::F8, Square::H8)?,
+            Square::C8 => state.position.board.move_piece(Square::D8, Square::A8)?,
+            _ => {}
+        }
+    }
+
     if !mv.en_passant() {
         if let Some(piece) = mv.captured() {
             state.position.board.add_piece(piece, mv.to_sq())?;
+            state.phase += piece.phase_weight();
+        }
+    }
+    if let Some(piece) = mv.captured() {
+        if state.position.variant.has_hands() {
+            state.position.hands[mover].remove(piece.piece_type());
         }
-        state.position.en_passant = None;
     }
 
     if mv.en_passant() {
-        match state.position.side_to_move {
-            Color::White => {
-                let sq = Square::from_mailbox_no(mv.to_sq() + Direction::North as i8);
-                state.position.board.add_piece(Piece::WhitePawn, sq)?
-            }
-            Color::Black => {
-                let sq = Square::from_mailbox_no(mv.to_sq() + Direction::South as i8);
-                state.position.board.add_piece(Piece::BlackPawn, sq)?
-            }
+        let (pawn, dir) = match state.position.side_to_move {
+            Color::White => (Piece::WhitePawn, Direction::North),
+            Color::Black => (Piece::BlackPawn, Direction::South),
         };
-        state.position.en_passant = Some(mv.to_sq());
+        if let Some(sq) = mv.to_sq().offset(dir) {
+            state.position.board.add_piece(pawn, sq)?
+        }
     }
 
-    // TODO: idk about the first castling condition
-
-    if mv.pawn_start() {}
-
-    state.ply -= 1;
+    state.game_ply -= 1;
+    state.search_ply = state.search_ply.saturating_sub(1);
     state.position.flip_side();
-
-    state.fifty_move_counter = state.fifty_move_country_hist.pop().ok_or(MoveError::new(
-        MoveErrorType::InsufficientHistory("fifty_move_counter".to_string()),
-    ))?;
-
-    state.position.castling_permissions =
-        state
-            .position
-            .castling_perms_history
-            .pop()
-            .ok_or(MoveError::new(MoveErrorType::InsufficientHistory(
-                "castling_permissions".to_string(),
-            )))?;
-
+    state.position.update_checks_and_pins();
     Ok(())
 }
 
@@ -178,6 +376,7 @@ pub enum MoveType {
     Promotion,
     EnPassant,
     Castle,
+    Drop,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -188,30 +387,136 @@ pub struct Move {
 
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let from_file = &self.from_sq().file();
-        let from_rank = &self.from_sq().rank();
-        let to_file = &self.to_sq().file();
-        let to_rank = &self.to_sq().rank();
-
-        if self.castle() & (*to_file == File::C) {
-            f.write_str("0-0-0");
-        } else if self.castle() & (*to_file == File::G) {
-            f.write_str("0-0");
+        f.write_str(&self.to_uci())
+    }
+}
+
+impl Move {
+    /// Coordinate notation as UCI expects it, e.g. `e2e4`, `e1g1` or `e7e8q`,
+    /// or `N@f3` for a drop.
+    pub fn to_uci(self) -> String {
+        if let Some(piece) = self.dropped() {
+            let piece_c: char = piece.into();
+            return format!("{}@{}", piece_c.to_ascii_uppercase(), self.to_sq());
+        }
+        let mut uci = format!("{}{}", self.from_sq(), self.to_sq());
+        if let Some(piece) = self.promoted() {
+            let piece_c: char = piece.into();
+            uci.push(piece_c.to_ascii_lowercase());
+        }
+        uci
+    }
+
+    /// Standard algebraic notation, e.g. `Nbd2`, `exd5`, `O-O` or `e8=Q#`,
+    /// for the move played from `position`.
+    pub fn to_san(self, position: &Position) -> String {
+        let mut san = String::new();
+        if let Some(piece) = self.dropped() {
+            san.push_str(&self.to_uci());
+            if piece.piece_type() == PieceType::Pawn {
+                // pawns are dropped without their letter
+                san.remove(0);
+            }
+            if position.gives_mate(self) {
+                san.push('#');
+            } else if position.gives_check(self) {
+                san.push('+');
+            }
+            return san;
+        }
+        let piece = position.board.piece(&self.from_sq());
+        let piece_type = piece.map(|p| p.piece_type());
+        let to_file = self.to_sq().file();
+
+        if self.castle() && to_file == File::G {
+            san.push_str("O-O");
+        } else if self.castle() && to_file == File::C {
+            san.push_str("O-O-O");
+        } else if piece_type == Some(PieceType::Pawn) {
+            if self.captured().is_some() {
+                san.push((&self.from_sq().file()).into());
+                san.push('x');
+            }
+            san.push_str(&self.to_sq().to_string());
+            if let Some(promoted) = self.promoted() {
+                let promoted_c: char = promoted.into();
+                san.push('=');
+                san.push(promoted_c.to_ascii_uppercase());
+            }
         } else {
-            f.write_char(from_file.into())?;
-            f.write_char(from_rank.into())?;
-            f.write_char(to_file.into())?;
-            f.write_char(to_rank.into())?;
-            if let Some(piece) = self.promoted() {
+            if let Some(piece) = piece {
                 let piece_c: char = piece.into();
-                f.write_char(piece_c.to_ascii_lowercase())?
+                san.push(piece_c.to_ascii_uppercase());
+            }
+            // name the file, else the rank, else both, of the piece that moved
+            // when another of the same type could reach the square too
+            let rivals: Vec<Move> = position
+                .legal_moves()
+                .into_iter()
+                .filter(|mv| {
+                    mv.to_sq() == self.to_sq()
+                        && mv.from_sq() != self.from_sq()
+                        && position.board.piece(&mv.from_sq()) == piece
+                        && position.is_legal(*mv)
+                })
+                .collect();
+            if !rivals.is_empty() {
+                let from = self.from_sq();
+                if rivals.iter().all(|mv| mv.from_sq().file() != from.file()) {
+                    san.push((&from.file()).into());
+                } else if rivals.iter().all(|mv| mv.from_sq().rank() != from.rank()) {
+                    san.push((&from.rank()).into());
+                } else {
+                    san.push_str(&from.to_string());
+                }
+            }
+            if self.captured().is_some() {
+                san.push('x');
             }
+            san.push_str(&self.to_sq().to_string());
         }
-        Ok(())
+
+        if position.gives_mate(self) {
+            san.push('#');
+        } else if position.gives_check(self) {
+            san.push('+');
+        }
+        san
+    }
+
+    /// A move read from coordinate notation, with its squares and any
+    /// promotion but nothing a position would add: what it captured, or
+    /// whether it castled or was a pawn's double step. A drop's piece is
+    /// read as White's. To play it, find the legal move with the same
+    /// [`Move::to_uci`].
+    pub fn from_uci(uci: &str) -> Option<Move> {
+        if let Some((piece, to)) = uci.split_once('@') {
+            let mut chars = piece.chars();
+            let ch = chars.next().filter(|_| chars.next().is_none())?;
+            let piece = Piece::try_from(ch.to_ascii_uppercase()).ok()?;
+            let to = Square::from_fen(to).ok().flatten()?;
+            return Some(Move::drop(piece, to));
+        }
+        let square = |s: Option<&str>| s.and_then(|s| Square::from_fen(s).ok().flatten());
+        let from = square(uci.get(0..2))?;
+        let to = square(uci.get(2..4))?;
+        let promoted = match uci.get(4..)? {
+            "" => None,
+            promoted => {
+                let mut chars = promoted.chars();
+                let ch = chars.next().filter(|_| chars.next().is_none())?;
+                // the piece is White's, in upper case, when it reaches the
+                // eighth rank
+                let ch = match to.rank() {
+                    Rank::Rank8 => ch.to_ascii_uppercase(),
+                    _ => ch.to_ascii_lowercase(),
+                };
+                Some(Piece::try_from(ch).ok()?)
+            }
+        };
+        Some(Move::new(from, to, None, promoted, false, false, false))
     }
-}
 
-impl Move {
     pub fn empty() -> Move {
         Move {
             repr: 0x0,
@@ -254,11 +559,91 @@ impl Move {
         }
     }
 
+    /// A move to an empty square that's nothing more, neither a pawn's
+    /// double push nor castling.
+    pub fn quiet(from: Square, to: Square) -> Move {
+        Move::new(from, to, None, None, false, false, false)
+    }
+
+    /// A pawn's two-square first move, which sets the en passant square.
+    pub fn double_push(from: Square, to: Square) -> Move {
+        Move::new(from, to, None, None, false, true, false)
+    }
+
+    /// A capture of `captured` on `to`, en passant aside.
+    pub fn capture(from: Square, to: Square, captured: Piece) -> Move {
+        Move::new(from, to, Some(captured), None, false, false, false)
+    }
+
+    /// A pawn reaching the last rank and becoming `promoted`, taking
+    /// `captured` on the way if it's a capture.
+    pub fn promotion(from: Square, to: Square, promoted: Piece, captured: Option<Piece>) -> Move {
+        Move::new(from, to, captured, Some(promoted), false, false, false)
+    }
+
+    /// A pawn taking en passant onto `to`, the pawn it takes being
+    /// White's when `to` is on the third rank and Black's on the sixth.
+    /// [`Move::en_passant`] being the flag's getter, this takes the longer
+    /// name.
+    pub fn en_passant_capture(from: Square, to: Square) -> Move {
+        let captured = match to.rank() {
+            Rank::Rank3 => Piece::WhitePawn,
+            _ => Piece::BlackPawn,
+        };
+        Move::new(from, to, Some(captured), None, true, false, false)
+    }
+
+    /// `color` castling short, as the king's move.
+    pub fn castle_kingside(color: Color) -> Move {
+        let (from, to) = match color {
+            Color::White => (Square::E1, Square::G1),
+            Color::Black => (Square::E8, Square::G8),
+        };
+        Move::new(from, to, None, None, false, false, true)
+    }
+
+    /// `color` castling long, as the king's move.
+    pub fn castle_queenside(color: Color) -> Move {
+        let (from, to) = match color {
+            Color::White => (Square::E1, Square::C1),
+            Color::Black => (Square::E8, Square::C8),
+        };
+        Move::new(from, to, None, None, false, false, true)
+    }
+
+    /// A move from `from` to `to` with its details to be filled in, see
+    /// [`MoveBuilder`].
+    pub fn builder(from: Square, to: Square) -> MoveBuilder {
+        MoveBuilder {
+            from,
+            to,
+            captured: None,
+            promoted: None,
+            en_passant: false,
+            pawn_start: false,
+            castle: false,
+        }
+    }
+
+    /// `piece` put on `to` from its side's hand. The piece is kept where a
+    /// promotion's would be, with `to` standing in as the from square too,
+    /// since there's no square it comes from.
+    pub fn drop(piece: Piece, to: Square) -> Move {
+        let promoted_piece_bits = (piece as u32 + 1) << 16;
+        let drop_bit = 0b1 << 23;
+        Move {
+            repr: to as u32 | (to as u32) << 6 | promoted_piece_bits | drop_bit,
+            score: 0,
+        }
+    }
+
+    #[allow(clippy::wrong_self_convention)]
     pub fn from_sq(&self) -> Square {
         let idx = self.repr & 0x3F;
         SQUARES[idx as usize]
     }
 
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_sq(&self) -> Square {
         let idx = (self.repr >> 6) & 0x3F;
         SQUARES[idx as usize]
@@ -275,7 +660,7 @@ impl Move {
 
     pub fn promoted(&self) -> Option<Piece> {
         let idx = (self.repr >> 16) & 0xF;
-        if idx == 0 {
+        if idx == 0 || self.is_drop() {
             None
         } else {
             Some(PIECES[idx as usize - 1])
@@ -283,11 +668,26 @@ impl Move {
     }
 
     pub fn en_passant(&self) -> bool {
-        ((self.repr >> 20) & (0b1 as u32)) == 0b1
+        ((self.repr >> 20) & 0b1_u32) == 0b1
+    }
+
+    pub fn is_drop(&self) -> bool {
+        ((self.repr >> 23) & 0b1_u32) == 0b1
+    }
+
+    /// The piece a drop puts on the board.
+    pub fn dropped(&self) -> Option<Piece> {
+        if !self.is_drop() {
+            return None;
+        }
+        let idx = (self.repr >> 16) & 0xF;
+        Some(PIECES[idx as usize - 1])
     }
 
     pub fn move_type(&self) -> MoveType {
-        if self.en_passant() {
+        if self.is_drop() {
+            MoveType::Drop
+        } else if self.en_passant() {
             MoveType::EnPassant
         } else if self.castle() {
             MoveType::Castle
@@ -299,37 +699,94 @@ impl Move {
     }
 
     pub fn pawn_start(&self) -> bool {
-        ((self.repr >> 21) & (0b1 as u32)) == 0b1
+        ((self.repr >> 21) & 0b1_u32) == 0b1
     }
 
     pub fn castle(&self) -> bool {
-        ((self.repr >> 22) & (0b1 as u32)) == 0b1
+        ((self.repr >> 22) & 0b1_u32) == 0b1
     }
 
     pub fn is_placeholder(&self) -> bool {
         self.repr == 0
     }
+
+    /// The move, its ordering score included, packed into one number for
+    /// storing compactly.
+    pub fn to_bits(self) -> u32 {
+        self.repr | (self.score as u8 as u32) << 24
+    }
+
+    /// Reverses [`Move::to_bits`].
+    pub fn from_bits(bits: u32) -> Move {
+        Move {
+            repr: bits & 0xFF_FFFF,
+            score: (bits >> 24) as u8 as i8,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct MoveList {
-    inner: [Move; MOVE_LIST_SIZE],
-    count: u8,
-    pos: u8,
+/// Puts a [`Move`] together a detail at a time, for the combinations the
+/// named constructors don't cover and where naming each flag reads better
+/// than [`Move::new`]'s row of booleans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveBuilder {
+    from: Square,
+    to: Square,
+    captured: Option<Piece>,
+    promoted: Option<Piece>,
+    en_passant: bool,
+    pawn_start: bool,
+    castle: bool,
 }
 
-impl Iterator for MoveList {
-    type Item = Move;
+impl MoveBuilder {
+    pub fn captured(mut self, piece: Piece) -> Self {
+        self.captured = Some(piece);
+        self
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.pos == self.count {
-            None
-        } else {
-            let idx = self.pos as usize;
-            self.pos += 1;
-            Some(self.inner[idx])
-        }
+    pub fn promoted(mut self, piece: Piece) -> Self {
+        self.promoted = Some(piece);
+        self
+    }
+
+    pub fn en_passant(mut self) -> Self {
+        self.en_passant = true;
+        self
+    }
+
+    pub fn pawn_start(mut self) -> Self {
+        self.pawn_start = true;
+        self
+    }
+
+    pub fn castle(mut self) -> Self {
+        self.castle = true;
+        self
     }
+
+    pub fn build(self) -> Move {
+        Move::new(
+            self.from,
+            self.to,
+            self.captured,
+            self.promoted,
+            self.en_passant,
+            self.pawn_start,
+            self.castle,
+        )
+    }
+}
+
+/// The moves of one position, kept inline. No legal position has more than
+/// 218 moves, so the inline space is only outgrown by something unusual, in
+/// which case the list moves onto the heap rather than losing moves.
+#[derive(Debug, Clone)]
+pub struct MoveList {
+    inner: [Move; MOVE_LIST_SIZE],
+    len: usize,
+    /// every move once the inline space has run out
+    spilled: Option<Vec<Move>>,
 }
 
 impl MoveList {
@@ -344,57 +801,214 @@ impl MoveList {
     pub fn empty() -> MoveList {
         MoveList {
             inner: [Move::empty(); MOVE_LIST_SIZE],
-            count: 0,
-            pos: 0,
+            len: 0,
+            spilled: None,
         }
     }
 
+    /// The list in move order, leaving this one as it is.
     pub fn sorted(&self) -> MoveList {
-        let mut v = self.inner.to_vec();
-        let _ = v.sort();
-        MoveList::new(v)
+        let mut l = self.clone();
+        l.as_mut_slice().sort();
+        l
+    }
+
+    pub fn push(&mut self, mv: Move) {
+        if let Some(spilled) = &mut self.spilled {
+            spilled.push(mv);
+        } else if self.len == MOVE_LIST_SIZE {
+            let mut spilled = self.inner.to_vec();
+            spilled.push(mv);
+            self.spilled = Some(spilled);
+        } else {
+            self.inner[self.len] = mv;
+            self.len += 1;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.spilled {
+            Some(spilled) => spilled.len(),
+            None => self.len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_slice(&self) -> &[Move] {
+        match &self.spilled {
+            Some(spilled) => spilled,
+            None => &self.inner[..self.len],
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [Move] {
+        match &mut self.spilled {
+            Some(spilled) => spilled,
+            None => &mut self.inner[..self.len],
+        }
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, Move> {
+        self.as_slice().iter()
+    }
+
+    pub fn contains(&self, mv: &Move) -> bool {
+        self.as_slice().contains(mv)
     }
+}
+
+impl PartialEq for MoveList {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for MoveList {}
 
-    pub fn push(&mut self, mv: Move) -> () {
-        if self.count == MOVE_LIST_SIZE as u8 {
-            panic!("MoveList full!");
+/// Hands out a [`MoveList`]'s moves by value, front to back.
+#[derive(Debug, Clone)]
+pub struct MoveListIntoIter {
+    list: MoveList,
+    pos: usize,
+}
+
+impl Iterator for MoveListIntoIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mv = self.list.as_slice().get(self.pos).copied();
+        if mv.is_some() {
+            self.pos += 1;
         }
-        self.inner[self.count as usize] = mv;
-        self.count += 1;
+        mv
     }
 
-    pub fn count(&self) -> u8 {
-        self.count
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.list.len() - self.pos;
+        (left, Some(left))
     }
+}
+
+impl ExactSizeIterator for MoveListIntoIter {}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = MoveListIntoIter;
 
-    // TODO: add accessor for nonempty moves as vec
+    fn into_iter(self) -> Self::IntoIter {
+        MoveListIntoIter { list: self, pos: 0 }
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = core::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::play::variant::Variant;
 
     #[test]
     fn test_move_display() {
-        let mv = Move::new(Square::C3, Square::C4, None, None, false, false, false);
+        let mv = Move::quiet(Square::C3, Square::C4);
         assert_eq!(format!("{}", mv), "c3c4");
 
-        let mv = Move::new(
-            Square::H7,
-            Square::H8,
-            None,
-            Some(Piece::WhiteQueen),
-            false,
-            false,
-            false,
-        );
+        let mv = Move::promotion(Square::H7, Square::H8, Piece::WhiteQueen, None);
         assert_eq!(format!("{}", mv), "h7h8q");
     }
 
+    #[test]
+    fn test_move_to_uci() {
+        let castle = Move::castle_kingside(Color::White);
+        assert_eq!(castle.to_uci(), "e1g1");
+        let castle = Move::castle_queenside(Color::Black);
+        assert_eq!(castle.to_uci(), "e8c8");
+        let promo = Move::promotion(
+            Square::B2,
+            Square::A1,
+            Piece::BlackKnight,
+            Some(Piece::WhiteRook),
+        );
+        assert_eq!(promo.to_uci(), "b2a1n");
+        assert_eq!(format!("{}", promo), promo.to_uci());
+        // a drop names its piece in upper case whatever its color
+        let drop = Move::drop(Piece::BlackKnight, Square::F6);
+        assert_eq!(drop.to_uci(), "N@f6");
+        assert_eq!(Move::from_uci("N@f6").unwrap().to_uci(), "N@f6");
+        assert_eq!(
+            Move::from_uci("p@e4"),
+            Some(Move::drop(Piece::WhitePawn, Square::E4))
+        );
+        assert_eq!(
+            Move::from_uci("K@e4").map(|mv| mv.to_uci()),
+            Some("K@e4".to_string())
+        );
+        assert_eq!(Move::from_uci("NN@e4"), None);
+    }
+
+    #[test]
+    fn test_move_drop() {
+        let drop = Move::drop(Piece::WhiteQueen, Square::D7);
+        assert!(drop.is_drop());
+        assert_eq!(drop.dropped(), Some(Piece::WhiteQueen));
+        assert_eq!(drop.to_sq(), Square::D7);
+        assert_eq!(drop.promoted(), None);
+        assert_eq!(drop.captured(), None);
+        assert!(!drop.castle() && !drop.en_passant() && !drop.pawn_start());
+        assert_eq!(drop.move_type(), MoveType::Drop);
+        assert_eq!(Move::from_bits(drop.to_bits()), drop);
+
+        let promo = Move::promotion(Square::D7, Square::D8, Piece::WhiteQueen, None);
+        assert!(!promo.is_drop());
+        assert_eq!(promo.dropped(), None);
+        assert_ne!(promo, drop);
+    }
+
+    #[test]
+    fn test_move_to_san() {
+        let quiet = |from, to| Move::quiet(from, to);
+
+        let pos = GameState::default().position;
+        assert_eq!(quiet(Square::E2, Square::E4).to_san(&pos), "e4");
+        assert_eq!(quiet(Square::G1, Square::F3).to_san(&pos), "Nf3");
+
+        // knights on b1 and f3 can both reach d2, rooks on a1 and a5 a3
+        let fen = "4k3/8/8/R2p4/4P3/5N2/8/RN2K2R w K - 0 1";
+        let pos = GameState::from_fen(fen).unwrap().position;
+        assert_eq!(quiet(Square::B1, Square::D2).to_san(&pos), "Nbd2");
+        assert_eq!(quiet(Square::A1, Square::A3).to_san(&pos), "R1a3");
+        let pawn_takes = Move::capture(Square::E4, Square::D5, Piece::BlackPawn);
+        assert_eq!(pawn_takes.to_san(&pos), "exd5");
+        let rook_takes = Move::capture(Square::A5, Square::D5, Piece::BlackPawn);
+        assert_eq!(rook_takes.to_san(&pos), "Rxd5");
+        let castle = Move::castle_kingside(Color::White);
+        assert_eq!(castle.to_san(&pos), "O-O");
+        assert_eq!(quiet(Square::H1, Square::H8).to_san(&pos), "Rh8+");
+
+        // fool's mate
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        let pos = GameState::from_fen(fen).unwrap().position;
+        assert_eq!(quiet(Square::D8, Square::H4).to_san(&pos), "Qh4#");
+
+        let fen = "8/1P2k3/8/8/8/8/8/4K3 w - - 0 1";
+        let pos = GameState::from_fen(fen).unwrap().position;
+        let promo = Move::promotion(Square::B7, Square::B8, Piece::WhiteQueen, None);
+        assert_eq!(promo.to_san(&pos), "b8=Q");
+    }
+
     #[test]
     fn test_move_new() {
-        let mv = Move::new(Square::C3, Square::C4, None, None, false, false, false);
+        let mv = Move::quiet(Square::C3, Square::C4);
         assert_eq!(mv.from_sq(), Square::C3);
         assert_eq!(mv.to_sq(), Square::C4);
         assert!(mv.captured().is_none());
@@ -403,15 +1017,7 @@ mod tests {
         assert!(!mv.pawn_start());
         assert!(!mv.castle());
 
-        let mv = Move::new(
-            Square::H8,
-            Square::H7,
-            Some(Piece::BlackBishop),
-            None,
-            false,
-            false,
-            false,
-        );
+        let mv = Move::capture(Square::H8, Square::H7, Piece::BlackBishop);
         assert_eq!(mv.from_sq(), Square::H8);
         assert_eq!(mv.to_sq(), Square::H7);
         assert_eq!(mv.captured(), Some(Piece::BlackBishop));
@@ -420,14 +1026,11 @@ mod tests {
         assert!(!mv.pawn_start());
         assert!(!mv.castle());
 
-        let mv = Move::new(
+        let mv = Move::promotion(
             Square::D5,
             Square::H1,
+            Piece::BlackQueen,
             Some(Piece::WhiteKnight),
-            Some(Piece::BlackQueen),
-            false,
-            false,
-            false,
         );
         assert_eq!(mv.from_sq(), Square::D5);
         assert_eq!(mv.to_sq(), Square::H1);
@@ -437,7 +1040,10 @@ mod tests {
         assert!(!mv.pawn_start());
         assert!(!mv.castle());
 
-        let mv = Move::new(Square::A2, Square::A4, None, None, true, true, false);
+        let mv = Move::builder(Square::A2, Square::A4)
+            .en_passant()
+            .pawn_start()
+            .build();
         assert_eq!(mv.from_sq(), Square::A2);
         assert_eq!(mv.to_sq(), Square::A4);
         assert!(mv.captured().is_none());
@@ -446,7 +1052,7 @@ mod tests {
         assert!(mv.pawn_start());
         assert!(!mv.castle());
 
-        let mv = Move::new(Square::E1, Square::B1, None, None, false, false, true);
+        let mv = Move::builder(Square::E1, Square::B1).castle().build();
         assert_eq!(mv.from_sq(), Square::E1);
         assert_eq!(mv.to_sq(), Square::B1);
         assert!(mv.captured().is_none());
@@ -456,24 +1062,102 @@ mod tests {
         assert!(mv.castle());
     }
 
+    #[test]
+    fn test_move_constructors() {
+        let quiet = Move::quiet(Square::G1, Square::F3);
+        assert_eq!(quiet.move_type(), MoveType::Normal);
+        assert_eq!((quiet.captured(), quiet.pawn_start()), (None, false));
+
+        let push = Move::double_push(Square::D7, Square::D5);
+        assert!(push.pawn_start() && !push.en_passant());
+
+        let capture = Move::capture(Square::E4, Square::D5, Piece::BlackPawn);
+        assert_eq!(capture.captured(), Some(Piece::BlackPawn));
+        assert_eq!(capture.move_type(), MoveType::Normal);
+
+        let promo = Move::promotion(
+            Square::B7,
+            Square::A8,
+            Piece::WhiteQueen,
+            Some(Piece::BlackRook),
+        );
+        assert_eq!(promo.promoted(), Some(Piece::WhiteQueen));
+        assert_eq!(promo.captured(), Some(Piece::BlackRook));
+        assert_eq!(promo.move_type(), MoveType::Promotion);
+
+        let white_ep = Move::en_passant_capture(Square::E5, Square::D6);
+        assert_eq!(white_ep.captured(), Some(Piece::BlackPawn));
+        assert_eq!(white_ep.move_type(), MoveType::EnPassant);
+        let black_ep = Move::en_passant_capture(Square::D4, Square::E3);
+        assert_eq!(black_ep.captured(), Some(Piece::WhitePawn));
+
+        for (mv, uci) in [
+            (Move::castle_kingside(Color::White), "e1g1"),
+            (Move::castle_queenside(Color::White), "e1c1"),
+            (Move::castle_kingside(Color::Black), "e8g8"),
+            (Move::castle_queenside(Color::Black), "e8c8"),
+        ] {
+            assert_eq!(mv.to_uci(), uci);
+            assert_eq!(mv.move_type(), MoveType::Castle);
+        }
+
+        // the builder sets just the details named, the same as the long form
+        assert_eq!(
+            Move::builder(Square::H2, Square::G1)
+                .captured(Piece::WhiteKnight)
+                .promoted(Piece::BlackQueen)
+                .build(),
+            Move::new(
+                Square::H2,
+                Square::G1,
+                Some(Piece::WhiteKnight),
+                Some(Piece::BlackQueen),
+                false,
+                false,
+                false,
+            )
+        );
+        assert_eq!(
+            Move::builder(Square::E2, Square::E4).pawn_start().build(),
+            Move::double_push(Square::E2, Square::E4)
+        );
+        assert_eq!(
+            Move::builder(Square::A1, Square::A2).build(),
+            Move::quiet(Square::A1, Square::A2)
+        );
+    }
+
+    #[test]
+    fn test_move_bits() {
+        let mut mv = Move::promotion(
+            Square::B7,
+            Square::A8,
+            Piece::WhiteQueen,
+            Some(Piece::BlackRook),
+        );
+        mv.score = -100;
+        assert_eq!(Move::from_bits(mv.to_bits()), mv);
+        assert_eq!(Move::from_bits(Move::empty().to_bits()), Move::empty());
+    }
+
     #[test]
     fn test_make_unmake_move_simple() {
         let mut state = GameState::default();
-        let mv = Move::new(Square::A2, Square::A3, None, None, false, true, false);
+        let mv = Move::quiet(Square::A2, Square::A3);
         assert_eq!(state.position.board.piece(&Square::A3), None);
-        assert_eq!(state.fifty_move_counter, 0);
-        assert_eq!(state.ply, 0);
+        assert_eq!(state.halfmove_clock, 0);
+        assert_eq!(state.game_ply, 0);
         assert!(make_move(mv, &mut state).is_ok());
         assert_eq!(
             state.position.board.piece(&Square::A3),
             Some(Piece::WhitePawn)
         );
-        assert_eq!(state.fifty_move_counter, 0);
-        assert_eq!(state.ply, 1);
+        assert_eq!(state.halfmove_clock, 0);
+        assert_eq!(state.game_ply, 1);
         assert!(unmake_move(mv, &mut state).is_ok());
         assert_eq!(state.position.board.piece(&Square::A3), None);
-        assert_eq!(state.fifty_move_counter, 0);
-        assert_eq!(state.ply, 0);
+        assert_eq!(state.halfmove_clock, 0);
+        assert_eq!(state.game_ply, 0);
     }
 
     #[test]
@@ -482,7 +1166,7 @@ mod tests {
         let mut state = GameState::from_fen(fen).unwrap();
 
         // white kingside
-        let wk_side_castle_mv = Move::new(Square::E1, Square::G1, None, None, false, false, true);
+        let wk_side_castle_mv = Move::castle_kingside(Color::White);
         assert_eq!(
             state.position.board.piece(&Square::E1),
             Some(Piece::WhiteKing)
@@ -503,7 +1187,7 @@ mod tests {
         assert!(unmake_move(wk_side_castle_mv, &mut state).is_ok());
 
         // white queenside
-        let wq_side_castle_mv = Move::new(Square::E1, Square::C1, None, None, false, false, true);
+        let wq_side_castle_mv = Move::castle_queenside(Color::White);
         assert_eq!(
             state.position.board.piece(&Square::E1),
             Some(Piece::WhiteKing)
@@ -524,7 +1208,7 @@ mod tests {
         assert!(unmake_move(wq_side_castle_mv, &mut state).is_ok());
 
         // black kingside
-        let bk_side_castle_mv = Move::new(Square::E8, Square::C8, None, None, false, false, true);
+        let bk_side_castle_mv = Move::castle_queenside(Color::Black);
         assert_eq!(
             state.position.board.piece(&Square::E8),
             Some(Piece::BlackKing)
@@ -545,7 +1229,7 @@ mod tests {
         assert!(unmake_move(bk_side_castle_mv, &mut state).is_ok());
 
         // black queenside
-        let bq_side_castle_mv = Move::new(Square::E8, Square::G8, None, None, false, false, true);
+        let bq_side_castle_mv = Move::castle_kingside(Color::Black);
         assert_eq!(
             state.position.board.piece(&Square::E8),
             Some(Piece::BlackKing)
@@ -571,16 +1255,8 @@ mod tests {
         let white_ep_fen = "rnbqkbnr/ppp3pp/4p3/3p4/3P1p2/2N5/PPP1PPPP/R1BQKBNR w KQkq - 0 1";
         let mut white_ep_state = GameState::from_fen(white_ep_fen).unwrap();
 
-        let pawn_start_mv = Move::new(Square::E2, Square::E4, None, None, false, true, false);
-        let capture_mv = Move::new(
-            Square::F4,
-            Square::E3,
-            Some(Piece::WhitePawn),
-            None,
-            true,
-            false,
-            false,
-        );
+        let pawn_start_mv = Move::double_push(Square::E2, Square::E4);
+        let capture_mv = Move::en_passant_capture(Square::F4, Square::E3);
 
         // make pawn start move
         assert!(make_move(pawn_start_mv, &mut white_ep_state).is_ok());
@@ -622,24 +1298,8 @@ mod tests {
         let starting_fen = "rnbqkb1r/ppp1pppp/5n2/3p2B1/3P4/2N5/PPP1PPPP/R2QKBNR b KQkq - 0 1";
         let mut state = GameState::from_fen(starting_fen).unwrap();
 
-        let capture_black_knight_f6 = Move::new(
-            Square::G5,
-            Square::F6,
-            Some(Piece::BlackKnight),
-            None,
-            false,
-            false,
-            false,
-        );
-        let capture_white_bishop_f6 = Move::new(
-            Square::E7,
-            Square::F6,
-            Some(Piece::WhiteBishop),
-            None,
-            false,
-            true,
-            false,
-        );
+        let capture_black_knight_f6 = Move::capture(Square::G5, Square::F6, Piece::BlackKnight);
+        let capture_white_bishop_f6 = Move::capture(Square::E7, Square::F6, Piece::WhiteBishop);
 
         // TODO: check on material diffs
         assert!(make_move(capture_black_knight_f6, &mut state).is_ok());
@@ -672,24 +1332,8 @@ mod tests {
         let fen = "8/6P1/8/8/1B1k4/8/1K3n1p/8 b - - 0 1";
         let mut state = GameState::from_fen(fen).unwrap();
 
-        let black_h2_promo = Move::new(
-            Square::H2,
-            Square::H1,
-            None,
-            Some(Piece::BlackQueen),
-            false,
-            false,
-            false,
-        );
-        let white_g7_promo = Move::new(
-            Square::G7,
-            Square::G8,
-            None,
-            Some(Piece::WhiteQueen),
-            false,
-            false,
-            false,
-        );
+        let black_h2_promo = Move::promotion(Square::H2, Square::H1, Piece::BlackQueen, None);
+        let white_g7_promo = Move::promotion(Square::G7, Square::G8, Piece::WhiteQueen, None);
 
         assert!(make_move(black_h2_promo, &mut state).is_ok());
         assert!(make_move(white_g7_promo, &mut state).is_ok());
@@ -699,43 +1343,269 @@ mod tests {
 
     #[test]
     fn test_make_unmake_move_capture_promotion() {
-        let fen = "6b1/5P2/7n/8/8/2K5/7p/4k1R1 w - - 0 1";
+        let fen = "6b1/5P2/7n/8/k7/2K5/7p/6R1 w - - 0 1";
         let mut state = GameState::from_fen(fen).unwrap();
 
-        let white_take_g8_promo = Move::new(
+        let white_take_g8_promo = Move::promotion(
             Square::F7,
             Square::G8,
+            Piece::WhiteQueen,
             Some(Piece::BlackBishop),
-            Some(Piece::WhiteQueen),
-            false,
-            false,
-            false,
         );
-        let black_take_g1_promo = Move::new(
+        let black_take_g1_promo = Move::promotion(
             Square::H2,
             Square::G1,
+            Piece::BlackQueen,
             Some(Piece::WhiteRook),
-            Some(Piece::BlackQueen),
-            false,
-            false,
-            false,
         );
 
+        // a bishop and a rook off, two queens on
+        assert_eq!(state.phase, 4);
         assert!(make_move(white_take_g8_promo, &mut state).is_ok());
+        assert_eq!(state.phase, 7);
         assert!(make_move(black_take_g1_promo, &mut state).is_ok());
+        assert_eq!(state.phase, 9);
         assert!(unmake_move(black_take_g1_promo, &mut state).is_ok());
         assert!(unmake_move(white_take_g8_promo, &mut state).is_ok());
+        assert_eq!(state.phase, 4);
     }
 
     #[test]
     fn test_make_unmake_move_pawn_start() {}
 
+    #[test]
+    fn test_make_move_castling_rights() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let mut state = GameState::from_fen(fen).unwrap();
+
+        let rook_mv = Move::quiet(Square::H1, Square::H5);
+        assert!(make_move(rook_mv, &mut state).is_ok());
+        assert!(!state.position.castling_permissions.white_kingside());
+        assert!(state.position.castling_permissions.white_queenside());
+
+        let rook_takes_rook = Move::capture(Square::A8, Square::A1, Piece::WhiteRook);
+        assert!(make_move(rook_takes_rook, &mut state).is_ok());
+        assert!(!state.position.castling_permissions.white_queenside());
+        assert!(!state.position.castling_permissions.black_queenside());
+        assert!(state.position.castling_permissions.black_kingside());
+
+        assert!(unmake_move(rook_takes_rook, &mut state).is_ok());
+        assert!(unmake_move(rook_mv, &mut state).is_ok());
+        assert_eq!(state, GameState::from_fen(fen).unwrap());
+    }
+
+    fn knight_shuffle() -> [Move; 4] {
+        [
+            Move::quiet(Square::G1, Square::F3),
+            Move::quiet(Square::G8, Square::F6),
+            Move::quiet(Square::F3, Square::G1),
+            Move::quiet(Square::F6, Square::G8),
+        ]
+    }
+
+    #[test]
+    fn test_make_unmake_long_shuffle() {
+        let start = GameState::default();
+        let mut state = start.clone();
+        let moves: Vec<Move> = knight_shuffle()
+            .iter()
+            .copied()
+            .cycle()
+            .take(1000)
+            .collect();
+
+        // 500 moves each, well past where u8 counters used to wrap
+        for mv in &moves {
+            assert!(make_move(*mv, &mut state).is_ok());
+        }
+        assert_eq!(state.game_ply, 1000);
+        assert_eq!(state.halfmove_clock, 1000);
+        assert_eq!(state.halfmove_clock_history.len(), 1000);
+        assert_eq!(state.position.board, start.position.board);
+
+        for mv in moves.iter().rev() {
+            assert!(unmake_move(*mv, &mut state).is_ok());
+        }
+        assert_eq!(state, start);
+    }
+
+    #[test]
+    fn test_make_unmake_random_game() {
+        random_game(Variant::Standard);
+        // captures fill the hands and drops empty them again
+        random_game(Variant::Crazyhouse);
+        // captures blow up whatever's around them
+        random_game(Variant::Atomic);
+        // kings can be taken, and castling's gone
+        random_game(Variant::Antichess);
+    }
+
+    fn random_game(variant: Variant) {
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(0x1a5e7);
+        let start = GameState::default().with_variant(variant);
+        let mut state = start.clone();
+        let mut snapshots = vec![];
+        let mut played = vec![];
+        while played.len() < 500 {
+            let mover = state.position.side_to_move;
+            let mut candidates: Vec<Move> = state.position.legal_moves().into_iter().collect();
+            candidates.shuffle(&mut rng);
+            let before = state.clone();
+            let mv = candidates.into_iter().find(|mv| {
+                let mut s = state.clone();
+                make_move(*mv, &mut s).is_ok() && variant.king_safe(&s.position.board, mover)
+            });
+            match mv {
+                Some(mv) => {
+                    make_move(mv, &mut state).unwrap();
+                    // the incremental key agrees with hashing from scratch
+                    assert_eq!(
+                        state.position_key,
+                        PositionKeyGenerator::global().hash_board(&state),
+                        "after {}",
+                        mv
+                    );
+                    assert_eq!(
+                        (state.pawn_key, state.non_pawn_key),
+                        PositionKeyGenerator::global().hash_sub_keys(&state.position),
+                        "after {}",
+                        mv
+                    );
+                    assert_eq!(state.phase, state.position.game_phase(), "after {}", mv);
+                    snapshots.push(before);
+                    played.push(mv);
+                }
+                None => break,
+            }
+        }
+
+        // every unmake lands back on exactly the state before its move
+        while let Some(mv) = played.pop() {
+            assert!(unmake_move(mv, &mut state).is_ok());
+            assert_eq!(state, snapshots.pop().unwrap(), "unmaking {}", mv);
+        }
+        assert_eq!(state, start);
+    }
+
+    #[test]
+    fn test_sub_keys() {
+        let keys = PositionKeyGenerator::global();
+        // castling, an en passant capture and a capturing promotion
+        let mut state = GameState::from_fen("r3k2r/1P6/8/8/3pP3/8/8/R3K2R b KQkq e3 0 1").unwrap();
+        let start = state.clone();
+        let ep = Move::en_passant_capture(Square::D4, Square::E3);
+        let white_castle = Move::castle_kingside(Color::White);
+        let black_castle = Move::castle_kingside(Color::Black);
+        let promo = Move::promotion(
+            Square::B7,
+            Square::A8,
+            Piece::WhiteQueen,
+            Some(Piece::BlackRook),
+        );
+        let moves = [ep, white_castle, black_castle, promo];
+        for mv in &moves {
+            let pawn_key = state.pawn_key;
+            assert!(make_move(*mv, &mut state).is_ok());
+            assert_eq!(
+                (state.pawn_key, state.non_pawn_key),
+                keys.hash_sub_keys(&state.position)
+            );
+            // only the king and rook moved when castling
+            if mv.castle() {
+                assert_eq!(state.pawn_key, pawn_key);
+            }
+        }
+        for mv in moves.iter().rev() {
+            assert!(unmake_move(*mv, &mut state).is_ok());
+        }
+        assert_eq!(state, start);
+    }
+
+    #[test]
+    fn test_make_move_history_full() {
+        let mut state = GameState::default();
+        for mv in knight_shuffle().iter().cycle().take(MAX_GAME_PLY) {
+            assert!(make_move(*mv, &mut state).is_ok());
+        }
+        let before = state.clone();
+        let mv = knight_shuffle()[0];
+        let err = make_move(mv, &mut state).unwrap_err();
+        assert_eq!(err.kind(), &MoveErrorType::HistoryFull(MAX_GAME_PLY));
+        assert_eq!(state, before);
+
+        // unmaking frees room again
+        assert!(unmake_move(knight_shuffle()[3], &mut state).is_ok());
+        assert!(make_move(knight_shuffle()[3], &mut state).is_ok());
+    }
+
+    #[test]
+    fn test_make_null_move() {
+        let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3";
+        let mut state = GameState::from_fen(fen).unwrap();
+        make_null_move(&mut state).unwrap();
+        assert_eq!(state.position.side_to_move, Color::White);
+        assert_eq!(state.position.en_passant, None);
+        assert_eq!(
+            state.position_key,
+            PositionKeyGenerator::global().hash_board(&state)
+        );
+        assert!(!state.is_repetition());
+
+        // passing back gives the same pieces, but no en passant any more
+        make_null_move(&mut state).unwrap();
+        assert_eq!(
+            state.position.board,
+            GameState::from_fen(fen).unwrap().position.board
+        );
+        assert_eq!(state.key_history.len(), 2);
+    }
+
+    #[test]
+    fn test_unmake_move_empty_history() {
+        let mut state = GameState::default();
+        let mv = Move::quiet(Square::A2, Square::A3);
+        let err = unmake_move(mv, &mut state).unwrap_err();
+        assert!(matches!(err.kind(), MoveErrorType::InsufficientHistory(_)));
+        assert_eq!(state, GameState::default());
+    }
+
     #[test]
     fn test_move_list_push() {
         let mut l = MoveList::empty();
-        assert_eq!(l.count, 0);
+        assert!(l.is_empty());
         l.push(Move::empty());
         l.push(Move::empty());
-        assert_eq!(l.count, 2);
+        assert_eq!(l.len(), 2);
+        assert_eq!(l.as_slice(), &[Move::empty(); 2]);
+    }
+
+    #[test]
+    fn test_move_list_outgrows_inline_space() {
+        let mut l = MoveList::empty();
+        for _ in 0..MOVE_LIST_SIZE {
+            l.push(Move::empty());
+        }
+        let mv = Move::double_push(Square::E2, Square::E4);
+        l.push(mv);
+        assert_eq!(l.len(), MOVE_LIST_SIZE + 1);
+        assert_eq!(l.iter().last(), Some(&mv));
+        assert_eq!(l.into_iter().len(), MOVE_LIST_SIZE + 1);
+    }
+
+    #[test]
+    fn test_move_list_iteration_does_not_consume() {
+        let a = Move::quiet(Square::G1, Square::F3);
+        let b = Move::quiet(Square::B1, Square::C3);
+        let l = MoveList::new(vec![a, b]);
+        assert_eq!(l.iter().count(), 2);
+        assert_eq!(l.iter().count(), 2);
+        // only the live moves are sorted, with no placeholders mixed in
+        let sorted = l.sorted();
+        assert_eq!(sorted.len(), 2);
+        assert!(sorted.iter().all(|mv| !mv.is_placeholder()));
+        assert!(sorted.as_slice().windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(l.into_iter().collect::<Vec<_>>(), vec![a, b]);
     }
 }
@@ -0,0 +1,211 @@
+use alloc::{format, string::ToString, vec::Vec};
+use core::convert::TryFrom;
+
+use crate::play::{
+    error::SanError,
+    position::Position,
+    types::{File, Piece, PieceType, Rank, Square},
+};
+
+use super::Move;
+
+impl Move {
+    /// Reads a move in standard algebraic notation, e.g. `Nf3`, `exd5`,
+    /// `O-O-O`, `e8=N+` or the drop `N@f3`, played from `position`. The
+    /// file or rank given to tell pieces apart is matched against the legal
+    /// moves, and check, capture and annotation marks are taken on trust.
+    pub fn from_san(position: &Position, san: &str) -> Result<Move, SanError> {
+        let malformed = || SanError::Malformed(san.to_string());
+        let body = san.trim_end_matches(['+', '#', '!', '?']);
+
+        let castle_file = match body {
+            "O-O" | "0-0" => Some(File::G),
+            "O-O-O" | "0-0-0" => Some(File::C),
+            _ => None,
+        };
+        // a drop is written as UCI has it, the pawn's letter left out
+        let drop = body.split_once('@').map(|(piece, to)| match piece {
+            "" => format!("P@{}", to),
+            piece => format!("{}@{}", piece, to),
+        });
+        let candidates: Vec<Move> = match (castle_file, drop) {
+            (Some(file), _) => position
+                .legal_moves()
+                .into_iter()
+                .filter(|mv| mv.castle() && mv.to_sq().file() == file)
+                .collect(),
+            (None, Some(uci)) => position
+                .legal_moves()
+                .into_iter()
+                .filter(|mv| mv.is_drop() && mv.to_uci() == uci)
+                .collect(),
+            (None, None) => {
+                let target = SanTarget::parse(body).ok_or_else(malformed)?;
+                position
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|mv| target.matches(position, *mv))
+                    .collect()
+            }
+        };
+        match candidates.as_slice() {
+            [mv] => Ok(*mv),
+            [] => Err(SanError::Illegal(san.to_string())),
+            _ => Err(SanError::Ambiguous(san.to_string())),
+        }
+    }
+}
+
+/// What a non-castling SAN move says about the move it stands for.
+struct SanTarget {
+    piece_type: PieceType,
+    from_file: Option<File>,
+    from_rank: Option<Rank>,
+    to: Square,
+    promoted: Option<PieceType>,
+}
+
+impl SanTarget {
+    fn parse(body: &str) -> Option<SanTarget> {
+        let mut chars: Vec<char> = body.chars().filter(|c| *c != 'x').collect();
+
+        // a promotion piece, with or without the `=` before it
+        let promoted = match chars.last() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let piece_type = piece_type(*c)?;
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(piece_type)
+            }
+            _ => None,
+        };
+
+        let piece_type = match chars.first() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let piece_type = piece_type(*c)?;
+                chars.remove(0);
+                piece_type
+            }
+            _ => PieceType::Pawn,
+        };
+
+        if chars.len() < 2 || chars.len() > 4 {
+            return None;
+        }
+        let (from, to) = chars.split_at(chars.len() - 2);
+        let to = Square::new(File::try_from(to[0]).ok()?, Rank::try_from(to[1]).ok()?);
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in from {
+            match (File::try_from(*c), Rank::try_from(*c)) {
+                (Ok(file), _) if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(file)
+                }
+                (_, Ok(rank)) if from_rank.is_none() => from_rank = Some(rank),
+                _ => return None,
+            }
+        }
+        Some(SanTarget {
+            piece_type,
+            from_file,
+            from_rank,
+            to,
+            promoted,
+        })
+    }
+
+    fn matches(&self, position: &Position, mv: Move) -> bool {
+        mv.to_sq() == self.to
+            && !mv.castle()
+            && position.board.piece(&mv.from_sq()).map(|p| p.piece_type()) == Some(self.piece_type)
+            && self
+                .from_file
+                .is_none_or(|file| mv.from_sq().file() == file)
+            && self
+                .from_rank
+                .is_none_or(|rank| mv.from_sq().rank() == rank)
+            && mv.promoted().map(|p| p.piece_type()) == self.promoted
+    }
+}
+
+/// The piece type an uppercase SAN letter names, pawns having none.
+fn piece_type(c: char) -> Option<PieceType> {
+    match Piece::try_from(c).ok()?.piece_type() {
+        PieceType::Pawn => None,
+        piece_type => Some(piece_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn uci(fen: &str, san: &str) -> Result<String, SanError> {
+        let position = Position::from_fen(fen).unwrap();
+        Move::from_san(&position, san).map(|mv| mv.to_uci())
+    }
+
+    #[test]
+    fn test_from_san() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        assert_eq!(uci(start, "Nf3").unwrap(), "g1f3");
+        assert_eq!(uci(start, "e4").unwrap(), "e2e4");
+        assert_eq!(uci(start, "Ng1f3!?").unwrap(), "g1f3");
+
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq -";
+        assert_eq!(uci(fen, "exd5").unwrap(), "e4d5");
+        assert_eq!(uci(fen, "ed5").unwrap(), "e4d5");
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq -";
+        assert_eq!(uci(fen, "O-O-O").unwrap(), "e8c8");
+        assert_eq!(uci(fen, "0-0").unwrap(), "e8g8");
+
+        let fen = "4k3/P7/8/8/8/8/8/4K3 w - -";
+        assert_eq!(uci(fen, "a8=Q+").unwrap(), "a7a8q");
+        assert_eq!(uci(fen, "a8Q").unwrap(), "a7a8q");
+        assert_eq!(uci(fen, "a8=R").unwrap(), "a7a8r");
+
+        // an underpromotion to a checking knight
+        let fen = "3r4/4P1k1/8/8/8/8/8/4K3 w - -";
+        assert_eq!(uci(fen, "e8=N+").unwrap(), "e7e8n");
+        assert_eq!(uci(fen, "exd8=B").unwrap(), "e7d8b");
+        let position = Position::from_fen(fen).unwrap();
+        let knight = Move::from_san(&position, "e8=N+").unwrap();
+        assert_eq!(knight.to_san(&position), "e8=N+");
+    }
+
+    #[test]
+    fn test_from_san_disambiguation() {
+        // knights on b1 and f3 can both reach d2, rooks on a1 and a5 a3
+        let fen = "4k3/8/8/R7/8/5N2/8/RN2K3 w - -";
+        assert_eq!(uci(fen, "Nd2"), Err(SanError::Ambiguous("Nd2".to_string())));
+        assert_eq!(uci(fen, "Nbd2").unwrap(), "b1d2");
+        assert_eq!(uci(fen, "Nf3d2").unwrap(), "f3d2");
+        assert_eq!(uci(fen, "Ra3"), Err(SanError::Ambiguous("Ra3".to_string())));
+        assert_eq!(uci(fen, "R5a3").unwrap(), "a5a3");
+        assert_eq!(uci(fen, "Rb3"), Err(SanError::Illegal("Rb3".to_string())));
+    }
+
+    #[test]
+    fn test_from_san_errors() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        assert_eq!(uci(start, "e5"), Err(SanError::Illegal("e5".to_string())));
+        assert_eq!(uci(start, "O-O"), Err(SanError::Illegal("O-O".to_string())));
+        for san in ["", "Zf3", "e9", "Nf", "N1gf3", "e2e4e5"] {
+            assert_eq!(uci(start, san), Err(SanError::Malformed(san.to_string())));
+        }
+    }
+
+    #[test]
+    fn test_from_san_round_trips_to_san() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+        let position = Position::from_fen(fen).unwrap();
+        for mv in position.legal_moves() {
+            let san = mv.to_san(&position);
+            assert_eq!(Move::from_san(&position, &san), Ok(mv), "{}", san);
+        }
+    }
+}
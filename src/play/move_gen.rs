@@ -1,234 +1,314 @@
 use super::{
-    board::Board,
-    constants::DIRECTIONS,
+    board::bitboard::Bitboard,
+    constants::{DIRECTIONS, PROMOTION_TYPES, SQUARES},
     position::Position,
     r#move::{Move, MoveList},
-    types::{Color, Piece, PieceType, Square},
-    utils,
+    types::{Color, File, Piece, PieceType, Rank, Square},
 };
 
-fn move_alleviates_check(board: &Board, side_to_move: &Color, m: &Move) -> bool {
-    let mut future_board = board.clone();
-    if future_board.sq_taken_by_color(m.to_sq(), side_to_move.opposing()) {
-        future_board.remove_piece(m.to_sq()).unwrap();
+/// Somewhere for generated moves to go, so a caller that only wants to
+/// know how many there are needn't keep them.
+pub trait MoveSink {
+    fn push(&mut self, mv: Move);
+
+    /// Whether the sink has all it wants, so generation can stop early.
+    fn is_full(&self) -> bool {
+        false
     }
-    future_board.move_piece(m.from_sq(), m.to_sq()).unwrap();
-    !future_board.is_king_in_check(*side_to_move)
 }
 
-// TODO: re-merge this with generate moves, caller doesn't care what piece it is
-pub fn generate_pawn_moves(position: &Position, sq: Square, moves: &mut MoveList) {
-    let promo_piece = Piece::of(PieceType::Queen, position.side_to_move);
-    let ep_captured = Piece::of(PieceType::Pawn, position.side_to_move.opposing());
-    let fwd_mailbox_no = sq + position.side_to_move.pawn_push_dir() as i8;
+impl MoveSink for MoveList {
+    fn push(&mut self, mv: Move) {
+        MoveList::push(self, mv)
+    }
+}
 
-    let is_check = position.board.is_king_in_check(position.side_to_move);
+/// Counts moves instead of keeping them, up to `limit`.
+pub struct MoveCounter {
+    pub count: usize,
+    limit: usize,
+}
 
-    if position.board.is_square_pinned(&sq) & !is_check {
-        return;
+impl MoveCounter {
+    pub fn new(limit: usize) -> Self {
+        MoveCounter { count: 0, limit }
     }
+}
 
-    // pawn start
-    if sq.rank() == position.side_to_move.pawn_start_rank() {
-        let sq_2_in_front =
-            Square::from_mailbox_no(sq + 2 * position.side_to_move.pawn_push_dir() as i8);
-
-        if (!position
-            .board
-            .sq_taken(Square::from_mailbox_no(fwd_mailbox_no)))
-            & (!position.board.sq_taken(sq_2_in_front))
-        {
-            let move_to_make = Move::new(sq, sq_2_in_front, None, None, false, true, false);
-            if is_check {
-                if move_alleviates_check(&position.board, &position.side_to_move, &move_to_make) {
-                    moves.push(move_to_make);
-                }
-            } else {
-                moves.push(move_to_make);
+impl MoveSink for MoveCounter {
+    fn push(&mut self, _mv: Move) {
+        self.count += 1;
+    }
+
+    fn is_full(&self) -> bool {
+        self.count >= self.limit
+    }
+}
+
+/// Passes on to `sink` those candidate moves that keep the mover's own king
+/// safe: a pinned piece may only move along the pin ray, in check every move
+/// has to get out of it, and en passant is played out to be sure. Atomic
+/// captures blow up pieces around them, pins and checks included, so there
+/// every move is played out.
+struct LegalFilter<'a, S: MoveSink> {
+    position: &'a Position,
+    in_check: bool,
+    explodes: bool,
+    sink: &'a mut S,
+}
+
+impl<'a, S: MoveSink> LegalFilter<'a, S> {
+    fn new(position: &'a Position, sink: &'a mut S) -> Self {
+        LegalFilter {
+            position,
+            in_check: position.in_check(),
+            explodes: position.variant.explodes(),
+            sink,
+        }
+    }
+
+    fn push(&mut self, mv: Move) {
+        if self.explodes {
+            if self.position.is_legal(mv) {
+                self.sink.push(mv);
             }
+            return;
         }
+        if let Some(ray) = self.position.pin_ray(mv.from_sq()) {
+            if (ray & mv.to_sq().into()).0 == 0x0 {
+                return;
+            }
+        }
+        // an en passant capture takes two pawns off the board at once, which
+        // can open the king's rank to a rook or queen beyond them
+        if (self.in_check || mv.en_passant()) && !self.position.is_legal(mv) {
+            return;
+        }
+        self.sink.push(mv);
     }
+}
 
-    // normal forward & promotion
-    if fwd_mailbox_no >= 0 {
-        let fwd_sq = Square::from_mailbox_no(fwd_mailbox_no);
-        let promoted = if sq.rank() == position.side_to_move.pawn_promo_rank() {
-            Some(promo_piece)
-        } else {
-            None
-        };
+/// `bb` moved `by` squares up the board (down when negative).
+fn shift(bb: u64, by: i8) -> u64 {
+    if by >= 0 {
+        bb << by
+    } else {
+        bb >> -by
+    }
+}
 
-        let move_to_make = Move::new(sq, fwd_sq, None, promoted, false, false, false);
+/// A move from `from` to `to` taking whatever stands there, if anything.
+fn step(from: Square, to: Square, captured: Option<Piece>) -> Move {
+    match captured {
+        Some(captured) => Move::capture(from, to, captured),
+        None => Move::quiet(from, to),
+    }
+}
 
-        if !position.board.sq_taken(fwd_sq) {
-            if is_check {
-                if move_alleviates_check(&position.board, &position.side_to_move, &move_to_make) {
-                    moves.push(move_to_make);
-                }
-            } else {
-                moves.push(move_to_make);
+/// The square `by` squares up the board from `sq`.
+fn offset(sq: Square, by: i8) -> Square {
+    SQUARES[(sq as i8 + by) as usize]
+}
+
+/// Pushes the legal moves of the side to move's pawns on `pawns`, all of
+/// them at once: each kind of pawn move is a shift of the whole set, masked
+/// by the squares it can land on.
+pub fn generate_pawn_set_moves<S: MoveSink>(position: &Position, pawns: Bitboard, moves: &mut S) {
+    let color = position.side_to_move;
+    let board = &position.board;
+    let pawns = (pawns & board.bitboard(Piece::of(PieceType::Pawn, color))).0;
+    let empty = board.occupied().flip().0;
+    let enemies = board.occupied_by(color.opposing()).0;
+    let promo_rank = Bitboard::from(color.pawn_promo_rank()).0;
+    // a pawn reaching the last rank turns into each piece it can be
+    let push_pawn_move = |candidates: &mut LegalFilter<S>, from: Square, to, captured| {
+        if promo_rank & Bitboard::from(from).0 == 0x0 {
+            candidates.push(step(from, to, captured));
+            return;
+        }
+        for piece_type in PROMOTION_TYPES {
+            candidates.push(Move::promotion(
+                from,
+                to,
+                Piece::of(piece_type, color),
+                captured,
+            ));
+        }
+    };
+
+    let push: i8 = match color {
+        Color::White => 8,
+        Color::Black => -8,
+    };
+    let single = shift(pawns, push) & empty;
+    let start_rank = Bitboard::from(color.pawn_start_rank()).0;
+    let double = shift(shift(pawns & start_rank, push) & empty, push) & empty;
+    // captures towards the a-file can't start on it, nor those towards the h-file on that
+    let not_a_file = Bitboard::from(File::A).flip().0;
+    let not_h_file = Bitboard::from(File::H).flip().0;
+    let captures = [
+        (shift(pawns & not_a_file, push - 1), push - 1),
+        (shift(pawns & not_h_file, push + 1), push + 1),
+    ];
+
+    let mut candidates = LegalFilter::new(position, moves);
+    for to in Bitboard(single) {
+        push_pawn_move(&mut candidates, offset(to, -push), to, None);
+    }
+    for to in Bitboard(double) {
+        let from = offset(to, -2 * push);
+        candidates.push(Move::double_push(from, to));
+    }
+    for (targets, by) in captures {
+        for to in Bitboard(targets & enemies) {
+            push_pawn_move(&mut candidates, offset(to, -by), to, board.piece(&to));
+        }
+        if let Some(ep_sq) = position.en_passant {
+            if targets & Bitboard::from(ep_sq).0 != 0x0 {
+                let from = offset(ep_sq, -by);
+                candidates.push(Move::en_passant_capture(from, ep_sq));
             }
         }
     }
+}
 
-    for dir in position.side_to_move.pawn_diagonals() {
-        let diag_mailbox_no = sq + *dir as i8;
-        if diag_mailbox_no >= 0 {
-            let diag_sq = Square::from_mailbox_no(diag_mailbox_no);
-            if position
-                .board
-                .sq_taken_by_color(diag_sq, position.side_to_move.opposing())
-            {
-                let captured = position.board.piece(&diag_sq);
-                let promoted = if sq.rank() == position.side_to_move.pawn_promo_rank() {
-                    Some(promo_piece)
-                } else {
-                    None
-                };
-                let move_to_make = Move::new(sq, diag_sq, captured, promoted, false, false, false);
-                if is_check {
-                    if move_alleviates_check(&position.board, &position.side_to_move, &move_to_make)
-                    {
-                        moves.push(move_to_make);
-                    }
-                } else {
-                    moves.push(move_to_make);
-                }
-            } else {
-                // TODO (tcd 9/2/24): check allevation
-                if let Some(ep_sq) = position.en_passant {
-                    if diag_sq == ep_sq {
-                        moves.push(Move::new(
-                            sq,
-                            diag_sq,
-                            Some(ep_captured),
-                            None,
-                            true,
-                            false,
-                            false,
-                        ))
-                    }
-                }
-            }
+/// The legal moves of the side to move's pawn on `sq`.
+pub fn generate_pawn_moves(position: &Position, sq: Square, moves: &mut MoveList) {
+    generate_pawn_set_moves(position, sq.into(), moves);
+}
+
+/// The forced capture stage, for variants where a side that can capture
+/// has to: passes on to `sink` only the captures among `moves` if there
+/// are any, else every move.
+pub fn forced_captures<S: MoveSink>(moves: MoveList, sink: &mut S) {
+    let must_capture = moves.iter().any(|mv| mv.captured().is_some());
+    for mv in moves {
+        if sink.is_full() {
+            return;
+        }
+        if !must_capture || mv.captured().is_some() {
+            sink.push(mv);
         }
     }
 }
 
 // TODO: figure out how this can return MoveList as opposed to mutating it
-pub fn generate_moves(position: &Position, piece: Piece, sq: Square, moves: &mut MoveList) {
-    // TODO: use refactoring to consider if the piece is pinned (i.e. can't move)
-    // OR if the king is currently in check (then it can ONLY move if king is not in check)
-
-    if position.board.is_square_pinned(&sq) {
+/// Pushes every legal drop of the side to move's pieces in hand onto an
+/// empty square, pawns only onto the second to seventh ranks. In check
+/// the only drops left are those that block it.
+pub fn generate_drops<S: MoveSink>(position: &Position, moves: &mut S) {
+    let color = position.side_to_move;
+    let hand = position.hands[color as usize];
+    if hand.is_empty() {
         return;
     }
+    let empty = position.board.occupied().flip();
+    let mut candidates = LegalFilter::new(position, moves);
+    for piece_type in hand.piece_types() {
+        let piece = Piece::of(piece_type, color);
+        for sq in empty {
+            if piece_type == PieceType::Pawn && matches!(sq.rank(), Rank::Rank1 | Rank::Rank8) {
+                continue;
+            }
+            candidates.push(Move::drop(piece, sq));
+        }
+    }
+}
+
+pub fn generate_moves<S: MoveSink>(position: &Position, piece: Piece, sq: Square, moves: &mut S) {
+    let mut candidates = LegalFilter::new(position, moves);
 
     if piece.can_slide() {
-        let dirs = &DIRECTIONS[piece.attack_direction_idx()].to_vec();
-        let color = piece.color();
-        utils::recur_move_search(&position.board, color, dirs, moves, sq, 1);
+        let board = &position.board;
+        let targets =
+            board.slider_attacks(piece.piece_type(), sq) & board.occupied_by(piece.color()).flip();
+        for other_sq in targets {
+            let captured = board.piece(&other_sq);
+            candidates.push(step(sq, other_sq, captured));
+        }
     }
 
     if piece.piece_type() == PieceType::Knight {
         let dirs = &DIRECTIONS[piece.attack_direction_idx()];
-        for dir in dirs {
-            let target_sq_mailbox_no = sq + *dir as i8;
-            if target_sq_mailbox_no >= 0 {
-                let other_sq = Square::from_mailbox_no(target_sq_mailbox_no);
-                if !position.board.sq_taken_by_color(other_sq, piece.color()) {
-                    let captured = position.board.piece(&other_sq);
-                    moves.push(Move::new(sq, other_sq, captured, None, false, false, false));
-                }
+        for other_sq in dirs.iter().filter_map(|dir| sq.offset(*dir)) {
+            if !position.board.sq_taken_by_color(other_sq, piece.color()) {
+                let captured = position.board.piece(&other_sq);
+                candidates.push(step(sq, other_sq, captured));
             }
         }
     }
 
     if piece.piece_type() == PieceType::King {
+        // attacks are worked out without the king, which would otherwise
+        // shield the squares behind it from a slider checking it
+        let mut kingless = position.board;
+        let _ = kingless.remove_piece(sq);
+        let danger = kingless.attack_map(piece.opposing_color());
+        // an atomic king can't capture, but may step next to the enemy
+        // king whatever attacks the square, which the filter plays out; a
+        // king that isn't royal goes wherever it likes
+        let explodes = position.variant.explodes();
+        let royal = position.variant.royal_king();
         let dirs = &DIRECTIONS[piece.attack_direction_idx()];
-        for dir in dirs {
-            let target_sq_mailbox_no = sq + *dir as i8;
-            if target_sq_mailbox_no >= 0 {
-                let other_sq = Square::from_mailbox_no(target_sq_mailbox_no);
-                if !position.board.sq_taken_by_color(other_sq, piece.color())
-                    & !position
-                        .board
-                        .is_square_attacked(other_sq, piece.opposing_color())
-                {
-                    let captured = position.board.piece(&other_sq);
-                    moves.push(Move::new(sq, other_sq, captured, None, false, false, false));
-                }
+        for other_sq in dirs.iter().filter_map(|dir| sq.offset(*dir)) {
+            let other_sq_bb: Bitboard = other_sq.into();
+            let safe = if explodes {
+                !position.board.sq_taken(other_sq)
+            } else if !royal {
+                !position.board.sq_taken_by_color(other_sq, piece.color())
+            } else {
+                !position.board.sq_taken_by_color(other_sq, piece.color())
+                    & ((danger & other_sq_bb).0 == 0x0)
+            };
+            if safe {
+                let captured = position.board.piece(&other_sq);
+                candidates.push(step(sq, other_sq, captured));
             }
         }
 
+        // the king can't castle out of check, nor through or into it; only
+        // the rook passes over the B-file square, which must just be empty
+        let in_check = (danger & Bitboard::from(sq)).0 != 0x0;
         match piece.color() {
-            Color::White => {
+            Color::White if !in_check => {
                 if position.castling_permissions.white_kingside()
                     & !position.board.sq_taken(Square::F1)
                     & !position.board.sq_taken(Square::G1)
                     & !position.board.is_square_attacked(Square::F1, Color::Black)
                     & !position.board.is_square_attacked(Square::G1, Color::Black)
                 {
-                    moves.push(Move::new(
-                        Square::E1,
-                        Square::G1,
-                        None,
-                        None,
-                        false,
-                        false,
-                        true,
-                    ));
+                    candidates.push(Move::castle_kingside(Color::White));
                 }
                 if position.castling_permissions.white_queenside()
+                    & !position.board.sq_taken(Square::B1)
                     & !position.board.sq_taken(Square::C1)
                     & !position.board.sq_taken(Square::D1)
                     & !position.board.is_square_attacked(Square::C1, Color::Black)
                     & !position.board.is_square_attacked(Square::D1, Color::Black)
                 {
-                    moves.push(Move::new(
-                        Square::E1,
-                        Square::C1,
-                        None,
-                        None,
-                        false,
-                        false,
-                        true,
-                    ));
+                    candidates.push(Move::castle_queenside(Color::White));
                 }
             }
-            Color::Black => {
+            Color::Black if !in_check => {
                 if position.castling_permissions.black_kingside()
                     & !position.board.sq_taken(Square::F8)
                     & !position.board.sq_taken(Square::G8)
                     & !position.board.is_square_attacked(Square::F8, Color::White)
                     & !position.board.is_square_attacked(Square::G8, Color::White)
                 {
-                    moves.push(Move::new(
-                        Square::E8,
-                        Square::G8,
-                        None,
-                        None,
-                        false,
-                        false,
-                        true,
-                    ));
+                    candidates.push(Move::castle_kingside(Color::Black));
                 }
                 if position.castling_permissions.black_queenside()
+                    & !position.board.sq_taken(Square::B8)
                     & !position.board.sq_taken(Square::C8)
                     & !position.board.sq_taken(Square::D8)
                     & !position.board.is_square_attacked(Square::C8, Color::White)
                     & !position.board.is_square_attacked(Square::D8, Color::White)
                 {
-                    moves.push(Move::new(
-                        Square::E8,
-                        Square::C8,
-                        None,
-                        None,
-                        false,
-                        false,
-                        true,
-                    ));
+                    candidates.push(Move::castle_queenside(Color::Black));
                 }
             }
+            _ => {}
         }
     }
 }
@@ -245,14 +325,11 @@ mod tests {
 
         generate_pawn_moves(&white_pos, Square::A2, white_moves);
         let white_expected = vec![
-            Move::new(Square::A2, Square::A3, None, None, false, false, false),
-            Move::new(Square::A2, Square::A4, None, None, false, true, false),
+            Move::quiet(Square::A2, Square::A3),
+            Move::double_push(Square::A2, Square::A4),
         ];
         assert_eq!(
-            white_moves
-                .sorted()
-                .filter(|mv| !mv.is_placeholder())
-                .collect::<Vec<Move>>(),
+            white_moves.sorted().into_iter().collect::<Vec<Move>>(),
             white_expected
         );
 
@@ -263,14 +340,11 @@ mod tests {
 
         generate_pawn_moves(&black_pos, Square::E7, black_moves);
         let black_expected = vec![
-            Move::new(Square::E7, Square::E6, None, None, false, false, false),
-            Move::new(Square::E7, Square::E5, None, None, false, true, false),
+            Move::quiet(Square::E7, Square::E6),
+            Move::double_push(Square::E7, Square::E5),
         ];
         assert_eq!(
-            black_moves
-                .sorted()
-                .filter(|mv| !mv.is_placeholder())
-                .collect::<Vec<Move>>(),
+            black_moves.sorted().into_iter().collect::<Vec<Move>>(),
             black_expected
         );
     }
@@ -283,7 +357,7 @@ mod tests {
         let a2_moves = &mut MoveList::empty();
 
         generate_pawn_moves(&a2_blocked_pos, Square::A2, a2_moves);
-        assert_eq!(a2_moves.count(), 0);
+        assert_eq!(a2_moves.len(), 0);
 
         // forward move with blocker
         let d5_blocked_fen = "rnbqk1nr/ppp2ppp/4p3/3pP2Q/3P4/b1N2P2/PPP3PP/R1B1KBNR b KQkq -";
@@ -291,7 +365,7 @@ mod tests {
         let d5_moves = &mut MoveList::empty();
 
         generate_pawn_moves(&d5_blocked_pos, Square::A2, d5_moves);
-        assert_eq!(d5_moves.count(), 0);
+        assert_eq!(d5_moves.len(), 0);
     }
 
     #[test]
@@ -302,7 +376,7 @@ mod tests {
         let f7_moves = &mut MoveList::empty();
 
         generate_pawn_moves(&f7_pinned_pos, Square::F7, f7_moves);
-        assert_eq!(f7_moves.count(), 0);
+        assert_eq!(f7_moves.len(), 0);
 
         // forward move respect pin
         let c3_pinned_fen = "rnbqk1nr/ppp2ppp/4p3/b2NP2Q/3P4/2P2P2/PP4PP/R1B1KBNR w KQkq -";
@@ -310,7 +384,7 @@ mod tests {
         let c3_moves = &mut MoveList::empty();
 
         generate_pawn_moves(&c3_pinned_pos, Square::C3, c3_moves);
-        assert_eq!(c3_moves.count(), 0);
+        assert_eq!(c3_moves.len(), 0);
     }
 
     #[test]
@@ -324,8 +398,8 @@ mod tests {
         generate_pawn_moves(&pos, Square::B7, b7_moves);
         generate_pawn_moves(&pos, Square::C7, c7_moves);
 
-        assert_eq!(c7_moves.count(), 1);
-        assert_eq!(b7_moves.count(), 1);
+        assert_eq!(c7_moves.len(), 1);
+        assert_eq!(b7_moves.len(), 1);
 
         // TODO: add diagonal test cases
     }
@@ -338,7 +412,8 @@ mod tests {
         let ok_moves = &mut MoveList::empty();
         generate_pawn_moves(&ok_pos, Square::F2, ok_moves);
 
-        assert_eq!(ok_moves.count(), 1);
+        // promoting on f1 blocks, to whichever piece
+        assert_eq!(ok_moves.len(), 4);
 
         let not_ok_fen = "8/8/8/8/8/2K5/5p2/R2k4 b - -";
         let not_ok_pos = Position::from_fen(not_ok_fen).unwrap();
@@ -346,7 +421,7 @@ mod tests {
         let not_ok_moves = &mut MoveList::empty();
         generate_pawn_moves(&not_ok_pos, Square::F2, not_ok_moves);
 
-        assert_eq!(not_ok_moves.count(), 0);
+        assert_eq!(not_ok_moves.len(), 0);
     }
 
     #[test]
@@ -361,21 +436,164 @@ mod tests {
         generate_pawn_moves(&se_pos, Square::F2, se_moves);
         generate_pawn_moves(&sw_pos, Square::F2, sw_moves);
 
-        assert_eq!(se_moves.count(), 0);
-        assert_eq!(sw_moves.count(), 0);
+        assert_eq!(se_moves.len(), 0);
+        assert_eq!(sw_moves.len(), 0);
+    }
+
+    fn targets(position: &Position, piece: Piece, sq: Square) -> Vec<Square> {
+        let mut moves = MoveList::empty();
+        if piece.piece_type() == PieceType::Pawn {
+            generate_pawn_moves(position, sq, &mut moves);
+        } else {
+            generate_moves(position, piece, sq, &mut moves);
+        }
+        let mut targets: Vec<Square> = moves.iter().map(|mv| mv.to_sq()).collect();
+        targets.sort();
+        targets
+    }
+
+    #[test]
+    fn test_pinned_pieces_move_along_pin_ray() {
+        // the rook is pinned along the file, so it can only slide up to and
+        // take the pinning rook
+        let fen = "4r1k1/8/8/8/8/8/4R3/4K3 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        let mut expected = vec![
+            Square::E3,
+            Square::E4,
+            Square::E5,
+            Square::E6,
+            Square::E7,
+            Square::E8,
+        ];
+        expected.sort();
+        assert_eq!(targets(&pos, Piece::WhiteRook, Square::E2), expected);
+
+        // pinned along a diagonal, a rook can't move at all, but a bishop can
+        let fen = "6k1/8/8/7b/8/8/4R3/3K4 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        assert!(targets(&pos, Piece::WhiteRook, Square::E2).is_empty());
+        let fen = "6k1/8/8/7b/8/8/4B3/3K4 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        let mut expected = vec![Square::F3, Square::G4, Square::H5];
+        expected.sort();
+        assert_eq!(targets(&pos, Piece::WhiteBishop, Square::E2), expected);
+
+        // and a pawn pinned on a diagonal can still take its pinner
+        let fen = "6k1/8/8/8/8/5b2/4P3/3K4 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        assert_eq!(
+            targets(&pos, Piece::WhitePawn, Square::E2),
+            vec![Square::F3]
+        );
+    }
+
+    #[test]
+    fn test_pieces_get_out_of_check() {
+        // in check, the knight can only block or take the checker
+        let fen = "4r1k1/8/8/8/8/8/8/2N1K3 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        assert_eq!(
+            targets(&pos, Piece::WhiteKnight, Square::C1),
+            vec![Square::E2]
+        );
+
+        // and the king has to get off the checking file
+        let mut expected = vec![Square::D1, Square::D2, Square::F1, Square::F2];
+        expected.sort();
+        assert_eq!(targets(&pos, Piece::WhiteKing, Square::E1), expected);
+    }
+
+    #[test]
+    fn test_pawn_set_moves() {
+        // pawns on the edge files can't capture round onto the other edge
+        let fen = "4k3/8/n7/7P/P7/7n/8/4K3 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        let mut moves = MoveList::empty();
+        generate_pawn_set_moves(&pos, Bitboard::universe(), &mut moves);
+        let mut uci: Vec<String> = moves.iter().map(|mv| mv.to_uci()).collect();
+        uci.sort();
+        assert_eq!(uci, vec!["a4a5", "h5h6"]);
+
+        // a capture and a push onto the last rank both promote, to any of
+        // four pieces, and a black
+        // pawn on its start rank can go two
+        let fen = "1r2k3/P6p/8/8/8/8/8/4K3 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        let promotions: Vec<Move> = pos
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.promoted().is_some())
+            .collect();
+        assert_eq!(promotions.len(), 8);
+        let pos = Position::from_fen(fen.replace(" w ", " b ")).unwrap();
+        let mut moves = MoveList::empty();
+        generate_pawn_set_moves(&pos, Bitboard::universe(), &mut moves);
+        assert_eq!(moves.iter().filter(|mv| mv.pawn_start()).count(), 1);
+    }
+
+    #[test]
+    fn test_castling_legality() {
+        // both ways are open
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq -";
+        let pos = Position::from_fen(fen).unwrap();
+        let king = targets(&pos, Piece::WhiteKing, Square::E1);
+        assert!(king.contains(&Square::C1) && king.contains(&Square::G1));
+
+        // but not out of check
+        let fen = "r3k2r/8/8/8/8/8/4r3/R3K2R w KQkq -";
+        let pos = Position::from_fen(fen).unwrap();
+        let king = targets(&pos, Piece::WhiteKing, Square::E1);
+        assert!(!king.contains(&Square::C1) && !king.contains(&Square::G1));
+
+        // a piece on b8 blocks the rook, even though the king never crosses it
+        let fen = "rn2k2r/8/8/8/8/8/8/R3K2R b KQkq -";
+        let pos = Position::from_fen(fen).unwrap();
+        let king = targets(&pos, Piece::BlackKing, Square::E8);
+        assert!(!king.contains(&Square::C8) && king.contains(&Square::G8));
+
+        // while an attack on b1 doesn't stop it
+        let fen = "r3k2r/8/8/8/8/8/1r6/R3K2R w KQkq -";
+        let pos = Position::from_fen(fen).unwrap();
+        let king = targets(&pos, Piece::WhiteKing, Square::E1);
+        assert!(king.contains(&Square::C1));
     }
 
     #[test]
     fn test_generate_pawn_moves_forward() {}
 
     #[test]
-    fn test_generate_pawn_moves_promotion() {}
+    fn test_generate_pawn_moves_promotion() {
+        let fen = "8/P6k/8/8/8/8/8/K7 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        let mut moves = MoveList::empty();
+        generate_pawn_moves(&pos, Square::A7, &mut moves);
+        let mut uci: Vec<String> = moves.iter().map(|mv| mv.to_uci()).collect();
+        uci.sort();
+        assert_eq!(uci, vec!["a7a8b", "a7a8n", "a7a8q", "a7a8r"]);
+        assert!(moves.iter().all(|mv| mv.captured().is_none()));
+        assert!(moves
+            .iter()
+            .all(|mv| mv.promoted().map(|p| p.color()) == Some(Color::White)));
+    }
 
     #[test]
     fn test_generate_pawn_moves_capture() {}
 
     #[test]
-    fn test_generate_pawn_moves_capture_promotion() {}
+    fn test_generate_pawn_moves_capture_promotion() {
+        // blocked ahead, so only the captures on a1 promote
+        let fen = "4k3/8/8/8/8/8/1p6/RN2K3 b - -";
+        let pos = Position::from_fen(fen).unwrap();
+        let mut moves = MoveList::empty();
+        generate_pawn_moves(&pos, Square::B2, &mut moves);
+        let mut uci: Vec<String> = moves.iter().map(|mv| mv.to_uci()).collect();
+        uci.sort();
+        assert_eq!(uci, vec!["b2a1b", "b2a1n", "b2a1q", "b2a1r"]);
+        assert!(moves
+            .iter()
+            .all(|mv| mv.captured() == Some(Piece::WhiteRook)));
+    }
 
     #[test]
     fn test_generate_pawn_moves_en_passant_capture() {}
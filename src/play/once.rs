@@ -0,0 +1,27 @@
+/// A value built the first time it's needed and shared from then on, for
+/// the tables behind move generation and hashing. It's std's `OnceLock`
+/// when there is one, else a spin lock.
+pub struct Once<T> {
+    #[cfg(feature = "std")]
+    inner: std::sync::OnceLock<T>,
+    #[cfg(not(feature = "std"))]
+    inner: spin::Once<T>,
+}
+
+impl<T> Once<T> {
+    pub const fn new() -> Self {
+        Once {
+            #[cfg(feature = "std")]
+            inner: std::sync::OnceLock::new(),
+            #[cfg(not(feature = "std"))]
+            inner: spin::Once::new(),
+        }
+    }
+
+    pub fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        #[cfg(feature = "std")]
+        return self.inner.get_or_init(init);
+        #[cfg(not(feature = "std"))]
+        return self.inner.call_once(init);
+    }
+}
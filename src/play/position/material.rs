@@ -0,0 +1,246 @@
+use alloc::{format, string::String};
+
+use alloc::vec::Vec;
+
+use crate::play::{
+    constants::{BLACK_PIECES, WHITE_PIECES},
+    once::Once,
+    types::{Color, Piece, PieceType},
+};
+
+use super::Position;
+
+static ENDGAMES: Once<Vec<(MaterialKey, Endgame)>> = Once::new();
+
+/// Bits given to each piece's count in a material key.
+const COUNT_BITS: u32 = 4;
+const COUNT_MASK: u64 = (1 << COUNT_BITS) - 1;
+
+/// The count of each piece on the board packed into one number, four bits
+/// a piece in `Piece` order, so positions with the same material share a key
+/// however the pieces stand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialKey(pub u64);
+
+impl MaterialKey {
+    pub fn count(&self, piece: Piece) -> u32 {
+        ((self.0 >> (piece as u32 * COUNT_BITS)) & COUNT_MASK) as u32
+    }
+
+    /// Men on the board, kings included, as tablebases count them.
+    pub fn piece_count(&self) -> u32 {
+        WHITE_PIECES
+            .iter()
+            .chain(BLACK_PIECES.iter())
+            .map(|piece| self.count(*piece))
+            .sum()
+    }
+
+    /// Whether a tablebase of positions with up to `max_pieces` men covers
+    /// this material.
+    pub fn probeable(&self, max_pieces: u32) -> bool {
+        self.piece_count() <= max_pieces
+    }
+
+    /// The same material with the colors swapped.
+    pub fn mirrored(&self) -> MaterialKey {
+        let half = 6 * COUNT_BITS;
+        let white = self.0 & ((1 << half) - 1);
+        MaterialKey((self.0 >> half) | (white << half))
+    }
+
+    /// Reads a signature like `KRPKR`: white's pieces after the first `K`,
+    /// black's after the second.
+    pub fn from_signature(signature: &str) -> Option<MaterialKey> {
+        let rest = signature.strip_prefix('K')?;
+        let (white, black) = rest.split_once('K')?;
+        let mut key = 0;
+        for (pieces, color) in [(white, Color::White), (black, Color::Black)] {
+            key += 1 << (Piece::of(PieceType::King, color) as u32 * COUNT_BITS);
+            for c in pieces.chars() {
+                let piece_type = match c {
+                    'P' => PieceType::Pawn,
+                    'N' => PieceType::Knight,
+                    'B' => PieceType::Bishop,
+                    'R' => PieceType::Rook,
+                    'Q' => PieceType::Queen,
+                    _ => return None,
+                };
+                key += 1 << (Piece::of(piece_type, color) as u32 * COUNT_BITS);
+            }
+        }
+        Some(MaterialKey(key))
+    }
+
+    /// The material as a signature, white's pieces first, strongest first.
+    pub fn signature(&self) -> String {
+        let mut signature = String::new();
+        for pieces in [WHITE_PIECES, BLACK_PIECES] {
+            for piece in pieces.iter().rev() {
+                let c: char = (*piece).into();
+                for _ in 0..self.count(*piece) {
+                    signature.push(c.to_ascii_uppercase());
+                }
+            }
+        }
+        signature
+    }
+}
+
+/// Endgames known well enough to be worth evaluating on their own terms.
+/// Each is named for the stronger side's pieces, then the weaker side's.
+// the names chess players use for them
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endgame {
+    KPK,
+    KNNK,
+    KBNK,
+    KRK,
+    KQK,
+    KRKP,
+    KRKN,
+    KRKB,
+    KQKP,
+    KQKR,
+    KBPK,
+    KRPKR,
+    KRPKB,
+    KBPKB,
+}
+
+impl Endgame {
+    pub const ALL: [Endgame; 14] = [
+        Endgame::KPK,
+        Endgame::KNNK,
+        Endgame::KBNK,
+        Endgame::KRK,
+        Endgame::KQK,
+        Endgame::KRKP,
+        Endgame::KRKN,
+        Endgame::KRKB,
+        Endgame::KQKP,
+        Endgame::KQKR,
+        Endgame::KBPK,
+        Endgame::KRPKR,
+        Endgame::KRPKB,
+        Endgame::KBPKB,
+    ];
+
+    /// The material of the endgame with white as the stronger side.
+    pub fn key(self) -> MaterialKey {
+        // the variant names are all valid signatures
+        MaterialKey::from_signature(&format!("{:?}", self)).unwrap_or(MaterialKey(0))
+    }
+
+    /// The endgame `key` is, if it's a known one, and which side is the
+    /// stronger.
+    pub fn classify(key: MaterialKey) -> Option<(Endgame, Color)> {
+        let endgames =
+            ENDGAMES.get_or_init(|| Endgame::ALL.iter().map(|e| (e.key(), *e)).collect());
+        let mirrored = key.mirrored();
+        endgames.iter().find_map(|(endgame_key, endgame)| {
+            if *endgame_key == key {
+                Some((*endgame, Color::White))
+            } else if *endgame_key == mirrored {
+                Some((*endgame, Color::Black))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Position {
+    /// The material on the board, for dispatching to endgame evaluation or
+    /// deciding whether a tablebase can be probed.
+    pub fn material_key(&self) -> MaterialKey {
+        let key = WHITE_PIECES
+            .iter()
+            .chain(BLACK_PIECES.iter())
+            .fold(0, |key, piece| {
+                let count = (self.board.bitboard(*piece).pop_count() as u64).min(COUNT_MASK);
+                key | count << (*piece as u32 * COUNT_BITS)
+            });
+        MaterialKey(key)
+    }
+
+    pub fn endgame(&self) -> Option<(Endgame, Color)> {
+        Endgame::classify(self.material_key())
+    }
+
+    /// How much of the middlegame is left, counted from the pieces on the
+    /// board: [`MAX_PHASE`] in the start position down to 0 with only kings
+    /// and pawns. Promotions can take it past the maximum.
+    ///
+    /// [`MAX_PHASE`]: crate::play::MAX_PHASE
+    pub fn game_phase(&self) -> u8 {
+        WHITE_PIECES
+            .iter()
+            .chain(BLACK_PIECES.iter())
+            .map(|piece| self.board.bitboard(*piece).pop_count() as u8 * piece.phase_weight())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::GameState;
+
+    fn position(fen: &str) -> Position {
+        GameState::from_fen(fen).unwrap().position
+    }
+
+    #[test]
+    fn test_material_key() {
+        let key = Position::default().material_key();
+        assert_eq!(key.count(Piece::WhitePawn), 8);
+        assert_eq!(key.count(Piece::BlackKnight), 2);
+        assert_eq!(key.count(Piece::BlackKing), 1);
+        assert_eq!(key.piece_count(), 32);
+        assert!(!key.probeable(7));
+        assert_eq!(key.signature(), "KQRRBBNNPPPPPPPPKQRRBBNNPPPPPPPP");
+
+        let key = position("8/8/4k3/8/2R5/8/1P2K3/5r2 w - - 0 1").material_key();
+        assert_eq!(key.signature(), "KRPKR");
+        assert_eq!(Some(key), MaterialKey::from_signature("KRPKR"));
+        assert!(key.probeable(5));
+        assert_eq!(key.mirrored().signature(), "KRKRP");
+        assert_eq!(MaterialKey::from_signature("KXK"), None);
+    }
+
+    #[test]
+    fn test_game_phase() {
+        assert_eq!(Position::default().game_phase(), 24);
+        assert_eq!(
+            position("8/8/4k3/8/2R5/8/1P2K3/5r2 w - - 0 1").game_phase(),
+            4
+        );
+        assert_eq!(position("8/8/4k3/8/8/8/1P2K3/8 w - - 0 1").game_phase(), 0);
+    }
+
+    #[test]
+    fn test_classify_endgame() {
+        assert_eq!(
+            position("8/8/4k3/8/2R5/8/1P2K3/5r2 w - - 0 1").endgame(),
+            Some((Endgame::KRPKR, Color::White))
+        );
+        assert_eq!(
+            position("8/8/3bk3/8/8/8/4K3/7n b - - 0 1").endgame(),
+            Some((Endgame::KBNK, Color::Black))
+        );
+        assert_eq!(
+            position("8/8/4k3/8/8/8/3PK3/8 w - - 0 1").endgame(),
+            Some((Endgame::KPK, Color::White))
+        );
+        assert_eq!(Position::default().endgame(), None);
+        for endgame in Endgame::ALL {
+            assert_eq!(
+                Endgame::classify(endgame.key()),
+                Some((endgame, Color::White))
+            );
+        }
+    }
+}
@@ -1,9 +1,23 @@
+pub mod material;
+mod see;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::convert::TryFrom;
+
 use super::{
-    board::Board,
-    error::FENParsingError,
-    move_gen,
-    r#move::MoveList,
-    types::{CastlingRights, Color, PieceType, Square},
+    board::{bitboard::Bitboard, Board},
+    constants::{BLACK_PIECES, HISTORY_RESERVE, WHITE_PIECES},
+    error::{FENParsingError, InvalidPosition, MoveError},
+    move_gen::{self, MoveCounter, MoveSink},
+    r#move::{info::MoveInfo, make_move, Move, MoveList},
+    types::{CastlingRights, Color, File, Piece, PieceType, Rank, Square},
+    variant::{self, Hand, Variant, HAND_PIECE_TYPES},
+    GameState,
 };
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
@@ -13,6 +27,17 @@ pub struct Position {
     pub en_passant: Option<Square>,
     pub castling_permissions: CastlingRights, // bits = [ wK, wQ, bK, bQ ]
     pub castling_perms_history: Vec<CastlingRights>,
+    pub en_passant_history: Vec<Option<Square>>,
+    /// the opponent's pieces giving check to the side to move
+    pub checkers: Bitboard,
+    /// each color's pieces pinned to their own king, by `Color`
+    pub pinned: [Bitboard; 2],
+    /// those pieces with their pin rays, see [`Board::pin_ray`]
+    pub pin_rays: [Vec<(Square, Bitboard)>; 2],
+    /// the rules the game is played under, which FEN doesn't say
+    pub variant: Variant,
+    /// each color's pieces in hand, by `Color`, for variants with drops
+    pub hands: [Hand; 2],
 }
 
 impl Default for Position {
@@ -22,13 +47,28 @@ impl Default for Position {
             side_to_move: Color::White,
             en_passant: None,
             castling_permissions: CastlingRights::all(),
-            castling_perms_history: vec![],
+            castling_perms_history: Vec::with_capacity(HISTORY_RESERVE),
+            en_passant_history: Vec::with_capacity(HISTORY_RESERVE),
+            checkers: Bitboard::empty(),
+            pinned: [Bitboard::empty(); 2],
+            pin_rays: [vec![], vec![]],
+            variant: Variant::Standard,
+            hands: [Hand::default(); 2],
         }
     }
 }
 
 impl Position {
     pub fn from_fields(fields: Vec<String>) -> Result<Position, FENParsingError> {
+        Position::from_variant_fields(fields, Variant::Standard)
+    }
+
+    /// The four position fields of a FEN, validated under `variant`'s
+    /// rules rather than standard chess's.
+    pub fn from_variant_fields(
+        fields: Vec<String>,
+        variant: Variant,
+    ) -> Result<Position, FENParsingError> {
         let n_req_fields = 4;
         if fields.len() != n_req_fields {
             return Err(FENParsingError::new(format!(
@@ -38,32 +78,202 @@ impl Position {
             )));
         }
 
-        let mut pos = Position::default();
-
-        // board
-        pos.board = Board::from_fen(&fields[0])?;
+        // board, with the pieces in hand after it in brackets for variants
+        // with drops
+        let (board, hands) = match fields[0].split_once('[') {
+            Some((board, pocket)) => (board, parse_pocket(pocket)?),
+            None => (fields[0].as_str(), [Hand::default(); 2]),
+        };
+        let board = Board::from_fen(board)?;
 
         // piece to move
-        if fields[1] == "b".to_string() {
-            pos.side_to_move = Color::Black
-        }
+        let side_to_move = match fields[1].as_str() {
+            "w" => Color::White,
+            "b" => Color::Black,
+            side => {
+                return Err(FENParsingError::new(format!(
+                    "Error parsing fen: side to move {}, expected w or b",
+                    side
+                )))
+            }
+        };
 
         // castling
-        pos.castling_permissions = CastlingRights::from_fen(&fields[2])?;
+        let castling_permissions = CastlingRights::from_fen(&fields[2])?;
 
         // en passant
-        pos.en_passant = Square::from_fen(&fields[3])?;
+        let en_passant = Square::from_fen(&fields[3])?;
+
+        let mut position = Position {
+            board,
+            side_to_move,
+            en_passant,
+            castling_permissions,
+            hands,
+            variant,
+            ..Position::default()
+        };
+        position.validate()?;
+        position.update_checks_and_pins();
+        Ok(position)
+    }
+
+    /// Checks the position is one a game could reach under its variant: a
+    /// king a side where the king is royal, any number otherwise, no
+    /// pawns on the back ranks, the side that just moved not in check, an
+    /// en passant square behind a pawn that just pushed two squares, and
+    /// castling rights only with the king and rook on their starting squares.
+    pub fn validate(&self) -> Result<(), InvalidPosition> {
+        for color in [Color::White, Color::Black] {
+            let kings = self
+                .board
+                .bitboard(Piece::of(PieceType::King, color))
+                .pop_count();
+            if kings != 1 && self.variant.royal_king() {
+                return Err(InvalidPosition::KingCount(color, kings));
+            }
+        }
+
+        let pawns = self.board.bitboard(Piece::WhitePawn) | self.board.bitboard(Piece::BlackPawn);
+        if let Some(sq) = pawns
+            .iter()
+            .find(|sq| matches!(sq.rank(), Rank::Rank1 | Rank::Rank8))
+        {
+            return Err(InvalidPosition::PawnOnBackRank(sq));
+        }
 
-        Ok(pos)
+        // as the variant counts it: atomic kings side by side are safe
+        if !self
+            .variant
+            .king_safe(&self.board, self.side_to_move.opposing())
+        {
+            return Err(InvalidPosition::OpponentInCheck);
+        }
+
+        if let Some(ep) = self.en_passant {
+            // white to move means black just pushed from rank 7 to rank 5
+            let (ep_rank, pushed_to, pushed_from) = match self.side_to_move {
+                Color::White => (Rank::Rank6, Rank::Rank5, Rank::Rank7),
+                Color::Black => (Rank::Rank3, Rank::Rank4, Rank::Rank2),
+            };
+            let pusher = Piece::of(PieceType::Pawn, self.side_to_move.opposing());
+            if ep.rank() != ep_rank
+                || self.board.piece(&Square::new(ep.file(), pushed_to)) != Some(pusher)
+                || self.board.sq_taken(ep)
+                || self.board.sq_taken(Square::new(ep.file(), pushed_from))
+            {
+                return Err(InvalidPosition::EnPassant(ep));
+            }
+        }
+
+        let rights = self.castling_permissions;
+        for (has_right, ch, color, rook_file) in [
+            (rights.white_kingside(), 'K', Color::White, File::H),
+            (rights.white_queenside(), 'Q', Color::White, File::A),
+            (rights.black_kingside(), 'k', Color::Black, File::H),
+            (rights.black_queenside(), 'q', Color::Black, File::A),
+        ] {
+            let back_rank = match color {
+                Color::White => Rank::Rank1,
+                Color::Black => Rank::Rank8,
+            };
+            let in_place = |piece_type, file| {
+                self.board.piece(&Square::new(file, back_rank))
+                    == Some(Piece::of(piece_type, color))
+            };
+            // there's no castling where the king isn't royal, and the rights
+            // are dropped when the variant is applied
+            if has_right
+                && self.variant.royal_king()
+                && !(in_place(PieceType::King, File::E) && in_place(PieceType::Rook, rook_file))
+            {
+                return Err(InvalidPosition::CastlingRight(ch));
+            }
+        }
+        Ok(())
     }
 
-    ///
-    ///
+    /// Recomputes `checkers`, `pinned` and `pin_rays` from the board, which
+    /// has to happen whenever it or the side to move changes.
+    pub fn update_checks_and_pins(&mut self) {
+        // a king that isn't royal can't be checked, nor anything pinned to it
+        if !self.variant.royal_king() {
+            self.checkers = Bitboard::empty();
+            self.pinned = [Bitboard::empty(); 2];
+            self.pin_rays = [vec![], vec![]];
+            return;
+        }
+        self.checkers = match self
+            .board
+            .bitboard(Piece::of(PieceType::King, self.side_to_move))
+        {
+            Bitboard(0x0) => Bitboard::empty(),
+            king => self
+                .board
+                .attackers_bitboard(Square::from_bitboard(king), self.side_to_move.opposing()),
+        };
+        // kings side by side can't be checked in atomic, taking one would
+        // blow up the other
+        if self.variant.explodes() && variant::kings_adjacent(&self.board) {
+            self.checkers = Bitboard::empty();
+        }
+        for color in [Color::White, Color::Black] {
+            let pins = self.board.pins(color);
+            self.pinned[color as usize] = pins
+                .iter()
+                .fold(Bitboard::empty(), |pinned, (sq, _)| pinned | (*sq).into());
+            self.pin_rays[color as usize] = pins;
+        }
+    }
+
+    /// The pin ray of the side to move's piece on `sq`, if it's pinned.
+    pub fn pin_ray(&self, sq: Square) -> Option<Bitboard> {
+        let color = self.side_to_move as usize;
+        if (self.pinned[color] & sq.into()).0 == 0x0 {
+            return None;
+        }
+        self.pin_rays[color]
+            .iter()
+            .find(|(pinned, _)| *pinned == sq)
+            .map(|(_, ray)| *ray)
+    }
+
+    /// Parses the first four (board) fields of a FEN string, i.e. everything
+    /// except the move clocks.
     pub fn from_fen(fen: impl ToString) -> Result<Position, FENParsingError> {
         let fields: Vec<String> = fen.to_string().split(' ').map(|s| s.to_string()).collect();
         Position::from_fields(fields)
     }
 
+    /// The four position fields of a FEN, as [`Position::from_fen`] reads
+    /// them.
+    pub fn to_fen(&self) -> String {
+        let side = match self.side_to_move {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+        let mut board = self.board.to_fen();
+        if self.variant.has_hands() {
+            board.push('[');
+            for color in [Color::White, Color::Black] {
+                let hand = &self.hands[color as usize];
+                for &piece_type in HAND_PIECE_TYPES.iter().rev() {
+                    for _ in 0..hand.count(piece_type) {
+                        board.push(Piece::of(piece_type, color).into());
+                    }
+                }
+            }
+            board.push(']');
+        }
+        format!(
+            "{} {} {} {}",
+            board,
+            side,
+            self.castling_permissions,
+            self.en_passant.map_or("-".to_string(), |sq| sq.to_string())
+        )
+    }
+
     pub fn flip_side(&mut self) {
         self.side_to_move = match self.side_to_move {
             Color::White => Color::Black,
@@ -71,42 +281,291 @@ impl Position {
         };
     }
 
+    /// Whether the side to move has a pawn that could capture onto the en
+    /// passant square. The square itself is tracked whenever a pawn makes a
+    /// double push (as FEN/UCI expect), but it only distinguishes positions
+    /// when this holds.
+    pub fn ep_capture_possible(&self) -> bool {
+        let ep_sq = match self.en_passant {
+            Some(sq) => sq,
+            None => return false,
+        };
+        let pawn = Piece::of(PieceType::Pawn, self.side_to_move);
+        // a capturing pawn sits one step back along the opponent's diagonals
+        self.side_to_move
+            .opposing()
+            .pawn_diagonals()
+            .iter()
+            .filter_map(|dir| ep_sq.offset(*dir))
+            .any(|sq| self.board.piece(&sq) == Some(pawn))
+    }
+
+    /// Every square attacked by `color`, see [`Board::attack_map`].
+    pub fn attack_map(&self, color: Color) -> Bitboard {
+        self.board.attack_map(color)
+    }
+
+    /// The winner, if the variant's own rules have ended the game, see
+    /// [`Variant::winner`].
+    pub fn winner(&self) -> Option<Color> {
+        self.variant.winner(self)
+    }
+
+    pub fn in_check(&self) -> bool {
+        self.checkers.0 != 0x0
+    }
+
+    /// Whether `mv` puts the opponent in check, directly or by discovery.
+    /// Only the board is played out, on a copy, so it's much cheaper than
+    /// making the move on the whole game state.
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let mut board = self.board;
+        match self.play_on_board(&mut board, mv) {
+            Ok(()) => !self.variant.king_safe(&board, self.side_to_move.opposing()),
+            Err(_) => false,
+        }
+    }
+
+    /// Whether `mv` checkmates the opponent.
+    pub fn gives_mate(&self, mv: Move) -> bool {
+        if !self.gives_check(mv) {
+            return false;
+        }
+        let mut state = GameState {
+            position: self.clone(),
+            phase: self.game_phase(),
+            ..GameState::default()
+        };
+        make_move(mv, &mut state).is_ok() && !state.position.has_legal_move()
+    }
+
+    /// Every legal move with its metadata, in generation order.
+    pub fn legal_move_infos(&self) -> Vec<MoveInfo> {
+        self.legal_moves()
+            .into_iter()
+            .filter(|mv| self.is_legal(*mv))
+            .map(|mv| MoveInfo::new(mv, self))
+            .collect()
+    }
+
+    /// Whether a generated move leaves the mover's own king safe, and in
+    /// atomic that it isn't a king capturing.
+    pub fn is_legal(&self, mv: Move) -> bool {
+        let king = Piece::of(PieceType::King, self.side_to_move);
+        if self.variant.explodes()
+            && mv.captured().is_some()
+            && self.board.piece(&mv.from_sq()) == Some(king)
+        {
+            return false;
+        }
+        let mut board = self.board;
+        match self.play_on_board(&mut board, mv) {
+            Ok(()) => self.variant.king_safe(&board, self.side_to_move),
+            Err(_) => false,
+        }
+    }
+
+    fn play_on_board(&self, board: &mut Board, mv: Move) -> Result<(), MoveError> {
+        if let Some(piece) = mv.dropped() {
+            return board.add_piece(piece, mv.to_sq());
+        }
+        if mv.en_passant() {
+            // the captured pawn sits behind the en passant square
+            let behind = self.side_to_move.opposing().pawn_push_dir();
+            if let Some(sq) = mv.to_sq().offset(behind) {
+                board.remove_piece(sq)?;
+            }
+        } else if mv.captured().is_some() {
+            board.remove_piece(mv.to_sq())?;
+        }
+        board.move_piece(mv.from_sq(), mv.to_sq())?;
+        if let Some(piece) = mv.promoted() {
+            board.remove_piece(mv.to_sq())?;
+            board.add_piece(piece, mv.to_sq())?;
+        }
+        if self.variant.explodes() && mv.captured().is_some() {
+            for sq in variant::exploded(board, mv.to_sq()) {
+                board.remove_piece(sq)?;
+            }
+        }
+        if mv.castle() {
+            match mv.to_sq() {
+                Square::G1 => board.move_piece(Square::H1, Square::F1)?,
+                Square::C1 => board.move_piece(Square::A1, Square::D1)?,
+                Square::G8 => board.move_piece(Square::H8, Square::F8)?,
+                Square::C8 => board.move_piece(Square::A8, Square::D8)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
     pub fn legal_moves(&self) -> MoveList {
-        let color = self.side_to_move;
         let mut moves = MoveList::empty();
+        self.generate_legal(&mut moves);
+        moves
+    }
+
+    /// Finds the legal move written in coordinate notation, e.g. `e2e4`,
+    /// `e7e8q` or, where pieces are dropped, `N@c3`.
+    pub fn parse_move(&self, uci: &str) -> Option<Move> {
+        self.legal_moves().into_iter().find(|mv| mv.to_uci() == uci)
+    }
+
+    /// How many legal moves the side to move has, without keeping them.
+    pub fn count_legal_moves(&self) -> usize {
+        let mut counter = MoveCounter::new(usize::MAX);
+        self.generate_legal(&mut counter);
+        counter.count
+    }
+
+    /// Whether the side to move has any legal move, stopping at the first.
+    pub fn has_legal_move(&self) -> bool {
+        let mut counter = MoveCounter::new(1);
+        self.generate_legal(&mut counter);
+        counter.count > 0
+    }
+
+    /// Nothing once the variant has decided the game, and only the
+    /// captures where there are any and the variant forces them.
+    fn generate_legal<S: MoveSink>(&self, moves: &mut S) {
+        if self.winner().is_some() {
+            return;
+        }
+        if self.variant.forces_captures() {
+            let mut all = MoveList::empty();
+            self.generate_all(&mut all);
+            move_gen::forced_captures(all, moves);
+        } else {
+            self.generate_all(moves);
+        }
+    }
 
-        for piece in self.board.pieces(color) {
+    fn generate_all<S: MoveSink>(&self, moves: &mut S) {
+        let pieces = match self.side_to_move {
+            Color::White => WHITE_PIECES,
+            Color::Black => BLACK_PIECES,
+        };
+        for piece in pieces {
+            if moves.is_full() {
+                return;
+            }
+            if piece.piece_type() == PieceType::Pawn {
+                move_gen::generate_pawn_set_moves(self, self.board.bitboard(piece), moves);
+                continue;
+            }
             for sq in self.board.bitboard(piece) {
-                if piece.piece_type() == PieceType::Pawn {
-                    move_gen::generate_pawn_moves(&self, sq, &mut moves);
-                } else {
-                    move_gen::generate_moves(&self, piece, sq, &mut moves);
-                }
+                move_gen::generate_moves(self, piece, sq, moves);
             }
         }
-        moves
+        if self.variant.has_hands() {
+            move_gen::generate_drops(self, moves);
+        }
     }
 
     // TODO: use piece Type here
 }
 
+/// The pieces in hand of a FEN's `[...]` pocket, White's in upper case,
+/// after the opening bracket.
+fn parse_pocket(pocket: &str) -> Result<[Hand; 2], FENParsingError> {
+    let pieces = pocket
+        .strip_suffix(']')
+        .ok_or_else(|| FENParsingError::new(format!("Unclosed pocket [{}", pocket)))?;
+    let mut hands = [Hand::default(); 2];
+    for ch in pieces.chars().filter(|&ch| ch != '-') {
+        let piece = Piece::try_from(ch)?;
+        if piece.piece_type() == PieceType::King {
+            return Err(FENParsingError::new(format!("King in pocket [{}", pocket)));
+        }
+        hands[piece.color() as usize].add(piece.piece_type());
+    }
+    Ok(hands)
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::play::error::FENErrorKind;
+
+    #[test]
+    fn test_validate() {
+        let invalid = |fen: &str| match Position::from_fen(fen).unwrap_err().kind() {
+            FENErrorKind::InvalidPosition(invalid) => invalid.clone(),
+            FENErrorKind::Syntax => panic!("{} should parse", fen),
+        };
+        assert_eq!(
+            invalid("8/8/8/8/8/8/8/4K3 w - -"),
+            InvalidPosition::KingCount(Color::Black, 0)
+        );
+        assert_eq!(
+            invalid("k7/8/8/8/8/8/8/KK6 w - -"),
+            InvalidPosition::KingCount(Color::White, 2)
+        );
+        assert_eq!(
+            invalid("k6P/8/8/8/8/8/8/K7 w - -"),
+            InvalidPosition::PawnOnBackRank(Square::H8)
+        );
+        assert_eq!(
+            invalid("k7/8/8/8/8/8/8/K6r b - -"),
+            InvalidPosition::OpponentInCheck
+        );
+        assert_eq!(
+            invalid("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e6"),
+            InvalidPosition::EnPassant(Square::E6)
+        );
+        assert_eq!(
+            invalid("rnbqkbnr/pppppppp/8/8/8/4P3/PPPP1PPP/RNBQKBNR b KQkq e3"),
+            InvalidPosition::EnPassant(Square::E3)
+        );
+        assert_eq!(
+            invalid("r3k2r/8/8/8/8/8/8/R4RK1 w Qkq -"),
+            InvalidPosition::CastlingRight('Q')
+        );
+        assert_eq!(
+            invalid("1r2k2r/8/8/8/8/8/8/R3K2R w KQq -"),
+            InvalidPosition::CastlingRight('q')
+        );
+        assert!(
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3").is_ok()
+        );
+        assert!(Position::from_fen("r3k2r/8/8/8/8/8/8/R4RK1 w kq -").is_ok());
+        assert!(Position::default().validate().is_ok());
+    }
 
     #[test]
     fn test_from_fields() {
-        let ok_fields: Vec<String> = vec!["8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8", "b", "-", "-"]
+        let ok_fields: Vec<String> = ["8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8", "b", "-", "-"]
             .iter()
             .map(|s| s.to_string())
             .collect();
         assert!(Position::from_fields(ok_fields).is_ok());
-        let err_fields: Vec<String> = vec!["8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8", "b"]
+        let err_fields: Vec<String> = ["8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8", "b"]
             .iter()
             .map(|s| s.to_string())
             .collect();
         assert!(Position::from_fields(err_fields).is_err());
+
+        // an antichess position needn't have kings, or castle
+        let kingless: Vec<String> = ["8/8/8/8/8/8/q6P/8", "w", "KQkq", "-"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(Position::from_fields(kingless.clone()).is_err());
+        let position = Position::from_variant_fields(kingless, Variant::Antichess).unwrap();
+        assert_eq!(position.variant, Variant::Antichess);
+        assert_eq!(position.checkers, Bitboard::empty());
+
+        // atomic kings side by side can't take each other, so neither is
+        // in check
+        let adjacent: Vec<String> = ["8/8/8/8/8/3k4/3K4/8", "w", "-", "-"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(Position::from_fields(adjacent.clone()).is_err());
+        let position = Position::from_variant_fields(adjacent, Variant::Atomic).unwrap();
+        assert_eq!(position.checkers, Bitboard::empty());
     }
 
     #[test]
@@ -115,8 +574,110 @@ mod tests {
         assert!(Position::from_fen(ok_fen).is_ok());
         let err_fen = "8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8 b";
         assert!(Position::from_fen(err_fen).is_err());
+        for side in ["x", "B", "white"] {
+            let fen = format!("8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8 {} - -", side);
+            assert!(Position::from_fen(&fen).is_err(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_ep_capture_possible() {
+        // no en passant square
+        assert!(!Position::default().ep_capture_possible());
+
+        // after 1. e4 nothing black can capture with
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3";
+        assert!(!Position::from_fen(fen).unwrap().ep_capture_possible());
+
+        // black pawn on d4 can take on e3
+        let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3";
+        assert!(Position::from_fen(fen).unwrap().ep_capture_possible());
+
+        // white pawn on e5 can take on d6, but not on f6 past the file edge
+        let fen = "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6";
+        assert!(Position::from_fen(fen).unwrap().ep_capture_possible());
+        let fen = "rnbqkbnr/ppppppp1/8/P6p/8/8/1PPPPPPP/RNBQKBNR w KQkq h6";
+        assert!(!Position::from_fen(fen).unwrap().ep_capture_possible());
+    }
+
+    #[test]
+    fn test_checks_and_pins() {
+        // the bishop on b4 pins the knight on c3, and the queen can check
+        let fen = "4k3/8/8/8/1b6/2N5/8/4K2Q w - - 0 1";
+        let mut state = GameState::from_fen(fen).unwrap();
+        let position = &state.position;
+        assert!(!position.in_check());
+        assert_eq!(position.pinned[Color::White as usize], Square::C3.into());
+        let ray: Bitboard = vec![Square::D2, Square::C3, Square::B4].into();
+        assert_eq!(position.pin_ray(Square::C3), Some(ray));
+        assert_eq!(position.pin_ray(Square::H1), None);
+        assert_eq!(position.pinned[Color::Black as usize], Bitboard::empty());
+
+        let check = Move::quiet(Square::H1, Square::H5);
+        let before = state.clone();
+        make_move(check, &mut state).unwrap();
+        assert!(state.position.in_check());
+        assert_eq!(state.position.checkers, Square::H5.into());
+        // the cache is kept from the side to move's point of view
+        assert_eq!(state.position.pin_ray(Square::C3), None);
+
+        crate::play::r#move::unmake_move(check, &mut state).unwrap();
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn test_gives_check() {
+        let quiet = |from, to| Move::quiet(from, to);
+
+        // direct checks from a knight and a rook, but not through a blocker
+        let fen = "4k3/4p3/8/8/2N5/8/8/K6R w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        assert!(pos.gives_check(quiet(Square::C4, Square::D6)));
+        assert!(!pos.gives_check(quiet(Square::C4, Square::B6)));
+        assert!(pos.gives_check(quiet(Square::H1, Square::H8)));
+        assert!(!pos.gives_check(quiet(Square::H1, Square::H7)));
+        assert!(!pos.gives_check(quiet(Square::H1, Square::E1)));
+
+        // a discovered check when the knight leaves the rook's file
+        let fen = "4k3/8/8/8/4N3/8/8/K3R3 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        assert!(pos.gives_check(quiet(Square::E4, Square::C5)));
+        assert!(pos.gives_check(quiet(Square::E4, Square::D6)));
+
+        // promoting to a queen checks along the rank
+        let fen = "7k/P7/8/8/8/8/8/4K3 w - -";
+        let pos = Position::from_fen(fen).unwrap();
+        let promo = Move::promotion(Square::A7, Square::A8, Piece::WhiteQueen, None);
+        assert!(pos.gives_check(promo));
+
+        // castling puts the rook on the king's file
+        let fen = "5k2/8/8/8/8/8/8/4K2R w K -";
+        let pos = Position::from_fen(fen).unwrap();
+        assert!(pos.gives_check(Move::castle_kingside(Color::White)));
     }
 
     #[test]
     fn test_legal_moves() {}
+
+    #[test]
+    fn test_count_legal_moves() {
+        let pos = Position::default();
+        assert_eq!(pos.count_legal_moves(), 20);
+        assert!(pos.has_legal_move());
+
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+        let pos = Position::from_fen(fen).unwrap();
+        assert_eq!(pos.count_legal_moves(), pos.legal_moves().len());
+        assert_eq!(pos.count_legal_moves(), 48);
+
+        // checkmated, and stalemated
+        for fen in [
+            "R5k1/5ppp/8/8/8/8/8/6K1 b - -",
+            "7k/5Q2/6K1/8/8/8/8/8 b - -",
+        ] {
+            let pos = Position::from_fen(fen).unwrap();
+            assert_eq!(pos.count_legal_moves(), 0);
+            assert!(!pos.has_legal_move());
+        }
+    }
 }
@@ -0,0 +1,131 @@
+use alloc::vec;
+
+use crate::play::{
+    constants::{BLACK_PIECES, PIECE_VALUES, WHITE_PIECES},
+    r#move::Move,
+    types::{Color, Piece, PieceType},
+};
+
+use super::Position;
+
+fn value(piece: Piece) -> i32 {
+    PIECE_VALUES[piece as usize] as i32
+}
+
+impl Position {
+    /// Static exchange evaluation: the material `mv` wins, or loses when
+    /// negative, once both sides have made every recapture on its target
+    /// square that pays, always with their least valuable piece first.
+    /// Pins and promotions on recapture aren't considered, and a king only
+    /// recaptures when nothing would take it back.
+    pub fn see(&self, mv: Move) -> i32 {
+        let target = mv.to_sq();
+        let mover = self.side_to_move;
+        let mut board = self.board;
+        let mut occupant = match board.piece(&mv.from_sq()) {
+            Some(piece) => piece,
+            None => return 0,
+        };
+
+        let mut first = mv.captured().map_or(0, value);
+        if let Some(promoted) = mv.promoted() {
+            first += value(promoted) - value(occupant);
+            occupant = promoted;
+        }
+        if self.play_on_board(&mut board, mv).is_err() {
+            return first;
+        }
+
+        // gains[d] is what the side making the dth capture wins if the
+        // exchange ends there
+        let mut gains = vec![first];
+        let mut side = mover.opposing();
+        loop {
+            let attackers = board.attackers_bitboard(target, side);
+            let pieces = match side {
+                Color::White => WHITE_PIECES,
+                Color::Black => BLACK_PIECES,
+            };
+            let attacker = pieces.iter().find_map(|piece| {
+                (board.bitboard(*piece) & attackers)
+                    .iter()
+                    .next()
+                    .map(|sq| (*piece, sq))
+            });
+            let (piece, from) = match attacker {
+                Some(attacker) => attacker,
+                None => break,
+            };
+            if piece.piece_type() == PieceType::King
+                && board.attackers_bitboard(target, side.opposing()).0 != 0x0
+            {
+                break;
+            }
+            gains.push(value(occupant) - gains[gains.len() - 1]);
+            if board.remove_piece(target).is_err() || board.move_piece(from, target).is_err() {
+                break;
+            }
+            occupant = piece;
+            side = side.opposing();
+        }
+
+        // either side can stop capturing whenever carrying on would lose
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap_or(0);
+            let prev = gains.len() - 1;
+            gains[prev] = -((-gains[prev]).max(last));
+        }
+        gains[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::types::Square;
+
+    fn find(position: &Position, from: Square, to: Square) -> Move {
+        position
+            .legal_moves()
+            .into_iter()
+            .find(|mv| mv.from_sq() == from && mv.to_sq() == to)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_see() {
+        // an undefended pawn
+        let position = Position::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - -").unwrap();
+        assert_eq!(position.see(find(&position, Square::E4, Square::D5)), 100);
+
+        // pawn takes pawn, pawn takes back
+        let position = Position::from_fen("4k3/8/4p3/3p4/4P3/8/8/4K3 w - -").unwrap();
+        assert_eq!(position.see(find(&position, Square::E4, Square::D5)), 0);
+
+        // queen takes a defended pawn
+        let position = Position::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - -").unwrap();
+        assert_eq!(position.see(find(&position, Square::D1, Square::D5)), -900);
+
+        // the bishop takes back, but it's still a knight for two pawns
+        let position = Position::from_fen("4k3/8/4p3/3p4/8/2N5/B7/4K3 w - -").unwrap();
+        assert_eq!(
+            position.see(find(&position, Square::C3, Square::D5)),
+            100 - 325 + 100
+        );
+
+        // a quiet move onto an attacked square
+        let position = Position::from_fen("4k3/8/4p3/8/8/2N5/8/4K3 w - -").unwrap();
+        assert_eq!(position.see(find(&position, Square::C3, Square::D5)), -325);
+        assert_eq!(position.see(find(&position, Square::C3, Square::B5)), 0);
+
+        // the king recaptures, but only when nothing would take it back
+        let position = Position::from_fen("8/8/2k5/8/8/4q3/3R4/2K5 b - -").unwrap();
+        assert_eq!(
+            position.see(find(&position, Square::E3, Square::D2)),
+            550 - 1000
+        );
+        let position = Position::from_fen("8/8/2k5/b7/8/4q3/3R4/2K5 b - -").unwrap();
+        assert_eq!(position.see(find(&position, Square::E3, Square::D2)), 550);
+    }
+}
@@ -0,0 +1,192 @@
+//! `Serialize` and `Deserialize` for the core types, each as the string it's
+//! written as in FEN or UCI, so that they read naturally in JSON.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::{convert::TryFrom, fmt::Display};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::play::{
+    position::Position,
+    r#move::Move,
+    types::{CastlingRights, Piece, Square},
+    GameState,
+};
+
+fn serialize_str<S: Serializer>(value: impl Display, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&value)
+}
+
+fn deserialize_str<'de, D, T, E>(
+    deserializer: D,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    E: Display,
+{
+    let s = String::deserialize(deserializer)?;
+    parse(&s).map_err(de::Error::custom)
+}
+
+/// As its name, e.g. `"e4"`.
+impl Serialize for Square {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_str(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Square {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, |s| match Square::from_fen(s) {
+            Ok(Some(sq)) => Ok(sq),
+            Ok(None) => Err(format!("no square {}", s)),
+            Err(e) => Err(e.to_string()),
+        })
+    }
+}
+
+/// As its FEN letter, e.g. `"N"` for a white knight or `"n"` for a black one.
+impl Serialize for Piece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_str(char::from(*self), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Piece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, |s| {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Piece::try_from(ch).map_err(|e| e.to_string()),
+                _ => Err(format!("no piece {}", s)),
+            }
+        })
+    }
+}
+
+/// In coordinate notation, e.g. `"e7e8q"`, and read back as
+/// [`Move::from_uci`] reads it.
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_str(self.to_uci(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, |s| {
+            Move::from_uci(s).ok_or_else(|| format!("not a move in coordinate notation: {}", s))
+        })
+    }
+}
+
+/// As the castling field of a FEN, e.g. `"KQkq"`, or `"-"` for none.
+impl Serialize for CastlingRights {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let rights = format!("{:?}", self);
+        serialize_str(if rights.is_empty() { "-" } else { &rights }, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CastlingRights {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, |s| CastlingRights::from_fen(s))
+    }
+}
+
+/// As the first four fields of its FEN.
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_str(self.to_fen(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, |s| Position::from_fen(s))
+    }
+}
+
+/// As its full FEN. The moves that led to it aren't kept, so a game read
+/// back can't see a repetition of a position from before it was written.
+impl Serialize for GameState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_str(self.to_fen(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GameState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, |s| GameState::from_fen(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn round_trip<T>(value: &T) -> (String, T)
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        let json = serde_json::to_string(value).unwrap();
+        let back = serde_json::from_str(&json).unwrap();
+        (json, back)
+    }
+
+    #[test]
+    fn test_types() {
+        assert_eq!(round_trip(&Square::E4), ("\"e4\"".to_string(), Square::E4));
+        assert_eq!(
+            round_trip(&Piece::BlackKnight),
+            ("\"n\"".to_string(), Piece::BlackKnight)
+        );
+        let rights = CastlingRights::from_fen("Kq").unwrap();
+        assert_eq!(round_trip(&rights), ("\"Kq\"".to_string(), rights));
+        let none = CastlingRights(0);
+        assert_eq!(round_trip(&none), ("\"-\"".to_string(), none));
+
+        assert!(serde_json::from_str::<Square>("\"-\"").is_err());
+        assert!(serde_json::from_str::<Piece>("\"Nn\"").is_err());
+    }
+
+    #[test]
+    fn test_moves() {
+        let quiet = Move::quiet(Square::G1, Square::F3);
+        assert_eq!(round_trip(&quiet), ("\"g1f3\"".to_string(), quiet));
+        for (uci, promoted) in [("e7e8q", Piece::WhiteQueen), ("b2a1n", Piece::BlackKnight)] {
+            let mv: Move = serde_json::from_str(&format!("\"{}\"", uci)).unwrap();
+            assert_eq!(mv.promoted(), Some(promoted));
+            assert_eq!(mv.to_uci(), uci);
+        }
+        // a capture comes back as its squares alone
+        let capture = Move::capture(Square::E4, Square::D5, Piece::BlackPawn);
+        let (json, back) = round_trip(&capture);
+        assert_eq!(json, "\"e4d5\"");
+        assert_eq!(back.to_uci(), capture.to_uci());
+
+        for bad in ["\"e2\"", "\"e2e4qq\"", "\"e7e8x\"", "\"z2e4\""] {
+            assert!(serde_json::from_str::<Move>(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_positions() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 3 12";
+        let state = GameState::from_fen(fen).unwrap();
+        let (json, back) = round_trip(&state);
+        assert_eq!(json, format!("\"{}\"", fen));
+        assert_eq!(back.to_fen(), fen);
+        let (json, back) = round_trip(&state.position);
+        assert_eq!(json, format!("\"{}\"", state.position.to_fen()));
+        assert_eq!(back, state.position);
+
+        let err = serde_json::from_str::<GameState>("\"8/8/8/8/8/8/8/8 w - - 0 1\"").unwrap_err();
+        assert!(err.to_string().contains("kings"));
+    }
+}
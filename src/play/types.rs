@@ -1,15 +1,18 @@
-use std::{
+use alloc::{format, string::ToString, vec::Vec};
+use core::{
     convert::TryFrom,
     fmt::{self, Write},
     ops::{Add, Range},
+    str::FromStr,
 };
 
 use crate::play::{
     board::bitboard::Bitboard,
     constants::{
-        FILES, FILE_A, IS_MAJOR_PIECE, IS_MINOR_PIECE, MAILBOX, MAILBOX_IDX, RANKS, RANK_1, SQUARES,
+        FILES, FILE_A, IS_MAJOR_PIECE, IS_MINOR_PIECE, MAILBOX, MAILBOX_IDX, PHASE_WEIGHTS, RANKS,
+        RANK_1, SQUARES, WHITE_SQUARES,
     },
-    error::{FENParsingError, InvalidCharError},
+    error::{FENParsingError, InvalidCharError, ParseTypeError},
 };
 
 use super::{
@@ -19,6 +22,15 @@ use super::{
 
 const FEN_BLANK: &str = "-";
 
+/// The one char of `s`, for types that are written as one.
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Some(ch),
+        _ => None,
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(u8)]
 pub enum Color {
@@ -63,6 +75,28 @@ impl Color {
     }
 }
 
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Color::White => f.write_str("white"),
+            Color::Black => f.write_str("black"),
+        }
+    }
+}
+
+/// `white` or `black` in any case, or FEN's `w` or `b`.
+impl FromStr for Color {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "w" | "white" => Ok(Color::White),
+            "b" | "black" => Ok(Color::Black),
+            _ => Err(ParseTypeError::new("color", s)),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(u8)]
 pub enum File {
@@ -77,9 +111,9 @@ pub enum File {
 }
 
 // TODO (tcd 12/17/22): impl IntoIterator for Rank + File to get back Squares
-impl Into<char> for &File {
-    fn into(self) -> char {
-        match self {
+impl From<&File> for char {
+    fn from(val: &File) -> Self {
+        match val {
             File::A => 'a',
             File::B => 'b',
             File::C => 'c',
@@ -99,9 +133,19 @@ impl fmt::Display for File {
     }
 }
 
-impl Into<Bitboard> for File {
-    fn into(self) -> Bitboard {
-        Bitboard(FILE_A << self as usize)
+impl FromStr for File {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        single_char(s)
+            .and_then(|ch| File::try_from(ch).ok())
+            .ok_or_else(|| ParseTypeError::new("file", s))
+    }
+}
+
+impl From<File> for Bitboard {
+    fn from(val: File) -> Self {
+        Bitboard(FILE_A << val as usize)
     }
 }
 
@@ -130,9 +174,9 @@ pub enum Rank {
     Rank8,
 }
 
-impl Into<char> for &Rank {
-    fn into(self) -> char {
-        match self {
+impl From<&Rank> for char {
+    fn from(val: &Rank) -> Self {
+        match val {
             Rank::Rank1 => '1',
             Rank::Rank2 => '2',
             Rank::Rank3 => '3',
@@ -147,14 +191,24 @@ impl Into<char> for &Rank {
 
 impl fmt::Display for Rank {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_char(self.into());
+        f.write_char(self.into())?;
         Ok(())
     }
 }
 
-impl Into<Bitboard> for Rank {
-    fn into(self) -> Bitboard {
-        Bitboard(RANK_1 << (8 * (self as usize - 1)))
+impl FromStr for Rank {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        single_char(s)
+            .and_then(|ch| Rank::try_from(ch).ok())
+            .ok_or_else(|| ParseTypeError::new("rank", s))
+    }
+}
+
+impl From<Rank> for Bitboard {
+    fn from(val: Rank) -> Self {
+        Bitboard(RANK_1 << (8 * (val as usize - 1)))
     }
 }
 
@@ -162,21 +216,15 @@ impl TryFrom<char> for Rank {
     type Error = InvalidCharError;
 
     fn try_from(value: char) -> Result<Self, Self::Error> {
-        if let Some(mut digit) = value.to_digit(10) {
-            digit -= 1;
-            if digit > 8 {
-                Err(InvalidCharError::new(value))
-            } else {
-                Ok(RANKS[digit as usize])
-            }
-        } else {
-            Err(InvalidCharError::new(value))
+        match value.to_digit(10) {
+            Some(digit @ 1..=8) => Ok(RANKS[digit as usize - 1]),
+            _ => Err(InvalidCharError::new(value)),
         }
     }
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Piece {
     WhitePawn,
     WhiteKnight,
@@ -204,14 +252,18 @@ pub enum PieceType {
 }
 
 impl Piece {
-    fn is_minor(self) -> bool {
+    pub fn is_minor(self) -> bool {
         IS_MINOR_PIECE[self as usize]
     }
 
-    fn is_major(self) -> bool {
+    pub fn is_major(self) -> bool {
         IS_MAJOR_PIECE[self as usize]
     }
 
+    pub fn phase_weight(self) -> u8 {
+        PHASE_WEIGHTS[self as usize]
+    }
+
     pub fn color(&self) -> Color {
         match self {
             Piece::WhitePawn
@@ -287,33 +339,33 @@ impl Piece {
     }
 
     pub fn can_slide(&self) -> bool {
-        match self {
+        !matches!(
+            self,
             Piece::WhitePawn
-            | Piece::BlackPawn
-            | Piece::WhiteKnight
-            | Piece::BlackKnight
-            | Piece::WhiteKing
-            | Piece::BlackKing => false,
-            _ => true,
-        }
+                | Piece::BlackPawn
+                | Piece::WhiteKnight
+                | Piece::BlackKnight
+                | Piece::WhiteKing
+                | Piece::BlackKing
+        )
     }
 }
 
-impl Into<char> for Piece {
-    fn into(self) -> char {
-        match self {
-            Self::WhitePawn => 'P',
-            Self::WhiteKnight => 'N',
-            Self::WhiteBishop => 'B',
-            Self::WhiteRook => 'R',
-            Self::WhiteQueen => 'Q',
-            Self::WhiteKing => 'K',
-            Self::BlackPawn => 'p',
-            Self::BlackKnight => 'n',
-            Self::BlackBishop => 'b',
-            Self::BlackRook => 'r',
-            Self::BlackQueen => 'q',
-            Self::BlackKing => 'k',
+impl From<Piece> for char {
+    fn from(val: Piece) -> Self {
+        match val {
+            Piece::WhitePawn => 'P',
+            Piece::WhiteKnight => 'N',
+            Piece::WhiteBishop => 'B',
+            Piece::WhiteRook => 'R',
+            Piece::WhiteQueen => 'Q',
+            Piece::WhiteKing => 'K',
+            Piece::BlackPawn => 'p',
+            Piece::BlackKnight => 'n',
+            Piece::BlackBishop => 'b',
+            Piece::BlackRook => 'r',
+            Piece::BlackQueen => 'q',
+            Piece::BlackKing => 'k',
         }
     }
 }
@@ -340,6 +392,23 @@ impl TryFrom<char> for Piece {
     }
 }
 
+/// Its FEN char, upper case for White.
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char((*self).into())
+    }
+}
+
+impl FromStr for Piece {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        single_char(s)
+            .and_then(|ch| Piece::try_from(ch).ok())
+            .ok_or_else(|| ParseTypeError::new("piece", s))
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(u8)]
 pub enum Square {
@@ -419,6 +488,22 @@ impl fmt::Display for Square {
     }
 }
 
+/// A file and rank, as in `e4`.
+impl FromStr for Square {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(f), Some(r), None) => match (File::try_from(f), Rank::try_from(r)) {
+                (Ok(f), Ok(r)) => Ok(Square::new(f, r)),
+                _ => Err(ParseTypeError::new("square", s)),
+            },
+            _ => Err(ParseTypeError::new("square", s)),
+        }
+    }
+}
+
 impl TryFrom<usize> for Square {
     type Error = SquareIndexError;
 
@@ -431,12 +516,14 @@ impl TryFrom<usize> for Square {
     }
 }
 
-impl Into<Bitboard> for Square {
-    fn into(self) -> Bitboard {
-        Bitboard(0x1 << self as usize)
+impl From<Square> for Bitboard {
+    fn from(val: Square) -> Self {
+        Bitboard(0x1 << val as usize)
     }
 }
 
+/// The raw mailbox number a step of `rhs` away, `-1` off the board. Prefer
+/// [`Square::offset`], which can't be used without the check.
 impl Add<i8> for Square {
     type Output = i8;
 
@@ -451,16 +538,15 @@ impl Square {
         SQUARES[mailbox_no as usize]
     }
 
+    /// The lowest square set in `bb`, which should have one set.
     pub fn from_bitboard(bb: Bitboard) -> Square {
-        SQUARES[bb.0 as usize]
+        SQUARES[bb.0.trailing_zeros() as usize]
     }
 
     pub fn new(f: File, r: Rank) -> Self {
         let fbb: Bitboard = f.into();
         let rbb: Bitboard = r.into();
-        let sq: Vec<Square> = (fbb & rbb).into();
-        assert_eq!(sq.len(), 1);
-        sq[0]
+        Square::from_bitboard(fbb & rbb)
     }
 
     pub fn rank(&self) -> Rank {
@@ -475,14 +561,17 @@ impl Square {
         *FILES.get(file_pos).unwrap()
     }
 
-    pub fn from_fen(fen: impl ToString) -> Result<Option<Square>, InvalidCharError> {
-        if fen.to_string() == FEN_BLANK {
+    pub fn from_fen(fen: impl ToString) -> Result<Option<Square>, FENParsingError> {
+        let fen = fen.to_string();
+        if fen == FEN_BLANK {
             return Ok(None);
         }
-        let chars: Vec<char> = fen.to_string().chars().collect();
+        let chars: Vec<char> = fen.chars().collect();
         if chars.len() != 2 {
-            // TODO (tcd 6/2/23): better error here
-            return Err(InvalidCharError::new('x'));
+            return Err(FENParsingError::new(format!(
+                "Error parsing fen: invalid square {}",
+                fen
+            )));
         }
 
         let f = File::try_from(chars[0])?;
@@ -494,6 +583,65 @@ impl Square {
     pub fn mailbox_idx(self) -> usize {
         MAILBOX_IDX[self as usize]
     }
+
+    /// The square a step in `dir` away, or `None` off the edge of the board.
+    pub fn offset(self, dir: Direction) -> Option<Square> {
+        let mailbox_no = self + dir as i8;
+        (mailbox_no >= 0).then(|| SQUARES[mailbox_no as usize])
+    }
+
+    /// The squares stepping in `dir` from this one, nearest first, out to
+    /// the edge of the board.
+    pub fn ray_iter(self, dir: Direction) -> Ray {
+        Ray {
+            next: self.offset(dir),
+            dir,
+        }
+    }
+
+    /// The color of the square itself, `White` for a light one.
+    pub fn color(self) -> Color {
+        if WHITE_SQUARES & (1 << self as u64) != 0x0 {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+
+    pub fn file_distance(self, other: Square) -> u8 {
+        (self.file() as u8).abs_diff(other.file() as u8)
+    }
+
+    pub fn rank_distance(self, other: Square) -> u8 {
+        (self.rank() as u8).abs_diff(other.rank() as u8)
+    }
+
+    /// The moves a king takes to walk from one square to the other.
+    pub fn distance(self, other: Square) -> u8 {
+        self.file_distance(other).max(self.rank_distance(other))
+    }
+
+    /// The moves a rook-stepping piece takes, one square at a time.
+    pub fn manhattan_distance(self, other: Square) -> u8 {
+        self.file_distance(other) + self.rank_distance(other)
+    }
+}
+
+/// The squares along a [`Direction`], see [`Square::ray_iter`].
+#[derive(Debug, Clone)]
+pub struct Ray {
+    next: Option<Square>,
+    dir: Direction,
+}
+
+impl Iterator for Ray {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        let sq = self.next?;
+        self.next = sq.offset(self.dir);
+        Some(sq)
+    }
 }
 
 #[repr(i8)]
@@ -563,6 +711,28 @@ impl fmt::Debug for CastlingRights {
     }
 }
 
+/// As FEN has them, `KQkq` or `-` for none.
+impl fmt::Display for CastlingRights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0x0 {
+            f.write_str(FEN_BLANK)
+        } else {
+            write!(f, "{:?}", self)
+        }
+    }
+}
+
+impl FromStr for CastlingRights {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseTypeError::new("castling rights", s));
+        }
+        CastlingRights::from_fen(s).map_err(|_| ParseTypeError::new("castling rights", s))
+    }
+}
+
 impl CastlingRights {
     pub fn from_fen(fen: impl ToString) -> Result<Self, FENParsingError> {
         let mut rights = CastlingRights(0x0);
@@ -614,7 +784,6 @@ impl CastlingRights {
 mod tests {
 
     use super::*;
-    use crate::play::utils::set_bits;
 
     #[test]
     fn test_try_from_usize_for_sq() {
@@ -625,6 +794,22 @@ mod tests {
         assert!(no_sq.is_err());
     }
 
+    #[test]
+    fn test_square_color_and_distance() {
+        assert_eq!(Square::A1.color(), Color::Black);
+        assert_eq!(Square::H1.color(), Color::White);
+        assert_eq!(Square::D1.color(), Color::White);
+        assert_eq!(Square::D8.color(), Color::Black);
+        assert_eq!(Square::H8.color(), Color::Black);
+
+        assert_eq!(Square::B1.file_distance(Square::G5), 5);
+        assert_eq!(Square::B1.rank_distance(Square::G5), 4);
+        assert_eq!(Square::B1.distance(Square::G5), 5);
+        assert_eq!(Square::B1.manhattan_distance(Square::G5), 9);
+        assert_eq!(Square::E4.distance(Square::E4), 0);
+        assert_eq!(Square::A8.distance(Square::H1), 7);
+    }
+
     #[test]
     fn test_try_from_fen_sq() {
         assert!(Square::from_fen("xx").is_err());
@@ -635,7 +820,7 @@ mod tests {
     #[test]
     fn test_file_to_bitboard() {
         let bb: Bitboard = File::B.into();
-        let expected_bit_idx: Vec<usize> = vec![
+        let expected_bit_idx: Vec<usize> = [
             Square::B1,
             Square::B2,
             Square::B3,
@@ -648,7 +833,7 @@ mod tests {
         .iter()
         .map(|sq| *sq as usize)
         .collect();
-        let set_bits = set_bits(bb.into());
+        let set_bits: Vec<usize> = bb.iter().map(|sq| sq as usize).collect();
         assert_eq!(expected_bit_idx, set_bits);
     }
 
@@ -661,7 +846,7 @@ mod tests {
     #[test]
     fn test_rank_to_bitboard() {
         let bb: Bitboard = Rank::Rank5.into();
-        let expected_bit_idx: Vec<usize> = vec![
+        let expected_bit_idx: Vec<usize> = [
             Square::A5,
             Square::B5,
             Square::C5,
@@ -674,7 +859,7 @@ mod tests {
         .iter()
         .map(|sq| *sq as usize)
         .collect();
-        let set_bits = set_bits(bb.into());
+        let set_bits: Vec<usize> = bb.iter().map(|sq| sq as usize).collect();
         assert_eq!(expected_bit_idx, set_bits);
     }
 
@@ -802,15 +987,15 @@ mod tests {
         assert_eq!(wq.0, CastlingRights::empty().0);
 
         all_rights.unset_black_bits();
-        assert_eq!(all_rights.black_kingside(), false);
-        assert_eq!(all_rights.black_queenside(), false);
-        assert_eq!(all_rights.white_queenside(), true);
-        assert_eq!(all_rights.white_kingside(), true);
+        assert!(!all_rights.black_kingside());
+        assert!(!all_rights.black_queenside());
+        assert!(all_rights.white_queenside());
+        assert!(all_rights.white_kingside());
         all_rights.unset_black_bits();
-        assert_eq!(all_rights.black_kingside(), false);
-        assert_eq!(all_rights.black_queenside(), false);
-        assert_eq!(all_rights.white_queenside(), true);
-        assert_eq!(all_rights.white_kingside(), true);
+        assert!(!all_rights.black_kingside());
+        assert!(!all_rights.black_queenside());
+        assert!(all_rights.white_queenside());
+        assert!(all_rights.white_kingside());
     }
 
     #[test]
@@ -960,4 +1145,87 @@ mod tests {
         let mailbox_no = Square::A1 + Direction::SouthWest as i8;
         assert!(mailbox_no < 0);
     }
+
+    #[test]
+    fn test_display_from_str_round_trip() {
+        for sq in SQUARES {
+            assert_eq!(sq.to_string().parse::<Square>().unwrap(), sq);
+        }
+        for file in FILES {
+            assert_eq!(file.to_string().parse::<File>().unwrap(), file);
+        }
+        for rank in RANKS {
+            assert_eq!(rank.to_string().parse::<Rank>().unwrap(), rank);
+        }
+        for color in [Color::White, Color::Black] {
+            assert_eq!(color.to_string().parse::<Color>().unwrap(), color);
+        }
+        for ch in "PNBRQKpnbrqk".chars() {
+            let piece = Piece::try_from(ch).unwrap();
+            assert_eq!(piece.to_string(), ch.to_string());
+            assert_eq!(piece.to_string().parse::<Piece>().unwrap(), piece);
+        }
+        for rights in 0..16 {
+            let rights = CastlingRights(rights);
+            assert_eq!(
+                rights.to_string().parse::<CastlingRights>().unwrap(),
+                rights
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("e4".parse::<Square>().unwrap(), Square::E4);
+        assert_eq!("W".parse::<Color>().unwrap(), Color::White);
+        assert_eq!("Black".parse::<Color>().unwrap(), Color::Black);
+        assert_eq!(CastlingRights::all().to_string(), "KQkq");
+        assert_eq!(CastlingRights::empty().to_string(), "-");
+        assert_eq!(
+            "Kq".parse::<CastlingRights>().unwrap(),
+            CastlingRights(CastlingRight::WhiteKing as u8 | CastlingRight::BlackQueen as u8)
+        );
+
+        for bad in ["", "e", "e9", "i4", "e44", "-"] {
+            assert!(bad.parse::<Square>().is_err(), "{}", bad);
+        }
+        assert!("x".parse::<Piece>().is_err());
+        assert!("Nn".parse::<Piece>().is_err());
+        assert!("9".parse::<Rank>().is_err());
+        assert!("A".parse::<File>().is_err());
+        assert!("red".parse::<Color>().is_err());
+        assert!("KX".parse::<CastlingRights>().is_err());
+        assert!("".parse::<CastlingRights>().is_err());
+        assert_eq!(
+            "z1".parse::<Square>().unwrap_err().to_string(),
+            "invalid square \"z1\""
+        );
+    }
+
+    #[test]
+    fn test_square_offset() {
+        assert_eq!(Square::B5.offset(Direction::West), Some(Square::A5));
+        assert_eq!(Square::D5.offset(Direction::NorthWestL), Some(Square::C7));
+        assert_eq!(Square::B2.offset(Direction::SouthEast), Some(Square::C1));
+        // off every edge, including the knight's two-square border
+        assert_eq!(Square::A5.offset(Direction::West), None);
+        assert_eq!(Square::H6.offset(Direction::East), None);
+        assert_eq!(Square::D8.offset(Direction::North), None);
+        assert_eq!(Square::D1.offset(Direction::South), None);
+        assert_eq!(Square::A1.offset(Direction::SouthWestL), None);
+        assert_eq!(Square::G6.offset(Direction::EastNorthL), None);
+    }
+
+    #[test]
+    fn test_ray_iter() {
+        let ray: Vec<Square> = Square::C3.ray_iter(Direction::NorthEast).collect();
+        assert_eq!(
+            ray,
+            vec![Square::D4, Square::E5, Square::F6, Square::G7, Square::H8]
+        );
+        assert_eq!(Square::A4.ray_iter(Direction::East).count(), 7);
+        assert_eq!(Square::E1.ray_iter(Direction::South).next(), None);
+        let hops: Vec<Square> = Square::B1.ray_iter(Direction::NorthEastL).collect();
+        assert_eq!(hops, vec![Square::C3, Square::D5, Square::E7]);
+    }
 }
@@ -0,0 +1,447 @@
+use super::{
+    board::{bitboard::Bitboard, Board},
+    constants::DIRECTIONS,
+    position::Position,
+    types::{Color, Piece, PieceType, Square},
+};
+
+/// The rule set a game is played under. Move generation and the search ask
+/// it whether a position has been decided before looking for moves, so a
+/// variant that ends the game some other way than checkmate slots in here.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// a king reaching one of the four centre squares wins on the spot
+    KingOfTheHill,
+    /// captured pieces go to the capturer's hand, to be dropped back onto
+    /// the board as a move. Only drops and hands are in place so far: a
+    /// captured promoted piece isn't turned back into a pawn and nothing
+    /// evaluates the pieces in hand, so it isn't offered over UCI yet.
+    Crazyhouse,
+    /// a capture blows up the capturer and every piece but a pawn next to
+    /// the square it lands on, kings can't capture, and blowing up the
+    /// enemy king wins
+    Atomic,
+    /// captures are forced, the king is a piece like any other and there's
+    /// no castling, and a side wins by losing all its pieces or by having
+    /// no move
+    Antichess,
+}
+
+/// Every variant that's playable, in the order UCI lists them.
+pub const VARIANTS: [Variant; 4] = [
+    Variant::Standard,
+    Variant::KingOfTheHill,
+    Variant::Atomic,
+    Variant::Antichess,
+];
+
+/// d4, e4, d5 and e5.
+const HILL: Bitboard = Bitboard(0x0000_0018_1800_0000);
+
+impl Variant {
+    /// The name UCI's `UCI_Variant` option uses, as lichess and most GUIs
+    /// spell it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Standard => "chess",
+            Variant::KingOfTheHill => "kingofthehill",
+            Variant::Crazyhouse => "crazyhouse",
+            Variant::Atomic => "atomic",
+            Variant::Antichess => "antichess",
+        }
+    }
+
+    /// The name the PGN `Variant` tag gives it.
+    pub fn pgn_name(self) -> &'static str {
+        match self {
+            Variant::Standard => "Standard",
+            Variant::KingOfTheHill => "King of the Hill",
+            Variant::Crazyhouse => "Crazyhouse",
+            Variant::Atomic => "Atomic",
+            Variant::Antichess => "Antichess",
+        }
+    }
+
+    /// The variant called `name`, by its UCI name or its PGN one, in any
+    /// case.
+    pub fn from_name(name: &str) -> Option<Variant> {
+        VARIANTS.iter().copied().find(|variant| {
+            name.eq_ignore_ascii_case(variant.name())
+                || name.eq_ignore_ascii_case(variant.pgn_name())
+        })
+    }
+
+    /// Whether captured pieces are kept in hand and can be dropped.
+    pub fn has_hands(self) -> bool {
+        self == Variant::Crazyhouse
+    }
+
+    /// Whether captures explode.
+    pub fn explodes(self) -> bool {
+        self == Variant::Atomic
+    }
+
+    /// Whether the king has to be kept out of check, and may castle.
+    pub fn royal_king(self) -> bool {
+        self != Variant::Antichess
+    }
+
+    /// Whether a side that can capture has to.
+    pub fn forces_captures(self) -> bool {
+        self == Variant::Antichess
+    }
+
+    /// Whether `color`'s king is safe on `board`, as a move has to leave
+    /// it. In atomic the king also has to have survived, and is safe
+    /// whatever attacks it once the enemy king is gone or stands next to
+    /// it, since taking it would blow up the taker's own king. A king that
+    /// isn't royal is always safe.
+    pub fn king_safe(self, board: &Board, color: Color) -> bool {
+        if !self.royal_king() {
+            return true;
+        }
+        if self.explodes() {
+            let king = |color| board.bitboard(Piece::of(PieceType::King, color)).0 != 0x0;
+            if !king(color) {
+                return false;
+            }
+            if !king(color.opposing()) || kings_adjacent(board) {
+                return true;
+            }
+        }
+        !board.is_king_in_check(color)
+    }
+
+    /// The winner, if the variant's own rules have already ended the game
+    /// at `position`. Checkmate and stalemate are left to the caller, who
+    /// finds them by running out of moves.
+    pub fn winner(self, position: &Position) -> Option<Color> {
+        match self {
+            Variant::Standard | Variant::Crazyhouse => None,
+            // the side whose king is still on the board, the other's having
+            // been blown up
+            Variant::Atomic => [Color::White, Color::Black].iter().copied().find(|&color| {
+                let king = position
+                    .board
+                    .bitboard(Piece::of(PieceType::King, color.opposing()));
+                king.0 == 0x0
+            }),
+            Variant::KingOfTheHill => [Color::White, Color::Black].iter().copied().find(|&color| {
+                let king = position.board.bitboard(Piece::of(PieceType::King, color));
+                (king & HILL).0 != 0x0
+            }),
+            // the side that's given everything away
+            Variant::Antichess => [Color::White, Color::Black]
+                .iter()
+                .copied()
+                .find(|&color| position.board.occupied_by(color).0 == 0x0),
+        }
+    }
+
+    /// The winner when `stalemated`, to move, has no move and isn't in
+    /// check: nobody, it being a draw, except in antichess where running
+    /// out of moves wins.
+    pub fn stalemate_winner(self, stalemated: Color) -> Option<Color> {
+        match self {
+            Variant::Antichess => Some(stalemated),
+            _ => None,
+        }
+    }
+}
+
+/// `sq` and the eight squares around it.
+pub fn blast_radius(sq: Square) -> Bitboard {
+    DIRECTIONS[Piece::WhiteKing.attack_direction_idx()]
+        .iter()
+        .filter_map(|dir| sq.offset(*dir))
+        .fold(Bitboard::from(sq), |blast, sq| blast | sq.into())
+}
+
+/// The squares of the pieces an atomic capture landing on `to` blows up,
+/// the captured piece having already gone: the capturer on `to` and every
+/// piece around it but the pawns.
+pub fn exploded(board: &Board, to: Square) -> Bitboard {
+    let pawns = board.bitboard(Piece::WhitePawn) | board.bitboard(Piece::BlackPawn);
+    (blast_radius(to) & board.occupied() & pawns.flip()) | to.into()
+}
+
+/// Whether the two kings stand side by side.
+pub fn kings_adjacent(board: &Board) -> bool {
+    let white = board.bitboard(Piece::WhiteKing);
+    let black = board.bitboard(Piece::BlackKing);
+    white.0 != 0x0
+        && black.0 != 0x0
+        && (blast_radius(Square::from_bitboard(white)) & black).0 != 0x0
+}
+
+/// The pieces a side holds off the board, ready to drop, counted by type
+/// from pawns to queens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hand(pub [u8; 5]);
+
+/// The piece types a hand can hold.
+pub const HAND_PIECE_TYPES: [PieceType; 5] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+];
+
+impl Hand {
+    /// How many pieces of `piece_type` are held, none for kings.
+    pub fn count(&self, piece_type: PieceType) -> u8 {
+        self.0.get(piece_type as usize).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, piece_type: PieceType) {
+        if let Some(count) = self.0.get_mut(piece_type as usize) {
+            *count += 1;
+        }
+    }
+
+    /// Takes a piece of `piece_type` out, or says there was none to take.
+    pub fn remove(&mut self, piece_type: PieceType) -> bool {
+        match self.0.get_mut(piece_type as usize) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&count| count == 0)
+    }
+
+    /// The types there's at least one of.
+    pub fn piece_types(&self) -> impl Iterator<Item = PieceType> + '_ {
+        HAND_PIECE_TYPES
+            .iter()
+            .copied()
+            .filter(move |&piece_type| self.count(piece_type) > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::{
+        play,
+        r#move::{make_move, unmake_move, Move},
+        types::{CastlingRights, Rank},
+        GameState,
+    };
+
+    #[test]
+    fn test_names() {
+        for variant in VARIANTS {
+            assert_eq!(Variant::from_name(variant.name()), Some(variant));
+        }
+        assert_eq!(
+            Variant::from_name("King of the Hill"),
+            Some(Variant::KingOfTheHill)
+        );
+        assert_eq!(Variant::from_name("standard"), Some(Variant::Standard));
+        assert_eq!(Variant::from_name("bughouse"), None);
+    }
+
+    #[test]
+    fn test_king_of_the_hill() {
+        let fen = "4k3/8/8/8/8/3K4/8/8 w - - 0 1";
+        let mut state = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::KingOfTheHill);
+        assert_eq!(state.position.winner(), None);
+        assert_eq!(state.position.count_legal_moves(), 8);
+
+        let climb = state.position.parse_move("d3d4").unwrap();
+        make_move(climb, &mut state).unwrap();
+        assert_eq!(state.position.winner(), Some(Color::White));
+        // the game is over, so Black has nothing to play
+        assert!(!state.position.has_legal_move());
+
+        let standard = GameState::from_fen("4k3/8/8/8/3K4/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(standard.position.winner(), None);
+        assert!(standard.position.has_legal_move());
+    }
+
+    #[test]
+    fn test_crazyhouse_hands() {
+        let fen = "4k3/8/8/3p4/4P3/8/8/4K3[Nn] w - - 0 1";
+        let mut state = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::Crazyhouse);
+        assert_eq!(
+            state.position.hands[Color::White as usize].count(PieceType::Knight),
+            1
+        );
+        assert_eq!(state.to_fen(), fen);
+
+        let before = state.clone();
+        let capture = state.position.parse_move("e4d5").unwrap();
+        make_move(capture, &mut state).unwrap();
+        assert_eq!(
+            state.position.hands[Color::White as usize].count(PieceType::Pawn),
+            1
+        );
+        assert!(state
+            .position
+            .to_fen()
+            .starts_with("4k3/8/8/3P4/8/8/8/4K3[NPn] b"));
+
+        // Black can drop its knight on any of the empty squares
+        let drops = state
+            .position
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.is_drop())
+            .count();
+        assert_eq!(drops, 64 - 3);
+        let drop = state.position.parse_move("N@c3").unwrap();
+        make_move(drop, &mut state).unwrap();
+        assert!(state.position.hands[Color::Black as usize].is_empty());
+        assert_eq!(state.to_fen(), "4k3/8/8/3P4/8/2n5/8/4K3[NP] w - - 0 2");
+        // pawns can't be dropped on the back ranks
+        assert!(state
+            .position
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.dropped() == Some(Piece::WhitePawn))
+            .all(|mv| !matches!(mv.to_sq().rank(), Rank::Rank1 | Rank::Rank8)));
+
+        unmake_move(drop, &mut state).unwrap();
+        unmake_move(capture, &mut state).unwrap();
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn test_crazyhouse_drops_in_check() {
+        // the rook checks down the e-file, so the knight has to block it
+        let fen = "4r1k1/8/8/8/8/8/8/4K3[N] w - - 0 1";
+        let state = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::Crazyhouse);
+        let drops: Vec<String> = state
+            .position
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.is_drop())
+            .map(|mv| mv.to_uci())
+            .collect();
+        assert_eq!(drops.len(), 6);
+        assert!(drops.iter().all(|drop| drop.starts_with("N@e")));
+
+        let blocked = Move::from_san(&state.position, "N@e4").unwrap();
+        assert_eq!(blocked.to_uci(), "N@e4");
+        assert!(Move::from_san(&state.position, "N@a4").is_err());
+
+        // the pocket needs a closing bracket and can't hold a king
+        assert!(GameState::from_fen("4k3/8/8/8/8/8/8/4K3[N w - - 0 1").is_err());
+        assert!(GameState::from_fen("4k3/8/8/8/8/8/8/4K3[K] w - - 0 1").is_err());
+    }
+
+    fn is_legal(state: &GameState, uci: &str) -> bool {
+        state.position.parse_move(uci).is_some()
+    }
+
+    #[test]
+    fn test_atomic_explosion() {
+        let fen = "4k3/8/2rp4/3b4/8/4N3/8/R3K3 w Q - 0 1";
+        let mut state = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::Atomic);
+        let before = state.clone();
+        let capture = play(&mut state, &["e3d5"]);
+        // the knight and the rook beside it go up, the pawn survives
+        assert_eq!(state.to_fen(), "4k3/8/3p4/8/8/8/8/R3K3 b Q - 0 1");
+        assert_eq!(state.position.winner(), None);
+        assert_eq!(state.explosion_history.len(), 1);
+        unmake_move(capture, &mut state).unwrap();
+        assert_eq!(state, before);
+
+        // a rook blown up in its corner takes its castling right with it
+        let fen = "4k3/8/8/8/2n5/8/1P6/R3K3 b Q - 0 1";
+        let mut state = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::Atomic);
+        play(&mut state, &["c4b2"]);
+        assert_eq!(state.to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 2");
+    }
+
+    #[test]
+    fn test_atomic_kings() {
+        // the king can't take the pawn checking it
+        let fen = "4k3/8/8/8/8/8/3p4/4K3 w - - 0 1";
+        let atomic = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::Atomic);
+        assert!(atomic.position.in_check());
+        assert!(!is_legal(&atomic, "e1d2"));
+        assert!(is_legal(&GameState::from_fen(fen).unwrap(), "e1d2"));
+
+        // kings side by side can't check each other, nor be checked
+        let fen = "4r3/8/8/8/8/3k4/8/5K2 w - - 0 1";
+        let mut atomic = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::Atomic);
+        assert!(is_legal(&atomic, "f1e2"));
+        assert!(!is_legal(&atomic, "f1e1"));
+        assert!(!is_legal(&GameState::from_fen(fen).unwrap(), "f1e2"));
+        play(&mut atomic, &["f1e2"]);
+        assert!(!atomic.position.in_check());
+
+        // taking next to the enemy king blows it up and wins, whatever
+        // that exposes
+        let fen = "4k3/4q3/8/8/8/8/8/4RK2 w - - 0 1";
+        let mut state = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::Atomic);
+        let win = play(&mut state, &["e1e7"]);
+        assert_eq!(state.position.winner(), Some(Color::White));
+        assert!(!state.position.has_legal_move());
+        unmake_move(win, &mut state).unwrap();
+        assert!(state.position.gives_mate(win));
+        assert_eq!(win.to_san(&state.position), "Rxe7#");
+    }
+
+    #[test]
+    fn test_antichess() {
+        let mut state = GameState::default().with_variant(Variant::Antichess);
+        assert_eq!(state.position.castling_permissions, CastlingRights::empty());
+        play(&mut state, &["e2e3"]);
+        play(&mut state, &["b7b5"]);
+        // the bishop has to take
+        let moves: Vec<String> = state
+            .position
+            .legal_moves()
+            .into_iter()
+            .map(|mv| mv.to_uci())
+            .collect();
+        assert_eq!(moves, ["f1b5"]);
+
+        // the king walks into the rook's line, and has to be taken
+        let fen = "4k3/8/8/8/8/8/8/2r1K3 w - - 0 1";
+        let mut state = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::Antichess);
+        assert!(!state.position.in_check());
+        assert_eq!(state.position.count_legal_moves(), 5);
+        play(&mut state, &["e1d1"]);
+        assert_eq!(state.position.count_legal_moves(), 1);
+        play(&mut state, &["c1d1"]);
+        // with nothing left to give away White has won
+        assert_eq!(state.position.winner(), Some(Color::White));
+        assert!(!state.position.has_legal_move());
+
+        assert_eq!(
+            Variant::Antichess.stalemate_winner(Color::Black),
+            Some(Color::Black)
+        );
+        assert_eq!(Variant::Standard.stalemate_winner(Color::Black), None);
+    }
+}
@@ -0,0 +1,436 @@
+//! The `lasker` Python module, modelled on python-chess: a `Board` to push
+//! and pop moves on, the `Move`s it takes, and an `Engine` to analyse it.
+//!
+//! ```python
+//! import lasker
+//!
+//! board = lasker.Board()
+//! board.push_uci("e2e4")
+//! info = lasker.Engine().analyse(board, depth=8)
+//! print(info["score"], [mv.uci() for mv in info["pv"]])
+//! ```
+
+use std::{
+    hash::{Hash, Hasher},
+    sync::Arc,
+    time::Duration,
+};
+
+use pyo3::{
+    exceptions::{PyIndexError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+
+use crate::{
+    perft::perft,
+    play::{
+        draw::DrawClaim,
+        r#move::{make_move, unmake_move, Move},
+        types::Color,
+        GameState, START_FEN,
+    },
+    search::{
+        mate_moves, search,
+        time::{Budget, NodeBudget, SearchControl},
+        tt::TranspositionTable,
+        SearchConfig, SearchResult, MAX_PLY, MAX_THREADS,
+    },
+    Error,
+};
+
+impl From<Error> for PyErr {
+    fn from(err: Error) -> Self {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+/// A move in coordinate notation. Two moves are equal when their notation
+/// is, so one read with `Move.from_uci` matches the board's own.
+#[pyclass(name = "Move", module = "lasker", frozen, eq, hash)]
+#[derive(Debug, Clone, Copy)]
+struct PyMove(Move);
+
+impl PartialEq for PyMove {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_uci() == other.0.to_uci()
+    }
+}
+
+impl Hash for PyMove {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_uci().hash(state)
+    }
+}
+
+#[pymethods]
+impl PyMove {
+    /// A move from its squares and any promotion alone, to be played with
+    /// `Board.push`.
+    #[staticmethod]
+    fn from_uci(uci: &str) -> PyResult<Self> {
+        Move::from_uci(uci)
+            .map(PyMove)
+            .ok_or_else(|| PyValueError::new_err(format!("invalid uci: {:?}", uci)))
+    }
+
+    fn uci(&self) -> String {
+        self.0.to_uci()
+    }
+
+    /// The square moved from, 0 for a1 through 63 for h8.
+    #[getter(from_square)]
+    fn origin(&self) -> u8 {
+        self.0.from_sq() as u8
+    }
+
+    #[getter(to_square)]
+    fn destination(&self) -> u8 {
+        self.0.to_sq() as u8
+    }
+
+    /// The letter of the piece promoted to, in lower case, if any.
+    #[getter]
+    fn promotion(&self) -> Option<char> {
+        self.0
+            .promoted()
+            .map(|piece| char::from(piece).to_ascii_lowercase())
+    }
+
+    fn __str__(&self) -> String {
+        self.uci()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Move.from_uci({:?})", self.uci())
+    }
+}
+
+/// A game in progress, from the position it was set up in, with the moves
+/// pushed on it since.
+#[pyclass(name = "Board", module = "lasker")]
+#[derive(Debug, Clone)]
+struct PyBoard {
+    state: GameState,
+    stack: Vec<Move>,
+}
+
+#[pymethods]
+impl PyBoard {
+    #[new]
+    #[pyo3(signature = (fen = None))]
+    fn new(fen: Option<&str>) -> PyResult<Self> {
+        let state = GameState::from_fen(fen.unwrap_or(START_FEN)).map_err(Error::from)?;
+        Ok(PyBoard {
+            state,
+            stack: vec![],
+        })
+    }
+
+    fn fen(&self) -> String {
+        self.state.to_fen()
+    }
+
+    /// `True` when it's White to move, as in python-chess.
+    #[getter]
+    fn turn(&self) -> bool {
+        self.state.position.side_to_move == Color::White
+    }
+
+    #[getter]
+    fn legal_moves(&self) -> Vec<PyMove> {
+        self.state
+            .position
+            .legal_moves()
+            .into_iter()
+            .map(PyMove)
+            .collect()
+    }
+
+    #[getter]
+    fn move_stack(&self) -> Vec<PyMove> {
+        self.stack.iter().copied().map(PyMove).collect()
+    }
+
+    /// Plays `mv`, which must be legal here.
+    fn push(&mut self, mv: PyMove) -> PyResult<()> {
+        let mv = self.legal_move(mv)?;
+        make_move(mv, &mut self.state).map_err(Error::from)?;
+        self.stack.push(mv);
+        Ok(())
+    }
+
+    fn push_uci(&mut self, uci: &str) -> PyResult<PyMove> {
+        let mv = PyMove::from_uci(uci)?;
+        self.push(mv)?;
+        self.peek()
+    }
+
+    /// Takes back the last move pushed, and returns it.
+    fn pop(&mut self) -> PyResult<PyMove> {
+        let mv = self
+            .stack
+            .pop()
+            .ok_or_else(|| PyIndexError::new_err("pop from empty move stack"))?;
+        unmake_move(mv, &mut self.state).map_err(Error::from)?;
+        Ok(PyMove(mv))
+    }
+
+    fn peek(&self) -> PyResult<PyMove> {
+        self.stack
+            .last()
+            .copied()
+            .map(PyMove)
+            .ok_or_else(|| PyIndexError::new_err("peek at empty move stack"))
+    }
+
+    fn is_check(&self) -> bool {
+        self.state.position.in_check()
+    }
+
+    fn is_checkmate(&self) -> bool {
+        self.is_check() && !self.state.position.has_legal_move()
+    }
+
+    fn is_stalemate(&self) -> bool {
+        !self.is_check() && !self.state.position.has_legal_move()
+    }
+
+    /// Whether the game's over by mate, stalemate, threefold repetition or
+    /// the fifty-move rule.
+    fn is_game_over(&self) -> bool {
+        !self.state.position.has_legal_move()
+            || self.state.is_threefold_repetition()
+            || self.state.is_fifty_move_draw()
+    }
+
+    /// Whether a draw can be claimed by threefold repetition or the
+    /// fifty-move rule, as things stand or with some move about to be played.
+    fn can_claim_draw(&self) -> bool {
+        let moves = self.state.position.legal_moves();
+        [DrawClaim::ThreefoldRepetition, DrawClaim::FiftyMoves]
+            .iter()
+            .any(|&claim| {
+                std::iter::once(None)
+                    .chain(moves.iter().copied().map(Some))
+                    .any(|mv| self.state.clone().claim_draw(claim, mv).is_ok())
+            })
+    }
+
+    /// Claims a draw by `"threefold"` repetition or the `"fifty"`-move rule,
+    /// as things stand or once `move` is played, which it then is. Raises
+    /// `ValueError` if the claim is wrong, leaving the board as it was.
+    #[pyo3(signature = (claim, r#move = None))]
+    fn claim_draw(&mut self, claim: &str, r#move: Option<PyMove>) -> PyResult<()> {
+        let claim = match claim {
+            "threefold" => DrawClaim::ThreefoldRepetition,
+            "fifty" => DrawClaim::FiftyMoves,
+            claim => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown draw claim {}, expected threefold or fifty",
+                    claim
+                )))
+            }
+        };
+        let mv = r#move.map(|mv| self.legal_move(mv)).transpose()?;
+        self.state.claim_draw(claim, mv).map_err(Error::from)?;
+        self.stack.extend(mv);
+        Ok(())
+    }
+
+    /// Counts the leaf nodes of the move tree `depth` plies deep.
+    fn perft(&self, py: Python<'_>, depth: u64) -> u64 {
+        let mut state = self.state.clone();
+        py.allow_threads(|| perft(&mut state, depth))
+    }
+
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+
+    fn __str__(&self) -> String {
+        format!("{:?}", self.state.position.board)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Board({:?})", self.fen())
+    }
+}
+
+impl PyBoard {
+    /// The legal move here `mv` is the notation of.
+    fn legal_move(&self, mv: PyMove) -> PyResult<Move> {
+        let uci = mv.0.to_uci();
+        self.state
+            .position
+            .parse_move(&uci)
+            .ok_or_else(|| PyValueError::new_err(format!("illegal move {} in {}", uci, self.fen())))
+    }
+}
+
+/// The search, with a transposition table kept from one search to the next.
+#[pyclass(name = "Engine", module = "lasker")]
+struct PyEngine {
+    tt: TranspositionTable,
+    config: SearchConfig,
+}
+
+impl PyEngine {
+    fn run(
+        &self,
+        py: Python<'_>,
+        board: &PyBoard,
+        depth: Option<u8>,
+        nodes: Option<u64>,
+        time: Option<f64>,
+    ) -> PyResult<SearchResult> {
+        if depth.is_none() && nodes.is_none() && time.is_none() {
+            return Err(PyValueError::new_err("needs a depth, nodes or time limit"));
+        }
+        let budget = match time.map(Duration::try_from_secs_f64) {
+            Some(Ok(time)) => Some(Budget {
+                soft: time,
+                hard: time,
+            }),
+            Some(Err(e)) => return Err(PyValueError::new_err(e.to_string())),
+            None => None,
+        };
+        let control = SearchControl::new(budget, false)
+            .with_node_budget(nodes.map(|n| NodeBudget { soft: n, hard: n }));
+        let depth = depth.unwrap_or(MAX_PLY).min(MAX_PLY);
+        let (state, config, tt) = (&board.state, &self.config, &self.tt);
+        Ok(py.allow_threads(|| search(state, depth, config, Arc::new(control), tt, &mut ())))
+    }
+}
+
+#[pymethods]
+impl PyEngine {
+    #[new]
+    #[pyo3(signature = (hash_mb = TranspositionTable::default_size_mb(), threads = 1))]
+    fn new(hash_mb: usize, threads: usize) -> PyResult<Self> {
+        let tt = TranspositionTable::try_with_size(hash_mb).map_err(|e| {
+            PyValueError::new_err(format!("couldn't allocate {} MB: {}", hash_mb, e))
+        })?;
+        let config = SearchConfig {
+            helper_threads: threads.clamp(1, MAX_THREADS) - 1,
+            ..SearchConfig::default()
+        };
+        Ok(PyEngine { tt, config })
+    }
+
+    /// Searches `board` to the limits given, returning a dict of the `depth`
+    /// reached, the `score` in centipawns for the side to move, or `mate` in
+    /// moves when it's found one, the `nodes` searched and the `pv`.
+    #[pyo3(signature = (board, depth = None, nodes = None, time = None))]
+    fn analyse<'py>(
+        &self,
+        py: Python<'py>,
+        board: &PyBoard,
+        depth: Option<u8>,
+        nodes: Option<u64>,
+        time: Option<f64>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let result = self.run(py, board, depth, nodes, time)?;
+        let mate = mate_moves(result.score);
+        let info = PyDict::new(py);
+        info.set_item("depth", result.depth)?;
+        info.set_item("score", mate.is_none().then_some(result.score))?;
+        info.set_item("mate", mate)?;
+        info.set_item("nodes", result.stats.nodes())?;
+        let pv: Vec<PyMove> = result.pv.iter().copied().map(PyMove).collect();
+        info.set_item("pv", pv)?;
+        Ok(info)
+    }
+
+    /// The move the search would play, or `None` if the game's over.
+    #[pyo3(signature = (board, depth = None, nodes = None, time = None))]
+    fn play(
+        &self,
+        py: Python<'_>,
+        board: &PyBoard,
+        depth: Option<u8>,
+        nodes: Option<u64>,
+        time: Option<f64>,
+    ) -> PyResult<Option<PyMove>> {
+        let result = self.run(py, board, depth, nodes, time)?;
+        Ok(result.best_move.map(PyMove))
+    }
+
+    /// Forgets what earlier searches learned, as between games.
+    fn clear(&mut self) {
+        self.tt.clear();
+    }
+}
+
+#[pymodule]
+fn lasker(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyBoard>()?;
+    m.add_class::<PyMove>()?;
+    m.add_class::<PyEngine>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::types::Square;
+
+    #[test]
+    fn test_board() {
+        let mut board = PyBoard::new(None).unwrap();
+        assert!(board.turn());
+        assert_eq!(board.legal_moves().len(), 20);
+        let e4 = board.push_uci("e2e4").unwrap();
+        assert_eq!(e4, PyMove::from_uci("e2e4").unwrap());
+        assert!(!board.turn());
+        assert!(board.push_uci("e2e4").is_err());
+        assert!(PyMove::from_uci("e2").is_err());
+        for uci in ["e7e5", "f1c4", "b8c6", "d1h5", "g8f6", "h5f7"] {
+            board.push_uci(uci).unwrap();
+        }
+        assert!(board.is_checkmate() && board.is_game_over());
+        assert_eq!(board.pop().unwrap().uci(), "h5f7");
+        assert!(!board.is_game_over());
+        while board.pop().is_ok() {}
+        assert_eq!(board.fen(), START_FEN);
+
+        let promotion = PyMove::from_uci("b2a1n").unwrap();
+        assert_eq!(promotion.promotion(), Some('n'));
+        assert_eq!(promotion.destination(), Square::A1 as u8);
+    }
+
+    #[test]
+    fn test_claim_draw() {
+        let mut board = PyBoard::new(None).unwrap();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"] {
+            board.push_uci(uci).unwrap();
+        }
+        // the position comes round a third time with f6g8
+        assert!(board.can_claim_draw());
+        assert!(board.claim_draw("threefold", None).is_err());
+        assert!(board.claim_draw("fifty", None).is_err());
+        assert!(board.claim_draw("agreed", None).is_err());
+        let f6g8 = PyMove::from_uci("f6g8").unwrap();
+        board.claim_draw("threefold", Some(f6g8)).unwrap();
+        assert_eq!(board.peek().unwrap(), f6g8);
+        assert!(board.claim_draw("threefold", None).is_ok());
+
+        assert!(!PyBoard::new(None).unwrap().can_claim_draw());
+    }
+
+    #[test]
+    fn test_engine() {
+        let board = PyBoard::new(Some("k7/8/2K5/8/8/8/8/7R w - - 0 1")).unwrap();
+        let engine = PyEngine::new(1, 1).unwrap();
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let info = engine.analyse(py, &board, Some(3), None, None).unwrap();
+            let mate: Option<i32> = info.get_item("mate").unwrap().unwrap().extract().unwrap();
+            assert_eq!(mate, Some(2));
+            assert!(engine.analyse(py, &board, None, None, None).is_err());
+            let mv = engine.play(py, &board, None, Some(1000), None).unwrap();
+            assert!(mv.is_some());
+        });
+    }
+}
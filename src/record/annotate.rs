@@ -0,0 +1,362 @@
+use std::{
+    fs,
+    io::{self, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    play::{
+        error::MoveError,
+        r#move::{make_move, Move},
+        types::Color,
+        GameState,
+    },
+    search::{
+        search,
+        time::{Budget, SearchControl},
+        tt::TranspositionTable,
+        SearchConfig, MAX_PLY,
+    },
+    Error,
+};
+
+use super::{
+    eval_command,
+    pgn::{parse_pgn, PgnError, PgnGame},
+};
+
+const DEFAULT_DEPTH: u8 = 10;
+const ANNOTATE_TT_MB: usize = 16;
+/// Scores are capped at this many centipawns either way before a move's
+/// loss is worked out, so a mate missed for a won ending isn't a bigger
+/// mistake than a won position thrown away for a lost one.
+const SCORE_CAP: i32 = 1000;
+/// The numeric annotation glyphs for an inaccuracy, a mistake and a
+/// blunder, `?!`, `?` and `??`.
+pub const NAG_INACCURACY: u8 = 6;
+pub const NAG_MISTAKE: u8 = 2;
+pub const NAG_BLUNDER: u8 = 4;
+
+/// How bad a move was, by the centipawns it lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    pub fn nag(self) -> u8 {
+        match self {
+            Judgement::Inaccuracy => NAG_INACCURACY,
+            Judgement::Mistake => NAG_MISTAKE,
+            Judgement::Blunder => NAG_BLUNDER,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "inaccuracy",
+            Judgement::Mistake => "mistake",
+            Judgement::Blunder => "blunder",
+        }
+    }
+}
+
+/// How hard each position is searched, and how many centipawns a played
+/// move has to lose against the engine's to be marked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnnotateConfig {
+    pub depth: u8,
+    /// a time to search each position for, with `depth` as a limit still
+    pub movetime: Option<Duration>,
+    pub inaccuracy: i32,
+    pub mistake: i32,
+    pub blunder: i32,
+}
+
+impl Default for AnnotateConfig {
+    fn default() -> Self {
+        AnnotateConfig {
+            depth: DEFAULT_DEPTH,
+            movetime: None,
+            inaccuracy: 50,
+            mistake: 100,
+            blunder: 300,
+        }
+    }
+}
+
+impl AnnotateConfig {
+    /// What a move losing `loss` centipawns counts as, if anything.
+    pub fn judge(&self, loss: i32) -> Option<Judgement> {
+        if loss >= self.blunder {
+            Some(Judgement::Blunder)
+        } else if loss >= self.mistake {
+            Some(Judgement::Mistake)
+        } else if loss >= self.inaccuracy {
+            Some(Judgement::Inaccuracy)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the search or a threshold from a command line flag, `false` if
+    /// the flag isn't one of them.
+    pub(super) fn set_option(&mut self, flag: &str, value: &str) -> Result<bool, String> {
+        let bad = || format!("bad value {} for {}", value, flag);
+        match flag {
+            "--depth" => self.depth = value.parse().map_err(|_| bad())?,
+            "--movetime" => {
+                let ms = value.parse().map_err(|_| bad())?;
+                self.movetime = Some(Duration::from_millis(ms));
+                // the time limits the search from here on, unless a depth
+                // comes after it
+                self.depth = MAX_PLY;
+            }
+            "--inaccuracy" => self.inaccuracy = value.parse().map_err(|_| bad())?,
+            "--mistake" => self.mistake = value.parse().map_err(|_| bad())?,
+            "--blunder" => self.blunder = value.parse().map_err(|_| bad())?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// The NAG a move losing `loss` centipawns earns, if any.
+    pub fn nag(&self, loss: i32) -> Option<u8> {
+        self.judge(loss).map(Judgement::nag)
+    }
+}
+
+/// A played move against the one the engine would have played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveReview {
+    pub mv: Move,
+    pub mover: Color,
+    pub best_move: Option<Move>,
+    /// the mover's score before the move, as the engine would go on
+    pub best_score: i32,
+    /// the mover's score once the move's been played
+    pub played_score: i32,
+}
+
+impl MoveReview {
+    /// The centipawns the move gave away, none for the engine's own move.
+    pub fn loss(&self) -> i32 {
+        // the search's move carries its ordering score, so only the moves
+        // themselves are compared
+        let played = (self.mv.from_sq(), self.mv.to_sq(), self.mv.promoted());
+        if self
+            .best_move
+            .is_some_and(|mv| (mv.from_sq(), mv.to_sq(), mv.promoted()) == played)
+        {
+            return 0;
+        }
+        let cap = |score: i32| score.clamp(-SCORE_CAP, SCORE_CAP);
+        (cap(self.best_score) - cap(self.played_score)).max(0)
+    }
+}
+
+/// Searches every position of the game played by `moves` from `start`, the
+/// one after the last move included, and compares each move with the
+/// engine's choice. A move's score is the next position's, turned round.
+pub fn review_moves(
+    start: &GameState,
+    moves: &[Move],
+    config: &AnnotateConfig,
+    tt: &TranspositionTable,
+) -> Result<Vec<MoveReview>, MoveError> {
+    let mut state = start.clone();
+    let mut searched = vec![analyse(&state, config, tt)];
+    for &mv in moves {
+        make_move(mv, &mut state)?;
+        searched.push(analyse(&state, config, tt));
+    }
+    let mut mover = start.position.side_to_move;
+    let mut reviews = vec![];
+    for (i, &mv) in moves.iter().enumerate() {
+        let (best_move, best_score) = searched[i];
+        reviews.push(MoveReview {
+            mv,
+            mover,
+            best_move,
+            best_score,
+            played_score: -searched[i + 1].1,
+        });
+        mover = mover.opposing();
+    }
+    Ok(reviews)
+}
+
+/// The engine's move at `state` and its score for the side to move.
+fn analyse(
+    state: &GameState,
+    config: &AnnotateConfig,
+    tt: &TranspositionTable,
+) -> (Option<Move>, i32) {
+    let budget = config.movetime.map(|time| Budget {
+        soft: time,
+        hard: time,
+    });
+    let control = Arc::new(SearchControl::new(budget, false));
+    let depth = config.depth.min(MAX_PLY);
+    let result = search(state, depth, &SearchConfig::default(), control, tt, &mut ());
+    (result.best_move, result.score)
+}
+
+/// The position `game` starts from and the moves of its main line.
+pub(super) fn main_line(game: &PgnGame) -> Result<(GameState, Vec<Move>), PgnError> {
+    let start = GameState::from_fen(game.start_fen())?;
+    let mut state = start.clone();
+    let mut moves = vec![];
+    for (index, pgn_move) in game.moves.iter().enumerate() {
+        let mv = Move::from_san(&state.position, &pgn_move.san)
+            .map_err(|err| PgnError::Move { index, err })?;
+        make_move(mv, &mut state)?;
+        moves.push(mv);
+    }
+    Ok((start, moves))
+}
+
+/// `game` with an `[%eval ...]` comment after every move of its main line
+/// and a `?!`, `?` or `??` on the moves that lost enough against the engine's.
+pub fn annotate_game(
+    game: &PgnGame,
+    config: &AnnotateConfig,
+    tt: &TranspositionTable,
+) -> Result<PgnGame, PgnError> {
+    let (start, moves) = main_line(game)?;
+    let mut annotated = game.clone();
+    let reviews = review_moves(&start, &moves, config, tt)?;
+    for (pgn_move, review) in annotated.moves.iter_mut().zip(&reviews) {
+        pgn_move
+            .comments
+            .push(eval_command(review.played_score, review.mover));
+        if let Some(nag) = config.nag(review.loss()) {
+            if !pgn_move.nags.contains(&nag) {
+                pgn_move.nags.push(nag);
+            }
+        }
+    }
+    Ok(annotated)
+}
+
+/// The command line of `lasker annotate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotateArgs {
+    pub input: String,
+    pub output: String,
+    pub config: AnnotateConfig,
+}
+
+impl AnnotateArgs {
+    /// Reads `<in.pgn> <out.pgn> [--depth N] [--movetime ms]
+    /// [--inaccuracy cp] [--mistake cp] [--blunder cp]`.
+    pub fn parse(args: &[String]) -> Result<AnnotateArgs, String> {
+        let (input, output, mut rest) = match args {
+            [input, output, rest @ ..] => (input.clone(), output.clone(), rest),
+            _ => return Err("annotate needs a PGN file to read and one to write".to_string()),
+        };
+        let mut config = AnnotateConfig::default();
+        while let [flag, value, tail @ ..] = rest {
+            if !config.set_option(flag, value)? {
+                return Err(format!("unknown option {}", flag));
+            }
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(format!("unknown options {}", rest.join(" ")));
+        }
+        Ok(AnnotateArgs {
+            input,
+            output,
+            config,
+        })
+    }
+}
+
+/// Annotates every game of `args`' input, writing them to its output and
+/// reporting each as it's done, and returns how many there were.
+pub fn run_annotate(args: &AnnotateArgs) -> Result<usize, Error> {
+    let games = parse_pgn(&fs::read_to_string(&args.input)?)?;
+    let mut out = io::BufWriter::new(fs::File::create(&args.output)?);
+    let mut tt = TranspositionTable::new(ANNOTATE_TT_MB);
+    for (i, game) in games.iter().enumerate() {
+        tt.clear();
+        let annotated = annotate_game(game, &args.config, &tt)?;
+        if i > 0 {
+            writeln!(out)?;
+        }
+        write!(out, "{}", annotated.to_pgn())?;
+        println!("game {}/{} annotated", i + 1, games.len());
+    }
+    out.flush()?;
+    Ok(games.len())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn config() -> AnnotateConfig {
+        AnnotateConfig {
+            depth: 3,
+            ..AnnotateConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_nags() {
+        let config = AnnotateConfig::default();
+        assert_eq!(config.nag(49), None);
+        assert_eq!(config.nag(50), Some(NAG_INACCURACY));
+        assert_eq!(config.nag(99), Some(NAG_INACCURACY));
+        assert_eq!(config.nag(100), Some(NAG_MISTAKE));
+        assert_eq!(config.nag(300), Some(NAG_BLUNDER));
+    }
+
+    #[test]
+    fn test_annotate_game() {
+        // Black walks into mate
+        let pgn = "[Event \"blunder\"]\n\n1. e4 e5 2. Qh5 Ke7 3. Qxe5# 1-0\n";
+        let game = &parse_pgn(pgn).unwrap()[0];
+        let tt = TranspositionTable::new(1);
+        let annotated = annotate_game(game, &config(), &tt).unwrap();
+        assert!(annotated
+            .moves
+            .iter()
+            .all(|mv| mv.comments.iter().any(|c| c.starts_with("[%eval "))));
+        let nags: Vec<&[u8]> = annotated.moves.iter().map(|mv| &mv.nags[..]).collect();
+        assert_eq!(nags[3], &[NAG_BLUNDER]);
+        assert!(nags[4].is_empty());
+        let text = annotated.to_pgn();
+        assert!(text.contains("Ke7 $4\n{[%eval #1]} 3. Qxe5# {[%eval #0]} 1-0"));
+
+        let illegal = &parse_pgn("1. e4 e5 2. Ke3 *").unwrap()[0];
+        assert!(matches!(
+            annotate_game(illegal, &config(), &tt),
+            Err(PgnError::Move { index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_annotate_args() {
+        let args: Vec<String> = ["in.pgn", "out.pgn", "--movetime", "200", "--blunder", "250"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let parsed = AnnotateArgs::parse(&args).unwrap();
+        assert_eq!(
+            (parsed.input.as_str(), parsed.output.as_str()),
+            ("in.pgn", "out.pgn")
+        );
+        assert_eq!(parsed.config.movetime, Some(Duration::from_millis(200)));
+        assert_eq!(parsed.config.depth, MAX_PLY);
+        assert_eq!(parsed.config.blunder, 250);
+        assert_eq!(parsed.config.mistake, AnnotateConfig::default().mistake);
+        assert!(AnnotateArgs::parse(&args[..1]).is_err());
+        assert!(AnnotateArgs::parse(&args[..3]).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+pub mod annotate;
+pub mod pgn;
+pub mod report;
+
+use std::time::Duration;
+
+use crate::{
+    play::{
+        error::{FENParsingError, MoveError},
+        r#move::{make_move, Move},
+        types::Color,
+        GameState, START_FEN,
+    },
+    search::{MATE, MAX_PLY},
+};
+
+use self::pgn::{PgnGame, PgnMove};
+
+/// Longest line of PGN movetext, as the export format asks.
+const PGN_LINE_WIDTH: usize = 79;
+
+/// How a recorded game ended, as PGN writes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PgnResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    #[default]
+    Unfinished,
+}
+
+impl PgnResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PgnResult::WhiteWins => "1-0",
+            PgnResult::BlackWins => "0-1",
+            PgnResult::Draw => "1/2-1/2",
+            PgnResult::Unfinished => "*",
+        }
+    }
+
+    /// The result a PGN termination marker or `Result` tag stands for.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "1-0" => Some(PgnResult::WhiteWins),
+            "0-1" => Some(PgnResult::BlackWins),
+            "1/2-1/2" => Some(PgnResult::Draw),
+            "*" => Some(PgnResult::Unfinished),
+            _ => None,
+        }
+    }
+
+    pub fn win_for(color: Color) -> Self {
+        match color {
+            Color::White => PgnResult::WhiteWins,
+            Color::Black => PgnResult::BlackWins,
+        }
+    }
+}
+
+/// A move as it was played, with what the player knew at the time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedMove {
+    pub mv: Move,
+    pub san: String,
+    /// the mover's time left once the move was made
+    pub clock: Option<Duration>,
+    /// the mover's score for the move, from its own point of view
+    pub score: Option<i32>,
+}
+
+/// Follows a game move by move, keeping each one with the mover's clock and
+/// evaluation, and writes it out once it's done as PGN or as the UCI
+/// `position` command that reaches its final position.
+#[derive(Debug, Clone)]
+pub struct GameRecorder {
+    start_fen: String,
+    /// game ply of the starting position, from its FEN move number
+    start_ply: u16,
+    state: GameState,
+    headers: Vec<(String, String)>,
+    moves: Vec<RecordedMove>,
+    result: PgnResult,
+}
+
+impl GameRecorder {
+    pub fn new(start_fen: &str) -> Result<Self, FENParsingError> {
+        let state = GameState::from_fen(start_fen)?;
+        Ok(GameRecorder {
+            start_fen: start_fen.to_string(),
+            start_ply: state.game_ply,
+            state,
+            headers: vec![],
+            moves: vec![],
+            result: PgnResult::default(),
+        })
+    }
+
+    /// The game as it stands after the moves recorded so far.
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn moves(&self) -> &[RecordedMove] {
+        &self.moves
+    }
+
+    /// Sets a PGN tag, replacing any of the same name. The seven tags PGN
+    /// requires are always written, as `?` where they haven't been set.
+    pub fn set_header(&mut self, name: &str, value: impl ToString) {
+        match self.headers.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn set_result(&mut self, result: PgnResult) {
+        self.result = result;
+    }
+
+    /// Plays `mv` on the recorded game.
+    pub fn record(
+        &mut self,
+        mv: Move,
+        clock: Option<Duration>,
+        score: Option<i32>,
+    ) -> Result<(), MoveError> {
+        let san = mv.to_san(&self.state.position);
+        make_move(mv, &mut self.state)?;
+        self.moves.push(RecordedMove {
+            mv,
+            san,
+            clock,
+            score,
+        });
+        Ok(())
+    }
+
+    /// The UCI command setting up the game's current position.
+    pub fn to_uci(&self) -> String {
+        let mut uci = if self.start_fen == START_FEN {
+            "position startpos".to_string()
+        } else {
+            format!("position fen {}", self.start_fen)
+        };
+        if !self.moves.is_empty() {
+            uci.push_str(" moves");
+            for recorded in &self.moves {
+                uci.push(' ');
+                uci.push_str(&recorded.mv.to_uci());
+            }
+        }
+        uci
+    }
+
+    /// The game as PGN has it: the seven tags PGN requires, as `?` where
+    /// they haven't been set, then the starting position's if it isn't the
+    /// usual one, then the rest, and each move with its evaluation and clock
+    /// in a comment.
+    pub fn to_pgn_game(&self) -> PgnGame {
+        let mut headers = vec![];
+        for name in ["Event", "Site", "Date", "Round", "White", "Black"] {
+            let value = self.header(name).unwrap_or("?");
+            headers.push((name.to_string(), value.to_string()));
+        }
+        headers.push(("Result".to_string(), self.result.as_str().to_string()));
+        if self.start_fen != START_FEN {
+            headers.push(("SetUp".to_string(), "1".to_string()));
+            headers.push(("FEN".to_string(), self.start_fen.clone()));
+        }
+        for (name, value) in &self.headers {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) && name != "SetUp" && name != "FEN" {
+                headers.push((name.clone(), value.clone()));
+            }
+        }
+
+        let moves = (self.start_ply..)
+            .zip(&self.moves)
+            .map(|(ply, recorded)| {
+                let mover = if ply.is_multiple_of(2) {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                PgnMove {
+                    comments: comment(recorded, mover).into_iter().collect(),
+                    ..PgnMove::new(&recorded.san)
+                }
+            })
+            .collect();
+        PgnGame {
+            headers,
+            moves,
+            result: self.result,
+        }
+    }
+
+    pub fn to_pgn(&self) -> String {
+        self.to_pgn_game().to_pgn()
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// The `[%eval ...] [%clk ...]` comment on a move, the evaluation turned
+/// round to white's point of view in pawns, or as `#N` for a mate.
+fn comment(recorded: &RecordedMove, mover: Color) -> Option<String> {
+    let mut commands = vec![];
+    if let Some(score) = recorded.score {
+        commands.push(eval_command(score, mover));
+    }
+    if let Some(clock) = recorded.clock {
+        let secs = clock.as_secs();
+        commands.push(format!(
+            "[%clk {}:{:02}:{:02}]",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        ));
+    }
+    if commands.is_empty() {
+        None
+    } else {
+        Some(commands.join(" "))
+    }
+}
+
+/// `[%eval ...]` for `mover`'s score from its own point of view.
+fn eval_command(score: i32, mover: Color) -> String {
+    format!("[%eval {}]", eval_string(score, mover))
+}
+
+/// `mover`'s score from White's point of view, in pawns or as `#N` for a
+/// mate in N moves.
+fn eval_string(score: i32, mover: Color) -> String {
+    let score = match mover {
+        Color::White => score,
+        Color::Black => -score,
+    };
+    if score.abs() >= MATE - MAX_PLY as i32 {
+        let moves = (MATE - score.abs() + 1) / 2;
+        format!("#{}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("{:.2}", score as f64 / 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::types::Square;
+
+    fn quiet(from: Square, to: Square) -> Move {
+        Move::quiet(from, to)
+    }
+
+    #[test]
+    fn test_record_to_uci_and_pgn() {
+        let mut recorder = GameRecorder::new(START_FEN).unwrap();
+        recorder.set_header("White", "lasker");
+        recorder.set_header("Black", "opponent");
+        recorder.set_header("Black", "lasker dev");
+        let pawn = Move::double_push(Square::E2, Square::E4);
+        recorder
+            .record(pawn, Some(Duration::from_secs(299)), Some(30))
+            .unwrap();
+        recorder
+            .record(quiet(Square::G8, Square::F6), None, Some(-MATE + 3))
+            .unwrap();
+        recorder.set_result(PgnResult::Unfinished);
+
+        assert_eq!(recorder.to_uci(), "position startpos moves e2e4 g8f6");
+        assert_eq!(recorder.state().game_ply, 2);
+        let pgn = recorder.to_pgn();
+        assert!(pgn.contains("[White \"lasker\"]\n[Black \"lasker dev\"]\n"));
+        assert!(pgn.contains("[Event \"?\"]"));
+        assert!(!pgn.contains("[FEN"));
+        // black being mated in 2 is good for white
+        assert!(pgn.ends_with("\n1. e4 {[%eval 0.30] [%clk 0:04:59]} Nf6 {[%eval #2]} *\n"));
+    }
+
+    #[test]
+    fn test_record_from_fen() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 b - - 3 40";
+        let mut recorder = GameRecorder::new(fen).unwrap();
+        recorder
+            .record(quiet(Square::G8, Square::H8), None, None)
+            .unwrap();
+        recorder
+            .record(quiet(Square::A1, Square::A8), None, None)
+            .unwrap();
+        recorder.set_result(PgnResult::win_for(Color::White));
+
+        assert_eq!(
+            recorder.to_uci(),
+            format!("position fen {} moves g8h8 a1a8", fen)
+        );
+        let pgn = recorder.to_pgn();
+        assert!(pgn.contains(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen)));
+        assert!(pgn.contains("[Result \"1-0\"]"));
+        assert!(pgn.ends_with("\n40... Kh8 41. Ra8# 1-0\n"));
+    }
+
+    #[test]
+    fn test_long_movetext_wraps() {
+        let mut recorder = GameRecorder::new(START_FEN).unwrap();
+        let shuffle = [
+            quiet(Square::G1, Square::F3),
+            quiet(Square::G8, Square::F6),
+            quiet(Square::F3, Square::G1),
+            quiet(Square::F6, Square::G8),
+        ];
+        for mv in shuffle.iter().cycle().take(40) {
+            recorder
+                .record(*mv, Some(Duration::from_secs(60)), Some(0))
+                .unwrap();
+        }
+        let pgn = recorder.to_pgn();
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() <= PGN_LINE_WIDTH));
+    }
+}
@@ -0,0 +1,699 @@
+use std::{error::Error, fmt, fs, io, iter::Peekable, path::Path, str::Chars};
+
+use crate::play::{
+    error::{FENParsingError, MoveError, SanError},
+    r#move::Move,
+    GameState, START_FEN,
+};
+
+use super::{GameRecorder, PgnResult, PGN_LINE_WIDTH};
+
+/// Why PGN couldn't be read, or a game in it couldn't be played through.
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    /// text that isn't PGN, on the given line counting from 1
+    Syntax {
+        line: usize,
+        msg: String,
+    },
+    Fen(FENParsingError),
+    /// a move that couldn't be read where it was played, by its index into
+    /// the game's moves
+    Move {
+        index: usize,
+        err: SanError,
+    },
+    Play(MoveError),
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::Io(err) => err.fmt(f),
+            PgnError::Syntax { line, msg } => write!(f, "line {}: {}", line, msg),
+            PgnError::Fen(err) => err.fmt(f),
+            PgnError::Move { index, err } => write!(f, "ply {}: {}", index + 1, err),
+            PgnError::Play(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for PgnError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PgnError::Io(err) => Some(err),
+            PgnError::Syntax { .. } => None,
+            PgnError::Fen(err) => Some(err),
+            PgnError::Move { err, .. } => Some(err),
+            PgnError::Play(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for PgnError {
+    fn from(err: io::Error) -> Self {
+        PgnError::Io(err)
+    }
+}
+
+impl From<FENParsingError> for PgnError {
+    fn from(err: FENParsingError) -> Self {
+        PgnError::Fen(err)
+    }
+}
+
+impl From<MoveError> for PgnError {
+    fn from(err: MoveError) -> Self {
+        PgnError::Play(err)
+    }
+}
+
+/// A move as PGN gives it, with whatever the annotator had to say about it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnMove {
+    pub san: String,
+    /// numeric annotation glyphs, `$1` for a good move and so on, with any
+    /// `!` or `?` after the move read as its NAG
+    pub nags: Vec<u8>,
+    /// comments ahead of the move, which only the first move of a game or
+    /// variation can have
+    pub comments_before: Vec<String>,
+    pub comments: Vec<String>,
+    /// lines played instead of this move, each of which may have its own
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(san: impl ToString) -> Self {
+        PgnMove {
+            san: san.to_string(),
+            ..PgnMove::default()
+        }
+    }
+}
+
+/// A game as PGN gives it: its tags in the order they came, its moves with
+/// their comments, NAGs and variations, and how it ended.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    pub headers: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    pub result: PgnResult,
+}
+
+impl PgnGame {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The position the game starts from, its `FEN` tag if it has one.
+    pub fn start_fen(&self) -> &str {
+        self.header("FEN").unwrap_or(START_FEN)
+    }
+
+    /// Plays the game's main line through from its starting position,
+    /// checking each move is legal where it's played, into a recorder holding
+    /// the final state, the moves made and the game's tags and result.
+    pub fn replay(&self) -> Result<GameRecorder, PgnError> {
+        let mut recorder = GameRecorder::new(self.start_fen())?;
+        for (name, value) in &self.headers {
+            recorder.set_header(name, value);
+        }
+        recorder.set_result(self.result);
+        for (index, pgn_move) in self.moves.iter().enumerate() {
+            let mv = Move::from_san(&recorder.state().position, &pgn_move.san)
+                .map_err(|err| PgnError::Move { index, err })?;
+            recorder.record(mv, None, None)?;
+        }
+        Ok(recorder)
+    }
+
+    /// The game in PGN export format, its tags in the order they're held and
+    /// its movetext wrapped to the line width PGN asks for.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (name, value) in &self.headers {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        pgn.push('\n');
+
+        let start_ply = GameState::from_fen(self.start_fen()).map_or(0, |state| state.game_ply);
+        let mut tokens = vec![];
+        write_movetext(&self.moves, start_ply, &mut tokens);
+        tokens.push(self.result.as_str().to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > PGN_LINE_WIDTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        pgn
+    }
+}
+
+/// Turns `moves`, the first of them played at `ply`, into movetext tokens.
+/// Black's moves are numbered at the start of a line and after a variation,
+/// so each can be told apart from white's.
+fn write_movetext(moves: &[PgnMove], ply: u16, tokens: &mut Vec<String>) {
+    let mut follows_on = false;
+    for (ply, mv) in (ply..).zip(moves) {
+        for comment in &mv.comments_before {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        let move_number = ply / 2 + 1;
+        if ply.is_multiple_of(2) {
+            tokens.push(format!("{}.", move_number));
+        } else if !follows_on {
+            tokens.push(format!("{}...", move_number));
+        }
+        tokens.push(mv.san.clone());
+        for nag in &mv.nags {
+            tokens.push(format!("${}", nag));
+        }
+        for comment in &mv.comments {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        for variation in &mv.variations {
+            let mut variation_tokens = vec![];
+            write_movetext(variation, ply, &mut variation_tokens);
+            match variation_tokens.as_mut_slice() {
+                [] => variation_tokens.push("()".to_string()),
+                [only] => {
+                    only.insert(0, '(');
+                    only.push(')');
+                }
+                [first, .., last] => {
+                    first.insert(0, '(');
+                    last.push(')');
+                }
+            }
+            tokens.extend(variation_tokens);
+        }
+        follows_on = mv.variations.is_empty();
+    }
+}
+
+/// Reads every game in `pgn`. A game ends at its termination marker, or
+/// failing that where the next one's tags start or the text runs out, when
+/// its `Result` tag gives its result.
+pub fn parse_pgn(pgn: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = vec![];
+    let mut game = GameReader::default();
+    let mut tokens = Tokens::new(pgn);
+    while let Some(token) = tokens.next_token()? {
+        match token {
+            Token::Tag(name, value) => {
+                if !game.lines[0].1.is_empty() {
+                    games.push(game.finish(None)?);
+                    game = GameReader::default();
+                }
+                game.headers.push((name, value));
+            }
+            Token::Movetext(movetext) => game.read(movetext, tokens.line)?,
+            Token::Result(result) => {
+                games.push(game.finish(Some(result))?);
+                game = GameReader::default();
+            }
+        }
+    }
+    if !game.headers.is_empty() || !game.lines[0].1.is_empty() {
+        games.push(game.finish(None)?);
+    }
+    Ok(games)
+}
+
+pub fn parse_pgn_file(path: impl AsRef<Path>) -> Result<Vec<PgnGame>, PgnError> {
+    parse_pgn(&fs::read_to_string(path)?)
+}
+
+/// A game part way through being read.
+struct GameReader {
+    headers: Vec<(String, String)>,
+    /// the main line, then each variation open inside the one before it,
+    /// with the line of the PGN each was opened on
+    lines: Vec<(usize, Vec<PgnMove>)>,
+    /// comments waiting for the move they come before
+    comments: Vec<String>,
+}
+
+impl Default for GameReader {
+    fn default() -> Self {
+        GameReader {
+            headers: vec![],
+            lines: vec![(1, vec![])],
+            comments: vec![],
+        }
+    }
+}
+
+impl GameReader {
+    fn read(&mut self, movetext: Movetext, line: usize) -> Result<(), PgnError> {
+        let syntax = |msg: &str| PgnError::Syntax {
+            line,
+            msg: msg.to_string(),
+        };
+        let (_, moves) = self
+            .lines
+            .last_mut()
+            .expect("the main line is never closed");
+        match movetext {
+            Movetext::Move(san, nag) => moves.push(PgnMove {
+                san,
+                nags: nag.into_iter().collect(),
+                comments_before: std::mem::take(&mut self.comments),
+                ..PgnMove::default()
+            }),
+            Movetext::Nag(nag) => moves
+                .last_mut()
+                .ok_or_else(|| syntax("NAG before any move"))?
+                .nags
+                .push(nag),
+            Movetext::Comment(comment) => match moves.last_mut() {
+                Some(mv) => mv.comments.push(comment),
+                None => self.comments.push(comment),
+            },
+            Movetext::VariationStart => {
+                if moves.is_empty() {
+                    return Err(syntax("variation before any move"));
+                }
+                self.lines.push((line, vec![]));
+            }
+            Movetext::VariationEnd => {
+                if self.lines.len() == 1 {
+                    return Err(syntax("variation closed but never opened"));
+                }
+                if let Some((_, variation)) = self.lines.pop() {
+                    if let Some(mv) = self.lines.last_mut().and_then(|(_, m)| m.last_mut()) {
+                        mv.variations.push(variation);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The game read, ended by `result` or, for a game missing its
+    /// termination marker, with the result its tags give.
+    fn finish(mut self, result: Option<PgnResult>) -> Result<PgnGame, PgnError> {
+        if let Some(&(line, _)) = self.lines.get(1) {
+            return Err(PgnError::Syntax {
+                line,
+                msg: "variation never closed".to_string(),
+            });
+        }
+        let mut game = PgnGame {
+            headers: self.headers,
+            moves: self.lines.pop().map(|(_, moves)| moves).unwrap_or_default(),
+            result: PgnResult::default(),
+        };
+        game.result = result
+            .or_else(|| game.header("Result").and_then(PgnResult::parse))
+            .unwrap_or_default();
+        Ok(game)
+    }
+}
+
+enum Token {
+    Tag(String, String),
+    Movetext(Movetext),
+    Result(PgnResult),
+}
+
+enum Movetext {
+    /// a move, with the NAG of any `!` or `?` written after it
+    Move(String, Option<u8>),
+    Nag(u8),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+}
+
+/// The NAG a move suffix such as `!?` stands for.
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+/// Splits PGN into tags, movetext and results, skipping escaped lines.
+struct Tokens<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    line_start: bool,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(pgn: &'a str) -> Self {
+        Tokens {
+            chars: pgn.chars().peekable(),
+            line: 1,
+            line_start: true,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.line_start = c == '\n';
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn syntax(&self, msg: impl ToString) -> PgnError {
+        PgnError::Syntax {
+            line: self.line,
+            msg: msg.to_string(),
+        }
+    }
+
+    fn rest_of_line(&mut self) -> String {
+        let mut rest = String::new();
+        while let Some(c) = self.bump().filter(|c| *c != '\n') {
+            rest.push(c);
+        }
+        rest
+    }
+
+    /// A `{comment}`, the opening brace already read.
+    fn comment(&mut self) -> Result<String, PgnError> {
+        let line = self.line;
+        let mut comment = String::new();
+        loop {
+            match self.bump() {
+                Some('}') => return Ok(comment.trim().to_string()),
+                Some(c) => comment.push(c),
+                None => {
+                    return Err(PgnError::Syntax {
+                        line,
+                        msg: "comment never closed".to_string(),
+                    })
+                }
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    /// `[Name "value"]`, the opening bracket already read.
+    fn tag(&mut self) -> Result<Token, PgnError> {
+        let line = self.line;
+        let syntax = |msg: &str| PgnError::Syntax {
+            line,
+            msg: msg.to_string(),
+        };
+        self.skip_whitespace();
+        let mut name = String::new();
+        while let Some(c) = self
+            .chars
+            .peek()
+            .filter(|c| c.is_alphanumeric() || **c == '_')
+        {
+            name.push(*c);
+            self.bump();
+        }
+        self.skip_whitespace();
+        if name.is_empty() || self.bump() != Some('"') {
+            return Err(syntax("tag without a name and quoted value"));
+        }
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => break,
+                Some('\\') => match self.bump() {
+                    Some(c) => value.push(c),
+                    None => return Err(syntax("tag value never closed")),
+                },
+                Some('\n') | None => return Err(syntax("tag value never closed")),
+                Some(c) => value.push(c),
+            }
+        }
+        self.skip_whitespace();
+        if self.bump() != Some(']') {
+            return Err(syntax("tag never closed"));
+        }
+        Ok(Token::Tag(name, value))
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, PgnError> {
+        while let Some(&c) = self.chars.peek() {
+            let movetext = match c {
+                '%' if self.line_start => {
+                    self.rest_of_line();
+                    continue;
+                }
+                c if c.is_whitespace() => {
+                    self.bump();
+                    continue;
+                }
+                '[' => {
+                    self.bump();
+                    return self.tag().map(Some);
+                }
+                ';' => {
+                    self.bump();
+                    Movetext::Comment(self.rest_of_line().trim().to_string())
+                }
+                '{' => {
+                    self.bump();
+                    Movetext::Comment(self.comment()?)
+                }
+                '(' => {
+                    self.bump();
+                    Movetext::VariationStart
+                }
+                ')' => {
+                    self.bump();
+                    Movetext::VariationEnd
+                }
+                '$' => {
+                    self.bump();
+                    let mut digits = String::new();
+                    while let Some(d) = self.chars.peek().filter(|d| d.is_ascii_digit()) {
+                        digits.push(*d);
+                        self.bump();
+                    }
+                    let nag = digits
+                        .parse()
+                        .map_err(|_| self.syntax(format!("bad NAG ${}", digits)))?;
+                    Movetext::Nag(nag)
+                }
+                _ => {
+                    let mut word = String::new();
+                    while let Some(&c) = self
+                        .chars
+                        .peek()
+                        .filter(|c| !c.is_whitespace() && !"{}()[];$".contains(**c))
+                    {
+                        word.push(c);
+                        self.bump();
+                    }
+                    if word.is_empty() {
+                        return Err(self.syntax(format!("unexpected {}", c)));
+                    }
+                    if let Some(result) = PgnResult::parse(&word) {
+                        return Ok(Some(Token::Result(result)));
+                    }
+                    // a move number, `12.` or `12...`, maybe run into its move
+                    let numbered = word.trim_start_matches(|c: char| c.is_ascii_digit());
+                    let word = if numbered.starts_with('.') {
+                        numbered.trim_start_matches('.')
+                    } else {
+                        &word
+                    };
+                    let san = word.trim_end_matches(['!', '?']);
+                    let nag = suffix_nag(&word[san.len()..]);
+                    match (san.is_empty(), nag) {
+                        (false, nag) => Movetext::Move(san.to_string(), nag),
+                        (true, Some(nag)) => Movetext::Nag(nag),
+                        (true, None) if word.is_empty() => continue,
+                        (true, None) => return Err(self.syntax(format!("unexpected {}", word))),
+                    }
+                }
+            };
+            return Ok(Some(Token::Movetext(movetext)));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const GAMES: &str = r#"% exported by hand
+[Event "Casual \"blitz\""]
+[Site "?"]
+[White "Morphy"]
+[Black "Duke of Brunswick"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {a dubious pin} 4. dxe5 Bxf3 5. Qxf3 dxe5
+6. Bc4 Nf6 7. Qb3 $1 Qe7 8. Nc3 c6 9. Bg5 b5 (9... Qb4+ 10. Qxb4 Bxb4) 10. Nxb5
+cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7
+16. Qb8+ Nxb8 17. Rd8# 1-0
+
+[Event "Endgame"]
+[SetUp "1"]
+[FEN "6k1/5ppp/8/8/8/8/8/R5K1 b - - 3 40"]
+[Result "1-0"]
+
+40... Kh8 ; the only move
+41.Ra8#!! 1-0
+"#;
+
+    fn sans(moves: &[PgnMove]) -> Vec<&str> {
+        moves.iter().map(|mv| mv.san.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_pgn() {
+        let games = parse_pgn(GAMES).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let opera = &games[0];
+        assert_eq!(opera.header("Event"), Some("Casual \"blitz\""));
+        assert_eq!(opera.header("White"), Some("Morphy"));
+        assert_eq!(opera.result, PgnResult::WhiteWins);
+        assert_eq!(opera.moves.len(), 33);
+        assert_eq!(&sans(&opera.moves)[..4], ["e4", "e5", "Nf3", "d6"]);
+        assert_eq!(opera.moves[5].comments, ["a dubious pin"]);
+        assert_eq!(opera.moves[12].nags, [1]);
+        assert_eq!(opera.moves[17].variations.len(), 1);
+        assert_eq!(
+            sans(&opera.moves[17].variations[0]),
+            ["Qb4+", "Qxb4", "Bxb4"]
+        );
+        assert_eq!(opera.moves[18].san, "Nxb5");
+        assert_eq!(opera.moves.last().unwrap().san, "Rd8#");
+        assert_eq!(opera.start_fen(), START_FEN);
+
+        let endgame = &games[1];
+        assert_eq!(endgame.start_fen(), "6k1/5ppp/8/8/8/8/8/R5K1 b - - 3 40");
+        assert_eq!(sans(&endgame.moves), ["Kh8", "Ra8#"]);
+        assert_eq!(endgame.moves[0].comments, ["the only move"]);
+        assert_eq!(endgame.moves[1].nags, [3]);
+    }
+
+    #[test]
+    fn test_replay() {
+        let games = parse_pgn(GAMES).unwrap();
+        let opera = games[0].replay().unwrap();
+        assert_eq!(opera.moves().len(), 33);
+        assert_eq!(opera.moves()[22].san, "O-O-O");
+        assert!(opera.state().position.checkers.pop_count() > 0);
+        assert!(!opera.state().position.has_legal_move());
+
+        let endgame = games[1].replay().unwrap();
+        assert_eq!(
+            endgame.to_uci(),
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 b - - 3 40 moves g8h8 a1a8"
+        );
+    }
+
+    #[test]
+    fn test_exported_pgn_reads_back() {
+        let games = parse_pgn(GAMES).unwrap();
+        let recorder = games[1].replay().unwrap();
+        let read_back = parse_pgn(&recorder.to_pgn()).unwrap();
+        assert_eq!(read_back.len(), 1);
+        assert_eq!(sans(&read_back[0].moves), ["Kh8", "Ra8#"]);
+        assert_eq!(read_back[0].result, PgnResult::WhiteWins);
+        assert_eq!(read_back[0].replay().unwrap().to_uci(), recorder.to_uci());
+    }
+
+    #[test]
+    fn test_annotations_round_trip() {
+        let pgn = "[Event \"Analysis\"]\n\n{start} 1. e4! {best by test} (1. d4 d5 \
+                   (1... Nf6 2. c4) 2. c4) 1... e5 $2 2. Nf3 *";
+        let games = parse_pgn(pgn).unwrap();
+        let game = &games[0];
+        assert_eq!(game.moves[0].comments_before, ["start"]);
+        assert_eq!(game.moves[0].nags, [1]);
+        assert_eq!(
+            sans(&game.moves[0].variations[0][1].variations[0]),
+            ["Nf6", "c4"]
+        );
+
+        let written = game.to_pgn();
+        assert_eq!(
+            written,
+            "[Event \"Analysis\"]\n\n{start} 1. e4 $1 {best by test} (1. d4 d5 (1... Nf6 2. c4) \
+             2. c4) 1... e5 $2 2.\nNf3 *\n"
+        );
+        assert_eq!(parse_pgn(&written).unwrap(), games);
+        assert_eq!(
+            game.replay().unwrap().to_uci(),
+            "position startpos moves e2e4 e7e5 g1f3"
+        );
+
+        let games = parse_pgn(GAMES).unwrap();
+        for game in &games {
+            assert_eq!(&parse_pgn(&game.to_pgn()).unwrap()[0], game);
+        }
+    }
+
+    #[test]
+    fn test_unterminated_games() {
+        let pgn = "[Result \"1/2-1/2\"]\n1. e4 e5\n[Result \"*\"]\n1. d4";
+        let games = parse_pgn(pgn).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].result, PgnResult::Draw);
+        assert_eq!(sans(&games[1].moves), ["d4"]);
+        assert_eq!(games[1].result, PgnResult::Unfinished);
+        assert!(parse_pgn("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pgn_errors() {
+        for (pgn, line) in [
+            ("1. e4 {never closed\n\n", 1),
+            ("1. e4 e5\n(1... c5 2. Nf3 *", 2),
+            ("[Event \"open]\n", 1),
+            ("\n\n[Event]", 3),
+            ("1. e4\n} e5", 2),
+            ("1. e4 e5)", 1),
+            ("{no moves yet} (1. d4) *", 1),
+            ("1. e4 $x", 1),
+        ] {
+            match parse_pgn(pgn) {
+                Err(PgnError::Syntax { line: l, .. }) => assert_eq!(l, line, "{}", pgn),
+                other => panic!("{}: {:?}", pgn, other),
+            }
+        }
+
+        let games = parse_pgn("1. e4 e5 2. Ke3 *").unwrap();
+        match games[0].replay() {
+            Err(PgnError::Move { index, err }) => {
+                assert_eq!(index, 2);
+                assert_eq!(err, SanError::Illegal("Ke3".to_string()));
+            }
+            other => panic!("{:?}", other.map(|r| r.to_uci())),
+        }
+    }
+}
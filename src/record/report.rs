@@ -0,0 +1,306 @@
+use std::{fmt, io::Write};
+
+use crate::{
+    play::{
+        r#move::{make_move, Move},
+        types::Color,
+        GameState,
+    },
+    search::tt::TranspositionTable,
+    uci::{error::UciError, parse_position},
+    Error,
+};
+
+use super::{
+    annotate::{main_line, review_moves, AnnotateConfig, Judgement, MoveReview},
+    eval_string,
+    pgn::parse_pgn_file,
+};
+
+const REPORT_TT_MB: usize = 16;
+
+/// A move of the game, as the report lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportedMove {
+    /// the full move number the move was played on
+    pub number: u16,
+    pub san: String,
+    pub best_san: Option<String>,
+    pub review: MoveReview,
+    pub judgement: Option<Judgement>,
+}
+
+/// How one side played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SideSummary {
+    pub moves: u32,
+    pub total_loss: i64,
+    pub inaccuracies: u32,
+    pub mistakes: u32,
+    pub blunders: u32,
+}
+
+impl SideSummary {
+    fn add(&mut self, loss: i32, judgement: Option<Judgement>) {
+        self.moves += 1;
+        self.total_loss += loss as i64;
+        match judgement {
+            Some(Judgement::Inaccuracy) => self.inaccuracies += 1,
+            Some(Judgement::Mistake) => self.mistakes += 1,
+            Some(Judgement::Blunder) => self.blunders += 1,
+            None => (),
+        }
+    }
+
+    /// The centipawns lost a move, on average.
+    pub fn average_loss(&self) -> i64 {
+        if self.moves == 0 {
+            0
+        } else {
+            self.total_loss / self.moves as i64
+        }
+    }
+}
+
+impl fmt::Display for SideSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} inaccuracies, {} mistakes, {} blunders, average loss {}",
+            self.inaccuracies,
+            self.mistakes,
+            self.blunders,
+            self.average_loss()
+        )
+    }
+}
+
+/// Every move of a game against the engine's choice, and how each side did
+/// over the game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameReport {
+    pub moves: Vec<ReportedMove>,
+    pub white: SideSummary,
+    pub black: SideSummary,
+}
+
+impl GameReport {
+    /// Searches every position of the game played by `moves` from `start`.
+    pub fn new(
+        start: &GameState,
+        moves: &[Move],
+        config: &AnnotateConfig,
+        tt: &TranspositionTable,
+    ) -> Result<GameReport, Error> {
+        let reviews = review_moves(start, moves, config, tt)?;
+        let mut report = GameReport {
+            moves: vec![],
+            white: SideSummary::default(),
+            black: SideSummary::default(),
+        };
+        let mut state = start.clone();
+        for review in reviews {
+            let mv = review.mv;
+            let loss = review.loss();
+            let judgement = config.judge(loss);
+            match review.mover {
+                Color::White => report.white.add(loss, judgement),
+                Color::Black => report.black.add(loss, judgement),
+            }
+            report.moves.push(ReportedMove {
+                number: state.game_ply / 2 + 1,
+                san: review.mv.to_san(&state.position),
+                best_san: review.best_move.map(|mv| mv.to_san(&state.position)),
+                review,
+                judgement,
+            });
+            make_move(mv, &mut state)?;
+        }
+        Ok(report)
+    }
+}
+
+/// A line a move, with the score after it from White's point of view, then
+/// the side summaries.
+impl fmt::Display for GameReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for mv in &self.moves {
+            let review = &mv.review;
+            let number = match review.mover {
+                Color::White => format!("{}.", mv.number),
+                Color::Black => format!("{}...", mv.number),
+            };
+            let mut line = format!(
+                "{:>5} {:<8} {:>7}",
+                number,
+                mv.san,
+                eval_string(review.played_score, review.mover)
+            );
+            let loss = review.loss();
+            if loss > 0 {
+                line.push_str(&format!("  loss {:>4}", loss));
+                if let Some(best) = &mv.best_san {
+                    line.push_str(&format!("  best {:<8}", best));
+                }
+            }
+            if let Some(judgement) = mv.judgement {
+                line.push_str(&format!("  {}", judgement.as_str()));
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        writeln!(f, "White: {}", self.white)?;
+        write!(f, "Black: {}", self.black)
+    }
+}
+
+/// The start and the moves of a `position [startpos | fen <fen>] moves ...`
+/// line, as a GUI would send it.
+pub fn position_line(line: &str) -> Result<(GameState, Vec<Move>), UciError> {
+    let (fen, move_strs) = parse_position(line);
+    let start = GameState::from_fen(&fen)?;
+    let mut state = start.clone();
+    let mut moves = vec![];
+    for mv_str in &move_strs {
+        let mv = state
+            .position
+            .parse_move(mv_str)
+            .ok_or_else(|| UciError::IllegalMove(mv_str.clone()))?;
+        make_move(mv, &mut state)?;
+        moves.push(mv);
+    }
+    Ok((start, moves))
+}
+
+/// Where the game to report on comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportSource {
+    /// a PGN file and which of its games, counting from 1
+    Pgn { path: String, game: usize },
+    /// a `position ... moves ...` line
+    Position(String),
+}
+
+/// The command line of `lasker report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportArgs {
+    pub source: ReportSource,
+    pub config: AnnotateConfig,
+}
+
+impl ReportArgs {
+    /// Reads `<game.pgn | "position ...">` followed by `[--game N]` and any
+    /// of the options of `lasker annotate`.
+    pub fn parse(args: &[String]) -> Result<ReportArgs, String> {
+        let (input, mut rest) = match args {
+            [input, rest @ ..] => (input, rest),
+            _ => return Err("report needs a PGN file or a position line".to_string()),
+        };
+        let mut game = 1;
+        let mut config = AnnotateConfig::default();
+        while let [flag, value, tail @ ..] = rest {
+            if flag == "--game" {
+                game = match value.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err(format!("bad value {} for {}", value, flag)),
+                };
+            } else if !config.set_option(flag, value)? {
+                return Err(format!("unknown option {}", flag));
+            }
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(format!("unknown options {}", rest.join(" ")));
+        }
+        let source = if input.starts_with("position") {
+            ReportSource::Position(input.clone())
+        } else {
+            ReportSource::Pgn {
+                path: input.clone(),
+                game,
+            }
+        };
+        Ok(ReportArgs { source, config })
+    }
+}
+
+/// Reports on the game `args` names, writing the report to `out`.
+pub fn run_report(args: &ReportArgs, out: &mut impl Write) -> Result<(), Error> {
+    let (start, moves) = match &args.source {
+        ReportSource::Pgn { path, game } => {
+            let games = parse_pgn_file(path)?;
+            let game = games.get(game - 1).ok_or_else(|| {
+                UciError::invalid_value("game", format!("{} of {}", game, games.len()))
+            })?;
+            main_line(game)?
+        }
+        ReportSource::Position(line) => position_line(line)?,
+    };
+    let tt = TranspositionTable::new(REPORT_TT_MB);
+    let report = GameReport::new(&start, &moves, &args.config, &tt)?;
+    writeln!(out, "{}", report)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_game_report() {
+        // White leaves the queen to be taken, and Black takes it
+        let line = "position startpos moves e2e4 e7e5 d1h5 g8f6 f1c4 f6h5 g1f3";
+        let (start, moves) = position_line(line).unwrap();
+        let config = AnnotateConfig {
+            depth: 3,
+            ..AnnotateConfig::default()
+        };
+        let tt = TranspositionTable::new(1);
+        let report = GameReport::new(&start, &moves, &config, &tt).unwrap();
+        assert_eq!(report.moves.len(), 7);
+        assert_eq!(report.moves[4].san, "Bc4");
+        assert_eq!(report.moves[4].number, 3);
+        assert_eq!(report.moves[4].judgement, Some(Judgement::Blunder));
+        assert_eq!(report.moves[5].san, "Nxh5");
+        assert_eq!(report.moves[5].review.loss(), 0);
+        assert_eq!(report.moves[5].judgement, None);
+        assert!(report.white.blunders >= 1);
+        assert_eq!(report.white.moves, 4);
+        assert_eq!(report.black.moves, 3);
+        assert!(report.white.average_loss() > report.black.average_loss());
+
+        let text = report.to_string();
+        assert!(text.contains("3. Bc4"));
+        assert!(text.contains("3... Nxh5"));
+        assert!(text.lines().any(|line| line.starts_with("White: ")));
+        assert!(text.ends_with(&format!("Black: {}", report.black)));
+
+        assert!(matches!(
+            position_line("position startpos moves e2e4 e2e4"),
+            Err(UciError::IllegalMove(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_report_args() {
+        let args: Vec<String> = ["games.pgn", "--game", "3", "--depth", "6"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let parsed = ReportArgs::parse(&args).unwrap();
+        assert_eq!(
+            parsed.source,
+            ReportSource::Pgn {
+                path: "games.pgn".to_string(),
+                game: 3
+            }
+        );
+        assert_eq!(parsed.config.depth, 6);
+        assert!(ReportArgs::parse(&args[..2]).is_err());
+        assert!(ReportArgs::parse(&[]).is_err());
+
+        let line = "position startpos moves e2e4".to_string();
+        let parsed = ReportArgs::parse(std::slice::from_ref(&line)).unwrap();
+        assert_eq!(parsed.source, ReportSource::Position(line));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::play::{
+    types::{Color, Piece},
+    GameState,
+};
+
+/// Contempt is capped either way so a wild rating gap can't make the
+/// engine throw away a sound position to dodge a draw, or give one away.
+pub const MAX_CONTEMPT: i32 = 100;
+/// Elo of rating advantage worth a centipawn of contempt.
+const ELO_PER_CP: i32 = 10;
+/// Full game phase: knights and bishops count 1, rooks 2 and queens 4.
+const MAX_PHASE: i32 = 24;
+/// Added to the phase so contempt fades towards, but not to nothing in, a
+/// bare endgame.
+const ENDGAME_PHASE_FLOOR: i32 = 8;
+/// When behind on material, draw aversion drops by this fraction (1/n) of the
+/// deficit.
+const DEFICIT_DIVISOR: i32 = 4;
+
+/// How much worse than level the engine scores a draw. A constant `base` is
+/// adjusted by the rating gap to the opponent when there's a hint of it, so
+/// the engine plays on against weaker opposition and settles against
+/// stronger, then scaled down as material comes off and as the side to move
+/// falls behind on it, so it stops playing on for a win it no longer has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contempt {
+    /// centipawns
+    pub base: i32,
+    /// the engine's own rating, against which the opponent's is compared
+    pub engine_rating: i32,
+    pub opponent_rating: Option<i32>,
+}
+
+impl Default for Contempt {
+    fn default() -> Self {
+        Contempt {
+            base: 0,
+            engine_rating: 2000,
+            opponent_rating: None,
+        }
+    }
+}
+
+impl Contempt {
+    /// Centipawns a draw is worth less than level to the side to move in
+    /// `state`, the root of a search. Negative when it would take the draw.
+    pub fn at_root(&self, state: &GameState) -> i32 {
+        let advantage = self
+            .opponent_rating
+            .map_or(0, |opponent| self.engine_rating - opponent);
+        let contempt = (self.base + advantage / ELO_PER_CP).clamp(-MAX_CONTEMPT, MAX_CONTEMPT);
+        let contempt =
+            contempt * (phase(state) + ENDGAME_PHASE_FLOOR) / (MAX_PHASE + ENDGAME_PHASE_FLOOR);
+
+        let us = state.position.side_to_move;
+        let board = &state.position.board;
+        let balance = board.material(us) as i32 - board.material(us.opposing()) as i32;
+        if balance < 0 && contempt > 0 {
+            (contempt + balance / DEFICIT_DIVISOR).max(0)
+        } else {
+            contempt
+        }
+    }
+}
+
+/// How much non-pawn material is left, from [`MAX_PHASE`] at the start to 0.
+fn phase(state: &GameState) -> i32 {
+    let board = &state.position.board;
+    let weighted: u32 = [Color::White, Color::Black]
+        .iter()
+        .flat_map(|color| {
+            let (knight, bishop, rook, queen) = match color {
+                Color::White => (
+                    Piece::WhiteKnight,
+                    Piece::WhiteBishop,
+                    Piece::WhiteRook,
+                    Piece::WhiteQueen,
+                ),
+                Color::Black => (
+                    Piece::BlackKnight,
+                    Piece::BlackBishop,
+                    Piece::BlackRook,
+                    Piece::BlackQueen,
+                ),
+            };
+            [(knight, 1), (bishop, 1), (rook, 2), (queen, 4)]
+        })
+        .map(|(piece, weight)| board.bitboard(piece).pop_count() * weight)
+        .sum();
+    (weighted as i32).min(MAX_PHASE)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_contempt_by_rating() {
+        let state = GameState::default();
+        assert_eq!(Contempt::default().at_root(&state), 0);
+
+        let base = Contempt {
+            base: 20,
+            ..Contempt::default()
+        };
+        assert_eq!(base.at_root(&state), 20);
+        // pressing harder against a weaker opponent, settling against a stronger
+        let weaker = Contempt {
+            opponent_rating: Some(1600),
+            ..base
+        };
+        assert_eq!(weaker.at_root(&state), 60);
+        let stronger = Contempt {
+            opponent_rating: Some(2500),
+            ..base
+        };
+        assert_eq!(stronger.at_root(&state), -30);
+        let huge = Contempt {
+            opponent_rating: Some(0),
+            ..base
+        };
+        assert_eq!(huge.at_root(&state), MAX_CONTEMPT);
+    }
+
+    #[test]
+    fn test_contempt_by_phase_and_material() {
+        let contempt = Contempt {
+            base: 40,
+            ..Contempt::default()
+        };
+        // level king and pawn endgame: only the floor is left
+        let state = GameState::from_fen("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(phase(&state), 0);
+        assert_eq!(contempt.at_root(&state), 10);
+
+        // a pawn down is less keen to avoid a draw, a rook down not at all
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+        assert_eq!(contempt.at_root(&state), 15);
+        let state = GameState::from_fen("r3k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(contempt.at_root(&state), 0);
+        // while the side a rook up keeps avoiding it
+        let state = GameState::from_fen("r3k3/4p3/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        assert_eq!(contempt.at_root(&state), 12);
+
+        // behind or not, contempt against a stronger opponent stays
+        let stronger = Contempt {
+            opponent_rating: Some(2400),
+            ..Contempt::default()
+        };
+        let state = GameState::from_fen("r3k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(stronger.at_root(&state), -12);
+    }
+}
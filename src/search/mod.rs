@@ -0,0 +1,1880 @@
+pub mod bench;
+pub mod contempt;
+pub mod observer;
+mod ordering;
+pub mod personality;
+pub mod skill;
+pub mod stats;
+pub mod testsuite;
+pub mod time;
+pub mod tree;
+pub mod tt;
+pub mod wdl;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    eval::{EvalWeights, Evaluator},
+    play::{
+        r#move::{compact::Move16, make_move, make_null_move, Move},
+        types::{Color, Piece},
+        GameState,
+    },
+};
+
+use self::{
+    contempt::Contempt,
+    observer::{CurrentMove, Iteration, SearchObserver},
+    skill::Strength,
+    stats::SearchStats,
+    time::SearchControl,
+    tree::{NodeKind, Outcome, SearchTree},
+    tt::{score_from_tt, score_to_tt, Bound, Entry, TranspositionTable},
+};
+
+pub const INFINITY: i32 = 32_000;
+pub const MATE: i32 = 31_000;
+pub const MAX_PLY: u8 = 64;
+/// Iterations from this depth start with a window around the last score.
+const ASPIRATION_MIN_DEPTH: u8 = 3;
+/// Initial half-width of the aspiration window, doubled on each re-search.
+const ASPIRATION_WINDOW: i32 = 50;
+/// Nodes between polls of the search control.
+const CHECK_INTERVAL: u64 = 1024;
+/// The search recurses a frame per ply, each holding its own move list, so
+/// search threads get far more stack than a thread's default.
+pub const SEARCH_STACK_SIZE: usize = 64 * 1024 * 1024;
+/// The most threads a search can be split between.
+pub const MAX_THREADS: usize = 256;
+
+#[derive(Debug)]
+pub struct SearchInfo<'a> {
+    pub nodes: u64,
+    /// deepest ply reached, quiescence and extensions included
+    pub seldepth: u8,
+    /// set once the control says to stop, after which scores are meaningless
+    pub stopped: bool,
+    control: Arc<SearchControl>,
+    /// the control's node limit, kept to hand as it's checked every node
+    node_limit: Option<u64>,
+    /// triangular principal variation table, indexed by ply
+    pv: Vec<Vec<Move>>,
+    tt: &'a TranspositionTable,
+    /// set by the main thread once its search is over, for the helpers
+    /// searching alongside it to stop on
+    finished: Option<&'a AtomicBool>,
+    /// the evaluation with the search's weights, keeping its pawn table for
+    /// the one search
+    eval: Evaluator,
+    /// centipawns a draw is worth less than level to the side to move at the
+    /// root, fixed for the whole search
+    contempt: i32,
+    /// ply at which the current line passed, so the reply doesn't pass back
+    null_ply: Option<u8>,
+    /// set while a null move cutoff is being verified, which mustn't try
+    /// another null move
+    verifying: bool,
+    pub stats: SearchStats,
+    /// where the main thread records what it searches, in a tree search
+    tree: Option<&'a mut SearchTree>,
+}
+
+impl<'a> SearchInfo<'a> {
+    fn new(control: Arc<SearchControl>, tt: &'a TranspositionTable) -> Self {
+        SearchInfo {
+            nodes: 0,
+            seldepth: 0,
+            stopped: false,
+            node_limit: control.node_limit(),
+            control,
+            pv: vec![],
+            tt,
+            finished: None,
+            eval: Evaluator::default(),
+            contempt: 0,
+            null_ply: None,
+            verifying: false,
+            stats: SearchStats::default(),
+            tree: None,
+        }
+    }
+
+    /// Passes the tree being recorded to `f`, if there is one.
+    fn trace(&mut self, f: impl FnOnce(&mut SearchTree)) {
+        if let Some(tree) = self.tree.as_deref_mut() {
+            f(tree)
+        }
+    }
+
+    /// Closes the tree's open node, which scored `score` unless the search
+    /// was stopped.
+    fn trace_leave(&mut self, score: i32) {
+        let stopped = self.stopped;
+        self.trace(|tree| {
+            if stopped {
+                tree.note(Outcome::Stopped);
+            }
+            tree.leave(score);
+        });
+    }
+
+    /// Stops the search once its node limit is reached, checked every node
+    /// so `go nodes N` searches exactly N, or once the control says to,
+    /// asked every [`CHECK_INTERVAL`] nodes.
+    fn poll(&mut self) {
+        if self.node_limit.is_some_and(|limit| self.nodes >= limit)
+            || self.nodes.is_multiple_of(CHECK_INTERVAL)
+                && (self.control.should_stop(self.nodes)
+                    || self.finished.is_some_and(|f| f.load(Ordering::Relaxed)))
+        {
+            self.stopped = true;
+        }
+    }
+
+    fn clear_pv(&mut self, ply: u8) {
+        let ply = ply as usize;
+        if self.pv.len() <= ply + 1 {
+            self.pv.resize(ply + 2, vec![]);
+        }
+        self.pv[ply].clear();
+    }
+
+    /// A draw's score for the side to move at `ply`.
+    fn draw_score(&self, ply: u8) -> i32 {
+        if ply.is_multiple_of(2) {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    /// `mv` is the new best move at `ply`, followed by the child's line.
+    fn update_pv(&mut self, ply: u8, mv: Move) {
+        let ply = ply as usize;
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        head[ply].clear();
+        head[ply].push(mv);
+        head[ply].extend_from_slice(&tail[0]);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub pv: Vec<Move>,
+    pub stats: SearchStats,
+}
+
+/// The moves to the mate a score finds, negative when getting mated, or
+/// `None` if it isn't a mate score.
+pub fn mate_moves(score: i32) -> Option<i32> {
+    if score >= MATE - MAX_PLY as i32 {
+        Some((MATE - score + 1) / 2)
+    } else if score <= -MATE + MAX_PLY as i32 {
+        Some(-(MATE + score) / 2)
+    } else {
+        None
+    }
+}
+
+/// A score as UCI reports it: `mate N` in moves (negative when getting
+/// mated), else `cp N`.
+pub fn uci_score(score: i32) -> String {
+    match mate_moves(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+impl SearchResult {
+    /// The reply expected to `best_move`, to ponder on.
+    pub fn ponder_move(&self) -> Option<Move> {
+        self.pv.get(1).copied()
+    }
+}
+
+/// Picks uniformly among the root moves scoring within `window` of the best
+/// one for the first `plies` plies of a game, so self-play and datagen games
+/// don't all repeat the same line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RootRandomization {
+    pub plies: u16,
+    pub window: i32,
+}
+
+impl RootRandomization {
+    /// How far below the best, in centipawns, a move picked at random may
+    /// score when only the plies are set.
+    pub const DEFAULT_WINDOW: i32 = 30;
+
+    /// Randomization for the first `plies` plies within the default window,
+    /// or none for no plies.
+    pub fn for_plies(plies: u16) -> Option<Self> {
+        (plies > 0).then_some(RootRandomization {
+            plies,
+            window: Self::DEFAULT_WINDOW,
+        })
+    }
+}
+
+/// Tunable parameters for late move reductions and late move pruning, which
+/// only apply to quiet moves that neither evade nor give check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LateMoveParams {
+    /// remaining depth from which late moves are reduced
+    pub reduction_min_depth: u8,
+    /// moves searched at full depth before reducing
+    pub reduction_min_moves: u32,
+    /// reduction = base + ln(depth) * ln(move number) / divisor
+    pub reduction_base: f64,
+    pub reduction_divisor: f64,
+    /// remaining depth up to which late moves are pruned outright
+    pub pruning_max_depth: u8,
+    /// moves searched before pruning, as base + depth^2
+    pub pruning_base: u32,
+}
+
+impl Default for LateMoveParams {
+    fn default() -> Self {
+        LateMoveParams {
+            reduction_min_depth: 3,
+            reduction_min_moves: 3,
+            reduction_base: 0.75,
+            reduction_divisor: 2.25,
+            pruning_max_depth: 3,
+            pruning_base: 3,
+        }
+    }
+}
+
+impl LateMoveParams {
+    /// Plies to reduce the `move_number`th move (1-based) by, leaving at
+    /// least one ply to search.
+    pub fn reduction(&self, depth: u8, move_number: u32) -> u8 {
+        if depth < self.reduction_min_depth || move_number <= self.reduction_min_moves {
+            return 0;
+        }
+        let r = self.reduction_base
+            + (depth as f64).ln() * (move_number as f64).ln() / self.reduction_divisor;
+        (r as u8).min(depth - 2)
+    }
+
+    /// Whether the `move_number`th move (1-based) is late enough to skip.
+    pub fn prunes(&self, depth: u8, move_number: u32) -> bool {
+        depth <= self.pruning_max_depth
+            && move_number > self.pruning_base + (depth as u32) * (depth as u32)
+    }
+}
+
+/// Tunable parameters for null move pruning: when passing still leaves the
+/// side to move at or above beta after a reduced search, the node is cut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NullMoveParams {
+    pub enabled: bool,
+    /// remaining depth from which a null move is tried
+    pub min_depth: u8,
+    /// plies the null move search is reduced by, on top of the pass itself
+    pub reduction: u8,
+    /// remaining depth from which a null move cutoff is only trusted once a
+    /// reduced search of the node's own moves also fails high, which guards
+    /// against zugzwang and against a cutoff resting on a stack of reductions;
+    /// `None` trusts every cutoff
+    pub verification_min_depth: Option<u8>,
+}
+
+impl Default for NullMoveParams {
+    fn default() -> Self {
+        NullMoveParams {
+            enabled: true,
+            min_depth: 3,
+            reduction: 2,
+            verification_min_depth: Some(6),
+        }
+    }
+}
+
+impl NullMoveParams {
+    /// Whether a cutoff found at `depth` has to be verified.
+    fn verifies(&self, depth: u8) -> bool {
+        self.verification_min_depth
+            .is_some_and(|min_depth| depth >= min_depth)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SearchConfig {
+    /// threads searching alongside the main one from the same root, sharing
+    /// its table (Lazy SMP), their results only reaching it through that
+    pub helper_threads: usize,
+    pub root_randomization: Option<RootRandomization>,
+    pub late_moves: LateMoveParams,
+    pub null_move: NullMoveParams,
+    pub contempt: Contempt,
+    pub eval_weights: EvalWeights,
+    pub strength: Strength,
+    /// mixed with the root's key to seed the root randomization's and skill
+    /// level's picks, so the same search of the same position picks the
+    /// same move
+    pub seed: u64,
+    /// set by `go mate N`: searches nothing but what could prove a mate in
+    /// at most this many moves, stopping as soon as one is
+    pub mate_in: Option<u8>,
+}
+
+/// The plies a mate in `moves` moves takes, the mating move included.
+pub fn mate_plies(moves: u8) -> u8 {
+    (2 * moves.min(MAX_PLY / 2)).saturating_sub(1).max(1)
+}
+
+impl SearchConfig {
+    /// The same search with nothing pruned, reduced or randomized, so that
+    /// a mate it doesn't find within its depth isn't there.
+    fn for_proof(&self) -> SearchConfig {
+        let mut config = *self;
+        config.null_move.enabled = false;
+        config.late_moves.reduction_min_depth = u8::MAX;
+        config.late_moves.pruning_max_depth = 0;
+        config.root_randomization = None;
+        config.strength = Strength::default();
+        config
+    }
+
+    /// Whether `score` proves the mate a mate search is after.
+    pub fn proves_mate(&self, score: i32) -> bool {
+        self.mate_in
+            .is_some_and(|moves| score >= MATE - mate_plies(moves) as i32)
+    }
+
+    /// The window root moves are picked from below the best, the wider of
+    /// the root randomization's, if it still applies at `state`, and the
+    /// skill level's.
+    fn root_window(&self, state: &GameState) -> Option<i32> {
+        let randomization = match self.root_randomization {
+            Some(r) if state.game_ply < r.plies => Some(r.window),
+            _ => None,
+        };
+        let skill = self.strength.level().map(skill::window);
+        match (randomization, skill) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Whether the side to move has anything but pawns and its king, without
+/// which passing is too often the best move for a null move to be trusted.
+fn has_non_pawn_material(state: &GameState) -> bool {
+    let pieces = match state.position.side_to_move {
+        Color::White => [
+            Piece::WhiteKnight,
+            Piece::WhiteBishop,
+            Piece::WhiteRook,
+            Piece::WhiteQueen,
+        ],
+        Color::Black => [
+            Piece::BlackKnight,
+            Piece::BlackBishop,
+            Piece::BlackRook,
+            Piece::BlackQueen,
+        ],
+    };
+    let board = &state.position.board;
+    pieces
+        .iter()
+        .any(|piece| board.bitboard(*piece).pop_count() > 0)
+}
+
+/// Makes `mv` on a copy of `state`, or `None` if it can't be made or leaves
+/// the mover in check.
+// TODO: the generator only produces legal moves now, so the check here is a
+// safety net, and this could make and unmake in place rather than copying
+fn try_move(state: &GameState, mv: Move) -> Option<GameState> {
+    let mover = state.position.side_to_move;
+    let mut s = state.clone();
+    make_move(mv, &mut s).ok()?;
+    if !s.position.variant.king_safe(&s.position.board, mover) {
+        return None;
+    }
+    Some(s)
+}
+
+/// Iteratively deepens up to `depth` or until `control` stops it, passing
+/// each completed iteration to `observer`. From `ASPIRATION_MIN_DEPTH`
+/// each iteration first searches a window around the previous score, widening
+/// it on whichever side the score falls outside of.
+pub fn search(
+    state: &GameState,
+    depth: u8,
+    config: &SearchConfig,
+    control: Arc<SearchControl>,
+    tt: &TranspositionTable,
+    observer: &mut dyn SearchObserver,
+) -> SearchResult {
+    search_with_tree(state, depth, config, control, tt, observer, None)
+}
+
+/// Searches as [`search`] does, on the one thread, recording the nodes of
+/// the iteration at `depth` into `tree` up to its budget, for looking into
+/// how the search went about it afterwards.
+pub fn search_tree(
+    state: &GameState,
+    depth: u8,
+    config: &SearchConfig,
+    control: Arc<SearchControl>,
+    tt: &TranspositionTable,
+    tree: &mut SearchTree,
+) -> SearchResult {
+    let config = SearchConfig {
+        helper_threads: 0,
+        ..*config
+    };
+    search_with_tree(state, depth, &config, control, tt, &mut (), Some(tree))
+}
+
+fn search_with_tree(
+    state: &GameState,
+    depth: u8,
+    config: &SearchConfig,
+    control: Arc<SearchControl>,
+    tt: &TranspositionTable,
+    observer: &mut dyn SearchObserver,
+    tree: Option<&mut SearchTree>,
+) -> SearchResult {
+    let mut root = state.clone();
+    root.search_ply = 0;
+    // however long the game, the search has the history it needs
+    root.trim_history();
+    let depth = match config.strength.level() {
+        Some(level) => depth.min(skill::max_depth(level)),
+        None => depth,
+    };
+    let proof;
+    let (depth, config) = match config.mate_in {
+        Some(moves) => {
+            proof = config.for_proof();
+            (depth.min(mate_plies(moves)), &proof)
+        }
+        None => (depth, config),
+    };
+    tt.new_search();
+    let new_info = || {
+        let mut info = SearchInfo::new(control.clone(), tt);
+        info.contempt = config.contempt.at_root(&root);
+        info.eval = Evaluator::new(config.eval_weights);
+        info
+    };
+    let finished = AtomicBool::new(false);
+    thread::scope(|scope| {
+        for helper in 1..=config.helper_threads {
+            let mut info = new_info();
+            info.finished = Some(&finished);
+            let root = &root;
+            // odd helpers start a ply deeper, so the threads aren't all on
+            // the same iteration at once; one that can't be started is
+            // simply done without
+            let _ = thread::Builder::new()
+                .name(format!("search helper {}", helper))
+                .stack_size(SEARCH_STACK_SIZE)
+                .spawn_scoped(scope, move || {
+                    iterate(
+                        root,
+                        depth,
+                        1 + helper as u8 % 2,
+                        config,
+                        &mut info,
+                        &mut (),
+                    );
+                });
+        }
+        let mut info = new_info();
+        info.tree = tree;
+        let mut result = iterate(&root, depth, 1, config, &mut info, observer);
+        finished.store(true, Ordering::Relaxed);
+        result.stats = info.stats;
+        result
+    })
+}
+
+/// Deepens from `first_depth`, the iterations before it left to the table.
+/// At least one ply is searched, whatever `depth` asks for, so there's
+/// always a move to play.
+fn iterate(
+    state: &GameState,
+    depth: u8,
+    first_depth: u8,
+    config: &SearchConfig,
+    info: &mut SearchInfo,
+    observer: &mut dyn SearchObserver,
+) -> SearchResult {
+    let depth = depth.max(1);
+    let mut result = SearchResult {
+        best_move: None,
+        score: 0,
+        depth: 0,
+        pv: vec![],
+        stats: SearchStats::default(),
+    };
+
+    // checkmate, stalemate or a variant's win already, so there's nothing to search
+    if first_legal_move(state).is_none() {
+        result.score = no_moves_score(state, info);
+        observer.on_iteration(&Iteration {
+            depth: 0,
+            seldepth: 0,
+            score: result.score,
+            nodes: 0,
+            time: info.control.elapsed(),
+            hashfull: info.tt.hashfull(),
+            pv: vec![],
+        });
+        return result;
+    }
+    // randomizing needs exact scores across the whole window at the root
+    let aspirate = config.root_window(state).is_none();
+
+    for d in first_depth..=depth {
+        info.seldepth = 0;
+        info.trace(|tree| tree.record(d == depth));
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = if aspirate && d >= ASPIRATION_MIN_DEPTH {
+            (
+                (result.score - delta).max(-INFINITY),
+                (result.score + delta).min(INFINITY),
+            )
+        } else {
+            (-INFINITY, INFINITY)
+        };
+
+        let (best_move, score) = loop {
+            info.trace(|tree| tree.enter(NodeKind::Root, 0, d, alpha, beta));
+            let (best_move, score) = search_root(state, d, alpha, beta, config, info, observer);
+            info.trace_leave(score);
+            if info.stopped {
+                break (best_move, score);
+            }
+            if score <= alpha && alpha > -INFINITY {
+                alpha = (alpha - delta).max(-INFINITY);
+            } else if score >= beta && beta < INFINITY {
+                beta = (beta + delta).min(INFINITY);
+            } else {
+                break (best_move, score);
+            }
+            delta *= 2;
+        };
+
+        if info.stopped {
+            // an interrupted iteration only counts if there's nothing better
+            if result.best_move.is_none() {
+                result.best_move = best_move.or_else(|| first_legal_move(state));
+            }
+            break;
+        }
+        result = SearchResult {
+            best_move,
+            score,
+            depth: d,
+            pv: info.pv[0].clone(),
+            stats: SearchStats::default(),
+        };
+        info.tt.store(Entry {
+            key: state.position_key,
+            depth: d,
+            score,
+            bound: Bound::Exact,
+            best_move: best_move.map(Move16::from),
+        });
+        observer.on_iteration(&Iteration {
+            depth: d,
+            seldepth: info.seldepth,
+            score,
+            nodes: info.nodes,
+            time: info.control.elapsed(),
+            hashfull: info.tt.hashfull(),
+            pv: result.pv.clone(),
+        });
+        if config.proves_mate(score) || info.control.should_stop_iterating(info.nodes) {
+            break;
+        }
+    }
+    result
+}
+
+/// The first move that can be played at `state`, for when there's no time,
+/// or no search, to find a better one.
+pub fn first_legal_move(state: &GameState) -> Option<Move> {
+    let mut root = state.clone();
+    root.trim_history();
+    root.position
+        .legal_moves()
+        .into_iter()
+        .find(|mv| try_move(&root, *mv).is_some())
+}
+
+/// Searches the root moves within (`alpha`, `beta`), failing hard: a fail
+/// low returns no move and `alpha`, a fail high the refuting move and `beta`.
+/// Each move is passed to `observer` as its search starts.
+fn search_root(
+    state: &GameState,
+    depth: u8,
+    mut alpha: i32,
+    beta: i32,
+    config: &SearchConfig,
+    info: &mut SearchInfo,
+    observer: &mut dyn SearchObserver,
+) -> (Option<Move>, i32) {
+    let window = config.root_window(state);
+    let mut legal = 0;
+    let mut scored: Vec<(Move, i32, Vec<Move>)> = vec![];
+    info.clear_pv(0);
+
+    let tt_move = info
+        .tt
+        .probe(state.position_key)
+        .and_then(|e| e.best_move)
+        .and_then(|mv| mv.to_move(&state.position));
+    for mv in ordering::order_moves(state, state.position.legal_moves(), tt_move) {
+        let s = match try_move(state, mv) {
+            Some(s) => s,
+            None => continue,
+        };
+        legal += 1;
+        info.trace(|tree| tree.next_move(mv));
+        observer.on_current_move(&CurrentMove {
+            depth,
+            mv,
+            number: legal,
+            nodes: info.nodes,
+            time: info.control.elapsed(),
+            hashfull: info.tt.hashfull(),
+        });
+
+        let score = match window {
+            // searching with alpha lowered by the window keeps the scores of
+            // every move that could still be randomized to exact
+            Some(window) => {
+                let floor = (alpha - window).max(-INFINITY);
+                -alpha_beta(&s, depth - 1, -beta, -floor, 1, config, info)
+            }
+            None if legal == 1 => -alpha_beta(&s, depth - 1, -beta, -alpha, 1, config, info),
+            None => {
+                let mut score = -alpha_beta(&s, depth - 1, -alpha - 1, -alpha, 1, config, info);
+                if score > alpha && score < beta {
+                    score = -alpha_beta(&s, depth - 1, -beta, -alpha, 1, config, info);
+                }
+                score
+            }
+        };
+        if info.stopped {
+            break;
+        }
+        if score >= beta {
+            info.update_pv(0, mv);
+            return (Some(mv), beta);
+        }
+        if score <= (alpha - window.unwrap_or(0)).max(-INFINITY) {
+            // a fail-low, so only an upper bound
+            continue;
+        }
+        info.update_pv(0, mv);
+        scored.push((mv, score, info.pv[0].clone()));
+        if score > alpha {
+            alpha = score;
+        }
+    }
+
+    if legal == 0 {
+        return (None, no_moves_score(state, info));
+    }
+    if scored.is_empty() {
+        return (None, alpha);
+    }
+
+    let candidates: Vec<&(Move, i32, Vec<Move>)> = scored
+        .iter()
+        .filter(|(_, score, _)| *score >= alpha - window.unwrap_or(0))
+        .collect();
+    let mut rng = StdRng::seed_from_u64(config.seed ^ state.position_key ^ depth as u64);
+    let (best_move, score, pv) = match (window, config.strength.level()) {
+        (Some(_), Some(level)) => {
+            let scores: Vec<i32> = candidates.iter().map(|(_, score, _)| *score).collect();
+            candidates[skill::pick(&scores, level, &mut rng)]
+        }
+        (Some(_), None) => candidates[rng.gen_range(0..candidates.len())],
+        (None, _) => candidates[0],
+    };
+    info.pv[0] = pv.clone();
+    (Some(*best_move), *score)
+}
+
+/// The score at the root when there's no move to search: the variant's
+/// result, checkmate or stalemate.
+fn no_moves_score(state: &GameState, info: &SearchInfo) -> i32 {
+    if let Some(score) = variant_score(state, 0) {
+        score
+    } else if state.position.in_check() {
+        -MATE
+    } else {
+        stalemate_score(state, 0, info)
+    }
+}
+
+/// The score of a side to move with no move out of check at `ply`: a
+/// draw, or a mate where the variant has the stalemated side win.
+fn stalemate_score(state: &GameState, ply: u8, info: &SearchInfo) -> i32 {
+    let position = &state.position;
+    match position.variant.stalemate_winner(position.side_to_move) {
+        Some(winner) if winner == position.side_to_move => MATE - ply as i32,
+        Some(_) => -MATE + ply as i32,
+        None => info.draw_score(ply),
+    }
+}
+
+/// The score of a game the variant's own rules have already ended, as a
+/// mate at `ply` for the winner.
+fn variant_score(state: &GameState, ply: u8) -> Option<i32> {
+    let winner = state.position.winner()?;
+    Some(if winner == state.position.side_to_move {
+        MATE - ply as i32
+    } else {
+        -MATE + ply as i32
+    })
+}
+
+fn alpha_beta(
+    state: &GameState,
+    depth: u8,
+    alpha: i32,
+    beta: i32,
+    ply: u8,
+    config: &SearchConfig,
+    info: &mut SearchInfo,
+) -> i32 {
+    if depth == 0 {
+        return quiescence(state, alpha, beta, ply, info);
+    }
+    info.trace(|tree| tree.enter(NodeKind::Main, ply, depth, alpha, beta));
+    let score = alpha_beta_node(state, depth, alpha, beta, ply, config, info);
+    info.trace_leave(score);
+    score
+}
+
+fn alpha_beta_node(
+    state: &GameState,
+    depth: u8,
+    mut alpha: i32,
+    beta: i32,
+    ply: u8,
+    config: &SearchConfig,
+    info: &mut SearchInfo,
+) -> i32 {
+    let pv_node = beta - alpha > 1;
+    info.nodes += 1;
+    if pv_node {
+        info.stats.pv_nodes += 1;
+    } else {
+        info.stats.non_pv_nodes += 1;
+    }
+    info.seldepth = info.seldepth.max(ply);
+    info.poll();
+    if info.stopped {
+        return 0;
+    }
+    info.clear_pv(ply);
+
+    if let Some(score) = variant_score(state, ply) {
+        info.trace(|tree| tree.note(Outcome::VariantEnd));
+        return score;
+    }
+
+    // a repeated position is scored as a draw straight away, since the side
+    // that went for it could keep on repeating; the root still needs a move
+    // whatever its history
+    if ply > 0 && (state.is_repetition() || state.is_fifty_move_draw()) {
+        info.trace(|tree| tree.note(Outcome::Draw));
+        return info.draw_score(ply);
+    }
+
+    // a bound from the table is only trusted off the principal variation,
+    // where cutting short would also cut the PV short, and never in a mate
+    // search, as it may come from a search that pruned the mate away
+    let entry = info.tt.probe(state.position_key);
+    info.stats.tt_probes += 1;
+    if let Some(e) = entry {
+        info.stats.tt_hits += 1;
+        let score = score_from_tt(e.score, ply);
+        if !pv_node && e.depth >= depth && config.mate_in.is_none() {
+            let cutoff = match e.bound {
+                Bound::Exact => Some(score.clamp(alpha, beta)),
+                Bound::Lower if score >= beta => Some(beta),
+                Bound::Upper if score <= alpha => Some(alpha),
+                _ => None,
+            };
+            if let Some(score) = cutoff {
+                info.stats.tt_cutoffs += 1;
+                info.trace(|tree| tree.note(Outcome::TtCutoff));
+                return score;
+            }
+        }
+    }
+
+    let in_check = state.position.in_check();
+    let null_move = &config.null_move;
+    if null_move.enabled
+        && !pv_node
+        && !in_check
+        && ply > 0
+        && depth >= null_move.min_depth
+        && !info.verifying
+        && info.null_ply != Some(ply - 1)
+        && has_non_pawn_material(state)
+        && info.eval.evaluate(state) >= beta
+    {
+        let mut s = state.clone();
+        if make_null_move(&mut s).is_ok() {
+            info.stats.null_move_tries += 1;
+            info.trace(|tree| tree.next_null());
+            let previous = info.null_ply.replace(ply);
+            let score = -alpha_beta(
+                &s,
+                depth.saturating_sub(null_move.reduction + 1),
+                -beta,
+                -beta + 1,
+                ply + 1,
+                config,
+                info,
+            );
+            info.null_ply = previous;
+            if info.stopped {
+                return 0;
+            }
+            if score >= beta {
+                if !null_move.verifies(depth) {
+                    info.stats.null_move_cutoffs += 1;
+                    info.trace(|tree| tree.note(Outcome::NullMoveCutoff));
+                    return beta;
+                }
+                info.verifying = true;
+                info.trace(|tree| tree.next_verification());
+                let verified = alpha_beta(
+                    state,
+                    depth - null_move.reduction,
+                    beta - 1,
+                    beta,
+                    ply,
+                    config,
+                    info,
+                );
+                info.verifying = false;
+                if info.stopped {
+                    return 0;
+                }
+                if verified >= beta {
+                    info.stats.null_move_cutoffs += 1;
+                    info.trace(|tree| tree.note(Outcome::NullMoveCutoff));
+                    return beta;
+                }
+                // the verification search cleared this ply's PV
+                info.clear_pv(ply);
+            }
+        }
+    }
+
+    let original_alpha = alpha;
+    let mut best_move = None;
+    let late_moves = &config.late_moves;
+    let mut legal = 0;
+    let tt_move = entry
+        .and_then(|e| e.best_move)
+        .and_then(|mv| mv.to_move(&state.position));
+    for mv in ordering::order_moves(state, state.position.legal_moves(), tt_move) {
+        let quiet = mv.captured().is_none() && mv.promoted().is_none();
+        let gives_check = state.position.gives_check(mv);
+        let late_move_candidate = quiet && !in_check && !gives_check;
+        // pruned before paying for the copy; a late move means legal moves
+        // have been found already, and alpha being no mate score means the
+        // last escape from one isn't being pruned away
+        if late_move_candidate
+            && alpha > -MATE + MAX_PLY as i32
+            && late_moves.prunes(depth, legal + 1)
+        {
+            info.trace(|tree| {
+                tree.prune(
+                    mv,
+                    Outcome::LateMovePruned,
+                    ply + 1,
+                    depth - 1,
+                    -alpha - 1,
+                    -alpha,
+                )
+            });
+            continue;
+        }
+
+        let s = match try_move(state, mv) {
+            Some(s) => s,
+            None => continue,
+        };
+        legal += 1;
+        info.trace(|tree| tree.next_move(mv));
+
+        // a check is searched a ply deeper, as long as the line still fits
+        let new_depth = if gives_check && ply as u16 + depth as u16 <= MAX_PLY as u16 {
+            depth
+        } else {
+            depth - 1
+        };
+        let reduction = if late_move_candidate {
+            late_moves.reduction(depth, legal)
+        } else {
+            0
+        };
+        // only the first move gets the full window; the rest are expected to
+        // fail low, which a zero window around alpha proves more cheaply
+        let score = if legal == 1 {
+            -alpha_beta(&s, new_depth, -beta, -alpha, ply + 1, config, info)
+        } else {
+            let mut score = -alpha_beta(
+                &s,
+                new_depth - reduction,
+                -alpha - 1,
+                -alpha,
+                ply + 1,
+                config,
+                info,
+            );
+            if reduction > 0 && score > alpha {
+                score = -alpha_beta(&s, new_depth, -alpha - 1, -alpha, ply + 1, config, info);
+            }
+            if score > alpha && score < beta {
+                score = -alpha_beta(&s, new_depth, -beta, -alpha, ply + 1, config, info);
+            }
+            score
+        };
+
+        if info.stopped {
+            return 0;
+        }
+
+        if score >= beta {
+            info.stats.beta_cutoffs += 1;
+            if legal == 1 {
+                info.stats.first_move_cutoffs += 1;
+            }
+            info.tt.store(Entry {
+                key: state.position_key,
+                depth,
+                score: score_to_tt(beta, ply),
+                bound: Bound::Lower,
+                best_move: Some(mv.into()),
+            });
+            return beta;
+        }
+        if score > alpha {
+            alpha = score;
+            best_move = Some(mv);
+            info.update_pv(ply, mv);
+        }
+    }
+
+    if legal == 0 {
+        return if in_check {
+            info.trace(|tree| tree.note(Outcome::Checkmate));
+            -MATE + ply as i32
+        } else {
+            info.trace(|tree| tree.note(Outcome::Stalemate));
+            stalemate_score(state, ply, info)
+        };
+    }
+    info.tt.store(Entry {
+        key: state.position_key,
+        depth,
+        score: score_to_tt(alpha, ply),
+        bound: if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        },
+        best_move: best_move.map(Move16::from),
+    });
+    alpha
+}
+
+fn quiescence(state: &GameState, alpha: i32, beta: i32, ply: u8, info: &mut SearchInfo) -> i32 {
+    info.trace(|tree| tree.enter(NodeKind::Quiescence, ply, 0, alpha, beta));
+    let score = quiescence_node(state, alpha, beta, ply, info);
+    info.trace_leave(score);
+    score
+}
+
+fn quiescence_node(
+    state: &GameState,
+    mut alpha: i32,
+    beta: i32,
+    ply: u8,
+    info: &mut SearchInfo,
+) -> i32 {
+    info.nodes += 1;
+    info.stats.qsearch_nodes += 1;
+    info.seldepth = info.seldepth.max(ply);
+    info.poll();
+    if info.stopped {
+        return 0;
+    }
+    info.clear_pv(ply);
+    if let Some(score) = variant_score(state, ply) {
+        info.trace(|tree| tree.note(Outcome::VariantEnd));
+        return score;
+    }
+    if ply >= MAX_PLY {
+        return info.eval.evaluate(state);
+    }
+
+    // standing pat assumes the side to move could decline every capture and
+    // keep the static score, which isn't an option when in check: there every
+    // evasion has to be searched, quiet or not
+    let in_check = state.position.in_check();
+    if !in_check {
+        let stand_pat = info.eval.evaluate(state);
+        if stand_pat >= beta {
+            info.trace(|tree| tree.note(Outcome::StandPat));
+            return beta;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+    }
+
+    // the generator is legal, so in check it only yields evasions
+    let mut legal = 0;
+    for mv in ordering::order_moves(state, state.position.legal_moves(), None) {
+        if !in_check && mv.captured().is_none() && mv.promoted().is_none() {
+            continue;
+        }
+        let s = match try_move(state, mv) {
+            Some(s) => s,
+            None => continue,
+        };
+        legal += 1;
+        info.trace(|tree| tree.next_move(mv));
+        let score = -quiescence(&s, -beta, -alpha, ply + 1, info);
+        if info.stopped {
+            return 0;
+        }
+        if score >= beta {
+            return beta;
+        }
+        if score > alpha {
+            alpha = score;
+            info.update_pv(ply, mv);
+        }
+    }
+
+    if in_check && legal == 0 {
+        info.trace(|tree| tree.note(Outcome::Checkmate));
+        return -MATE + ply as i32;
+    }
+    alpha
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::{types::Square, variant::Variant};
+    use crate::search::time::NodeBudget;
+
+    #[test]
+    fn test_quiescence_no_stand_pat_when_mated() {
+        // fool's mate: material is level, but white has no evasions
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        let state = GameState::from_fen(fen).unwrap();
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let score = quiescence(&state, -INFINITY, INFINITY, 0, &mut info);
+        assert_eq!(score, -MATE);
+    }
+
+    #[test]
+    fn test_quiescence_searches_quiet_evasions() {
+        // white is up the exchange but the knight checks and forks the rook,
+        // so every (quiet) king evasion drops it
+        let fen = "4k3/8/8/8/8/8/2n5/R3K3 w - - 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+        assert!(Evaluator::default().evaluate(&state) > 0);
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let score = quiescence(&state, -INFINITY, INFINITY, 0, &mut info);
+        assert!(score < 0);
+    }
+
+    #[test]
+    fn test_quiescence_stand_pat() {
+        let state = GameState::default();
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let score = quiescence(&state, -INFINITY, INFINITY, 0, &mut info);
+        assert_eq!(score, 0);
+        assert_eq!(info.nodes, 1);
+    }
+
+    #[test]
+    fn test_search_finds_mate_in_one() {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        let state = GameState::from_fen(fen).unwrap();
+        let result = search(
+            &state,
+            2,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut (),
+        );
+        let best_move = result.best_move.unwrap();
+        assert_eq!(best_move.from_sq(), Square::D8);
+        assert_eq!(best_move.to_sq(), Square::H4);
+        assert_eq!(result.score, MATE - 1);
+        assert_eq!(result.pv, vec![best_move]);
+        assert_eq!(result.ponder_move(), None);
+    }
+
+    #[test]
+    fn test_search_depth_zero() {
+        let result = search(
+            &GameState::default(),
+            0,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut (),
+        );
+        assert_eq!(result.depth, 1);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_search_king_of_the_hill() {
+        // a quiet king move wins outright once the centre counts
+        let fen = "4k3/8/8/8/8/4K3/8/8 w - - 0 1";
+        let state = GameState::from_fen(fen)
+            .unwrap()
+            .with_variant(Variant::KingOfTheHill);
+        let result = search(
+            &state,
+            3,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut (),
+        );
+        let best_move = result.best_move.unwrap();
+        assert!([Square::D4, Square::E4].contains(&best_move.to_sq()));
+        assert_eq!(result.score, MATE - 1);
+    }
+
+    #[test]
+    fn test_search_pv() {
+        let result = search(
+            &GameState::default(),
+            3,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut (),
+        );
+        assert_eq!(result.pv.len(), 3);
+        assert_eq!(result.pv[0], result.best_move.unwrap());
+        assert!(result.ponder_move().is_some());
+    }
+
+    #[test]
+    fn test_search_stopped() {
+        let control = Arc::new(SearchControl::default());
+        control.stop();
+        // the control is only polled every so often, but a stopped search
+        // never starts another iteration and still has a move to play
+        let result = search(
+            &GameState::default(),
+            5,
+            &SearchConfig::default(),
+            control,
+            &TranspositionTable::default(),
+            &mut (),
+        );
+        assert!(result.best_move.is_some());
+        assert!(result.depth <= 1);
+    }
+
+    #[test]
+    fn test_search_root_window() {
+        let config = SearchConfig::default();
+        let state = GameState::default();
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let (best_move, score) =
+            search_root(&state, 3, -INFINITY, INFINITY, &config, &mut info, &mut ());
+        assert!(best_move.is_some());
+
+        // a window entirely above the score fails low, with no move
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let (best_move, bound) = search_root(
+            &state,
+            3,
+            score + 10,
+            score + 20,
+            &config,
+            &mut info,
+            &mut (),
+        );
+        assert_eq!((best_move, bound), (None, score + 10));
+
+        // and one entirely below it fails high on a refutation
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let (best_move, bound) = search_root(
+            &state,
+            3,
+            score - 20,
+            score - 10,
+            &config,
+            &mut info,
+            &mut (),
+        );
+        assert!(best_move.is_some());
+        assert_eq!(bound, score - 10);
+
+        // a window around it finds the same score as the full one
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let (_, exact) = search_root(
+            &state,
+            3,
+            score - 10,
+            score + 10,
+            &config,
+            &mut info,
+            &mut (),
+        );
+        assert_eq!(exact, score);
+    }
+
+    #[test]
+    fn test_aspiration_matches_full_window() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        let state = GameState::from_fen(fen).unwrap();
+        let config = SearchConfig::default();
+        let result = search(
+            &state,
+            4,
+            &config,
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut (),
+        );
+
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let (best_move, score) =
+            search_root(&state, 4, -INFINITY, INFINITY, &config, &mut info, &mut ());
+        assert_eq!(result.depth, 4);
+        assert_eq!(result.score, score);
+        assert_eq!(result.best_move, best_move);
+    }
+
+    #[test]
+    fn test_seldepth() {
+        // the rook check is extended, and the capture after it left to
+        // quiescence, so the line runs past the nominal depth
+        let fen = "4k3/8/8/8/8/8/1p6/R3K3 w - - 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        search_root(
+            &state,
+            2,
+            -INFINITY,
+            INFINITY,
+            &SearchConfig::default(),
+            &mut info,
+            &mut (),
+        );
+        assert!(info.seldepth > 2);
+    }
+
+    #[test]
+    fn test_uci_score() {
+        assert_eq!(uci_score(35), "cp 35");
+        assert_eq!(uci_score(-120), "cp -120");
+        assert_eq!(uci_score(MATE - 1), "mate 1");
+        assert_eq!(uci_score(MATE - 3), "mate 2");
+        assert_eq!(uci_score(-MATE + 2), "mate -1");
+        assert_eq!(uci_score(-MATE), "mate 0");
+    }
+
+    #[test]
+    fn test_search_root_mated_or_stalemated() {
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        let state = GameState::from_fen(fen).unwrap();
+        let tt = TranspositionTable::default();
+        let result = search(
+            &state,
+            3,
+            &SearchConfig::default(),
+            Arc::default(),
+            &tt,
+            &mut (),
+        );
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, -MATE);
+
+        let fen = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+        let result = search(
+            &state,
+            3,
+            &SearchConfig::default(),
+            Arc::default(),
+            &tt,
+            &mut (),
+        );
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, 0);
+
+        // with negative contempt the stalemate is welcome, scaled down to the
+        // queen being all that is left
+        let config = SearchConfig {
+            contempt: Contempt {
+                base: -50,
+                ..Contempt::default()
+            },
+            ..SearchConfig::default()
+        };
+        let result = search(&state, 3, &config, Arc::default(), &tt, &mut ());
+        assert_eq!(result.score, 18);
+    }
+
+    #[test]
+    fn test_contempt_draw_score() {
+        let tt = TranspositionTable::default();
+        let info = SearchInfo {
+            contempt: 30,
+            ..SearchInfo::new(Arc::default(), &tt)
+        };
+        // from the root side's perspective at even plies, the opponent's at odd
+        assert_eq!(info.draw_score(0), -30);
+        assert_eq!(info.draw_score(3), 30);
+    }
+
+    #[test]
+    fn test_repetition_is_draw() {
+        let mut state = GameState::default();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            let mv = state.position.parse_move(uci).unwrap();
+            make_move(mv, &mut state).unwrap();
+        }
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo {
+            contempt: 30,
+            ..SearchInfo::new(Arc::default(), &tt)
+        };
+        let config = SearchConfig::default();
+        let score = alpha_beta(&state, 3, -INFINITY, INFINITY, 1, &config, &mut info);
+        assert_eq!(score, info.draw_score(1));
+        assert_eq!(info.nodes, 1);
+
+        // but at the root it's searched as usual
+        let (best_move, _) =
+            search_root(&state, 2, -INFINITY, INFINITY, &config, &mut info, &mut ());
+        assert!(best_move.is_some());
+    }
+
+    #[test]
+    fn test_mate_search() {
+        // 1. Kb6 Kb8 2. Rh8#, with no mate in one
+        let state = GameState::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let mate_search = |moves| {
+            let config = SearchConfig {
+                mate_in: Some(moves),
+                ..SearchConfig::default()
+            };
+            let tt = TranspositionTable::new(1);
+            search(&state, MAX_PLY, &config, Arc::default(), &tt, &mut ())
+        };
+        let result = mate_search(2);
+        assert_eq!((result.score, result.depth), (MATE - 3, 3));
+        assert_eq!(result.pv.len(), 3);
+        // a longer mate than asked for is never looked for
+        let result = mate_search(1);
+        assert_eq!(result.depth, 1);
+        assert!(result.score < MATE - MAX_PLY as i32);
+        // and a shorter one ends the search early
+        assert_eq!(mate_search(5).depth, 3);
+        assert_eq!(mate_plies(3), 5);
+    }
+
+    #[test]
+    fn test_node_limit_is_exact() {
+        let state = GameState::default();
+        let limited = |nodes| {
+            let control = SearchControl::default().with_node_budget(Some(NodeBudget {
+                soft: nodes,
+                hard: nodes,
+            }));
+            search(
+                &state,
+                MAX_PLY,
+                &SearchConfig::default(),
+                Arc::new(control),
+                &TranspositionTable::new(1),
+                &mut (),
+            )
+        };
+        let result = limited(5_000);
+        assert_eq!(result.stats.nodes(), 5_000);
+        // and the same limit searches the same tree
+        let again = limited(5_000);
+        assert_eq!(
+            (again.best_move, again.score, again.depth, again.pv),
+            (result.best_move, result.score, result.depth, result.pv)
+        );
+        assert_eq!(limited(5_001).stats.nodes(), 5_001);
+    }
+
+    #[test]
+    fn test_helper_threads() {
+        // helpers searching alongside don't change what's found, and share
+        // the table the search is given
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        let state = GameState::from_fen(fen).unwrap();
+        let config = SearchConfig {
+            helper_threads: 3,
+            ..SearchConfig::default()
+        };
+        let tt = TranspositionTable::new(1);
+        let mut iterations: Vec<Iteration> = vec![];
+        let result = search(&state, 4, &config, Arc::default(), &tt, &mut iterations);
+        assert_eq!(result.score, MATE - 1);
+        assert_eq!(result.best_move.unwrap().to_sq(), Square::H4);
+        assert_eq!(iterations.last().unwrap().depth, 4);
+        assert!(tt.probe(state.position_key).is_some());
+
+        let state = GameState::default();
+        let result = search(&state, 4, &config, Arc::default(), &tt, &mut ());
+        assert_eq!(result.depth, 4);
+        let best = result.best_move.unwrap().to_uci();
+        assert!(state.position.parse_move(&best).is_some());
+    }
+
+    #[test]
+    fn test_search_with_tt() {
+        // mate scores read back from the table keep their distance, search
+        // after search
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        let state = GameState::from_fen(fen).unwrap();
+        let tt = TranspositionTable::new(1);
+        for _ in 0..2 {
+            let result = search(
+                &state,
+                4,
+                &SearchConfig::default(),
+                Arc::default(),
+                &tt,
+                &mut (),
+            );
+            assert_eq!(result.score, MATE - 1);
+            assert_eq!(result.best_move.unwrap().to_sq(), Square::H4);
+        }
+        let entry = tt.probe(state.position_key).unwrap();
+        assert_eq!(entry.best_move.unwrap().to_sq(), Square::H4);
+
+        // and the table doesn't change what a plain search finds
+        let state = GameState::default();
+        let no_tt = TranspositionTable::default();
+        let plain = search(
+            &state,
+            4,
+            &SearchConfig::default(),
+            Arc::default(),
+            &no_tt,
+            &mut (),
+        );
+        let tt = TranspositionTable::new(1);
+        let with_tt = search(
+            &state,
+            4,
+            &SearchConfig::default(),
+            Arc::default(),
+            &tt,
+            &mut (),
+        );
+        assert_eq!(plain.score, with_tt.score);
+    }
+
+    #[test]
+    fn test_late_move_reduction() {
+        let params = LateMoveParams::default();
+        // shallow or early moves aren't reduced
+        assert_eq!(params.reduction(2, 20), 0);
+        assert_eq!(params.reduction(6, 1), 0);
+        assert_eq!(params.reduction(6, params.reduction_min_moves), 0);
+        // later moves are reduced more, but always keep a ply to search
+        assert!(params.reduction(6, 20) >= params.reduction(6, 5));
+        assert!(params.reduction(6, 20) > 0);
+        for depth in params.reduction_min_depth..20 {
+            assert!(params.reduction(depth, 200) <= depth - 2);
+        }
+    }
+
+    #[test]
+    fn test_late_move_pruning() {
+        let params = LateMoveParams::default();
+        assert!(!params.prunes(1, 1));
+        assert!(params.prunes(1, 5));
+        assert!(!params.prunes(2, 5));
+        assert!(!params.prunes(params.pruning_max_depth + 1, 200));
+    }
+
+    #[test]
+    fn test_late_moves_reduce_nodes() {
+        let state = GameState::default();
+        let mut config = SearchConfig::default();
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        search_root(&state, 4, -INFINITY, INFINITY, &config, &mut info, &mut ());
+        let reduced_nodes = info.nodes;
+
+        config.late_moves.reduction_min_depth = u8::MAX;
+        config.late_moves.pruning_max_depth = 0;
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        search_root(&state, 4, -INFINITY, INFINITY, &config, &mut info, &mut ());
+        assert!(reduced_nodes < info.nodes);
+    }
+
+    #[test]
+    fn test_search_observer() {
+        let state = GameState::default();
+        let mut iterations: Vec<Iteration> = vec![];
+        let result = search(
+            &state,
+            3,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut iterations,
+        );
+        let depths: Vec<u8> = iterations.iter().map(|i| i.depth).collect();
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert!(iterations.windows(2).all(|w| w[0].nodes < w[1].nodes));
+        let last = iterations.last().unwrap();
+        assert_eq!((last.score, &last.pv), (result.score, &result.pv));
+
+        // with no moves, the single report is at depth 0
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        let state = GameState::from_fen(fen).unwrap();
+        let mut iterations: Vec<Iteration> = vec![];
+        search(
+            &state,
+            3,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut iterations,
+        );
+        assert_eq!(iterations.len(), 1);
+        assert_eq!((iterations[0].depth, iterations[0].score), (0, -MATE));
+    }
+
+    /// Keeps the number of each root move searched, and the depth it was at.
+    #[derive(Default)]
+    struct CurrentMoves(Vec<(u8, usize)>);
+
+    impl SearchObserver for CurrentMoves {
+        fn on_current_move(&mut self, current: &CurrentMove) {
+            self.0.push((current.depth, current.number));
+        }
+    }
+
+    #[test]
+    fn test_current_moves_observed() {
+        let mut observer = CurrentMoves::default();
+        search(
+            &GameState::default(),
+            2,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::new(1),
+            &mut observer,
+        );
+        let expected: Vec<(u8, usize)> = (1..=2)
+            .flat_map(|depth| (1..=20).map(move |number| (depth, number)))
+            .collect();
+        assert_eq!(observer.0, expected);
+    }
+
+    #[test]
+    fn test_null_move_pruning() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let state = GameState::from_fen(fen).unwrap();
+        let mut config = SearchConfig::default();
+        config.null_move.verification_min_depth = Some(3);
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let (verified_move, verified_score) =
+            search_root(&state, 5, -INFINITY, INFINITY, &config, &mut info, &mut ());
+        let verified_nodes = info.nodes;
+
+        // unverified cutoffs are cheaper, and here change nothing
+        config.null_move.verification_min_depth = None;
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        let (best_move, score) =
+            search_root(&state, 5, -INFINITY, INFINITY, &config, &mut info, &mut ());
+        assert_eq!((best_move, score), (verified_move, verified_score));
+        assert!(info.nodes < verified_nodes);
+        let unverified_nodes = info.nodes;
+
+        config.null_move.enabled = false;
+        let tt = TranspositionTable::default();
+        let mut info = SearchInfo::new(Arc::default(), &tt);
+        search_root(&state, 5, -INFINITY, INFINITY, &config, &mut info, &mut ());
+        assert!(unverified_nodes < info.nodes);
+        assert!(verified_nodes < info.nodes);
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let state = GameState::from_fen(fen).unwrap();
+        let mut iterations: Vec<Iteration> = vec![];
+        let result = search(
+            &state,
+            5,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::new(1),
+            &mut iterations,
+        );
+        let stats = result.stats;
+        assert_eq!(stats.nodes(), iterations.last().unwrap().nodes);
+        assert!(stats.pv_nodes > 0 && stats.non_pv_nodes > 0 && stats.qsearch_nodes > 0);
+        assert!(0 < stats.first_move_cutoffs && stats.first_move_cutoffs <= stats.beta_cutoffs);
+        assert!(0 < stats.tt_cutoffs && stats.tt_cutoffs <= stats.tt_hits);
+        assert!(stats.tt_hits <= stats.tt_probes);
+        assert!(0 < stats.null_move_cutoffs && stats.null_move_cutoffs <= stats.null_move_tries);
+    }
+
+    #[test]
+    fn test_search_tree() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let state = GameState::from_fen(fen).unwrap();
+        let config = SearchConfig::default();
+        let plain = search(
+            &state,
+            5,
+            &config,
+            Arc::default(),
+            &TranspositionTable::new(1),
+            &mut (),
+        );
+        let mut tree = SearchTree::new(usize::MAX);
+        let traced = search_tree(
+            &state,
+            5,
+            &config,
+            Arc::default(),
+            &TranspositionTable::new(1),
+            &mut tree,
+        );
+        // recording changes nothing about the search itself
+        assert_eq!(
+            (traced.best_move, traced.score),
+            (plain.best_move, plain.score)
+        );
+        assert_eq!(traced.stats, plain.stats);
+        assert!(!tree.truncated);
+
+        let root = &tree.nodes[0];
+        assert_eq!(
+            (root.kind, root.depth, root.parent),
+            (NodeKind::Root, 5, None)
+        );
+        assert!(tree.nodes.iter().all(|node| node.outcome.is_some()));
+        assert!(tree.nodes[1..]
+            .iter()
+            .all(|node| node.parent < Some(node.id)));
+        let has = |outcome| tree.nodes.iter().any(|node| node.outcome == Some(outcome));
+        assert!(has(Outcome::TtCutoff) && has(Outcome::LateMovePruned) && has(Outcome::StandPat));
+        assert!(tree
+            .nodes
+            .iter()
+            .any(|node| node.kind == NodeKind::NullMove));
+
+        let mut small = SearchTree::new(100);
+        search_tree(
+            &state,
+            5,
+            &config,
+            Arc::default(),
+            &TranspositionTable::new(1),
+            &mut small,
+        );
+        assert!(small.truncated);
+        assert_eq!(small.nodes[..], tree.nodes[..100]);
+    }
+
+    #[test]
+    fn test_null_move_not_tried_without_pieces() {
+        // passing is the only way to hold this, so a pawn ending mustn't be
+        // cut short by a null move
+        let state = GameState::from_fen("8/8/8/8/8/2k5/2p5/2K5 w - - 0 1").unwrap();
+        assert!(!has_non_pawn_material(&state));
+        let state = GameState::from_fen("8/8/8/8/8/2k5/2p5/2K4N w - - 0 1").unwrap();
+        assert!(has_non_pawn_material(&state));
+    }
+
+    #[test]
+    fn test_skill_level() {
+        let config = SearchConfig {
+            strength: Strength {
+                skill_level: 0,
+                ..Strength::default()
+            },
+            ..SearchConfig::default()
+        };
+        let tt = TranspositionTable::default();
+        // the weakest level searches a ply, and picks among the moves near
+        // the best, which one depending on the seed
+        let state = GameState::default();
+        let mut best_moves: Vec<Move> = (0..20)
+            .filter_map(|seed| {
+                let config = SearchConfig { seed, ..config };
+                let result = search(&state, 8, &config, Arc::default(), &tt, &mut ());
+                assert_eq!(result.depth, 1);
+                result.best_move
+            })
+            .collect();
+        best_moves.sort();
+        best_moves.dedup();
+        assert!(best_moves.len() > 1);
+
+        // but never misses a mate for a move that's far worse
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        let mate_state = GameState::from_fen(fen).unwrap();
+        for _ in 0..5 {
+            let result = search(&mate_state, 8, &config, Arc::default(), &tt, &mut ());
+            assert_eq!(result.best_move.unwrap().to_sq(), Square::H4);
+        }
+    }
+
+    #[test]
+    fn test_search_with_full_history() {
+        // a game as long as the history holds still has its moves searched
+        let mut state = GameState::default();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        for uci in shuffle.iter().cycle().take(crate::play::MAX_GAME_PLY) {
+            let mv = state.position.parse_move(uci).unwrap();
+            make_move(mv, &mut state).unwrap();
+        }
+        let result = search(
+            &state,
+            3,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut (),
+        );
+        assert!(result.best_move.is_some());
+        assert!(result.score.abs() < MATE - MAX_PLY as i32);
+        assert!(first_legal_move(&state).is_some());
+    }
+
+    #[test]
+    fn test_search_root_randomization() {
+        let mut config = SearchConfig {
+            root_randomization: Some(RootRandomization {
+                plies: 8,
+                window: 10,
+            }),
+            ..SearchConfig::default()
+        };
+
+        // every opening move is level on material, so they're all candidates
+        let state = GameState::default();
+        let pick = |config: &SearchConfig| {
+            search(
+                &state,
+                1,
+                config,
+                Arc::default(),
+                &TranspositionTable::default(),
+                &mut (),
+            )
+            .best_move
+        };
+        let mut best_moves: Vec<Move> = (0..10)
+            .filter_map(|seed| pick(&SearchConfig { seed, ..config }))
+            .collect();
+        best_moves.sort();
+        best_moves.dedup();
+        assert!(best_moves.len() > 1);
+        // though the same seed always picks the same one
+        assert_eq!(pick(&config), pick(&config));
+
+        // but moves outside the window never are
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        let mate_state = GameState::from_fen(fen).unwrap();
+        for _ in 0..5 {
+            let best_move = search(
+                &mate_state,
+                1,
+                &config,
+                Arc::default(),
+                &TranspositionTable::default(),
+                &mut (),
+            )
+            .best_move
+            .unwrap();
+            assert_eq!(best_move.to_sq(), Square::H4);
+        }
+
+        // and past the opening plies the choice is the plain best move
+        config.root_randomization = Some(RootRandomization {
+            plies: 0,
+            window: 10,
+        });
+        let first = search(
+            &state,
+            1,
+            &config,
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut (),
+        )
+        .best_move;
+        for _ in 0..5 {
+            assert_eq!(
+                search(
+                    &state,
+                    1,
+                    &config,
+                    Arc::default(),
+                    &TranspositionTable::default(),
+                    &mut ()
+                )
+                .best_move,
+                first
+            );
+        }
+    }
+}
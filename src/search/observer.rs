@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use crate::play::r#move::Move;
+
+/// A completed iteration of iterative deepening.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Iteration {
+    /// 0 when the root has no moves, and so nothing was searched
+    pub depth: u8,
+    pub seldepth: u8,
+    pub score: i32,
+    /// nodes searched so far, over all iterations
+    pub nodes: u64,
+    /// time spent so far, over all iterations
+    pub time: Duration,
+    /// how full the transposition table is, per mille
+    pub hashfull: u16,
+    pub pv: Vec<Move>,
+}
+
+impl Iteration {
+    pub fn nps(&self) -> u64 {
+        nps(self.nodes, self.time)
+    }
+}
+
+/// A root move about to be searched, partway through an iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentMove {
+    pub depth: u8,
+    pub mv: Move,
+    /// counting from 1, in the order the root moves are searched
+    pub number: usize,
+    pub nodes: u64,
+    pub time: Duration,
+    pub hashfull: u16,
+}
+
+impl CurrentMove {
+    pub fn nps(&self) -> u64 {
+        nps(self.nodes, self.time)
+    }
+}
+
+fn nps(nodes: u64, time: Duration) -> u64 {
+    let micros = time.as_micros().max(1);
+    (nodes as u128 * 1_000_000 / micros) as u64
+}
+
+/// Receives a search's progress as it's made, so embedders (GUIs, services,
+/// tests) can follow it without parsing UCI output.
+pub trait SearchObserver {
+    fn on_iteration(&mut self, _iteration: &Iteration) {}
+
+    fn on_current_move(&mut self, _current: &CurrentMove) {}
+}
+
+/// Ignores everything.
+impl SearchObserver for () {}
+
+/// Keeps every iteration.
+impl SearchObserver for Vec<Iteration> {
+    fn on_iteration(&mut self, iteration: &Iteration) {
+        self.push(iteration.clone());
+    }
+}
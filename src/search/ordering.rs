@@ -0,0 +1,93 @@
+use std::cmp::Reverse;
+
+use crate::play::{
+    r#move::{Move, MoveList},
+    types::PieceType,
+    GameState,
+};
+
+const CAPTURE_BASE: i8 = 10;
+const PROMOTION_BONUS: i8 = 40;
+/// Above any capture or promotion, as the table's move was best last time.
+const TT_MOVE_SCORE: i8 = i8::MAX;
+
+/// Most valuable victim / least valuable attacker for captures, plus a bonus
+/// for promotions. Quiet moves score 0.
+pub fn score_move(state: &GameState, mv: &Move) -> i8 {
+    let mut score = 0;
+    if let Some(victim) = mv.captured() {
+        let attacker = state
+            .position
+            .board
+            .piece(&mv.from_sq())
+            .map_or(0, |p| p.piece_type() as i8);
+        score += CAPTURE_BASE * (victim.piece_type() as i8 + 1) + (5 - attacker);
+    }
+    // underpromotions are rarely better, so go with the quiet moves
+    if mv.promoted().map(|p| p.piece_type()) == Some(PieceType::Queen) {
+        score += PROMOTION_BONUS;
+    }
+    score
+}
+
+fn same_move(a: &Move, b: &Move) -> bool {
+    a.from_sq() == b.from_sq() && a.to_sq() == b.to_sq() && a.promoted() == b.promoted()
+}
+
+/// Scores `moves` and returns them best first, keeping generation order
+/// among equals. `tt_move` goes first whatever it scores.
+pub fn order_moves(state: &GameState, moves: MoveList, tt_move: Option<Move>) -> Vec<Move> {
+    let mut ordered: Vec<Move> = moves
+        .into_iter()
+        .map(|mut mv| {
+            mv.score = match tt_move {
+                Some(tt_mv) if same_move(&mv, &tt_mv) => TT_MOVE_SCORE,
+                _ => score_move(state, &mv),
+            };
+            mv
+        })
+        .collect();
+    ordered.sort_by_key(|mv| Reverse(mv.score));
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::types::{Piece, Square};
+
+    #[test]
+    fn test_score_move() {
+        let fen = "4k3/8/8/3q1r2/4P3/8/8/3QK3 w - - 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+
+        let quiet = Move::quiet(Square::D1, Square::D2);
+        let pawn_takes_queen = Move::capture(Square::E4, Square::D5, Piece::BlackQueen);
+        let pawn_takes_rook = Move::capture(Square::E4, Square::F5, Piece::BlackRook);
+        let queen_takes_queen = Move::capture(Square::D1, Square::D5, Piece::BlackQueen);
+
+        assert_eq!(score_move(&state, &quiet), 0);
+        assert!(score_move(&state, &pawn_takes_queen) > score_move(&state, &queen_takes_queen));
+        assert!(score_move(&state, &queen_takes_queen) > score_move(&state, &pawn_takes_rook));
+    }
+
+    #[test]
+    fn test_order_moves() {
+        let fen = "4k3/8/8/3q1r2/4P3/8/8/3QK3 w - - 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+        let ordered = order_moves(&state, state.position.legal_moves(), None);
+        assert_eq!(ordered[0].to_sq(), Square::D5);
+        assert_eq!(ordered[0].from_sq(), Square::E4);
+        assert!(ordered
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+
+        // a quiet move from the table jumps the captures
+        let tt_move = Move::quiet(Square::D1, Square::D2);
+        let ordered = order_moves(&state, state.position.legal_moves(), Some(tt_move));
+        assert_eq!(ordered[0].from_sq(), Square::D1);
+        assert_eq!(ordered[0].to_sq(), Square::D2);
+        assert_eq!(ordered[1].to_sq(), Square::D5);
+    }
+}
@@ -0,0 +1,92 @@
+use super::SearchConfig;
+
+/// A playing style bundled as a preset: how much the engine avoids draws and
+/// how it weighs its king's safety against its pieces' activity. Strength is
+/// meant to be about the same across them; only the choices differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Personality {
+    #[default]
+    Default,
+    /// takes draws readily and keeps its king covered
+    Solid,
+    /// plays on for a win and for active pieces
+    Aggressive,
+    /// further still, giving up king safety for activity
+    Gambit,
+}
+
+impl Personality {
+    pub const ALL: [Personality; 4] = [
+        Personality::Default,
+        Personality::Solid,
+        Personality::Aggressive,
+        Personality::Gambit,
+    ];
+
+    /// The name the UCI `Personality` option knows it by.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Personality::Default => "default",
+            Personality::Solid => "solid",
+            Personality::Aggressive => "aggressive",
+            Personality::Gambit => "gambit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Personality> {
+        Personality::ALL
+            .iter()
+            .find(|personality| personality.name().eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    /// (contempt in centipawns, king safety %, mobility %)
+    fn settings(&self) -> (i32, u32, u32) {
+        match self {
+            Personality::Default => (0, 100, 100),
+            Personality::Solid => (-10, 130, 90),
+            Personality::Aggressive => (30, 90, 120),
+            Personality::Gambit => (50, 75, 140),
+        }
+    }
+
+    /// Sets the contempt and evaluation weights in `config` to the preset's,
+    /// leaving the rest of it alone.
+    pub fn apply(&self, config: &mut SearchConfig) {
+        let (contempt, king_safety, mobility) = self.settings();
+        config.contempt.base = contempt;
+        config.eval_weights.king_safety = king_safety;
+        config.eval_weights.mobility = mobility;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::eval::EvalWeights;
+
+    #[test]
+    fn test_personalities() {
+        for personality in Personality::ALL {
+            assert_eq!(
+                Personality::from_name(personality.name()),
+                Some(personality)
+            );
+        }
+        assert_eq!(
+            Personality::from_name("Aggressive"),
+            Some(Personality::Aggressive)
+        );
+        assert_eq!(Personality::from_name("reckless"), None);
+
+        let mut config = SearchConfig::default();
+        Personality::Gambit.apply(&mut config);
+        assert_eq!(config.contempt.base, 50);
+        assert!(config.eval_weights.mobility > config.eval_weights.king_safety);
+        // going back to the default undoes the preset
+        Personality::Default.apply(&mut config);
+        assert_eq!(config.contempt, SearchConfig::default().contempt);
+        assert_eq!(config.eval_weights, EvalWeights::default());
+    }
+}
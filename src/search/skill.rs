@@ -0,0 +1,141 @@
+use rand::Rng;
+
+/// Full strength, at which nothing is held back.
+pub const MAX_SKILL_LEVEL: u8 = 20;
+/// The range `UCI_Elo` is mapped onto the skill levels over, the weakest
+/// level playing at roughly the first and the one below full strength at
+/// roughly the last.
+pub const MIN_ELO: u16 = 800;
+pub const MAX_ELO: u16 = 2400;
+
+/// Centipawns below the best root score, at level 0, within which a weaker
+/// move can still be picked, shrinking by [`WINDOW_PER_LEVEL`] a level.
+const BASE_WINDOW: i32 = 450;
+const WINDOW_PER_LEVEL: i32 = 20;
+/// The softmax temperature, in centipawns, at level 0, and how much it
+/// cools a level: the hotter, the likelier a worse move is picked.
+const BASE_TEMPERATURE: f64 = 210.0;
+const TEMPERATURE_PER_LEVEL: f64 = 10.0;
+
+/// How strongly to play, from the `Skill Level`, `UCI_LimitStrength` and
+/// `UCI_Elo` options. A weakened search stops at a shallow depth and picks
+/// its move at random from the root moves, weighted by a softmax over their
+/// scores, so it mostly plays the best move it found but now and then one
+/// that's a little, or at low levels a lot, worse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strength {
+    pub skill_level: u8,
+    /// whether `elo` sets the level rather than `skill_level`
+    pub limit_strength: bool,
+    pub elo: u16,
+}
+
+impl Default for Strength {
+    fn default() -> Self {
+        Strength {
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: MAX_ELO,
+        }
+    }
+}
+
+impl Strength {
+    /// The level to play at, or `None` at full strength.
+    pub fn level(&self) -> Option<u8> {
+        let level = if self.limit_strength {
+            let elo = self.elo.clamp(MIN_ELO, MAX_ELO) - MIN_ELO;
+            (elo as u32 * MAX_SKILL_LEVEL as u32 / (MAX_ELO - MIN_ELO) as u32) as u8
+        } else {
+            self.skill_level
+        };
+        (level < MAX_SKILL_LEVEL).then_some(level)
+    }
+}
+
+/// The deepest a search at `level` goes, a ply at level 0.
+pub fn max_depth(level: u8) -> u8 {
+    1 + level / 2
+}
+
+/// How far below the best root score a move at `level` can still be picked.
+pub fn window(level: u8) -> i32 {
+    BASE_WINDOW - WINDOW_PER_LEVEL * level as i32
+}
+
+/// Picks one of `scores`, each weighted by how close it is to the best of
+/// them, at `level`'s temperature.
+pub fn pick(scores: &[i32], level: u8, rng: &mut impl Rng) -> usize {
+    let temperature = BASE_TEMPERATURE - TEMPERATURE_PER_LEVEL * level as f64;
+    let best = scores.iter().copied().max().unwrap_or(0);
+    let weights: Vec<f64> = scores
+        .iter()
+        .map(|score| ((score - best) as f64 / temperature).exp())
+        .collect();
+    let mut roll = rng.gen_range(0.0..weights.iter().sum::<f64>());
+    for (i, weight) in weights.iter().enumerate() {
+        if roll < *weight {
+            return i;
+        }
+        roll -= weight;
+    }
+    weights.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_strength_level() {
+        assert_eq!(Strength::default().level(), None);
+        let weak = Strength {
+            skill_level: 5,
+            ..Strength::default()
+        };
+        assert_eq!(weak.level(), Some(5));
+        // the Elo takes over once strength is limited
+        let limited = Strength {
+            limit_strength: true,
+            elo: 1600,
+            ..weak
+        };
+        assert_eq!(limited.level(), Some(10));
+        let limited = Strength {
+            elo: 100,
+            ..limited
+        };
+        assert_eq!(limited.level(), Some(0));
+        let limited = Strength {
+            elo: MAX_ELO,
+            ..limited
+        };
+        assert_eq!(limited.level(), None);
+        assert_eq!(max_depth(0), 1);
+        assert!(window(MAX_SKILL_LEVEL - 1) > 0);
+    }
+
+    #[test]
+    fn test_pick() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let scores = [40, 30, -300];
+        let mut picks = [0; 3];
+        for _ in 0..1000 {
+            picks[pick(&scores, 10, &mut rng)] += 1;
+        }
+        // the best is picked most, a close second often, a blunder rarely
+        assert!(picks[0] > picks[1] && picks[1] > picks[2]);
+        assert!(picks[1] > 300);
+        assert!(picks[2] < 50);
+
+        // higher levels stick closer to the best
+        let mut strong = [0; 3];
+        for _ in 0..1000 {
+            strong[pick(&scores, MAX_SKILL_LEVEL - 1, &mut rng)] += 1;
+        }
+        assert!(strong[0] > picks[0]);
+    }
+}
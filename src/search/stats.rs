@@ -0,0 +1,91 @@
+use std::fmt;
+
+/// What a search did, counted over all its iterations, for checking whether
+/// a change to pruning or move ordering does what it was meant to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// main search nodes with an open window, on the principal variation
+    pub pv_nodes: u64,
+    /// main search nodes with a zero window
+    pub non_pv_nodes: u64,
+    pub qsearch_nodes: u64,
+    /// main search nodes failing high on a move
+    pub beta_cutoffs: u64,
+    /// of those, how many on the first move searched
+    pub first_move_cutoffs: u64,
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    /// hits whose bound cut the node short
+    pub tt_cutoffs: u64,
+    pub null_move_tries: u64,
+    pub null_move_cutoffs: u64,
+}
+
+/// `part` as a percentage of `whole`, 0 when there's no whole.
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        100.0 * part as f64 / whole as f64
+    }
+}
+
+impl SearchStats {
+    pub fn nodes(&self) -> u64 {
+        self.pv_nodes + self.non_pv_nodes + self.qsearch_nodes
+    }
+
+    /// How often a cutoff came from the first move, the measure of move
+    /// ordering.
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        percent(self.first_move_cutoffs, self.beta_cutoffs)
+    }
+
+    pub fn tt_hit_rate(&self) -> f64 {
+        percent(self.tt_hits, self.tt_probes)
+    }
+}
+
+impl fmt::Display for SearchStats {
+    /// One line, as it follows `info string` after a search.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "nodes {} pv {} nonpv {} qsearch {} cutoffs {} firstmove {:.1}% \
+             tt probes {} hits {} ({:.1}%) cutoffs {} nullmove tries {} cutoffs {}",
+            self.nodes(),
+            self.pv_nodes,
+            self.non_pv_nodes,
+            self.qsearch_nodes,
+            self.beta_cutoffs,
+            self.first_move_cutoff_rate(),
+            self.tt_probes,
+            self.tt_hits,
+            self.tt_hit_rate(),
+            self.tt_cutoffs,
+            self.null_move_tries,
+            self.null_move_cutoffs
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_rates() {
+        let stats = SearchStats {
+            beta_cutoffs: 8,
+            first_move_cutoffs: 6,
+            tt_probes: 4,
+            tt_hits: 1,
+            ..SearchStats::default()
+        };
+        assert_eq!(stats.first_move_cutoff_rate(), 75.0);
+        assert_eq!(stats.tt_hit_rate(), 25.0);
+        assert_eq!(SearchStats::default().tt_hit_rate(), 0.0);
+        assert!(stats.to_string().contains("firstmove 75.0%"));
+    }
+}
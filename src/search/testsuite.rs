@@ -0,0 +1,165 @@
+use std::{
+    fs,
+    io::{self, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    epd::EpdRecord,
+    play::{r#move::Move, GameState},
+};
+
+use super::{
+    search,
+    time::{Budget, SearchControl},
+    tt::TranspositionTable,
+    SearchConfig, MAX_PLY,
+};
+
+pub const DEFAULT_MOVETIME_MS: u64 = 1000;
+const TESTSUITE_HASH_MB: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TestSuiteSummary {
+    pub solved: usize,
+    pub failed: usize,
+}
+
+impl TestSuiteSummary {
+    pub fn total(&self) -> usize {
+        self.solved + self.failed
+    }
+}
+
+/// A position's best and avoid moves, as the moves they stand for.
+fn expected_moves(record: &EpdRecord, state: &GameState) -> Result<(Vec<Move>, Vec<Move>), String> {
+    let resolve = |sans: &[String]| {
+        sans.iter()
+            .map(|san| Move::from_san(&state.position, san).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<Move>, String>>()
+    };
+    let best = resolve(&record.best_moves)?;
+    let avoid = resolve(&record.avoid_moves)?;
+    if best.is_empty() && avoid.is_empty() {
+        return Err("no bm or am to check against".to_string());
+    }
+    Ok((best, avoid))
+}
+
+/// Searches each position of `epd`, one per line, for `movetime`, and checks
+/// the move played is one of its `bm` moves and none of its `am` ones,
+/// writing each result and then the score to `out`. Blank lines and those
+/// starting `#` are skipped; a line that can't be read counts as a failure.
+pub fn run_testsuite(
+    epd: &str,
+    movetime: Duration,
+    out: &mut dyn Write,
+) -> io::Result<TestSuiteSummary> {
+    let config = SearchConfig::default();
+    let mut tt = TranspositionTable::new(TESTSUITE_HASH_MB);
+    let mut summary = TestSuiteSummary::default();
+    for (i, line) in epd.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let setup = EpdRecord::parse(line)
+            .map_err(|e| e.to_string())
+            .and_then(|record| {
+                let state = GameState::from_fen(record.to_fen()).map_err(|e| e.to_string())?;
+                let expected = expected_moves(&record, &state)?;
+                Ok((record, state, expected))
+            });
+        let (record, state, (best, avoid)) = match setup {
+            Ok(setup) => setup,
+            Err(e) => {
+                summary.failed += 1;
+                writeln!(out, "line {}: FAIL, {}", i + 1, e)?;
+                continue;
+            }
+        };
+
+        tt.clear();
+        let budget = Budget {
+            soft: movetime,
+            hard: movetime,
+        };
+        let result = search(
+            &state,
+            MAX_PLY,
+            &config,
+            Arc::new(SearchControl::new(Some(budget), false)),
+            &tt,
+            &mut (),
+        );
+        let played = result.best_move.map(|mv| mv.to_uci());
+        let is_played = |mv: &Move| played.as_deref() == Some(mv.to_uci().as_str());
+        let solved =
+            (best.is_empty() || best.iter().any(is_played)) && !avoid.iter().any(is_played);
+        if solved {
+            summary.solved += 1;
+        } else {
+            summary.failed += 1;
+        }
+
+        let mut expected = vec![];
+        if !record.best_moves.is_empty() {
+            expected.push(format!("bm {}", record.best_moves.join(" ")));
+        }
+        if !record.avoid_moves.is_empty() {
+            expected.push(format!("am {}", record.avoid_moves.join(" ")));
+        }
+        writeln!(
+            out,
+            "{}: {}, played {} ({})",
+            record.id.unwrap_or_else(|| format!("line {}", i + 1)),
+            if solved { "solved" } else { "FAIL" },
+            result
+                .best_move
+                .map_or("none".to_string(), |mv| mv.to_san(&state.position)),
+            expected.join("; ")
+        )?;
+    }
+    writeln!(out, "Score: {}/{} solved", summary.solved, summary.total())?;
+    Ok(summary)
+}
+
+/// [`run_testsuite`] on the file at `path`, reporting to stdout.
+pub fn run_testsuite_file(path: &str, movetime: Duration) -> io::Result<TestSuiteSummary> {
+    let epd = fs::read_to_string(path)?;
+    run_testsuite(&epd, movetime, &mut io::stdout())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_run_testsuite() {
+        let epd = "\
+# a mate in one, a blunder to avoid, and a move that isn't there
+6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"mate\";
+4k3/8/4p3/3p4/8/8/8/3QK3 w - - am Qxd5; id \"avoid\";
+
+4k3/8/8/8/8/8/8/4K3 w - - bm Ke8;
+";
+        let mut out = vec![];
+        let summary = run_testsuite(epd, Duration::from_millis(50), &mut out).unwrap();
+        assert_eq!(
+            summary,
+            TestSuiteSummary {
+                solved: 2,
+                failed: 1
+            }
+        );
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "mate: solved, played Ra8# (bm Ra8#)");
+        assert!(lines[1].starts_with("avoid: solved, played "));
+        assert!(lines[1].ends_with(" (am Qxd5)"));
+        assert!(lines[2].starts_with("line 5: FAIL, illegal move Ke8"));
+        assert_eq!(lines[3], "Score: 2/3 solved");
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::play::types::Color;
+
+/// Moves assumed left in the game when the GUI doesn't send `movestogo`.
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+/// Kept back from every allocation for GUI and process latency.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+/// Time spent pondering on a hit counts this fraction (1/n) against the
+/// allocation, since the search it bought carries straight on.
+const PONDER_CREDIT_DIVISOR: u32 = 2;
+/// Search speed assumed when a time budget is turned into nodes, roughly what
+/// a release build manages from the start position.
+const NODES_PER_MS: u64 = 50;
+
+/// Clock state from a UCI `go` command, all in milliseconds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u64>,
+    pub movetime: Option<u64>,
+}
+
+impl TimeControl {
+    fn remaining(&self, color: Color) -> Option<(u64, u64)> {
+        let (time, inc) = match color {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        time.map(|t| (t, inc.unwrap_or(0)))
+    }
+}
+
+/// How long to think for: no new iteration is started past `soft`, and the
+/// search is cut off at `hard`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    pub soft: Duration,
+    pub hard: Duration,
+}
+
+impl Budget {
+    /// The same budget as a node count, searched identically on any machine.
+    pub fn in_nodes(&self) -> NodeBudget {
+        NodeBudget {
+            soft: self.soft.as_millis() as u64 * NODES_PER_MS,
+            hard: self.hard.as_millis() as u64 * NODES_PER_MS,
+        }
+    }
+}
+
+/// Like [`Budget`], but counted in nodes searched rather than time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeBudget {
+    pub soft: u64,
+    pub hard: u64,
+}
+
+/// Spreads the clock over the game. Pondering only earns time back when the
+/// predicted move is played, so with it enabled a little more is spent after
+/// a ponder hit and a little less after a miss.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimeManager {
+    pub ponder: bool,
+    /// budgets are spent in nodes rather than time, so a search can be
+    /// reproduced exactly
+    pub deterministic: bool,
+    last_ponder_hit: Option<bool>,
+}
+
+impl TimeManager {
+    /// Forgets ponder results from the previous game, keeping the options.
+    pub fn new_game(&mut self) {
+        self.last_ponder_hit = None;
+    }
+
+    pub fn record_ponder(&mut self, hit: bool) {
+        self.last_ponder_hit = Some(hit);
+    }
+
+    /// `None` when there's no clock to manage, e.g. `go depth` or `go infinite`.
+    pub fn allocate(&self, tc: &TimeControl, color: Color) -> Option<Budget> {
+        if let Some(movetime) = tc.movetime {
+            let t = Duration::from_millis(movetime).saturating_sub(MOVE_OVERHEAD);
+            return Some(Budget { soft: t, hard: t });
+        }
+
+        let (time, inc) = tc.remaining(color)?;
+        let moves_to_go = tc.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let mut soft = time / moves_to_go + inc * 3 / 4;
+        if self.ponder {
+            match self.last_ponder_hit {
+                Some(true) => soft = soft * 5 / 4,
+                Some(false) => soft = soft * 3 / 4,
+                None => {}
+            }
+        }
+
+        let available = Duration::from_millis(time).saturating_sub(MOVE_OVERHEAD);
+        let hard = (Duration::from_millis(soft) * 3).min(available / 2);
+        Some(Budget {
+            soft: Duration::from_millis(soft).min(hard),
+            hard,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Deadlines {
+    soft: Instant,
+    hard: Instant,
+}
+
+/// Shared between the UCI loop and a running search: the loop can stop it or
+/// turn a ponder search into a timed one, and the search polls it.
+#[derive(Debug)]
+pub struct SearchControl {
+    stop: AtomicBool,
+    discarded: AtomicBool,
+    pondering: AtomicBool,
+    start: Instant,
+    budget: Option<Budget>,
+    deadlines: Mutex<Option<Deadlines>>,
+    nodes: Option<NodeBudget>,
+}
+
+impl Default for SearchControl {
+    fn default() -> Self {
+        SearchControl::new(None, false)
+    }
+}
+
+impl SearchControl {
+    /// The clock only starts once pondering ends, so a ponder search has no
+    /// deadlines until `ponderhit`.
+    pub fn new(budget: Option<Budget>, pondering: bool) -> Self {
+        let start = Instant::now();
+        let deadlines = match budget {
+            Some(b) if !pondering => Some(Deadlines {
+                soft: start + b.soft,
+                hard: start + b.hard,
+            }),
+            _ => None,
+        };
+        SearchControl {
+            stop: AtomicBool::new(false),
+            discarded: AtomicBool::new(false),
+            pondering: AtomicBool::new(pondering),
+            start,
+            budget,
+            deadlines: Mutex::new(deadlines),
+            nodes: None,
+        }
+    }
+
+    /// Also limits the search to `nodes`, which unlike the clock still
+    /// applies while pondering.
+    pub fn with_node_budget(mut self, nodes: Option<NodeBudget>) -> Self {
+        self.nodes = nodes;
+        self
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+
+    /// Stops the search, marking its result as no longer wanted.
+    pub fn discard(&self) {
+        self.discarded.store(true, Ordering::SeqCst);
+        self.stop();
+    }
+
+    pub fn is_discarded(&self) -> bool {
+        self.discarded.load(Ordering::SeqCst)
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::SeqCst)
+    }
+
+    /// The opponent played the predicted move: start the clock, crediting
+    /// part of the time already spent pondering against the soft limit. The
+    /// hard limit is left alone as the clock really only starts now.
+    pub fn ponderhit(&self) {
+        let now = Instant::now();
+        if let Some(b) = self.budget {
+            let pondered = (now - self.start) / PONDER_CREDIT_DIVISOR;
+            let soft = b.soft.saturating_sub(pondered).max(b.soft / 4);
+            *self.deadlines.lock().unwrap() = Some(Deadlines {
+                soft: now + soft,
+                hard: now + b.hard,
+            });
+        }
+        self.pondering.store(false, Ordering::SeqCst);
+    }
+
+    /// Time since the search started, pondering included.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// The most nodes the search may take, if it's limited.
+    pub fn node_limit(&self) -> Option<u64> {
+        self.nodes.map(|n| n.hard)
+    }
+
+    /// Whether to abort the search in progress, `nodes` into it.
+    pub fn should_stop(&self, nodes: u64) -> bool {
+        self.is_stopped() || self.past(|d| d.hard) || self.nodes.is_some_and(|n| nodes >= n.hard)
+    }
+
+    /// Whether to skip starting another iteration, `nodes` into the search.
+    pub fn should_stop_iterating(&self, nodes: u64) -> bool {
+        self.is_stopped() || self.past(|d| d.soft) || self.nodes.is_some_and(|n| nodes >= n.soft)
+    }
+
+    fn past(&self, deadline: impl Fn(&Deadlines) -> Instant) -> bool {
+        match *self.deadlines.lock().unwrap() {
+            Some(d) => Instant::now() >= deadline(&d),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_allocate() {
+        let tm = TimeManager::default();
+        assert_eq!(tm.allocate(&TimeControl::default(), Color::White), None);
+
+        let tc = TimeControl {
+            wtime: Some(60_000),
+            btime: Some(1_000),
+            winc: Some(1_000),
+            ..TimeControl::default()
+        };
+        let white = tm.allocate(&tc, Color::White).unwrap();
+        assert_eq!(white.soft, Duration::from_millis(2_750));
+        assert!(white.hard > white.soft);
+        // never more than half of what's left
+        let black = tm.allocate(&tc, Color::Black).unwrap();
+        assert!(black.hard <= Duration::from_millis(500));
+        assert!(black.soft <= black.hard);
+
+        let tc = TimeControl {
+            movetime: Some(1_000),
+            ..TimeControl::default()
+        };
+        let budget = tm.allocate(&tc, Color::Black).unwrap();
+        assert_eq!(budget.soft, budget.hard);
+        assert!(budget.hard < Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn test_allocate_after_ponder() {
+        let tc = TimeControl {
+            wtime: Some(60_000),
+            ..TimeControl::default()
+        };
+        let mut tm = TimeManager {
+            ponder: true,
+            ..TimeManager::default()
+        };
+        let base = tm.allocate(&tc, Color::White).unwrap().soft;
+        tm.record_ponder(true);
+        let hit = tm.allocate(&tc, Color::White).unwrap().soft;
+        tm.record_ponder(false);
+        let miss = tm.allocate(&tc, Color::White).unwrap().soft;
+        assert!(miss < base && base < hit);
+
+        // without pondering enabled the history doesn't matter
+        tm.ponder = false;
+        assert_eq!(tm.allocate(&tc, Color::White).unwrap().soft, base);
+    }
+
+    #[test]
+    fn test_search_control_ponderhit() {
+        let budget = Budget {
+            soft: Duration::from_millis(0),
+            hard: Duration::from_millis(0),
+        };
+        // a ponder search ignores the clock until the hit
+        let control = SearchControl::new(Some(budget), true);
+        assert!(control.is_pondering());
+        assert!(!control.should_stop(0));
+        control.ponderhit();
+        assert!(!control.is_pondering());
+        assert!(control.should_stop(0));
+
+        let control = SearchControl::new(None, false);
+        assert!(!control.should_stop_iterating(0));
+        control.stop();
+        assert!(control.should_stop_iterating(0));
+        assert!(!control.is_discarded());
+        control.discard();
+        assert!(control.is_discarded());
+    }
+
+    #[test]
+    fn test_node_budget() {
+        let budget = Budget {
+            soft: Duration::from_millis(10),
+            hard: Duration::from_millis(20),
+        };
+        let nodes = budget.in_nodes();
+        assert_eq!(nodes.soft * 2, nodes.hard);
+
+        let control = SearchControl::new(None, false).with_node_budget(Some(nodes));
+        assert!(!control.should_stop_iterating(nodes.soft - 1));
+        assert!(control.should_stop_iterating(nodes.soft));
+        assert!(!control.should_stop(nodes.soft));
+        assert!(control.should_stop(nodes.hard));
+    }
+
+    #[test]
+    fn test_ponderhit_credits_ponder_time() {
+        let budget = Budget {
+            soft: Duration::from_millis(100),
+            hard: Duration::from_secs(10),
+        };
+        let control = SearchControl::new(Some(budget), true);
+        std::thread::sleep(Duration::from_millis(120));
+        control.ponderhit();
+        // the soft limit shrinks to a quarter, but the hard one is untouched
+        assert!(!control.should_stop_iterating(0));
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(control.should_stop_iterating(0));
+        assert!(!control.should_stop(0));
+    }
+}
@@ -0,0 +1,462 @@
+use std::{fmt::Write, io, sync::Arc};
+
+use serde::Serialize;
+
+use crate::play::{r#move::Move, GameState, START_FEN};
+
+use super::{search_tree, tt::TranspositionTable, SearchConfig, MAX_PLY};
+
+/// Nodes kept unless `--max-nodes` says otherwise, as many as Graphviz
+/// still lays out in reasonable time.
+pub const DEFAULT_MAX_NODES: usize = 2000;
+const TREE_TT_MB: usize = 16;
+
+/// How a node of the tree was reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// the root, once for each window it's searched with
+    Root,
+    Main,
+    /// reached by passing, to test whether the side to move is so far ahead
+    /// it could afford to
+    NullMove,
+    /// the same position again, searched shallower to check a null move
+    /// cutoff
+    Verification,
+    Quiescence,
+}
+
+/// Why a node returned what it did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// a score inside the window
+    Exact,
+    FailHigh,
+    FailLow,
+    /// cut short by a bound from the transposition table
+    TtCutoff,
+    NullMoveCutoff,
+    /// the static evaluation was already at least beta
+    StandPat,
+    /// a repetition or the fifty-move rule
+    Draw,
+    Checkmate,
+    Stalemate,
+    /// won or lost by the variant's own rules, not by checkmate
+    VariantEnd,
+    /// never searched, being a quiet move late in the move list
+    LateMovePruned,
+    /// the search was stopped partway, and the score means nothing
+    Stopped,
+}
+
+/// A node of the recorded tree. Scores are the side to move's, as the
+/// search has them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeNode {
+    pub id: usize,
+    pub parent: Option<usize>,
+    pub kind: NodeKind,
+    /// the move leading to the node, in coordinate notation, if it was
+    /// reached by one
+    #[serde(rename = "move")]
+    pub mv: Option<String>,
+    pub ply: u8,
+    pub depth: u8,
+    /// the window the node was searched with
+    pub alpha: i32,
+    pub beta: i32,
+    /// unset while the node's still being searched
+    pub score: Option<i32>,
+    pub outcome: Option<Outcome>,
+}
+
+/// What the next node opened from one will be reached by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edge {
+    Move(Move),
+    Null,
+    Verification,
+}
+
+/// A node on the path to the one being searched.
+#[derive(Debug, Clone, Copy)]
+struct Open {
+    /// where it is in the tree, unless it's past the budget
+    id: Option<usize>,
+    next: Option<Edge>,
+}
+
+/// The nodes searched in the last iteration of a search, up to a budget,
+/// recorded so pruning decisions can be looked into after the fact. The
+/// earlier iterations are left out, as the last searches the same tree
+/// deeper with all they found to go on.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchTree {
+    pub nodes: Vec<TreeNode>,
+    /// whether the search went on past the budget's last node
+    pub truncated: bool,
+    #[serde(skip)]
+    max_nodes: usize,
+    #[serde(skip)]
+    recording: bool,
+    #[serde(skip)]
+    path: Vec<Open>,
+}
+
+impl SearchTree {
+    pub fn new(max_nodes: usize) -> Self {
+        SearchTree {
+            max_nodes,
+            ..SearchTree::default()
+        }
+    }
+
+    /// Records what's searched from now on, or stops recording.
+    pub(super) fn record(&mut self, recording: bool) {
+        self.recording = recording;
+    }
+
+    /// Opens a node, as a child of the one open before it.
+    pub(super) fn enter(&mut self, kind: NodeKind, ply: u8, depth: u8, alpha: i32, beta: i32) {
+        let parent = self.path.last().copied();
+        if !self.recording || parent.is_some_and(|p| p.id.is_none()) {
+            self.path.push(Open {
+                id: None,
+                next: None,
+            });
+            return;
+        }
+        if self.nodes.len() >= self.max_nodes {
+            self.truncated = true;
+            self.path.push(Open {
+                id: None,
+                next: None,
+            });
+            return;
+        }
+        let edge = parent.and_then(|p| p.next);
+        let kind = match (kind, edge) {
+            (NodeKind::Main, Some(Edge::Null)) => NodeKind::NullMove,
+            (NodeKind::Main, Some(Edge::Verification)) => NodeKind::Verification,
+            (kind, _) => kind,
+        };
+        let id = self.nodes.len();
+        self.nodes.push(TreeNode {
+            id,
+            parent: parent.and_then(|p| p.id),
+            kind,
+            mv: match edge {
+                Some(Edge::Move(mv)) => Some(mv.to_uci()),
+                _ => None,
+            },
+            ply,
+            depth,
+            alpha,
+            beta,
+            score: None,
+            outcome: None,
+        });
+        self.path.push(Open {
+            id: Some(id),
+            next: None,
+        });
+    }
+
+    /// Closes the open node with its score, saying why it fell where it did
+    /// against its window unless [`SearchTree::note`] already has.
+    pub(super) fn leave(&mut self, score: i32) {
+        let id = match self.path.pop() {
+            Some(Open { id: Some(id), .. }) => id,
+            _ => return,
+        };
+        let node = &mut self.nodes[id];
+        node.score = Some(score);
+        node.outcome.get_or_insert(if score >= node.beta {
+            Outcome::FailHigh
+        } else if score <= node.alpha {
+            Outcome::FailLow
+        } else {
+            Outcome::Exact
+        });
+    }
+
+    /// Gives why the open node is returning, ahead of it closing.
+    pub(super) fn note(&mut self, outcome: Outcome) {
+        if let Some(Open { id: Some(id), .. }) = self.path.last() {
+            self.nodes[*id].outcome = Some(outcome);
+        }
+    }
+
+    /// The next node opened from this one is reached by `mv`.
+    pub(super) fn next_move(&mut self, mv: Move) {
+        self.set_next(Edge::Move(mv));
+    }
+
+    pub(super) fn next_null(&mut self) {
+        self.set_next(Edge::Null);
+    }
+
+    pub(super) fn next_verification(&mut self) {
+        self.set_next(Edge::Verification);
+    }
+
+    fn set_next(&mut self, edge: Edge) {
+        if let Some(open) = self.path.last_mut() {
+            open.next = Some(edge);
+        }
+    }
+
+    /// Records `mv` as skipped by `outcome` without being searched, though
+    /// it would have been to `depth` within (`alpha`, `beta`).
+    pub(super) fn prune(
+        &mut self,
+        mv: Move,
+        outcome: Outcome,
+        ply: u8,
+        depth: u8,
+        alpha: i32,
+        beta: i32,
+    ) {
+        self.next_move(mv);
+        self.enter(NodeKind::Main, ply, depth, alpha, beta);
+        self.note(outcome);
+        if let Some(Open { id: Some(id), .. }) = self.path.pop() {
+            self.nodes[id].score = None;
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a tree always serializes")
+    }
+
+    /// The tree as a Graphviz digraph, each node labelled with its depth,
+    /// window, score and outcome and each edge with its move.
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph search {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for node in &self.nodes {
+            let score = node
+                .score
+                .map_or("-".to_string(), |score| score.to_string());
+            let outcome = node.outcome.map_or("open", outcome_name);
+            let style = match node.outcome {
+                Some(Outcome::LateMovePruned) => ", style=dashed, color=gray",
+                Some(Outcome::TtCutoff) | Some(Outcome::NullMoveCutoff) => ", color=blue",
+                Some(Outcome::FailHigh) => ", color=red",
+                Some(Outcome::Exact) => ", color=darkgreen",
+                _ => "",
+            };
+            let _ = writeln!(
+                dot,
+                "    n{} [label=\"{} d{} ply {}\\n[{}, {}]\\n{} {}\"{}];",
+                node.id,
+                kind_name(node.kind),
+                node.depth,
+                node.ply,
+                node.alpha,
+                node.beta,
+                score,
+                outcome,
+                style
+            );
+            if let Some(parent) = node.parent {
+                let label = node.mv.as_deref().unwrap_or(match node.kind {
+                    NodeKind::NullMove => "null",
+                    _ => "",
+                });
+                let _ = writeln!(
+                    dot,
+                    "    n{} -> n{} [label=\"{}\"];",
+                    parent, node.id, label
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The format a tree is written out in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeFormat {
+    Json,
+    Dot,
+}
+
+/// The command line of `lasker tree`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeArgs {
+    pub depth: u8,
+    pub fen: String,
+    pub max_nodes: usize,
+    pub format: TreeFormat,
+}
+
+impl TreeArgs {
+    /// Reads `<depth> [--fen <fen>] [--max-nodes N] [--format json|dot]`.
+    pub fn parse(args: &[String]) -> Result<TreeArgs, String> {
+        let (depth, mut rest) = match args {
+            [depth, rest @ ..] => (depth, rest),
+            _ => return Err("tree needs a depth to search to".to_string()),
+        };
+        let depth = match depth.parse::<u8>() {
+            Ok(depth) if (1..=MAX_PLY).contains(&depth) => depth,
+            _ => return Err(format!("bad depth {}", depth)),
+        };
+        let mut parsed = TreeArgs {
+            depth,
+            fen: START_FEN.to_string(),
+            max_nodes: DEFAULT_MAX_NODES,
+            format: TreeFormat::Json,
+        };
+        while let [flag, value, tail @ ..] = rest {
+            let bad = || format!("bad value {} for {}", value, flag);
+            match flag.as_str() {
+                "--fen" => parsed.fen = value.clone(),
+                "--max-nodes" => parsed.max_nodes = value.parse().map_err(|_| bad())?,
+                "--format" => {
+                    parsed.format = match value.as_str() {
+                        "json" => TreeFormat::Json,
+                        "dot" => TreeFormat::Dot,
+                        _ => return Err(bad()),
+                    }
+                }
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(format!("unknown options {}", rest.join(" ")));
+        }
+        Ok(parsed)
+    }
+}
+
+/// Searches the position `args` give and writes the tree of its last
+/// iteration to `out`.
+pub fn run_tree(args: &TreeArgs, out: &mut impl io::Write) -> io::Result<SearchTree> {
+    let state = GameState::from_fen(&args.fen)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut tree = SearchTree::new(args.max_nodes);
+    search_tree(
+        &state,
+        args.depth,
+        &SearchConfig::default(),
+        Arc::default(),
+        &TranspositionTable::new(TREE_TT_MB),
+        &mut tree,
+    );
+    match args.format {
+        TreeFormat::Json => writeln!(out, "{}", tree.to_json())?,
+        TreeFormat::Dot => write!(out, "{}", tree.to_dot())?,
+    }
+    Ok(tree)
+}
+
+fn kind_name(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Root => "root",
+        NodeKind::Main => "main",
+        NodeKind::NullMove => "null move",
+        NodeKind::Verification => "verification",
+        NodeKind::Quiescence => "qsearch",
+    }
+}
+
+fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Exact => "exact",
+        Outcome::FailHigh => "fail high",
+        Outcome::FailLow => "fail low",
+        Outcome::TtCutoff => "tt cutoff",
+        Outcome::NullMoveCutoff => "null move cutoff",
+        Outcome::StandPat => "stand pat",
+        Outcome::Draw => "draw",
+        Outcome::Checkmate => "checkmate",
+        Outcome::Stalemate => "stalemate",
+        Outcome::VariantEnd => "variant end",
+        Outcome::LateMovePruned => "late move pruned",
+        Outcome::Stopped => "stopped",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{play::GameState, search::INFINITY};
+
+    #[test]
+    fn test_recording() {
+        let state = GameState::default();
+        let mut moves = state.position.legal_moves().into_iter();
+        let (first, second) = (moves.next().unwrap(), moves.next().unwrap());
+
+        let mut tree = SearchTree::new(3);
+        // nothing's kept until recording starts
+        tree.enter(NodeKind::Root, 0, 1, -INFINITY, INFINITY);
+        tree.leave(0);
+        tree.record(true);
+
+        tree.enter(NodeKind::Root, 0, 2, -50, 50);
+        tree.next_move(first);
+        tree.enter(NodeKind::Main, 1, 1, -50, 50);
+        tree.note(Outcome::TtCutoff);
+        tree.leave(20);
+        tree.prune(second, Outcome::LateMovePruned, 1, 1, -50, 50);
+        tree.next_null();
+        tree.enter(NodeKind::Main, 1, 0, -50, 50);
+        tree.leave(0);
+        tree.leave(-20);
+
+        assert!(tree.truncated);
+        let nodes = &tree.nodes;
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(
+            (nodes[0].score, nodes[0].outcome),
+            (Some(-20), Some(Outcome::Exact))
+        );
+        assert_eq!(nodes[1].mv, Some(first.to_uci()));
+        assert_eq!(
+            (nodes[1].parent, nodes[1].outcome),
+            (Some(0), Some(Outcome::TtCutoff))
+        );
+        assert_eq!(nodes[2].score, None);
+        assert_eq!(nodes[2].outcome, Some(Outcome::LateMovePruned));
+
+        let dot = tree.to_dot();
+        assert!(
+            dot.contains("n0 [label=\"root d2 ply 0\\n[-50, 50]\\n-20 exact\", color=darkgreen];")
+        );
+        assert!(dot.contains(&format!("n0 -> n1 [label=\"{}\"];", first.to_uci())));
+        let json: serde_json::Value = serde_json::from_str(&tree.to_json()).unwrap();
+        assert_eq!(json["nodes"][1]["outcome"], "tt_cutoff");
+        assert_eq!(json["nodes"][1]["move"], first.to_uci());
+    }
+
+    #[test]
+    fn test_run_tree() {
+        let args: Vec<String> = ["3", "--max-nodes", "50", "--format", "dot"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let parsed = TreeArgs::parse(&args).unwrap();
+        assert_eq!((parsed.depth, parsed.max_nodes), (3, 50));
+        assert_eq!(parsed.fen, START_FEN);
+        let mut out = vec![];
+        let tree = run_tree(&parsed, &mut out).unwrap();
+        assert_eq!(tree.nodes.len(), 50);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("digraph search {"));
+        assert_eq!(out.matches(" -> ").count(), 49);
+
+        assert!(TreeArgs::parse(&[]).is_err());
+        assert!(TreeArgs::parse(&args[..2]).is_err());
+        assert!(TreeArgs::parse(&["0".to_string()]).is_err());
+        let json = ["2", "--format", "xml"].map(String::from);
+        assert!(TreeArgs::parse(&json).is_err());
+    }
+}
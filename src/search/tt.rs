@@ -0,0 +1,525 @@
+use std::{
+    fs,
+    mem::size_of,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
+
+use crate::play::r#move::compact::Move16;
+
+use super::{MATE, MAX_PLY};
+
+pub const DEFAULT_SIZE_MB: usize = 16;
+pub const MAX_SIZE_MB: usize = 1 << 20;
+/// The default table never takes more than this fraction (1/n) of the memory
+/// that's free, so the engine starts in a small container.
+const DEFAULT_MEMORY_DIVISOR: usize = 4;
+const BYTES_PER_MB: usize = 1024 * 1024;
+/// Entries `hashfull` looks at.
+const HASHFULL_SAMPLE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// the score failed high, so the true one is at least this
+    Lower,
+    /// the score failed low, so the true one is at most this
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+    /// compacted, see [`Move16::to_move`] for getting the move back
+    pub best_move: Option<Move16>,
+}
+
+/// Mate scores are stored as distance to mate from the entry's own node, as
+/// the same position can be reached at different plies from the root.
+pub fn score_to_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Reverses [`score_to_tt`] for a node at `ply`.
+pub fn score_from_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// An entry as it sits in the table, with the search that stored it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Slot {
+    key: u64,
+    best_move: Option<Move16>,
+    score: i32,
+    depth: u8,
+    bound: Bound,
+    generation: u8,
+}
+
+impl Slot {
+    fn new(entry: Entry, generation: u8) -> Self {
+        Slot {
+            key: entry.key,
+            best_move: entry.best_move,
+            score: entry.score,
+            depth: entry.depth,
+            bound: entry.bound,
+            generation,
+        }
+    }
+
+    fn entry(&self) -> Entry {
+        Entry {
+            key: self.key,
+            depth: self.depth,
+            score: self.score,
+            bound: self.bound,
+            best_move: self.best_move,
+        }
+    }
+}
+
+/// A [`Slot`] packed into two words that threads read and write without
+/// locking. The key is stored XORed with the rest, so a slot caught halfway
+/// through being written by another thread reads back under a key no
+/// position has, and is simply missed.
+#[derive(Debug, Default)]
+struct AtomicSlot {
+    check: AtomicU64,
+    /// the move, then the depth, bound and generation a byte each, then
+    /// the score in the top 24 bits. The bound counts from 1 so that an
+    /// empty slot is all zeros, and a move of all zeros is none.
+    data: AtomicU64,
+}
+
+impl AtomicSlot {
+    fn load(&self) -> Option<Slot> {
+        let check = self.check.load(Ordering::Relaxed);
+        let data = self.data.load(Ordering::Relaxed);
+        let bound = match (data >> 24) as u8 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None,
+        };
+        let mv = Move16::from_bits(data as u16);
+        Some(Slot {
+            key: check ^ data,
+            best_move: (!mv.is_none()).then_some(mv),
+            score: (data as i64 >> 40) as i32,
+            depth: (data >> 16) as u8,
+            bound,
+            generation: (data >> 32) as u8,
+        })
+    }
+
+    fn store(&self, slot: Slot) {
+        let mv = slot.best_move.map_or(0, |mv| mv.bits() as u64);
+        let bound: u64 = match slot.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        let data = mv
+            | (slot.depth as u64) << 16
+            | bound << 24
+            | (slot.generation as u64) << 32
+            | (slot.score as u64) << 40;
+        self.data.store(data, Ordering::Relaxed);
+        self.check.store(slot.key ^ data, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.data.store(0, Ordering::Relaxed);
+        self.check.store(0, Ordering::Relaxed);
+    }
+}
+
+/// The two entries a key can go in, half a cache line's worth.
+#[derive(Debug, Default)]
+#[repr(align(32))]
+struct Bucket {
+    /// the deepest entry of the current search, which only a deeper one, or
+    /// one for the same position, pushes out
+    deep: AtomicSlot,
+    /// whatever was stored last that didn't go in `deep`
+    recent: AtomicSlot,
+}
+
+/// A table of two-entry buckets indexed by position key, one entry kept by
+/// depth and the other always replaced. Entries are stamped with the search
+/// that stored them, and those from earlier searches give way to any new
+/// one whatever their depth, so a long analysis session doesn't fill up
+/// with deep entries from positions long gone. Search threads share it,
+/// probing and storing without locks. The default table has no buckets and
+/// so stores nothing.
+#[derive(Debug, Default)]
+pub struct TranspositionTable {
+    /// a power of two of them, so the key's low bits pick one
+    buckets: Vec<Bucket>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// A table of `size_mb`, or if that can't be had of the largest power of
+    /// two megabytes that can, so asking for too much never aborts. Check
+    /// [`TranspositionTable::size_mb`] for what was given.
+    pub fn new(size_mb: usize) -> Self {
+        fallback_sizes(size_mb, available_memory_mb())
+            .find_map(|mb| TranspositionTable::try_with_size(mb).ok())
+            .unwrap_or_default()
+    }
+
+    /// A table of as many buckets as fit in `size_mb`, rounded down to a
+    /// power of two, if it can be allocated.
+    pub fn try_with_size(size_mb: usize) -> Result<Self, std::collections::TryReserveError> {
+        let n_buckets =
+            prev_power_of_two(size_mb.saturating_mul(BYTES_PER_MB) / size_of::<Bucket>());
+        let mut buckets = Vec::new();
+        buckets.try_reserve_exact(n_buckets)?;
+        buckets.resize_with(n_buckets, Bucket::default);
+        Ok(TranspositionTable {
+            buckets,
+            generation: AtomicU8::new(0),
+        })
+    }
+
+    /// The size the table starts at: [`DEFAULT_SIZE_MB`], or less when the
+    /// machine has little memory free.
+    pub fn default_size_mb() -> usize {
+        available_memory_mb().map_or(DEFAULT_SIZE_MB, |available| {
+            DEFAULT_SIZE_MB
+                .min(available / DEFAULT_MEMORY_DIVISOR)
+                .max(1)
+        })
+    }
+
+    /// How much of `size_mb` a table of that size uses, its buckets being a
+    /// power of two.
+    pub fn usable_mb(size_mb: usize) -> usize {
+        prev_power_of_two(size_mb.max(1))
+    }
+
+    /// The memory the table's buckets take up, rounded down.
+    pub fn size_mb(&self) -> usize {
+        self.buckets.len() * size_of::<Bucket>() / BYTES_PER_MB
+    }
+
+    fn bucket(&self, key: u64) -> &Bucket {
+        &self.buckets[(key & (self.buckets.len() as u64 - 1)) as usize]
+    }
+
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Starts a new search, after which the entries stored so far are the
+    /// first to be replaced.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let bucket = self.bucket(key);
+        [&bucket.deep, &bucket.recent]
+            .iter()
+            .filter_map(|slot| slot.load())
+            .find(|slot| slot.key == key)
+            .map(|slot| slot.entry())
+    }
+
+    /// Stores `entry` in the deep slot if it's at least as deep as what's
+    /// there, is for the same position, or what's there is from an earlier
+    /// search, moving what was there to the recent slot. Otherwise it goes
+    /// in the recent slot.
+    pub fn store(&self, entry: Entry) {
+        if self.buckets.is_empty() {
+            return;
+        }
+        let generation = self.generation();
+        let bucket = self.bucket(entry.key);
+        let slot = Slot::new(entry, generation);
+        let deep = bucket.deep.load();
+        let replaces_deep = deep.is_none_or(|deep| {
+            deep.key == entry.key || deep.generation != generation || entry.depth >= deep.depth
+        });
+        if !replaces_deep {
+            bucket.recent.store(slot);
+            return;
+        }
+        bucket.deep.store(slot);
+        // the same position is only ever in one of the two
+        match deep {
+            Some(old) if old.key != entry.key => bucket.recent.store(old),
+            _ if bucket
+                .recent
+                .load()
+                .is_some_and(|recent| recent.key == entry.key) =>
+            {
+                bucket.recent.clear()
+            }
+            _ => {}
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for bucket in &self.buckets {
+            bucket.deep.clear();
+            bucket.recent.clear();
+        }
+        *self.generation.get_mut() = 0;
+    }
+
+    /// How full the table is with the current search's entries, per mille,
+    /// going by its first thousand entries as UCI's `hashfull` does.
+    pub fn hashfull(&self) -> u16 {
+        let sample = &self.buckets[..self.buckets.len().min(HASHFULL_SAMPLE / 2)];
+        if sample.is_empty() {
+            return 0;
+        }
+        let generation = self.generation();
+        let used = sample
+            .iter()
+            .flat_map(|b| [b.deep.load(), b.recent.load()])
+            .flatten()
+            .filter(|slot| slot.generation == generation)
+            .count();
+        (used * 1000 / (2 * sample.len())) as u16
+    }
+}
+
+/// The largest power of two no bigger than `n`, and 1 for 0.
+fn prev_power_of_two(n: usize) -> usize {
+    match n {
+        0 => 1,
+        n => 1 << (usize::BITS - 1 - n.leading_zeros()),
+    }
+}
+
+/// The sizes to try for a table of `requested_mb`, largest first: the size
+/// asked for, then each smaller power of two, skipping any beyond the memory
+/// that's available when that's known.
+fn fallback_sizes(requested_mb: usize, available_mb: Option<usize>) -> impl Iterator<Item = usize> {
+    let limit = available_mb.unwrap_or(usize::MAX).max(1);
+    let requested_mb = requested_mb.max(1);
+    let below = if requested_mb.is_power_of_two() {
+        requested_mb / 2
+    } else {
+        1 << requested_mb.ilog2()
+    };
+    std::iter::once(requested_mb)
+        .chain(std::iter::successors(Some(below), |mb| Some(mb / 2)).take_while(|mb| *mb >= 1))
+        .filter(move |mb| *mb <= limit)
+}
+
+/// Megabytes of memory free for the taking, from the kernel's estimate and
+/// any container limit, where the platform says.
+fn available_memory_mb() -> Option<usize> {
+    let meminfo = fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|meminfo| {
+            meminfo
+                .lines()
+                .find(|line| line.starts_with("MemAvailable:"))?
+                .split_ascii_whitespace()
+                .nth(1)?
+                .parse::<usize>()
+                .ok()
+                .map(|kb| kb / 1024)
+        });
+    // "max" when there's no limit, which doesn't parse
+    let cgroup = fs::read_to_string("/sys/fs/cgroup/memory.max")
+        .ok()
+        .and_then(|max| max.trim().parse::<usize>().ok())
+        .map(|bytes| bytes / BYTES_PER_MB);
+    match (meminfo, cgroup) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::{r#move::Move, types::Square};
+
+    #[test]
+    fn test_mate_score_round_trip() {
+        // mated 5 plies from the root, found at ply 3: mated in 2 from there
+        let score = -MATE + 5;
+        let stored = score_to_tt(score, 3);
+        assert_eq!(stored, -MATE + 2);
+        // and reached again at ply 7 it's 9 from the root
+        assert_eq!(score_from_tt(stored, 7), -MATE + 9);
+        assert_eq!(score_from_tt(score_to_tt(MATE - 4, 2), 2), MATE - 4);
+
+        // other scores pass straight through
+        assert_eq!(score_to_tt(150, 10), 150);
+        assert_eq!(score_from_tt(-150, 10), -150);
+    }
+
+    #[test]
+    fn test_probe_store() {
+        let mut tt = TranspositionTable::new(1);
+        let entry = Entry {
+            key: 0xdead_beef,
+            depth: 3,
+            score: 42,
+            bound: Bound::Exact,
+            best_move: None,
+        };
+        assert_eq!(tt.probe(entry.key), None);
+        tt.store(entry);
+        assert_eq!(tt.probe(entry.key), Some(entry));
+        // a different key in the same bucket misses
+        let n_buckets = tt.buckets.len() as u64;
+        assert_eq!(tt.probe(entry.key + n_buckets), None);
+        tt.clear();
+        assert_eq!(tt.probe(entry.key), None);
+        assert_eq!(tt.hashfull(), 0);
+
+        // the default table stores nothing
+        let tt = TranspositionTable::default();
+        assert_eq!(tt.size_mb(), 0);
+        tt.store(entry);
+        assert_eq!(tt.probe(entry.key), None);
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn test_hashfull() {
+        let tt = TranspositionTable::new(1);
+        for key in 0..25 {
+            tt.store(Entry {
+                key,
+                depth: 1,
+                score: 0,
+                bound: Bound::Exact,
+                best_move: None,
+            });
+        }
+        assert_eq!(tt.hashfull(), 25);
+        // a slot past the sample doesn't count
+        tt.store(Entry {
+            key: HASHFULL_SAMPLE as u64,
+            depth: 1,
+            score: 0,
+            bound: Bound::Exact,
+            best_move: None,
+        });
+        assert_eq!(tt.hashfull(), 25);
+        // and neither do those of an earlier search
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn test_replacement() {
+        let tt = TranspositionTable::new(1);
+        let n_buckets = tt.buckets.len() as u64;
+        // keys all in the same bucket, with their depths
+        let entry = |n: u64, depth: u8| Entry {
+            key: 7 + n * n_buckets,
+            depth,
+            score: 0,
+            bound: Bound::Lower,
+            best_move: None,
+        };
+        let held = |tt: &TranspositionTable| -> Vec<u64> {
+            (0..5)
+                .filter(|n| tt.probe(entry(*n, 0).key).is_some())
+                .collect()
+        };
+
+        tt.store(entry(0, 5));
+        tt.store(entry(1, 3));
+        assert_eq!(held(&tt), vec![0, 1]);
+        // a shallower entry only takes the recent slot
+        tt.store(entry(2, 2));
+        assert_eq!(held(&tt), vec![0, 2]);
+        // a deeper one takes the deep slot, pushing its entry to the other
+        tt.store(entry(3, 6));
+        assert_eq!(held(&tt), vec![0, 3]);
+        // the same position is replaced in place, whatever the depth
+        tt.store(entry(3, 1));
+        assert_eq!(tt.probe(entry(3, 0).key).map(|e| e.depth), Some(1));
+        assert_eq!(held(&tt), vec![0, 3]);
+
+        // in a new search the deep entry gives way to a shallow one
+        tt.store(entry(3, 6));
+        tt.new_search();
+        tt.store(entry(4, 1));
+        assert_eq!(held(&tt), vec![3, 4]);
+        assert_eq!(
+            tt.buckets[7].deep.load().map(|slot| slot.key),
+            Some(entry(4, 0).key)
+        );
+    }
+
+    #[test]
+    fn test_torn_slot_misses() {
+        let slot = AtomicSlot::default();
+        assert_eq!(slot.load(), None);
+        let stored = Slot {
+            key: 0x1234_5678_9abc_def0,
+            best_move: Some(Move::double_push(Square::E2, Square::E4).into()),
+            score: -MATE + 3,
+            depth: 12,
+            bound: Bound::Upper,
+            generation: 250,
+        };
+        slot.store(stored);
+        assert_eq!(slot.load(), Some(stored));
+        // another thread's write, caught halfway, reads back under another key
+        slot.data.store(0x0000_6401_0201_0000, Ordering::Relaxed);
+        assert_ne!(slot.load().unwrap().key, stored.key);
+    }
+
+    #[test]
+    fn test_fallback_sizes() {
+        let sizes: Vec<usize> = fallback_sizes(100, None).collect();
+        assert_eq!(sizes, vec![100, 64, 32, 16, 8, 4, 2, 1]);
+        let sizes: Vec<usize> = fallback_sizes(64, Some(20)).collect();
+        assert_eq!(sizes, vec![16, 8, 4, 2, 1]);
+        // even with next to nothing free there's something to try
+        let sizes: Vec<usize> = fallback_sizes(0, Some(0)).collect();
+        assert_eq!(sizes, vec![1]);
+    }
+
+    #[test]
+    fn test_sizing() {
+        // two buckets to a cache line
+        assert_eq!(size_of::<Bucket>(), 32);
+        let tt = TranspositionTable::new(2);
+        assert_eq!(tt.size_mb(), 2);
+        // the bucket count is rounded down to a power of two
+        let tt = TranspositionTable::try_with_size(3).unwrap();
+        assert!(tt.buckets.len().is_power_of_two());
+        assert_eq!(tt.size_mb(), 2);
+        assert_eq!(TranspositionTable::usable_mb(3), 2);
+        assert_eq!(TranspositionTable::usable_mb(100), 64);
+        assert!(TranspositionTable::try_with_size(1).is_ok());
+        // more than could ever be addressed fails rather than aborting
+        assert!(TranspositionTable::try_with_size(usize::MAX).is_err());
+        let default_mb = TranspositionTable::default_size_mb();
+        assert!((1..=DEFAULT_SIZE_MB).contains(&default_mb));
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use crate::play::MAX_PHASE;
+
+use super::mate_moves;
+
+/// The chances of a win, a draw and a loss for the side to move, in
+/// thousandths that add up to 1000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wdl {
+    pub win: u16,
+    pub draw: u16,
+    pub loss: u16,
+}
+
+impl Wdl {
+    /// The expected score out of 1, a draw counting half.
+    pub fn expected(&self) -> f64 {
+        (self.win as f64 + self.draw as f64 / 2.0) / 1000.0
+    }
+}
+
+/// `W D L`, as UCI's `wdl` wants it.
+impl fmt::Display for Wdl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.win, self.draw, self.loss)
+    }
+}
+
+/// Turns a centipawn score into [`Wdl`] chances. The chance of winning is a
+/// logistic curve in the score, centred on `a` centipawns and `b` wide, both
+/// cubics in how much of the middlegame is left, so the same score wins
+/// more often with fewer pieces to hold the position together. The chance
+/// of losing is the same curve for the score turned round, and a draw is
+/// what's left over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WdlModel {
+    /// coefficients of `a`, the score winning half the time, the cubic
+    /// term first
+    pub a: [f64; 4],
+    /// coefficients of `b`, the spread of the curve, the cubic term first
+    pub b: [f64; 4],
+}
+
+impl Default for WdlModel {
+    /// A pawn up wins half the time in the opening, and a pawn and a half
+    /// up in a pawn ending.
+    fn default() -> Self {
+        WdlModel {
+            a: [0.0, 0.0, -50.0, 150.0],
+            b: [0.0, 0.0, -10.0, 70.0],
+        }
+    }
+}
+
+impl WdlModel {
+    /// The chances for the side to move, with `score` its own and `phase`
+    /// the game phase of the position.
+    pub fn wdl(&self, score: i32, phase: u8) -> Wdl {
+        match mate_moves(score) {
+            Some(moves) if moves > 0 => {
+                return Wdl {
+                    win: 1000,
+                    draw: 0,
+                    loss: 0,
+                }
+            }
+            Some(_) => {
+                return Wdl {
+                    win: 0,
+                    draw: 0,
+                    loss: 1000,
+                }
+            }
+            None => (),
+        }
+        let m = phase.min(MAX_PHASE) as f64 / MAX_PHASE as f64;
+        let cubic = |c: &[f64; 4]| ((c[0] * m + c[1]) * m + c[2]) * m + c[3];
+        let (a, b) = (cubic(&self.a), cubic(&self.b).max(1.0));
+        let chance = |score: f64| (1000.0 / (1.0 + ((a - score) / b).exp())).round() as u16;
+        let win = chance(score as f64);
+        let loss = chance(-score as f64);
+        Wdl {
+            win,
+            draw: 1000 - win - loss,
+            loss,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::search::MATE;
+
+    #[test]
+    fn test_wdl() {
+        let model = WdlModel::default();
+        let level = model.wdl(0, MAX_PHASE);
+        assert_eq!(level.win, level.loss);
+        assert!(level.draw > level.win);
+        assert_eq!(level.win + level.draw + level.loss, 1000);
+
+        // a pawn up is an even chance of a win with every piece on
+        assert_eq!(model.wdl(100, MAX_PHASE).win, 500);
+        assert!(model.wdl(100, 0).win < 500);
+        assert!(model.wdl(300, MAX_PHASE).win > 950);
+
+        let up = model.wdl(250, 12);
+        let down = model.wdl(-250, 12);
+        assert_eq!((up.win, up.draw, up.loss), (down.loss, down.draw, down.win));
+        assert_eq!(
+            up.to_string(),
+            format!("{} {} {}", up.win, up.draw, up.loss)
+        );
+        assert!(up.expected() > 0.5);
+
+        assert_eq!(model.wdl(MATE - 3, 0).win, 1000);
+        assert_eq!(model.wdl(-MATE + 2, 0).loss, 1000);
+    }
+}
@@ -0,0 +1,300 @@
+mod view;
+
+use std::{
+    io,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    DefaultTerminal,
+};
+
+use crate::{
+    play::{
+        draw::DrawClaim,
+        error::MoveError,
+        r#move::{make_move, unmake_move, Move},
+        GameState,
+    },
+    search::{
+        observer::{Iteration, SearchObserver},
+        search,
+        time::SearchControl,
+        tt::TranspositionTable,
+        SearchConfig, MAX_PLY, SEARCH_STACK_SIZE,
+    },
+};
+
+/// How long to wait for a key before redrawing with whatever the search has
+/// found since.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Analyses `fen` in a full-screen board until `q` is pressed. The search
+/// runs on, deepening, in the background; the best move it's found can be
+/// played to analyse the position after it, and taken back again.
+pub fn run(fen: &str) -> io::Result<()> {
+    let state =
+        GameState::from_fen(fen).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut analysis = Analysis::new(state, TranspositionTable::default_size_mb());
+    analysis.start();
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut analysis);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, analysis: &mut Analysis) -> io::Result<()> {
+    loop {
+        analysis.poll();
+        terminal.draw(|frame| view::render(frame, analysis))?;
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Right | KeyCode::Char('m') => {
+                if let Some(mv) = analysis.best_move() {
+                    analysis.play(mv).map_err(io::Error::other)?;
+                }
+            }
+            KeyCode::Left | KeyCode::Char('u') => {
+                analysis.take_back().map_err(io::Error::other)?;
+            }
+            KeyCode::Char('d') => analysis.claim_draw(),
+            _ => {}
+        }
+    }
+}
+
+/// Passes each iteration on to the UI thread.
+struct Forward(Sender<Iteration>);
+
+impl SearchObserver for Forward {
+    fn on_iteration(&mut self, iteration: &Iteration) {
+        // the UI may have moved on to another position and dropped the
+        // receiver, which is fine
+        let _ = self.0.send(iteration.clone());
+    }
+}
+
+struct RunningSearch {
+    control: Arc<SearchControl>,
+    iterations: Receiver<Iteration>,
+    handle: JoinHandle<()>,
+}
+
+/// The position being analysed, and what the search has found in it so far.
+pub struct Analysis {
+    state: GameState,
+    /// the moves played from the starting position, to take back
+    played: Vec<Move>,
+    iterations: Vec<Iteration>,
+    /// how long the search ran for, once it's finished
+    finished: Option<Duration>,
+    search: Option<RunningSearch>,
+    tt: Arc<TranspositionTable>,
+    config: SearchConfig,
+    /// once a draw's been claimed in the current position, the rule it was
+    /// drawn by, or `None` for a wrong claim
+    claim: Option<Option<DrawClaim>>,
+}
+
+impl Analysis {
+    pub fn new(state: GameState, hash_mb: usize) -> Self {
+        Analysis {
+            state,
+            played: vec![],
+            iterations: vec![],
+            finished: None,
+            search: None,
+            tt: Arc::new(TranspositionTable::new(hash_mb)),
+            config: SearchConfig::default(),
+            claim: None,
+        }
+    }
+
+    /// Starts analysing the current position afresh, stopping any search
+    /// of the last one.
+    pub fn start(&mut self) {
+        self.stop();
+        self.iterations.clear();
+        self.finished = None;
+        self.claim = None;
+        let (sender, iterations) = mpsc::channel();
+        let control = Arc::new(SearchControl::default());
+        let (state, config, tt) = (self.state.clone(), self.config, self.tt.clone());
+        let search_control = control.clone();
+        let handle = thread::Builder::new()
+            .name("search".to_string())
+            .stack_size(SEARCH_STACK_SIZE)
+            .spawn(move || {
+                search(
+                    &state,
+                    MAX_PLY,
+                    &config,
+                    search_control,
+                    &tt,
+                    &mut Forward(sender),
+                );
+            })
+            .expect("couldn't spawn the search thread");
+        self.search = Some(RunningSearch {
+            control,
+            iterations,
+            handle,
+        });
+    }
+
+    fn stop(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.control.stop();
+            let _ = search.handle.join();
+        }
+    }
+
+    /// Takes in the iterations completed since the last poll.
+    pub fn poll(&mut self) {
+        let search = match &self.search {
+            Some(search) => search,
+            None => return,
+        };
+        self.iterations.extend(search.iterations.try_iter());
+        if self.finished.is_none() && search.handle.is_finished() {
+            self.iterations.extend(search.iterations.try_iter());
+            self.finished = Some(search.control.elapsed());
+        }
+    }
+
+    /// The first move of the deepest line found.
+    pub fn best_move(&self) -> Option<Move> {
+        self.iterations.last()?.pv.first().copied()
+    }
+
+    /// How long the search has been running, or ran for.
+    pub fn elapsed(&self) -> Duration {
+        match (self.finished, &self.search) {
+            (Some(elapsed), _) => elapsed,
+            (None, Some(search)) => search.control.elapsed(),
+            (None, None) => Duration::ZERO,
+        }
+    }
+
+    pub fn play(&mut self, mv: Move) -> Result<(), MoveError> {
+        make_move(mv, &mut self.state)?;
+        self.played.push(mv);
+        self.start();
+        Ok(())
+    }
+
+    /// Claims a draw in the current position, by threefold repetition or
+    /// else the fifty-move rule, keeping whether the claim held to show.
+    pub fn claim_draw(&mut self) {
+        let drawn = [DrawClaim::ThreefoldRepetition, DrawClaim::FiftyMoves]
+            .iter()
+            .copied()
+            .find(|claim| self.state.claim_draw(*claim, None).is_ok());
+        self.claim = Some(drawn);
+    }
+
+    pub fn take_back(&mut self) -> Result<(), MoveError> {
+        if let Some(mv) = self.played.pop() {
+            unmake_move(mv, &mut self.state)?;
+            self.start();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Analysis {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::START_FEN;
+
+    fn wait_for_iteration(analysis: &mut Analysis) {
+        for _ in 0..500 {
+            analysis.poll();
+            if analysis.best_move().is_some() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("no iteration completed");
+    }
+
+    #[test]
+    fn test_analysis() {
+        let mut analysis = Analysis::new(GameState::default(), 1);
+        analysis.start();
+        wait_for_iteration(&mut analysis);
+        assert!(analysis.elapsed() > Duration::ZERO);
+
+        let best = analysis.best_move().unwrap();
+        analysis.play(best).unwrap();
+        // the new position's analysis starts from nothing
+        assert!(analysis.iterations.is_empty());
+        assert_ne!(analysis.state.to_fen(), START_FEN);
+        wait_for_iteration(&mut analysis);
+
+        analysis.take_back().unwrap();
+        assert_eq!(analysis.state.to_fen(), START_FEN);
+        // nothing left to take back
+        analysis.take_back().unwrap();
+        assert_eq!(analysis.state.to_fen(), START_FEN);
+    }
+
+    #[test]
+    fn test_claim_draw() {
+        let mut state = GameState::default();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"].repeat(2) {
+            let mv = state.position.parse_move(uci).unwrap();
+            make_move(mv, &mut state).unwrap();
+        }
+        let mut analysis = Analysis::new(GameState::default(), 1);
+        analysis.claim_draw();
+        assert_eq!(analysis.claim, Some(None));
+        // a claim is for the position it was made in
+        analysis.start();
+        assert_eq!(analysis.claim, None);
+
+        let mut analysis = Analysis::new(state, 1);
+        analysis.claim_draw();
+        assert_eq!(analysis.claim, Some(Some(DrawClaim::ThreefoldRepetition)));
+    }
+
+    #[test]
+    fn test_analysis_finishes() {
+        // mated, so there's nothing to search and the clock stops at once
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        let mut analysis = Analysis::new(GameState::from_fen(fen).unwrap(), 1);
+        analysis.start();
+        for _ in 0..500 {
+            analysis.poll();
+            if analysis.finished.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let elapsed = analysis.elapsed();
+        assert!(analysis.finished.is_some());
+        assert_eq!(analysis.best_move(), None);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(analysis.elapsed(), elapsed);
+    }
+}
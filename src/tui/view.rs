@@ -0,0 +1,307 @@
+use std::{fmt, time::Duration};
+
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph},
+    Frame,
+};
+
+use crate::{
+    play::{
+        constants::{FILES, RANKS},
+        draw::DrawClaim,
+        r#move::{make_move, Move},
+        types::{Color as Side, Square},
+        GameState,
+    },
+    search::{mate_moves, observer::Iteration},
+};
+
+use super::Analysis;
+
+const LIGHT_SQUARE: Color = Color::Rgb(240, 217, 181);
+const DARK_SQUARE: Color = Color::Rgb(181, 136, 99);
+/// Three columns a square, a rank label and the border.
+const BOARD_WIDTH: u16 = 8 * 3 + 2 + 2;
+const BOARD_HEIGHT: u16 = 8 + 1 + 2;
+/// The evaluation graph runs from this many pawns down for Black to as
+/// many up for White, mates pinned to either edge.
+const GRAPH_PAWNS: f64 = 5.0;
+
+pub fn render(frame: &mut Frame, analysis: &Analysis) {
+    let [main, graph, help] = Layout::vertical([
+        Constraint::Min(BOARD_HEIGHT),
+        Constraint::Length(10),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [board, side] =
+        Layout::horizontal([Constraint::Length(BOARD_WIDTH), Constraint::Min(0)]).areas(main);
+    let [info, lines] = Layout::vertical([Constraint::Length(6), Constraint::Min(0)]).areas(side);
+
+    frame.render_widget(board_view(&analysis.state), board);
+    frame.render_widget(info_view(analysis), info);
+    render_lines(frame, analysis, lines);
+    render_graph(frame, analysis, graph);
+    frame.render_widget(
+        Paragraph::new("→/m play the best move   ←/u take back   d claim a draw   q quit")
+            .style(Style::default().fg(Color::DarkGray)),
+        help,
+    );
+}
+
+fn board_view(state: &GameState) -> Paragraph<'static> {
+    let mut lines: Vec<Line> = RANKS
+        .iter()
+        .rev()
+        .map(|&rank| {
+            let mut spans = vec![Span::raw(format!("{} ", rank as u8))];
+            for &file in FILES.iter() {
+                let sq = Square::new(file, rank);
+                let background = if (file as u8 + rank as u8).is_multiple_of(2) {
+                    LIGHT_SQUARE
+                } else {
+                    DARK_SQUARE
+                };
+                let (symbol, color) = match state.position.board.piece(&sq) {
+                    Some(piece) if piece.color() == Side::White => {
+                        (char::from(piece), Color::White)
+                    }
+                    Some(piece) => (char::from(piece), Color::Black),
+                    None => (' ', Color::Reset),
+                };
+                spans.push(Span::styled(
+                    format!(" {} ", symbol.to_ascii_uppercase()),
+                    Style::default()
+                        .bg(background)
+                        .fg(color)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let files: String = FILES
+        .iter()
+        .map(|file| format!(" {} ", char::from(file)))
+        .collect();
+    lines.push(Line::raw(format!("  {}", files)));
+    Paragraph::new(lines).block(Block::bordered())
+}
+
+fn info_view(analysis: &Analysis) -> Paragraph<'_> {
+    let state = &analysis.state;
+    let side = match state.position.side_to_move {
+        Side::White => "White",
+        Side::Black => "Black",
+    };
+    let status = if let Some(claim) = analysis.claim {
+        match claim {
+            Some(DrawClaim::ThreefoldRepetition) => "Drawn by threefold repetition".to_string(),
+            Some(DrawClaim::FiftyMoves) => "Drawn by the fifty-move rule".to_string(),
+            None => format!("{} to move, no draw to claim", side),
+        }
+    } else if state.position.has_legal_move() {
+        format!("{} to move", side)
+    } else if state.position.in_check() {
+        format!("{} is checkmated", side)
+    } else {
+        "Stalemate".to_string()
+    };
+    let mut lines = vec![Line::raw(status)];
+    if let Some(latest) = analysis.iterations.last() {
+        lines.push(Line::raw(format!(
+            "depth {}/{}  score {}",
+            latest.depth,
+            latest.seldepth,
+            white_score(state, latest.score)
+        )));
+        lines.push(Line::raw(format!(
+            "nodes {}  nps {}  hash {}‰",
+            latest.nodes,
+            latest.nps(),
+            latest.hashfull
+        )));
+    }
+    lines.push(Line::raw(format!(
+        "time {}",
+        clock_text(analysis.elapsed())
+    )));
+    Paragraph::new(lines).block(Block::bordered().title(" Analysis "))
+}
+
+/// The line found at each depth, deepest first.
+fn render_lines(frame: &mut Frame, analysis: &Analysis, area: Rect) {
+    let lines: Vec<Line> = analysis
+        .iterations
+        .iter()
+        .rev()
+        .map(|iteration| iteration_line(&analysis.state, iteration))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Lines ")),
+        area,
+    );
+}
+
+fn iteration_line(state: &GameState, iteration: &Iteration) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(
+            format!("{:>2} ", iteration.depth),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::styled(
+            format!("{:>6}  ", white_score(state, iteration.score)),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(san_line(state, &iteration.pv)),
+    ])
+}
+
+/// The score after each depth, from White's side, as a line graph.
+fn render_graph(frame: &mut Frame, analysis: &Analysis, area: Rect) {
+    let points: Vec<(f64, f64)> = analysis
+        .iterations
+        .iter()
+        .map(|iteration| {
+            let pawns = match white_score(&analysis.state, iteration.score) {
+                WhiteScore::Mate { white_mates, .. } if white_mates => GRAPH_PAWNS,
+                WhiteScore::Mate { .. } => -GRAPH_PAWNS,
+                WhiteScore::Centipawns(cp) => (cp as f64 / 100.0).clamp(-GRAPH_PAWNS, GRAPH_PAWNS),
+            };
+            (iteration.depth as f64, pawns)
+        })
+        .collect();
+    let max_depth = points.last().map_or(0.0, |(depth, _)| *depth).max(10.0);
+    let dataset = Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::Cyan))
+        .data(&points);
+    let chart = Chart::new(vec![dataset])
+        .block(Block::bordered().title(" Evaluation "))
+        .x_axis(
+            Axis::default()
+                .title("depth")
+                .bounds([1.0, max_depth])
+                .labels(["1".to_string(), format!("{}", max_depth)]),
+        )
+        .y_axis(Axis::default().bounds([-GRAPH_PAWNS, GRAPH_PAWNS]).labels([
+            format!("{:+}", -GRAPH_PAWNS),
+            "0".to_string(),
+            format!("{:+}", GRAPH_PAWNS),
+        ]));
+    frame.render_widget(chart, area);
+}
+
+/// A score from White's side, where the search's are the side to move's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WhiteScore {
+    Mate { moves: i32, white_mates: bool },
+    Centipawns(i32),
+}
+
+fn white_score(state: &GameState, score: i32) -> WhiteScore {
+    let white = state.position.side_to_move == Side::White;
+    match mate_moves(score) {
+        Some(moves) => WhiteScore::Mate {
+            moves: moves.abs(),
+            white_mates: (score > 0) == white,
+        },
+        None if white => WhiteScore::Centipawns(score),
+        None => WhiteScore::Centipawns(-score),
+    }
+}
+
+/// Pawns to two places with their sign, or `#N` for White mating in N and
+/// `-#N` for Black.
+impl fmt::Display for WhiteScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            WhiteScore::Mate { moves, white_mates } if *white_mates => format!("#{}", moves),
+            WhiteScore::Mate { moves, .. } => format!("-#{}", moves),
+            WhiteScore::Centipawns(cp) => format!("{:+.2}", *cp as f64 / 100.0),
+        };
+        // padded for the columns of the lines
+        f.pad(&text)
+    }
+}
+
+fn clock_text(elapsed: Duration) -> String {
+    let tenths = elapsed.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+/// `pv` in SAN, each move played from where the last left off.
+fn san_line(state: &GameState, pv: &[Move]) -> String {
+    let mut state = state.clone();
+    let mut sans = vec![];
+    for &mv in pv {
+        sans.push(mv.to_san(&state.position));
+        if make_move(mv, &mut state).is_err() {
+            break;
+        }
+    }
+    sans.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::*;
+    use crate::{
+        play::START_FEN,
+        search::{tt::TranspositionTable, MATE},
+    };
+
+    fn screen(analysis: &Analysis) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+        terminal.draw(|frame| render(frame, analysis)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_render() {
+        let state = GameState::from_fen(START_FEN).unwrap();
+        let e4 = state.position.parse_move("e2e4").unwrap();
+        let mut analysis = Analysis::new(state, TranspositionTable::default_size_mb());
+        analysis.iterations.push(Iteration {
+            depth: 7,
+            seldepth: 11,
+            score: 35,
+            nodes: 120_000,
+            time: Duration::from_millis(250),
+            hashfull: 12,
+            pv: vec![e4],
+        });
+        let screen = screen(&analysis);
+        assert!(screen.contains(" R  N  B  Q  K  B  N  R "));
+        assert!(screen.contains("White to move"));
+        assert!(screen.contains("depth 7/11  score +0.35"));
+        assert!(screen.contains(" 7  +0.35  e4"));
+    }
+
+    #[test]
+    fn test_scores() {
+        let state = GameState::from_fen("k7/8/2K5/8/8/8/8/7R b - - 0 1").unwrap();
+        // Black to move, and getting mated in two
+        assert_eq!(white_score(&state, -(MATE - 4)).to_string(), "#2");
+        assert_eq!(white_score(&state, MATE - 3).to_string(), "-#2");
+        assert_eq!(white_score(&state, 120).to_string(), "-1.20");
+        let state = GameState::default();
+        assert_eq!(white_score(&state, -MATE).to_string(), "-#0");
+        assert_eq!(white_score(&state, 5).to_string(), "+0.05");
+        assert_eq!(clock_text(Duration::from_millis(83_450)), "1:23.4");
+    }
+}
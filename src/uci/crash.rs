@@ -0,0 +1,65 @@
+use std::{
+    backtrace::Backtrace,
+    fs::OpenOptions,
+    io::{self, Write},
+    panic,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    thread,
+};
+
+const CRASH_LOG: &str = "lasker-crash.log";
+
+/// The log panics go to once the hook is installed.
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Logs every panic, with its thread and a backtrace whatever
+/// `RUST_BACKTRACE` says, to `lasker-crash.log` in the temp directory,
+/// before the usual report on stderr. A GUI or tournament manager rarely
+/// keeps the engine's stderr, so without it a crash leaves nothing behind
+/// but a forfeit.
+pub fn install_panic_hook() {
+    let path = LOG_PATH.get_or_init(|| std::env::temp_dir().join(CRASH_LOG));
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let thread = thread::current();
+        let report = format!(
+            "thread '{}' {}\n{}",
+            thread.name().unwrap_or("<unnamed>"),
+            info,
+            Backtrace::force_capture()
+        );
+        if append(path, &report).is_ok() {
+            eprintln!("panic logged to {}", path.display());
+        }
+        previous(info);
+    }));
+}
+
+/// Adds `text` to the crash log, if the hook's been installed.
+pub fn log(text: &str) {
+    if let Some(path) = LOG_PATH.get() {
+        let _ = append(path, text);
+    }
+}
+
+fn append(path: &Path, text: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", text)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_append() {
+        let path = std::env::temp_dir().join(format!("lasker-crash-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        append(&path, "first").unwrap();
+        append(&path, "second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
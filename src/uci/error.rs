@@ -0,0 +1,95 @@
+use std::{error::Error, fmt, io};
+
+use crate::play::error::{FENParsingError, MoveError};
+
+/// Why a command couldn't be carried out. The loop tells the GUI with an
+/// `info string error: ...` and carries on, since a GUI drops an engine that
+/// dies on a typo for good.
+#[derive(Debug)]
+pub enum UciError {
+    Fen(FENParsingError),
+    /// a move, in coordinate notation, that isn't legal where it was played
+    IllegalMove(String),
+    Move(MoveError),
+    /// a value that doesn't fit what the command wanted of it
+    InvalidValue {
+        name: &'static str,
+        value: String,
+    },
+    Io(io::Error),
+}
+
+impl UciError {
+    pub fn invalid_value(name: &'static str, value: impl ToString) -> Self {
+        UciError::InvalidValue {
+            name,
+            value: value.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UciError::Fen(err) => err.fmt(f),
+            UciError::IllegalMove(mv) => write!(f, "illegal move {}", mv),
+            UciError::Move(err) => err.fmt(f),
+            UciError::InvalidValue { name, value } if value.is_empty() => {
+                write!(f, "missing {}", name)
+            }
+            UciError::InvalidValue { name, value } => write!(f, "invalid {} {}", name, value),
+            UciError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for UciError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UciError::Fen(err) => Some(err),
+            UciError::Move(err) => Some(err),
+            UciError::Io(err) => Some(err),
+            UciError::IllegalMove(_) | UciError::InvalidValue { .. } => None,
+        }
+    }
+}
+
+impl From<FENParsingError> for UciError {
+    fn from(err: FENParsingError) -> Self {
+        UciError::Fen(err)
+    }
+}
+
+impl From<MoveError> for UciError {
+    fn from(err: MoveError) -> Self {
+        UciError::Move(err)
+    }
+}
+
+impl From<io::Error> for UciError {
+    fn from(err: io::Error) -> Self {
+        UciError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            UciError::invalid_value("perft depth", "deep").to_string(),
+            "invalid perft depth deep"
+        );
+        assert_eq!(
+            UciError::invalid_value("perft depth", "").to_string(),
+            "missing perft depth"
+        );
+        assert_eq!(
+            UciError::IllegalMove("e2e5".to_string()).to_string(),
+            "illegal move e2e5"
+        );
+    }
+}
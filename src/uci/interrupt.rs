@@ -0,0 +1,58 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+use signal_hook::{consts::SIGINT, flag};
+
+/// What the Ctrl-C handler sees: whether it should end the engine as usual,
+/// and whether it has been pressed since the last [`catch`].
+struct Flags {
+    idle: Arc<AtomicBool>,
+    interrupted: Arc<AtomicBool>,
+}
+
+static FLAGS: OnceLock<Flags> = OnceLock::new();
+
+/// Has Ctrl-C cut short whatever's running under [`catch`], the way `stop`
+/// does, and end the engine as it always has at any other time.
+pub fn install() -> io::Result<()> {
+    let flags = Flags {
+        idle: Arc::new(AtomicBool::new(true)),
+        interrupted: Arc::new(AtomicBool::new(false)),
+    };
+    // the default action has to be registered first to run before the flag
+    flag::register_conditional_default(SIGINT, flags.idle.clone())?;
+    flag::register(SIGINT, flags.interrupted.clone())?;
+    let _ = FLAGS.set(flags);
+    Ok(())
+}
+
+/// Catches Ctrl-C until the guard is dropped, for [`interrupted`] to say so.
+pub fn catch() -> Catch {
+    if let Some(flags) = FLAGS.get() {
+        flags.interrupted.store(false, Ordering::SeqCst);
+        flags.idle.store(false, Ordering::SeqCst);
+    }
+    Catch
+}
+
+/// Whether Ctrl-C has been pressed since [`catch`].
+pub fn interrupted() -> bool {
+    FLAGS
+        .get()
+        .is_some_and(|flags| flags.interrupted.load(Ordering::SeqCst))
+}
+
+pub struct Catch;
+
+impl Drop for Catch {
+    fn drop(&mut self) {
+        if let Some(flags) = FLAGS.get() {
+            flags.idle.store(true, Ordering::SeqCst);
+        }
+    }
+}
@@ -1,50 +1,1741 @@
-use std::io::{self, stdin};
+mod crash;
+pub mod error;
+mod interrupt;
+mod output;
+pub mod session;
 
-use crate::{perft::run_perft, play::GameState};
+use std::{
+    any::Any,
+    io::{self, stdin},
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
 
-fn handle_position(mut buf: String) -> GameState {
+use crate::{
+    eval::Evaluator,
+    perft::{perft_detail_until, run_perft, PerftObserver, PerftTiming},
+    play::{
+        r#move::{make_move, Move},
+        types::Color,
+        variant::{Variant, VARIANTS},
+        GameState,
+    },
+    search::{
+        bench::{bench, BENCH_POSITIONS, DEFAULT_BENCH_DEPTH},
+        contempt::MAX_CONTEMPT,
+        first_legal_move,
+        observer::{CurrentMove, Iteration, SearchObserver},
+        personality::Personality,
+        search,
+        skill::{MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO},
+        stats::SearchStats,
+        testsuite::{run_testsuite, DEFAULT_MOVETIME_MS},
+        time::{NodeBudget, SearchControl, TimeControl, TimeManager},
+        tt::{TranspositionTable, DEFAULT_SIZE_MB, MAX_SIZE_MB},
+        uci_score,
+        wdl::WdlModel,
+        NullMoveParams, RootRandomization, SearchConfig, SearchResult, MAX_PLY, MAX_THREADS,
+        SEARCH_STACK_SIZE,
+    },
+};
+
+use self::{error::UciError, output::UciOutput, session::AnalysisSession};
+
+pub use crate::play::START_FEN;
+const DEFAULT_DEPTH: u8 = 4;
+/// How long a search runs before the root move it's on is reported, so
+/// short searches aren't flooded with lines nobody reads.
+const CURRMOVE_DELAY: Duration = Duration::from_secs(3);
+/// The most plies `RootRandomization` picks the move at random for.
+const MAX_RANDOMIZED_PLIES: u16 = 40;
+
+/// Reports search and perft progress to the GUI. Only the primary search
+/// thread's reporter passes its iterations on; helpers searching the same
+/// root keep quiet so the GUI sees a single line of analysis.
+struct UciReporter {
+    output: UciOutput,
+    primary: bool,
+    /// every iteration is written, and nothing that depends on the clock,
+    /// so a search limited in nodes always prints the same lines
+    reproducible: bool,
+    /// the game phase of the root, when scores come with `wdl` chances
+    wdl_phase: Option<u8>,
+}
+
+impl UciReporter {
+    fn primary(output: UciOutput) -> Self {
+        UciReporter {
+            output,
+            primary: true,
+            reproducible: false,
+            wdl_phase: None,
+        }
+    }
+
+    // helper threads search with no observer at all, so only tests need one
+    #[cfg(test)]
+    fn helper(output: UciOutput) -> Self {
+        UciReporter {
+            output,
+            primary: false,
+            reproducible: false,
+            wdl_phase: None,
+        }
+    }
+
+    fn reproducible(self, reproducible: bool) -> Self {
+        UciReporter {
+            reproducible,
+            ..self
+        }
+    }
+
+    fn show_wdl(self, wdl_phase: Option<u8>) -> Self {
+        UciReporter { wdl_phase, ..self }
+    }
+
+    /// `score ...`, followed by `wdl ...` if they're shown.
+    fn score(&self, score: i32) -> String {
+        match self.wdl_phase {
+            Some(phase) => format!(
+                "score {} wdl {}",
+                uci_score(score),
+                WdlModel::default().wdl(score, phase)
+            ),
+            None => format!("score {}", uci_score(score)),
+        }
+    }
+}
+
+impl SearchObserver for UciReporter {
+    fn on_iteration(&mut self, iteration: &Iteration) {
+        if !self.primary {
+            return;
+        }
+        if iteration.depth == 0 {
+            self.output
+                .info(0, &format!("info depth 0 {}", self.score(iteration.score)));
+            return;
+        }
+        let pv: Vec<String> = iteration.pv.iter().map(|mv| mv.to_uci()).collect();
+        if self.reproducible {
+            self.output.line(&format!(
+                "info depth {} seldepth {} {} nodes {} hashfull {} pv {}",
+                iteration.depth,
+                iteration.seldepth,
+                self.score(iteration.score),
+                iteration.nodes,
+                iteration.hashfull,
+                pv.join(" ")
+            ));
+            return;
+        }
+        self.output.info(
+            iteration.depth,
+            &format!(
+                "info depth {} seldepth {} {} nodes {} nps {} hashfull {} time {} pv {}",
+                iteration.depth,
+                iteration.seldepth,
+                self.score(iteration.score),
+                iteration.nodes,
+                iteration.nps(),
+                iteration.hashfull,
+                iteration.time.as_millis(),
+                pv.join(" ")
+            ),
+        );
+    }
+
+    /// Goes out whatever the rate limit, as each is about a different move,
+    /// but only once the search has run long enough for them to be useful.
+    fn on_current_move(&mut self, current: &CurrentMove) {
+        if !self.primary || self.reproducible || current.time < CURRMOVE_DELAY {
+            return;
+        }
+        self.output.line(&format!(
+            "info depth {} currmove {} currmovenumber {} nodes {} nps {} hashfull {} time {}",
+            current.depth,
+            current.mv.to_uci(),
+            current.number,
+            current.nodes,
+            current.nps(),
+            current.hashfull,
+            current.time.as_millis()
+        ));
+    }
+}
+
+impl PerftObserver for UciReporter {
+    fn on_root_move(&mut self, mv: Move, nodes: u64) {
+        self.output.line(&format!("{}: {}", mv.to_uci(), nodes));
+    }
+
+    fn on_depth(&mut self, _depth: u64, nodes: u64) {
+        self.output.line(&format!("\nNodes searched: {nodes}"));
+    }
+
+    fn on_finish(&mut self, timing: &PerftTiming) {
+        if !timing.complete {
+            self.output
+                .line("info string perft stopped, the count is partial");
+        }
+        self.output.line(&format!(
+            "info nodes {} time {} nps {}",
+            timing.nodes,
+            timing.elapsed.as_millis(),
+            timing.nps()
+        ));
+    }
+}
+
+/// A search running on its own thread, so the loop can keep reading commands.
+struct ActiveSearch {
+    control: Arc<SearchControl>,
+    handle: JoinHandle<()>,
+    /// a perft always comes to an end, so it's left to when input runs out
+    perft: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct GoParams {
+    depth: Option<u8>,
+    nodes: Option<u64>,
+    /// `go mate N`, moves to find a mate in
+    mate: Option<u8>,
+    time_control: TimeControl,
+    ponder: bool,
+    infinite: bool,
+}
+
+/// The FEN, with `startpos` spelled out, and the moves of a `position` command.
+pub(crate) fn parse_position(buf: &str) -> (String, Vec<String>) {
     let mut whitespace_it = buf.split_ascii_whitespace();
     whitespace_it.next(); // consume position
-    if let Some(sub) = whitespace_it.next() {
-        if sub == "fen" {
-            let fen_str = whitespace_it
-                .fold(String::new(), |mut acc, el| {
-                    acc.push_str(" ");
-                    acc.push_str(el);
-                    acc
-                })
-                .trim()
-                .to_string();
-            GameState::from_fen(fen_str).unwrap()
+    let fen = match whitespace_it.next() {
+        Some("fen") => whitespace_it
+            .by_ref()
+            .take_while(|el| *el != "moves")
+            .collect::<Vec<&str>>()
+            .join(" "),
+        Some("startpos") => {
+            whitespace_it.next(); // consume moves
+            START_FEN.to_string()
+        }
+        _ => START_FEN.to_string(),
+    };
+    (fen, whitespace_it.map(|mv| mv.to_string()).collect())
+}
+
+/// Plays `moves` in coordinate notation from `fen`, under `variant`'s rules.
+/// Only the history repetitions can reach back to is kept, so a game of
+/// any length can be set up.
+fn position_state(fen: &str, moves: &[String], variant: Variant) -> Result<GameState, UciError> {
+    let mut state = GameState::from_variant_fen(fen, variant)?;
+    for mv_str in moves {
+        let mv = state
+            .position
+            .parse_move(mv_str)
+            .ok_or_else(|| UciError::IllegalMove(mv_str.clone()))?;
+        state.trim_history();
+        make_move(mv, &mut state)?;
+    }
+    Ok(state)
+}
+
+/// Sets up the position, starting a new analysis session unless it's the
+/// one `session` is already on. A position that can't be set up leaves the
+/// session as it was.
+fn handle_position(
+    buf: String,
+    variant: Variant,
+    session: &mut AnalysisSession,
+) -> Result<GameState, UciError> {
+    let (fen, moves) = parse_position(&buf);
+    let state = position_state(&fen, &moves, variant)?;
+    if !session.is_on(&fen, &moves) {
+        *session = AnalysisSession::new(fen, moves);
+    }
+    Ok(state)
+}
+
+/// The variant a `setoption name UCI_Variant value <name>` picks.
+fn handle_variant(buf: &str) -> Result<Variant, UciError> {
+    let (_, value) = parse_setoption(buf);
+    Variant::from_name(&value).ok_or_else(|| UciError::invalid_value("variant", value))
+}
+
+/// `session save <file>` writes the current session out as JSON, and
+/// `session load <file>` reads one back in and sets up its position.
+fn handle_session(
+    buf: &str,
+    variant: Variant,
+    session: &mut AnalysisSession,
+    output: &UciOutput,
+) -> Result<Option<GameState>, UciError> {
+    let mut whitespace_it = buf.split_ascii_whitespace().skip(1);
+    let command = whitespace_it.next();
+    let path = whitespace_it.collect::<Vec<&str>>().join(" ");
+    match command {
+        Some("save") => {
+            session.save(&path).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("couldn't save session to {}: {}", path, e),
+                )
+            })?;
+            output.line(&format!("info string saved session to {}", path));
+            Ok(None)
+        }
+        Some("load") => {
+            let loaded = AnalysisSession::load(&path).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("couldn't load session from {}: {}", path, e),
+                )
+            })?;
+            let state = position_state(&loaded.root_fen, &loaded.moves, variant)?;
+            *session = loaded;
+            if let Some(line) = session.best_line() {
+                output.line(&format!(
+                    "info string loaded session at depth {} score {} pv {}",
+                    line.depth,
+                    uci_score(line.score),
+                    line.pv.join(" ")
+                ));
+            }
+            Ok(Some(state))
+        }
+        _ => Err(UciError::invalid_value(
+            "session command",
+            command.unwrap_or_default(),
+        )),
+    }
+}
+
+/// `go perft N` counts the nodes below each root move; `go perft detail N`
+/// instead breaks the total down by the kind of move made at the last ply.
+/// Either runs on its own thread, so a `stop` or Ctrl-C can cut it short.
+fn handle_perft(
+    buf: String,
+    pos: &GameState,
+    output: &UciOutput,
+) -> Result<ActiveSearch, UciError> {
+    let depth = buf.split_ascii_whitespace().nth(2).unwrap_or_default();
+    let depth = match depth {
+        "detail" => buf.split_ascii_whitespace().nth(3).unwrap_or_default(),
+        depth => depth,
+    };
+    let depth = depth
+        .parse()
+        .map_err(|_| UciError::invalid_value("perft depth", depth))?;
+    let detail = buf.split_ascii_whitespace().any(|token| token == "detail");
+    let control = Arc::new(SearchControl::default());
+    let perft_control = control.clone();
+    let mut state = pos.clone();
+    let output = output.clone();
+    let handle = thread::spawn(move || {
+        let _catching = interrupt::catch();
+        let stopped = || perft_control.is_stopped() || interrupt::interrupted();
+        if detail {
+            let stats = perft_detail_until(&mut state, depth, &stopped);
+            if stopped() {
+                output.line("info string perft stopped, counts are partial");
+            }
+            for line in stats.to_string().lines() {
+                output.line(line);
+            }
         } else {
-            GameState::default()
+            run_perft(
+                &mut state,
+                depth,
+                &mut UciReporter::primary(output),
+                &stopped,
+            );
         }
-    } else {
-        GameState::default()
+    });
+    Ok(ActiveSearch {
+        control,
+        handle,
+        perft: true,
+    })
+}
+
+/// `d` shows the current position and what the engine makes of it, for
+/// checking a `position` command did what was meant.
+fn handle_display(state: &GameState, session: &AnalysisSession, output: &UciOutput) {
+    let position = &state.position;
+    let board = format!("{:?}", position.board);
+    for line in board.lines().filter(|line| !line.is_empty()) {
+        output.line(line);
     }
+    let mut setup = format!("position fen {}", session.root_fen);
+    if !session.moves.is_empty() {
+        setup.push_str(" moves ");
+        setup.push_str(&session.moves.join(" "));
+    }
+    output.line(&format!("Set up by: {}", setup));
+    output.line(&format!("Fen: {}", state.to_fen()));
+    output.line(&format!("Key: {:016X}", state.position_key));
+    output.line(&format!("Side to move: {:?}", position.side_to_move));
+    output.line(&format!("Castling: {}", position.castling_permissions));
+    output.line(&format!(
+        "En passant: {}",
+        position
+            .en_passant
+            .map_or("-".to_string(), |sq| sq.to_string())
+    ));
+    let checkers: Vec<String> = position.checkers.iter().map(|sq| sq.to_string()).collect();
+    output.line(&format!(
+        "Checkers: {}",
+        if checkers.is_empty() {
+            "-".to_string()
+        } else {
+            checkers.join(" ")
+        }
+    ));
 }
 
-fn handle_perft(buf: String, pos: &mut GameState) {
-    if let Some(depth_str) = buf.split_ascii_whitespace().next_back() {
-        println!("\nevaluating position: {:?}", pos);
-        let depth = depth_str.parse().unwrap();
-        let nodes_searched = run_perft(pos, depth);
-        println!("\ntotal nodes searched: {nodes_searched}");
+/// `eval` takes the static evaluation of the current position apart, term
+/// by term and side by side, with the weights the search would use.
+fn handle_eval(state: &GameState, config: &SearchConfig, output: &UciOutput) {
+    let trace = Evaluator::new(config.eval_weights).trace(state);
+    for line in trace.to_string().lines() {
+        output.line(line);
     }
 }
 
+/// `heatmap [attacks|pst|king]` draws a map of the board for each, or the
+/// one named, of what the evaluation sees square by square: the attacks of
+/// each side, the piece-square bonuses and the danger around each king.
+fn handle_heatmap(
+    buf: &str,
+    state: &GameState,
+    config: &SearchConfig,
+    output: &UciOutput,
+) -> Result<(), UciError> {
+    let eval = Evaluator::new(config.eval_weights);
+    let maps = match buf.split_ascii_whitespace().nth(1) {
+        None => eval.heatmaps(state),
+        Some("attacks") => vec![
+            eval.attack_heatmap(state, Color::White),
+            eval.attack_heatmap(state, Color::Black),
+        ],
+        Some("pst") => vec![eval.pst_heatmap(state)],
+        Some("king") => vec![eval.king_danger_heatmap(state)],
+        Some(map) => return Err(UciError::invalid_value("heatmap", map)),
+    };
+    for (i, map) in maps.iter().enumerate() {
+        if i > 0 {
+            output.line("");
+        }
+        for line in map.to_string().lines() {
+            output.line(line);
+        }
+    }
+    Ok(())
+}
+
+/// `bench [depth]` searches the bench positions, for a node count to check
+/// a change against and a speed to compare.
+fn handle_bench(buf: &str, output: &UciOutput) -> Result<(), UciError> {
+    let depth = match buf.split_ascii_whitespace().nth(1) {
+        Some(depth) => depth
+            .parse()
+            .map_err(|_| UciError::invalid_value("bench depth", depth))?,
+        None => DEFAULT_BENCH_DEPTH,
+    };
+    let mut report = vec![];
+    bench(&BENCH_POSITIONS, depth, &mut report)?;
+    for line in String::from_utf8_lossy(&report).lines() {
+        output.line(line);
+    }
+    Ok(())
+}
+
+/// `testsuite <file> [movetime <ms>]` searches each position of an EPD test
+/// suite and scores the moves played against its `bm` and `am` opcodes.
+fn handle_testsuite(buf: &str, output: &UciOutput) -> Result<(), UciError> {
+    let mut words = buf.split_ascii_whitespace().skip(1);
+    let path = words
+        .next()
+        .ok_or_else(|| UciError::invalid_value("testsuite file", ""))?;
+    let movetime = match (words.next(), words.next()) {
+        (None, _) => DEFAULT_MOVETIME_MS,
+        (Some("movetime"), Some(ms)) => ms
+            .parse()
+            .map_err(|_| UciError::invalid_value("testsuite movetime", ms))?,
+        (Some(word), _) => return Err(UciError::invalid_value("testsuite option", word)),
+    };
+    let epd = std::fs::read_to_string(path)?;
+    let mut report = vec![];
+    run_testsuite(&epd, Duration::from_millis(movetime), &mut report)?;
+    for line in String::from_utf8_lossy(&report).lines() {
+        output.line(line);
+    }
+    Ok(())
+}
+
+/// The parameters of a `go` command, refusing any value that isn't a
+/// number of the kind it should be rather than searching without it.
+fn parse_go(buf: &str) -> Result<GoParams, UciError> {
+    fn value<'a, T: FromStr>(
+        name: &'static str,
+        tokens: &mut impl Iterator<Item = &'a str>,
+    ) -> Result<Option<T>, UciError> {
+        let value = tokens.next().unwrap_or_default();
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| UciError::invalid_value(name, value))
+    }
+
+    let mut params = GoParams::default();
+    let tc = &mut params.time_control;
+    let mut whitespace_it = buf.split_ascii_whitespace();
+    while let Some(token) = whitespace_it.next() {
+        match token {
+            "ponder" => params.ponder = true,
+            "infinite" => params.infinite = true,
+            "depth" => params.depth = value("go depth", &mut whitespace_it)?,
+            "nodes" => params.nodes = value("go nodes", &mut whitespace_it)?,
+            "mate" => params.mate = value("go mate", &mut whitespace_it)?,
+            "wtime" => tc.wtime = value("go wtime", &mut whitespace_it)?,
+            "btime" => tc.btime = value("go btime", &mut whitespace_it)?,
+            "winc" => tc.winc = value("go winc", &mut whitespace_it)?,
+            "binc" => tc.binc = value("go binc", &mut whitespace_it)?,
+            "movestogo" => tc.movestogo = value("go movestogo", &mut whitespace_it)?,
+            "movetime" => tc.movetime = value("go movetime", &mut whitespace_it)?,
+            _ => {}
+        }
+    }
+    Ok(params)
+}
+
+/// Starts searching `pos` on a thread of its own, which says `bestmove` when
+/// done, after the search's counters if `stats` is set. Scores come with
+/// win, draw and loss chances if `show_wdl` is.
+#[allow(clippy::too_many_arguments)]
+fn handle_go(
+    buf: String,
+    pos: &GameState,
+    time_manager: &TimeManager,
+    config: &SearchConfig,
+    stats: bool,
+    show_wdl: bool,
+    tt: &Arc<Mutex<TranspositionTable>>,
+    session: &Arc<Mutex<AnalysisSession>>,
+    output: &UciOutput,
+) -> Result<ActiveSearch, UciError> {
+    let params = parse_go(&buf)?;
+    let budget = time_manager.allocate(&params.time_control, pos.position.side_to_move);
+    let depth = match params.depth {
+        Some(d) => d,
+        None if budget.is_some()
+            || params.nodes.is_some()
+            || params.mate.is_some()
+            || params.ponder
+            || params.infinite =>
+        {
+            MAX_PLY
+        }
+        None => DEFAULT_DEPTH,
+    };
+    // deterministic mode spends the clock in nodes, so the same commands
+    // always give the same search
+    let (budget, node_budget) = match budget {
+        Some(b) if time_manager.deterministic => (None, Some(b.in_nodes())),
+        _ => (budget, None),
+    };
+    let node_budget = params
+        .nodes
+        .map(|n| NodeBudget { soft: n, hard: n })
+        .or(node_budget);
+    let control = Arc::new(SearchControl::new(budget, params.ponder).with_node_budget(node_budget));
+    // and starts from an empty table, so what earlier searches stored
+    // doesn't change it either
+    if time_manager.deterministic {
+        lock_tt(tt).clear();
+    }
+
+    let state = pos.clone();
+    let wdl_phase = Some(state.phase).filter(|_| show_wdl);
+    let config = SearchConfig {
+        mate_in: params.mate,
+        ..*config
+    };
+    let search_control = control.clone();
+    let tt = tt.clone();
+    let session = session.clone();
+    let output = output.clone();
+    let handle = thread::Builder::new()
+        .name("search".to_string())
+        .stack_size(SEARCH_STACK_SIZE)
+        .spawn(move || {
+            let searched = panic::catch_unwind(AssertUnwindSafe(|| {
+                search(
+                    &state,
+                    depth,
+                    &config,
+                    search_control.clone(),
+                    &lock_tt(&tt),
+                    &mut UciReporter::primary(output.clone())
+                        .reproducible(node_budget.is_some())
+                        .show_wdl(wdl_phase),
+                )
+            }));
+            let result = searched.unwrap_or_else(|payload| {
+                report_search_panic(&buf, &state, payload.as_ref(), &output);
+                // the table may hold whatever the search was writing when it
+                // fell over, so it starts again empty
+                tt.clear_poison();
+                lock_tt(&tt).clear();
+                // the move generator may be what fell over, in which case
+                // there's no move to give and the GUI gets a null one
+                let fallback = panic::catch_unwind(|| first_legal_move(&state));
+                SearchResult {
+                    best_move: fallback.ok().flatten(),
+                    score: 0,
+                    depth: 0,
+                    pv: vec![],
+                    stats: SearchStats::default(),
+                }
+            });
+            session.lock().unwrap().record(&result);
+            // the GUI expects no bestmove while pondering or in infinite mode
+            // until it says so, even if the search itself has run out
+            while (search_control.is_pondering() || params.infinite) && !search_control.is_stopped()
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            if search_control.is_discarded() {
+                output.discard_info();
+                return;
+            }
+            if stats {
+                output.line(&format!("info string stats {}", result.stats));
+            }
+            if let Some(moves) = params.mate.filter(|_| !config.proves_mate(result.score)) {
+                output.line(&format!(
+                    "info string no mate in {} found to depth {}",
+                    moves, result.depth
+                ));
+            }
+            let bestmove = match (result.best_move, result.ponder_move()) {
+                (Some(mv), Some(ponder)) => {
+                    format!("bestmove {} ponder {}", mv.to_uci(), ponder.to_uci())
+                }
+                (Some(mv), None) => format!("bestmove {}", mv.to_uci()),
+                (None, _) => "bestmove 0000".to_string(),
+            };
+            output.line(&bestmove);
+        })
+        .expect("couldn't spawn the search thread");
+    Ok(ActiveSearch {
+        control,
+        handle,
+        perft: false,
+    })
+}
+
+/// Locks the table even if a search panicked holding it; what that search
+/// left in it is only ever a worse move ordering.
+fn lock_tt(tt: &Mutex<TranspositionTable>) -> MutexGuard<'_, TranspositionTable> {
+    tt.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Tells the GUI the search fell over, and leaves what's needed to reproduce
+/// it on stderr, next to the panic's own message and backtrace.
+fn report_search_panic(
+    go: &str,
+    state: &GameState,
+    payload: &(dyn Any + Send),
+    output: &UciOutput,
+) {
+    let msg = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+        .unwrap_or("unknown panic");
+    output.line(&format!("info string search failed: {}", msg));
+    eprintln!("search panicked on `{}` in:\n{:#?}", go, state);
+    crash::log(&format!(
+        "search panicked on `{}` in {}",
+        go.trim(),
+        state.to_fen()
+    ));
+}
+
+/// Stops a running search and waits for it to report its move. A ponder
+/// search stopped without a `ponderhit` means the predicted move was missed.
+fn stop_search(active: &mut Option<ActiveSearch>, time_manager: &mut TimeManager) {
+    end_search(active, time_manager, false);
+}
+
+/// Stops a running search overtaken by a new command, e.g. a `position` or
+/// `go` sent without a `stop` first, and waits for it without it reporting a
+/// move, so nothing from the old search follows the new command.
+fn abandon_search(active: &mut Option<ActiveSearch>, time_manager: &mut TimeManager) {
+    end_search(active, time_manager, true);
+}
+
+fn end_search(active: &mut Option<ActiveSearch>, time_manager: &mut TimeManager, discard: bool) {
+    if let Some(ActiveSearch {
+        control, handle, ..
+    }) = active.take()
+    {
+        if control.is_pondering() {
+            time_manager.record_ponder(false);
+        }
+        if discard {
+            control.discard();
+        } else {
+            control.stop();
+        }
+        let _ = handle.join();
+    }
+}
+
+/// Replaces the table with one of the size a `setoption name Hash` asks for,
+/// saying so when only a smaller one could be had or the size isn't a power
+/// of two, and so only part of it is used.
+fn handle_hash(
+    buf: &str,
+    tt: &Mutex<TranspositionTable>,
+    output: &UciOutput,
+) -> Result<(), UciError> {
+    let (_, value) = parse_setoption(buf);
+    let requested = value
+        .parse::<usize>()
+        .map_err(|_| UciError::invalid_value("Hash size", &value))?
+        .clamp(1, MAX_SIZE_MB);
+    resize_tt(tt, requested, output);
+    Ok(())
+}
+
+/// Turns deterministic mode on or off. While it's on the search keeps to one
+/// thread and the table stays at [`DEFAULT_SIZE_MB`] whatever the machine,
+/// since how many entries fit changes what a search finds.
+fn handle_deterministic(
+    buf: &str,
+    time_manager: &mut TimeManager,
+    config: &mut SearchConfig,
+    tt: &Mutex<TranspositionTable>,
+    output: &UciOutput,
+) {
+    let (name, value) = parse_setoption(buf);
+    set_option(&name, &value, time_manager, config);
+    if time_manager.deterministic && lock_tt(tt).size_mb() != DEFAULT_SIZE_MB {
+        resize_tt(tt, DEFAULT_SIZE_MB, output);
+    }
+}
+
+/// Replaces the table with one of `requested` MB, saying so when less of it
+/// than that could be had or used.
+fn resize_tt(tt: &Mutex<TranspositionTable>, requested: usize, output: &UciOutput) {
+    let mut tt = lock_tt(tt);
+    // the old table goes first so its memory is there for the new one
+    *tt = TranspositionTable::default();
+    *tt = TranspositionTable::new(requested);
+    let usable = TranspositionTable::usable_mb(requested);
+    if tt.size_mb() < usable {
+        output.line(&format!(
+            "info string couldn't allocate {} MB of hash, using {} MB",
+            requested,
+            tt.size_mb()
+        ));
+    } else if usable < requested {
+        output.line(&format!(
+            "info string using {} MB of hash, the largest power of two within {} MB",
+            usable, requested
+        ));
+    }
+}
+
+/// The rating in a `UCI_Opponent` value, `<title> <rating> <computer|human>
+/// <name>`, where an unknown rating is `none`.
+fn parse_opponent_rating(value: &str) -> Option<i32> {
+    value.split_ascii_whitespace().nth(1)?.parse().ok()
+}
+
+/// The name and value of a `setoption name <id> [value <x>]` command, where
+/// both may contain spaces.
+fn parse_setoption(buf: &str) -> (String, String) {
+    let mut whitespace_it = buf.split_ascii_whitespace().skip(2);
+    let name: Vec<&str> = whitespace_it
+        .by_ref()
+        .take_while(|el| *el != "value")
+        .collect();
+    let value: Vec<&str> = whitespace_it.collect();
+    (name.join(" "), value.join(" "))
+}
+
+fn handle_setoption(buf: &str, time_manager: &mut TimeManager, config: &mut SearchConfig) {
+    let (name, value) = parse_setoption(buf);
+    set_option(&name, &value, time_manager, config);
+}
+
+/// Sets the search option `name` to `value`, as `setoption` would, and
+/// returns whether it's one of the engine's. A value that doesn't parse
+/// leaves the option as it was.
+pub fn set_option(
+    name: &str,
+    value: &str,
+    time_manager: &mut TimeManager,
+    config: &mut SearchConfig,
+) -> bool {
+    let contempt = &mut config.contempt;
+    match name {
+        "Ponder" => time_manager.ponder = value == "true",
+        // helpers race each other through the table, so deterministic
+        // searches keep to one thread
+        "Threads" => {
+            if let Ok(threads) = value.parse::<usize>() {
+                config.helper_threads = if time_manager.deterministic {
+                    0
+                } else {
+                    threads.clamp(1, MAX_THREADS) - 1
+                };
+            }
+        }
+        "Deterministic" => {
+            time_manager.deterministic = value == "true";
+            if time_manager.deterministic {
+                config.helper_threads = 0;
+            }
+        }
+        "Contempt" => {
+            if let Ok(base) = value.parse::<i32>() {
+                contempt.base = base.clamp(-MAX_CONTEMPT, MAX_CONTEMPT);
+            }
+        }
+        "EngineRating" => {
+            if let Ok(rating) = value.parse() {
+                contempt.engine_rating = rating;
+            }
+        }
+        "UCI_Opponent" => contempt.opponent_rating = parse_opponent_rating(value),
+        "Skill Level" => {
+            if let Ok(level) = value.parse::<u8>() {
+                config.strength.skill_level = level.min(MAX_SKILL_LEVEL);
+            }
+        }
+        "UCI_LimitStrength" => config.strength.limit_strength = value == "true",
+        "UCI_Elo" => {
+            if let Ok(elo) = value.parse::<u16>() {
+                config.strength.elo = elo.clamp(MIN_ELO, MAX_ELO);
+            }
+        }
+        "RootRandomization" => {
+            if let Ok(plies) = value.parse::<u16>() {
+                config.root_randomization =
+                    RootRandomization::for_plies(plies.min(MAX_RANDOMIZED_PLIES));
+            }
+        }
+        "NullMoveVerification" => {
+            config.null_move.verification_min_depth = match value {
+                "true" => NullMoveParams::default().verification_min_depth,
+                _ => None,
+            }
+        }
+        "Personality" => {
+            if let Some(personality) = Personality::from_name(value) {
+                personality.apply(config);
+            }
+        }
+        // searches started from now on pick the network up
+        #[cfg(feature = "nnue")]
+        "EvalFile" => {
+            if let Err(e) = crate::eval::nnue::load_network(value) {
+                UciOutput::stdout().line(&format!(
+                    "info string couldn't load network {}: {}, using the classical evaluation",
+                    value, e
+                ));
+                let _ = crate::eval::nnue::load_network("");
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
 pub fn uci_loop() -> Result<(), io::Error> {
+    crash::install_panic_hook();
+    // without the handler Ctrl-C ends a perft along with the engine
+    let _ = interrupt::install();
     let stdin = stdin();
     let mut pos = GameState::default();
+    let mut time_manager = TimeManager::default();
+    let mut config = SearchConfig::default();
+    // whether each search's counters follow its bestmove's info lines
+    let mut stats = false;
+    let mut show_wdl = false;
+    let mut variant = Variant::Standard;
+    let mut active: Option<ActiveSearch> = None;
+    let output = UciOutput::stdout();
+    let hash_mb = TranspositionTable::default_size_mb();
+    let tt = Arc::new(Mutex::new(TranspositionTable::new(hash_mb)));
+    let session = Arc::new(Mutex::new(AnalysisSession::new(
+        START_FEN.to_string(),
+        vec![],
+    )));
 
     loop {
         let mut buf = String::new();
-        let _ = stdin.read_line(&mut buf);
+        if stdin.read_line(&mut buf)? == 0 {
+            // piped in `go perft` commands still get their counts
+            if let Some(perft) = active.take_if(|a| a.perft) {
+                let _ = perft.handle.join();
+            }
+            abandon_search(&mut active, &mut time_manager);
+            return Ok(());
+        }
 
-        if buf.starts_with("position") {
-            pos = handle_position(buf);
+        if buf.starts_with("ucinewgame") {
+            abandon_search(&mut active, &mut time_manager);
+            pos = GameState::default().with_variant(variant);
+            *session.lock().unwrap() = AnalysisSession::new(START_FEN.to_string(), vec![]);
+            time_manager.new_game();
+            // nothing the last game's searches stored applies to this one
+            lock_tt(&tt).clear();
+        } else if buf.starts_with("uci") {
+            output.line("id name lasker");
+            output.line("id author thomas");
+            output.line(&format!(
+                "option name Hash type spin default {} min 1 max {}",
+                hash_mb, MAX_SIZE_MB
+            ));
+            output.line(&format!(
+                "option name Threads type spin default 1 min 1 max {}",
+                MAX_THREADS
+            ));
+            output.line("option name Clear Hash type button");
+            output.line("option name Ponder type check default false");
+            output.line("option name Deterministic type check default false");
+            output.line("option name Stats type check default false");
+            output.line("option name UCI_ShowWDL type check default false");
+            let vars: Vec<String> = VARIANTS
+                .iter()
+                .map(|variant| format!("var {}", variant.name()))
+                .collect();
+            output.line(&format!(
+                "option name UCI_Variant type combo default {} {}",
+                Variant::Standard.name(),
+                vars.join(" ")
+            ));
+            output.line(&format!(
+                "option name Contempt type spin default {} min {} max {}",
+                config.contempt.base, -MAX_CONTEMPT, MAX_CONTEMPT
+            ));
+            output.line(&format!(
+                "option name EngineRating type spin default {} min 0 max 4000",
+                config.contempt.engine_rating
+            ));
+            output.line("option name UCI_Opponent type string default <empty>");
+            output.line(&format!(
+                "option name Skill Level type spin default {0} min 0 max {0}",
+                MAX_SKILL_LEVEL
+            ));
+            output.line("option name UCI_LimitStrength type check default false");
+            output.line(&format!(
+                "option name UCI_Elo type spin default {1} min {0} max {1}",
+                MIN_ELO, MAX_ELO
+            ));
+            output.line(&format!(
+                "option name RootRandomization type spin default 0 min 0 max {}",
+                MAX_RANDOMIZED_PLIES
+            ));
+            output.line(&format!(
+                "option name NullMoveVerification type check default {}",
+                config.null_move.verification_min_depth.is_some()
+            ));
+            let vars: Vec<String> = Personality::ALL
+                .iter()
+                .map(|personality| format!("var {}", personality.name()))
+                .collect();
+            output.line(&format!(
+                "option name Personality type combo default {} {}",
+                Personality::default().name(),
+                vars.join(" ")
+            ));
+            #[cfg(feature = "nnue")]
+            output.line("option name EvalFile type string default <empty>");
+            output.line("uciok");
+        } else if buf.starts_with("isready") {
+            output.line("readyok");
+        } else if buf.starts_with("setoption name Hash ") {
+            abandon_search(&mut active, &mut time_manager);
+            if time_manager.deterministic {
+                output.line(&format!(
+                    "info string Hash stays at {} MB while Deterministic is on",
+                    DEFAULT_SIZE_MB
+                ));
+            } else if let Err(e) = handle_hash(&buf, &tt, &output) {
+                output.error(&e);
+            }
+        } else if buf.starts_with("setoption name Deterministic ") {
+            abandon_search(&mut active, &mut time_manager);
+            handle_deterministic(&buf, &mut time_manager, &mut config, &tt, &output);
+        } else if buf.trim() == "setoption name Clear Hash" {
+            abandon_search(&mut active, &mut time_manager);
+            lock_tt(&tt).clear();
+        } else if buf.starts_with("setoption name Stats ") {
+            stats = parse_setoption(&buf).1 == "true";
+        } else if buf.starts_with("setoption name UCI_Variant ") {
+            abandon_search(&mut active, &mut time_manager);
+            match handle_variant(&buf) {
+                Ok(chosen) => {
+                    variant = chosen;
+                    pos = pos.with_variant(variant);
+                    // scores stored under one set of rules mean nothing
+                    // under another
+                    lock_tt(&tt).clear();
+                }
+                Err(e) => output.error(&e),
+            }
+        } else if buf.starts_with("setoption name UCI_ShowWDL ") {
+            show_wdl = parse_setoption(&buf).1 == "true";
+        } else if buf.starts_with("setoption") {
+            handle_setoption(&buf, &mut time_manager, &mut config);
+        } else if buf.starts_with("position") {
+            abandon_search(&mut active, &mut time_manager);
+            match handle_position(buf, variant, &mut session.lock().unwrap()) {
+                Ok(state) => pos = state,
+                Err(e) => output.error(&e),
+            }
+        } else if buf.starts_with("session load") {
+            abandon_search(&mut active, &mut time_manager);
+            match handle_session(&buf, variant, &mut session.lock().unwrap(), &output) {
+                Ok(Some(state)) => pos = state,
+                Ok(None) => {}
+                Err(e) => output.error(&e),
+            }
+        } else if buf.starts_with("session") {
+            if let Err(e) = handle_session(&buf, variant, &mut session.lock().unwrap(), &output) {
+                output.error(&e);
+            }
+        } else if buf.trim() == "d" {
+            handle_display(&pos, &session.lock().unwrap(), &output);
+        } else if buf.trim() == "eval" {
+            handle_eval(&pos, &config, &output);
+        } else if buf.starts_with("heatmap") {
+            if let Err(e) = handle_heatmap(&buf, &pos, &config, &output) {
+                output.error(&e);
+            }
+        } else if buf.starts_with("bench") {
+            abandon_search(&mut active, &mut time_manager);
+            if let Err(e) = handle_bench(&buf, &output) {
+                output.error(&e);
+            }
+        } else if buf.starts_with("testsuite") {
+            abandon_search(&mut active, &mut time_manager);
+            if let Err(e) = handle_testsuite(&buf, &output) {
+                output.error(&e);
+            }
         } else if buf.starts_with("go perft") {
-            handle_perft(buf, &mut pos);
+            abandon_search(&mut active, &mut time_manager);
+            match handle_perft(buf, &pos, &output) {
+                Ok(perft) => active = Some(perft),
+                Err(e) => output.error(&e),
+            }
+        } else if buf.starts_with("go") {
+            abandon_search(&mut active, &mut time_manager);
+            match handle_go(
+                buf,
+                &pos,
+                &time_manager,
+                &config,
+                stats,
+                show_wdl,
+                &tt,
+                &session,
+                &output,
+            ) {
+                Ok(search) => active = Some(search),
+                Err(e) => output.error(&e),
+            }
+        } else if buf.starts_with("ponderhit") {
+            if let Some(search) = &active {
+                time_manager.record_ponder(true);
+                search.control.ponderhit();
+            }
+        } else if buf.starts_with("stop") {
+            stop_search(&mut active, &mut time_manager);
+        } else if buf.starts_with("quit") {
+            abandon_search(&mut active, &mut time_manager);
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::play::types::{Color, Piece, Square};
+    use crate::search::MATE;
+    use output::{SharedBuf, MIN_INFO_INTERVAL};
+
+    #[test]
+    fn test_parse_go() {
+        let params =
+            parse_go("go ponder wtime 1000 btime 2000 winc 10 binc 20 movestogo 5\n").unwrap();
+        assert!(params.ponder);
+        assert!(!params.infinite);
+        assert_eq!(params.depth, None);
+        assert_eq!(
+            params.time_control,
+            TimeControl {
+                wtime: Some(1000),
+                btime: Some(2000),
+                winc: Some(10),
+                binc: Some(20),
+                movestogo: Some(5),
+                movetime: None,
+            }
+        );
+
+        let params = parse_go("go depth 6 nodes 5000").unwrap();
+        assert_eq!(params.depth, Some(6));
+        assert_eq!(params.nodes, Some(5000));
+        assert_eq!(params.mate, None);
+        assert_eq!(parse_go("go mate 3").unwrap().mate, Some(3));
+        assert_eq!(params.time_control, TimeControl::default());
+
+        for (go, error) in [
+            ("go depth -1", "invalid go depth -1"),
+            ("go nodes abc", "invalid go nodes abc"),
+            ("go wtime 1000 btime", "missing go btime"),
+            ("go movetime 1.5", "invalid go movetime 1.5"),
+        ] {
+            assert_eq!(parse_go(go).unwrap_err().to_string(), error);
         }
     }
+
+    #[test]
+    fn test_handle_position_moves() {
+        let mut session = AnalysisSession::new(START_FEN.to_string(), vec![]);
+        let state = handle_position(
+            "position startpos moves e2e4 e7e5 g1f3\n".to_string(),
+            Variant::Standard,
+            &mut session,
+        )
+        .unwrap();
+        let board = &state.position.board;
+        assert!(board.piece(&Square::E4).is_some());
+        assert!(board.piece(&Square::E5).is_some());
+        assert!(board.piece(&Square::F3).is_some());
+        assert!(board.piece(&Square::G1).is_none());
+
+        let fen = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        let state = handle_position(
+            format!("position fen {} moves a7a8q", fen),
+            Variant::Standard,
+            &mut session,
+        )
+        .unwrap();
+        assert!(state.position.board.piece(&Square::A8).is_some());
+        assert!(state.position.board.piece(&Square::A7).is_none());
+        assert_eq!(session.root_fen, fen);
+        assert_eq!(session.moves, vec!["a7a8q".to_string()]);
+
+        // a game longer than the history holds, that's still a repetition
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let moves: Vec<&str> = shuffle
+            .iter()
+            .cycle()
+            .take(crate::play::MAX_GAME_PLY + 4)
+            .copied()
+            .collect();
+        let state = handle_position(
+            format!("position startpos moves {}", moves.join(" ")),
+            Variant::Standard,
+            &mut session,
+        )
+        .unwrap();
+        assert!(state.is_threefold_repetition());
+
+        // and to a knight just the same
+        let state = handle_position(
+            format!("position fen {} moves a7a8n", fen),
+            Variant::Standard,
+            &mut session,
+        )
+        .unwrap();
+        assert_eq!(
+            state.position.board.piece(&Square::A8),
+            Some(Piece::WhiteKnight)
+        );
+
+        // a kingless antichess position is set up by its own rules
+        let fen = "8/8/8/8/8/8/q6P/8 w - - 0 1";
+        assert!(handle_position(
+            format!("position fen {}", fen),
+            Variant::Standard,
+            &mut session
+        )
+        .is_err());
+        let state = handle_position(
+            format!("position fen {} moves h2h3", fen),
+            Variant::Antichess,
+            &mut session,
+        )
+        .unwrap();
+        assert_eq!(state.position.variant, Variant::Antichess);
+        assert!(state.position.board.piece(&Square::H3).is_some());
+    }
+
+    #[test]
+    fn test_handle_session() {
+        let mut session = AnalysisSession::new(START_FEN.to_string(), vec![]);
+        let buf = "position startpos moves e2e4".to_string();
+        let state = handle_position(buf.clone(), Variant::Standard, &mut session).unwrap();
+        session.record(&search(
+            &state,
+            2,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::default(),
+            &mut (),
+        ));
+        // the same position again carries on the session
+        handle_position(buf, Variant::Standard, &mut session).unwrap();
+        assert_eq!(session.lines.len(), 1);
+
+        let path =
+            std::env::temp_dir().join(format!("lasker-uci-session-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        assert!(handle_session(
+            &format!("session save {}", path),
+            Variant::Standard,
+            &mut session,
+            &output,
+        )
+        .unwrap()
+        .is_none());
+        let mut loaded = AnalysisSession::new(START_FEN.to_string(), vec![]);
+        let loaded_state = handle_session(
+            &format!("session load {}\n", path),
+            Variant::Standard,
+            &mut loaded,
+            &output,
+        )
+        .unwrap()
+        .unwrap();
+        std::fs::remove_file(path).unwrap();
+        let lines = buf.lines();
+        assert_eq!(lines[0], format!("info string saved session to {}", path));
+        assert!(lines[1].starts_with("info string loaded session at depth 2 "));
+        assert_eq!(loaded, session);
+        assert_eq!(loaded_state.position_key, state.position_key);
+    }
+
+    #[test]
+    fn test_handle_variant() {
+        let variant = handle_variant("setoption name UCI_Variant value kingofthehill\n").unwrap();
+        assert_eq!(variant, Variant::KingOfTheHill);
+        assert!(matches!(
+            handle_variant("setoption name UCI_Variant value crazyhouse"),
+            Err(UciError::InvalidValue { .. })
+        ));
+
+        // White's king reaches e4 and wins, so Black has no reply to play
+        let mut session = AnalysisSession::new(START_FEN.to_string(), vec![]);
+        let buf = "position fen 4k3/8/8/8/8/4K3/8/8 w - - 0 1 moves e3e4".to_string();
+        let state = handle_position(buf.clone(), variant, &mut session).unwrap();
+        assert_eq!(state.position.winner(), Some(Color::White));
+        assert!(handle_position(format!("{} e8e7", buf), variant, &mut session).is_err());
+        let standard = handle_position(format!("{} e8e7", buf), Variant::Standard, &mut session);
+        assert!(standard.is_ok());
+
+        let result = search(
+            &state,
+            3,
+            &SearchConfig::default(),
+            Arc::default(),
+            &TranspositionTable::new(1),
+            &mut (),
+        );
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, -MATE);
+    }
+
+    #[test]
+    fn test_handle_setoption() {
+        let mut time_manager = TimeManager::default();
+        let mut config = SearchConfig::default();
+        handle_setoption(
+            "setoption name Ponder value true\n",
+            &mut time_manager,
+            &mut config,
+        );
+        assert!(time_manager.ponder);
+
+        handle_setoption(
+            "setoption name Contempt value 500\n",
+            &mut time_manager,
+            &mut config,
+        );
+        assert_eq!(config.contempt.base, MAX_CONTEMPT);
+        handle_setoption(
+            "setoption name EngineRating value 2200",
+            &mut time_manager,
+            &mut config,
+        );
+        assert_eq!(config.contempt.engine_rating, 2200);
+
+        let opponent = "setoption name UCI_Opponent value IM 2350 human Some Player\n";
+        handle_setoption(opponent, &mut time_manager, &mut config);
+        assert_eq!(config.contempt.opponent_rating, Some(2350));
+        let opponent = "setoption name UCI_Opponent value none none computer Other Engine";
+        handle_setoption(opponent, &mut time_manager, &mut config);
+        assert_eq!(config.contempt.opponent_rating, None);
+
+        let verification = "setoption name NullMoveVerification value false";
+        handle_setoption(verification, &mut time_manager, &mut config);
+        assert_eq!(config.null_move.verification_min_depth, None);
+        let verification = "setoption name NullMoveVerification value true";
+        handle_setoption(verification, &mut time_manager, &mut config);
+        assert_eq!(config.null_move, NullMoveParams::default());
+
+        let personality = "setoption name Personality value solid";
+        handle_setoption(personality, &mut time_manager, &mut config);
+        assert_eq!(config.contempt.base, -10);
+        assert_eq!(config.eval_weights.king_safety, 130);
+
+        let threads = "setoption name Threads value 4";
+        handle_setoption(threads, &mut time_manager, &mut config);
+        assert_eq!(config.helper_threads, 3);
+        let threads = "setoption name Threads value 0";
+        handle_setoption(threads, &mut time_manager, &mut config);
+        assert_eq!(config.helper_threads, 0);
+
+        let randomization = "setoption name RootRandomization value 12";
+        handle_setoption(randomization, &mut time_manager, &mut config);
+        assert_eq!(
+            config.root_randomization,
+            Some(RootRandomization {
+                plies: 12,
+                window: RootRandomization::DEFAULT_WINDOW,
+            })
+        );
+        let randomization = "setoption name RootRandomization value 0";
+        handle_setoption(randomization, &mut time_manager, &mut config);
+        assert_eq!(config.root_randomization, None);
+
+        let skill = "setoption name Skill Level value 7";
+        handle_setoption(skill, &mut time_manager, &mut config);
+        assert_eq!(config.strength.level(), Some(7));
+        handle_setoption(
+            "setoption name UCI_Elo value 2000",
+            &mut time_manager,
+            &mut config,
+        );
+        handle_setoption(
+            "setoption name UCI_LimitStrength value true",
+            &mut time_manager,
+            &mut config,
+        );
+        assert_eq!(config.strength.level(), Some(15));
+    }
+
+    #[test]
+    fn test_handle_hash() {
+        let tt = Mutex::new(TranspositionTable::new(1));
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        handle_hash("setoption name Hash value 4\n", &tt, &output).unwrap();
+        assert_eq!(tt.lock().unwrap().size_mb(), 4);
+        // nothing to report when the size asked for was had
+        assert!(buf.lines().is_empty());
+        assert!(handle_hash("setoption name Hash value lots", &tt, &output).is_err());
+        assert_eq!(tt.lock().unwrap().size_mb(), 4);
+
+        handle_hash("setoption name Hash value 6\n", &tt, &output).unwrap();
+        assert_eq!(tt.lock().unwrap().size_mb(), 4);
+        assert_eq!(
+            buf.lines(),
+            vec!["info string using 4 MB of hash, the largest power of two within 6 MB"]
+        );
+    }
+
+    #[test]
+    fn test_handle_deterministic() {
+        let mut time_manager = TimeManager::default();
+        let mut config = SearchConfig::default();
+        let tt = Mutex::new(TranspositionTable::new(1));
+        let output = UciOutput::new(io::sink(), Duration::ZERO);
+        handle_setoption(
+            "setoption name Threads value 4",
+            &mut time_manager,
+            &mut config,
+        );
+        handle_deterministic(
+            "setoption name Deterministic value true\n",
+            &mut time_manager,
+            &mut config,
+            &tt,
+            &output,
+        );
+        assert!(time_manager.deterministic);
+        assert_eq!(config.helper_threads, 0);
+        assert_eq!(tt.lock().unwrap().size_mb(), DEFAULT_SIZE_MB);
+        handle_setoption(
+            "setoption name Threads value 4",
+            &mut time_manager,
+            &mut config,
+        );
+        assert_eq!(config.helper_threads, 0);
+
+        handle_deterministic(
+            "setoption name Deterministic value false\n",
+            &mut time_manager,
+            &mut config,
+            &tt,
+            &output,
+        );
+        assert!(!time_manager.deterministic);
+        handle_setoption(
+            "setoption name Threads value 4",
+            &mut time_manager,
+            &mut config,
+        );
+        assert_eq!(config.helper_threads, 3);
+    }
+
+    #[test]
+    fn test_handle_perft_matches_stockfish_format() {
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        let perft = handle_perft("go perft 2\n".to_string(), &GameState::default(), &output);
+        perft.unwrap().handle.join().unwrap();
+        let lines = buf.lines();
+        assert_eq!(lines.len(), 23);
+        assert_eq!(lines[0], "a2a3: 20");
+        assert_eq!(lines[19], "h2h4: 20");
+        assert_eq!(lines[20], "");
+        assert_eq!(lines[21], "Nodes searched: 400");
+        assert!(lines[22].starts_with("info nodes 400 time "));
+    }
+
+    #[test]
+    fn test_handle_display() {
+        let mut session = AnalysisSession::new(START_FEN.to_string(), vec![]);
+        let state = handle_position(
+            "position startpos moves e2e4 d7d5 f1b5".to_string(),
+            Variant::Standard,
+            &mut session,
+        )
+        .unwrap();
+        let buf = SharedBuf::default();
+        handle_display(
+            &state,
+            &session,
+            &UciOutput::new(buf.clone(), Duration::ZERO),
+        );
+        let lines = buf.lines();
+        assert!(lines.contains(&"8 | r | n | b | q | k | b | n | r |".to_string()));
+        assert!(lines.contains(&format!(
+            "Set up by: position fen {} moves e2e4 d7d5 f1b5",
+            START_FEN
+        )));
+        assert!(lines.contains(
+            &"Fen: rnbqkbnr/ppp1pppp/8/1B1p4/4P3/8/PPPP1PPP/RNBQK1NR b KQkq - 1 2".to_string()
+        ));
+        assert!(lines.contains(&format!("Key: {:016X}", state.position_key)));
+        assert!(lines.contains(&"Side to move: Black".to_string()));
+        assert!(lines.contains(&"Castling: KQkq".to_string()));
+        assert!(lines.contains(&"En passant: -".to_string()));
+        assert!(lines.contains(&"Checkers: b5".to_string()));
+    }
+
+    #[test]
+    fn test_handle_heatmap() {
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        let config = SearchConfig::default();
+        handle_heatmap("heatmap", &GameState::default(), &config, &output).unwrap();
+        let lines = buf.lines();
+        // four maps of a title, eight ranks and the files, blank lines between
+        assert_eq!(lines.len(), 4 * 10 + 3);
+        assert_eq!(lines[0], "Attacks by White");
+
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        handle_heatmap("heatmap pst", &GameState::default(), &config, &output).unwrap();
+        assert_eq!(
+            buf.lines()[0],
+            "Piece-square bonuses, White's point of view"
+        );
+        assert!(
+            handle_heatmap("heatmap mobility", &GameState::default(), &config, &output).is_err()
+        );
+    }
+
+    #[test]
+    fn test_stop_perft() {
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        let mut active =
+            handle_perft("go perft 12".to_string(), &GameState::default(), &output).ok();
+        stop_search(&mut active, &mut TimeManager::default());
+        assert!(active.is_none());
+        let lines = buf.lines();
+        assert!(lines.contains(&"info string perft stopped, the count is partial".to_string()));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("Nodes searched: ")));
+    }
+
+    #[test]
+    fn test_bad_input_is_an_error() {
+        let output = UciOutput::new(io::sink(), Duration::ZERO);
+        let state = GameState::default();
+        for go in ["go perft deep", "go perft", "go perft detail x"] {
+            let err = handle_perft(go.to_string(), &state, &output).err().unwrap();
+            assert!(matches!(err, UciError::InvalidValue { .. }), "{}", go);
+        }
+
+        let mut session = AnalysisSession::new(START_FEN.to_string(), vec![]);
+        let err = handle_position(
+            "position fen 8/8/8 w - -".to_string(),
+            Variant::Standard,
+            &mut session,
+        );
+        assert!(matches!(err, Err(UciError::Fen(_))));
+        let err = handle_position(
+            "position startpos moves e2e4 e2e4".to_string(),
+            Variant::Standard,
+            &mut session,
+        );
+        assert!(matches!(err, Err(UciError::IllegalMove(mv)) if mv == "e2e4"));
+        // the session stays on the last position that could be set up
+        assert_eq!(session, AnalysisSession::new(START_FEN.to_string(), vec![]));
+
+        assert!(handle_session(
+            "session load /nonexistent/session.json",
+            Variant::Standard,
+            &mut session,
+            &output,
+        )
+        .is_err());
+        assert!(handle_bench("bench deep", &output).is_err());
+        assert!(handle_testsuite("testsuite", &output).is_err());
+        assert!(handle_testsuite("testsuite wac.epd movetime soon", &output).is_err());
+        let tt = Mutex::new(TranspositionTable::new(1));
+        assert!(handle_hash("setoption name Hash value lots", &tt, &output).is_err());
+
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        output.error(&UciError::IllegalMove("e2e5".to_string()));
+        assert_eq!(buf.lines(), vec!["info string error: illegal move e2e5"]);
+    }
+
+    #[test]
+    fn test_search_panic_is_contained() {
+        let tt = Arc::new(Mutex::new(TranspositionTable::new(1)));
+        let poisoner = tt.clone();
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("search fell over");
+        })
+        .join();
+        assert!(tt.is_poisoned());
+        assert_eq!(lock_tt(&tt).size_mb(), 1);
+
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        let payload = panic::catch_unwind(|| panic!("index out of bounds")).unwrap_err();
+        report_search_panic(
+            "go depth 5",
+            &GameState::default(),
+            payload.as_ref(),
+            &output,
+        );
+        assert_eq!(
+            buf.lines(),
+            vec!["info string search failed: index out of bounds"]
+        );
+    }
+
+    #[test]
+    fn test_only_primary_reporter_reports() {
+        let iteration = Iteration {
+            depth: 1,
+            seldepth: 1,
+            score: 20,
+            nodes: 20,
+            time: Duration::from_millis(10),
+            hashfull: 3,
+            pv: vec![],
+        };
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        UciReporter::helper(output.clone()).on_iteration(&iteration);
+        assert!(buf.lines().is_empty());
+        UciReporter::primary(output).on_iteration(&iteration);
+        assert_eq!(
+            buf.lines(),
+            vec!["info depth 1 seldepth 1 score cp 20 nodes 20 nps 2000 hashfull 3 time 10 pv "]
+        );
+    }
+
+    #[test]
+    fn test_currmove_reported_on_long_searches() {
+        let mv = GameState::default().position.parse_move("e2e4").unwrap();
+        let mut current = CurrentMove {
+            depth: 12,
+            mv,
+            number: 2,
+            nodes: 1_000_000,
+            time: CURRMOVE_DELAY / 2,
+            hashfull: 40,
+        };
+        let buf = SharedBuf::default();
+        let mut reporter = UciReporter::primary(UciOutput::new(buf.clone(), Duration::ZERO));
+        reporter.on_current_move(&current);
+        assert!(buf.lines().is_empty());
+        current.time = Duration::from_secs(4);
+        UciReporter::helper(UciOutput::new(buf.clone(), Duration::ZERO)).on_current_move(&current);
+        assert!(buf.lines().is_empty());
+        reporter.on_current_move(&current);
+        assert_eq!(
+            buf.lines(),
+            vec![
+                "info depth 12 currmove e2e4 currmovenumber 2 nodes 1000000 nps 250000 \
+                 hashfull 40 time 4000"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stop_ponder_search_is_a_miss() {
+        let mut time_manager = TimeManager::default();
+        time_manager.ponder = true;
+        let tc = TimeControl {
+            wtime: Some(60_000),
+            ..TimeControl::default()
+        };
+        let base = time_manager.allocate(&tc, Color::White).unwrap().soft;
+
+        let mut active = Some(
+            handle_go(
+                "go ponder wtime 60000".to_string(),
+                &GameState::default(),
+                &time_manager,
+                &SearchConfig::default(),
+                false,
+                false,
+                &Arc::default(),
+                &Arc::new(Mutex::new(AnalysisSession::new(
+                    START_FEN.to_string(),
+                    vec![],
+                ))),
+                &UciOutput::new(io::sink(), MIN_INFO_INTERVAL),
+            )
+            .unwrap(),
+        );
+        stop_search(&mut active, &mut time_manager);
+        assert!(active.is_none());
+        assert!(time_manager.allocate(&tc, Color::White).unwrap().soft < base);
+    }
+
+    #[test]
+    fn test_abandon_search() {
+        let mut time_manager = TimeManager::default();
+        let tt = Arc::new(Mutex::new(TranspositionTable::new(1)));
+        let mut active = Some(
+            handle_go(
+                "go infinite".to_string(),
+                &GameState::default(),
+                &time_manager,
+                &SearchConfig::default(),
+                false,
+                false,
+                &tt,
+                &Arc::new(Mutex::new(AnalysisSession::new(
+                    START_FEN.to_string(),
+                    vec![],
+                ))),
+                &UciOutput::new(io::sink(), MIN_INFO_INTERVAL),
+            )
+            .unwrap(),
+        );
+        let control = active.as_ref().unwrap().control.clone();
+        abandon_search(&mut active, &mut time_manager);
+        assert!(active.is_none());
+        assert!(control.is_discarded());
+        // the old search has let go of the table for the next one
+        assert!(tt.try_lock().is_ok());
+    }
+
+    #[test]
+    fn test_deterministic_go_starts_from_an_empty_table() {
+        let mut time_manager = TimeManager::default();
+        time_manager.deterministic = true;
+        let tt = Arc::new(Mutex::new(TranspositionTable::new(1)));
+        let run = || {
+            let buf = SharedBuf::default();
+            let active = handle_go(
+                "go nodes 3000".to_string(),
+                &GameState::default(),
+                &time_manager,
+                &SearchConfig::default(),
+                false,
+                false,
+                &tt,
+                &Arc::new(Mutex::new(AnalysisSession::new(
+                    START_FEN.to_string(),
+                    vec![],
+                ))),
+                &UciOutput::new(buf.clone(), MIN_INFO_INTERVAL),
+            )
+            .unwrap();
+            active.handle.join().unwrap();
+            buf.lines()
+        };
+        // the second search shares the first's table, yet finds what it did
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn test_go_nodes_is_reproducible() {
+        let run = |show_wdl| {
+            let buf = SharedBuf::default();
+            let active = handle_go(
+                "go nodes 3000".to_string(),
+                &GameState::default(),
+                &TimeManager::default(),
+                &SearchConfig::default(),
+                false,
+                show_wdl,
+                &Arc::new(Mutex::new(TranspositionTable::new(1))),
+                &Arc::new(Mutex::new(AnalysisSession::new(
+                    START_FEN.to_string(),
+                    vec![],
+                ))),
+                &UciOutput::new(buf.clone(), MIN_INFO_INTERVAL),
+            )
+            .unwrap();
+            active.handle.join().unwrap();
+            buf.lines()
+        };
+        let lines = run(false);
+        assert!(lines.len() > 2);
+        assert!(lines.last().unwrap().starts_with("bestmove"));
+        assert!(lines.iter().all(|line| !line.contains(" wdl ")));
+        assert_eq!(run(false), lines);
+
+        // UCI_ShowWDL puts the chances straight after each score
+        let with_wdl = run(true);
+        assert_eq!(with_wdl.len(), lines.len());
+        assert!(with_wdl
+            .iter()
+            .filter(|line| line.starts_with("info depth"))
+            .all(|line| line.contains(" wdl ")));
+    }
 }
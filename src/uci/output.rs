@@ -0,0 +1,204 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Closest together two `info` lines are written; those in between are held
+/// back, the latest iteration's replacing any before it.
+pub const MIN_INFO_INTERVAL: Duration = Duration::from_millis(20);
+
+struct OutputState {
+    writer: Box<dyn Write + Send>,
+    min_info_interval: Duration,
+    last_info: Option<Instant>,
+    /// the latest `info` line held back for coming too soon, with the depth
+    /// of the iteration it's about
+    pending_info: Option<(u8, String)>,
+}
+
+impl OutputState {
+    fn write(&mut self, line: &str) {
+        // a GUI that has gone away isn't worth dying over
+        let _ = writeln!(self.writer, "{}", line);
+        let _ = self.writer.flush();
+    }
+
+    fn flush_pending(&mut self) {
+        if let Some((_, info)) = self.pending_info.take() {
+            self.write(&info);
+        }
+    }
+}
+
+/// The one way out to the GUI, shared by every thread that has something to
+/// say. Each line is written whole under a lock so none are interleaved, and
+/// `info` lines are rate limited so a burst of fast iterations can't flood
+/// the GUI.
+#[derive(Clone)]
+pub struct UciOutput {
+    state: Arc<Mutex<OutputState>>,
+}
+
+impl UciOutput {
+    pub fn new(writer: impl Write + Send + 'static, min_info_interval: Duration) -> Self {
+        UciOutput {
+            state: Arc::new(Mutex::new(OutputState {
+                writer: Box::new(writer),
+                min_info_interval,
+                last_info: None,
+                pending_info: None,
+            })),
+        }
+    }
+
+    pub fn stdout() -> Self {
+        UciOutput::new(io::stdout(), MIN_INFO_INTERVAL)
+    }
+
+    /// Writes `line` straight away, after any `info` line still held back so
+    /// the GUI has the last word on the search before e.g. its `bestmove`.
+    pub fn line(&self, line: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.flush_pending();
+        state.write(line);
+    }
+
+    /// Tells the GUI a command couldn't be carried out.
+    pub fn error(&self, err: &dyn fmt::Display) {
+        self.line(&format!("info string error: {}", err));
+    }
+
+    /// Writes an `info` line about the iteration at `depth`, or holds it
+    /// back if the last one went out less than the minimum interval ago. A
+    /// line held back is dropped once one about the same or a deeper
+    /// iteration is written or held back in its place.
+    pub fn info(&self, depth: u8, line: &str) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let due = state
+            .last_info
+            .is_none_or(|last| now.duration_since(last) >= state.min_info_interval);
+        let superseded = state
+            .pending_info
+            .as_ref()
+            .is_none_or(|(pending, _)| *pending <= depth);
+        if due {
+            if superseded {
+                state.pending_info = None;
+            }
+            state.write(line);
+            state.last_info = Some(now);
+        } else if superseded {
+            state.pending_info = Some((depth, line.to_string()));
+        }
+    }
+
+    /// Drops the `info` line held back, if any, for a search that's been
+    /// abandoned and so has nothing more to say.
+    pub fn discard_info(&self) {
+        self.state.lock().unwrap().pending_info = None;
+    }
+}
+
+/// A writer whose output the test can still read once it's been boxed.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(super) struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl SharedBuf {
+    pub(super) fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_info_rate_limit() {
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::from_secs(60));
+        output.info(1, "info depth 1");
+        output.info(2, "info depth 2");
+        output.info(3, "info depth 3");
+        // only the first made it out, and the last is held back for bestmove
+        assert_eq!(buf.lines(), vec!["info depth 1"]);
+        output.line("bestmove e2e4");
+        assert_eq!(
+            buf.lines(),
+            vec!["info depth 1", "info depth 3", "bestmove e2e4"]
+        );
+
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        output.info(1, "info depth 1");
+        output.info(2, "info depth 2");
+        assert_eq!(buf.lines(), vec!["info depth 1", "info depth 2"]);
+    }
+
+    #[test]
+    fn test_pending_info_stays_latest() {
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::from_secs(60));
+        output.info(1, "info depth 1");
+        output.info(3, "info depth 3");
+        // a shallower line doesn't replace a deeper one held back
+        output.info(2, "info depth 2");
+        output.line("bestmove e2e4");
+        assert_eq!(
+            buf.lines(),
+            vec!["info depth 1", "info depth 3", "bestmove e2e4"]
+        );
+
+        // an abandoned search's line doesn't follow whatever comes next
+        output.info(4, "info depth 4");
+        output.discard_info();
+        output.line("readyok");
+        assert_eq!(buf.lines().last().unwrap(), "readyok");
+        assert!(!buf.lines().contains(&"info depth 4".to_string()));
+    }
+
+    #[test]
+    fn test_lines_from_threads_stay_whole() {
+        let buf = SharedBuf::default();
+        let output = UciOutput::new(buf.clone(), Duration::ZERO);
+        let handles: Vec<_> = (0..4)
+            .map(|thread| {
+                let output = output.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        output.line(&format!("info string thread {} line {}", thread, i));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let lines = buf.lines();
+        assert_eq!(lines.len(), 400);
+        assert!(lines
+            .iter()
+            .all(|line| line.starts_with("info string thread ") && line.contains(" line ")));
+    }
+}
@@ -0,0 +1,120 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::search::SearchResult;
+
+/// One completed search of a session's position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalysisLine {
+    pub depth: u8,
+    pub score: i32,
+    /// the principal variation, in coordinate notation
+    pub pv: Vec<String>,
+}
+
+/// The searches run on a position, kept so long-term (e.g. correspondence)
+/// analysis can be saved to a JSON file and picked up again later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalysisSession {
+    pub root_fen: String,
+    /// moves played from the root to the analysed position, in coordinate
+    /// notation
+    pub moves: Vec<String>,
+    /// in the order they were searched
+    pub lines: Vec<AnalysisLine>,
+}
+
+impl AnalysisSession {
+    pub fn new(root_fen: String, moves: Vec<String>) -> Self {
+        AnalysisSession {
+            root_fen,
+            moves,
+            lines: vec![],
+        }
+    }
+
+    /// Whether this is a session on the position reached by `moves` from
+    /// `root_fen`.
+    pub fn is_on(&self, root_fen: &str, moves: &[String]) -> bool {
+        self.root_fen == root_fen && self.moves == moves
+    }
+
+    /// Adds a finished search, unless it didn't complete an iteration.
+    pub fn record(&mut self, result: &SearchResult) {
+        if result.depth == 0 {
+            return;
+        }
+        self.lines.push(AnalysisLine {
+            depth: result.depth,
+            score: result.score,
+            pv: result.pv.iter().map(|mv| mv.to_uci()).collect(),
+        });
+    }
+
+    /// The deepest line, and of those the latest.
+    pub fn best_line(&self) -> Option<&AnalysisLine> {
+        self.lines.iter().max_by_key(|line| line.depth)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{play::GameState, search::stats::SearchStats, uci::START_FEN};
+
+    fn result(state: &GameState, depth: u8, score: i32, pv: &[&str]) -> SearchResult {
+        let pv: Vec<_> = pv
+            .iter()
+            .map(|uci| state.position.parse_move(uci).unwrap())
+            .collect();
+        SearchResult {
+            best_move: pv.first().copied(),
+            score,
+            depth,
+            pv,
+            stats: SearchStats::default(),
+        }
+    }
+
+    #[test]
+    fn test_record_and_best_line() {
+        let state = GameState::default();
+        let mut session = AnalysisSession::new(START_FEN.to_string(), vec![]);
+        assert!(session.is_on(START_FEN, &[]));
+        assert_eq!(session.best_line(), None);
+
+        session.record(&result(&state, 0, 0, &[]));
+        assert!(session.lines.is_empty());
+        session.record(&result(&state, 6, 20, &["e2e4"]));
+        session.record(&result(&state, 4, 35, &["d2d4"]));
+        session.record(&result(&state, 6, 15, &["g1f3"]));
+        let best = session.best_line().unwrap();
+        assert_eq!((best.depth, best.score), (6, 15));
+        assert_eq!(best.pv, vec!["g1f3".to_string()]);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let state = GameState::default();
+        let mut session = AnalysisSession::new(START_FEN.to_string(), vec!["e2e4".to_string()]);
+        session.record(&result(&state, 5, -10, &["d2d4"]));
+
+        let path = std::env::temp_dir().join(format!("lasker-session-{}.json", std::process::id()));
+        session.save(&path).unwrap();
+        let loaded = AnalysisSession::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, session);
+
+        assert!(AnalysisSession::load(path).is_err());
+    }
+}
@@ -18,7 +18,7 @@ cat << EOF > perft.exp
    lassign \$argv depth result
    spawn ./target/debug/lasker
    send "go perft \$depth\\n"
-   expect "total nodes searched: \$result" {} timeout {exit 1}
+   expect "Nodes searched: \$result" {} timeout {exit 1}
    expect eof
 EOF
 